@@ -1,5 +1,5 @@
 use flatbuffers::FlatBufferBuilder;
-use protocol::{Message, MessageArgs};
+use protocol::{root_as_message, Message, MessageArgs};
 
 pub mod protocol;
 
@@ -7,6 +7,8 @@ pub mod protocol;
 pub enum MessageError {
     AppNameTooLong,
     PayloadTooLong,
+    ChunkTooLong,
+    InvalidMessage,
 }
 
 // Spotify -> Imagine Dragons - Believer
@@ -23,8 +25,68 @@ pub fn send_message(app: &str, payload: &str) -> Result<Vec<u8>, MessageError> {
     let app_offset = builder.create_string(app);
     let payload_offset = builder.create_string(payload);
 
-    let message = Message::create(&mut builder, &MessageArgs { app: Some(app_offset), payload: Some(payload_offset) });
+    let message = Message::create(
+        &mut builder,
+        &MessageArgs { app: Some(app_offset), payload: Some(payload_offset), ..Default::default() },
+    );
     builder.finish(message, None);
 
     Ok(builder.finished_data().to_vec())
-}
\ No newline at end of file
+}
+
+/// Largest `data` in one chunk, so that a chunk with its header still fits the screen's
+/// 1056-byte read buffer.
+pub const MAX_CHUNK_SIZE: usize = 768;
+
+/// Largest image the screen accepts over chunks; `MAX_ALBUM_ART_BYTES` in the firmware.
+pub const MAX_IMAGE_SIZE: usize = 32 * 1024;
+
+// Spotify -> {"image":"album_art",...} + bytes 768..1536 of the cover, chunk 1 of 7
+pub fn send_chunk(
+    app: &str,
+    payload: &str,
+    data: &[u8],
+    chunk_index: u16,
+    chunk_count: u16,
+) -> Result<Vec<u8>, MessageError> {
+    if app.len() > 32 {
+        return Err(MessageError::AppNameTooLong);
+    }
+
+    if payload.len() > 192 {
+        return Err(MessageError::PayloadTooLong);
+    }
+
+    if data.len() > MAX_CHUNK_SIZE {
+        return Err(MessageError::ChunkTooLong);
+    }
+    let mut builder = FlatBufferBuilder::with_capacity(1056);
+
+    let app_offset = builder.create_string(app);
+    let payload_offset = builder.create_string(payload);
+    let data_offset = builder.create_vector(data);
+
+    let message = Message::create(
+        &mut builder,
+        &MessageArgs {
+            app: Some(app_offset),
+            payload: Some(payload_offset),
+            data: Some(data_offset),
+            chunk_index,
+            chunk_count,
+        },
+    );
+    builder.finish(message, None);
+
+    Ok(builder.finished_data().to_vec())
+}
+
+// Spotify <- next
+pub fn read_message(buffer: &[u8]) -> Result<(String, String), MessageError> {
+    let message = root_as_message(buffer).map_err(|_| MessageError::InvalidMessage)?;
+
+    match (message.app(), message.payload()) {
+        (Some(app), Some(payload)) => Ok((app.to_string(), payload.to_string())),
+        _ => Err(MessageError::InvalidMessage),
+    }
+}
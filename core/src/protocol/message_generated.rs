@@ -38,6 +38,9 @@ impl<'a> flatbuffers::Follow<'a> for Message<'a> {
 impl<'a> Message<'a> {
   pub const VT_APP: flatbuffers::VOffsetT = 4;
   pub const VT_PAYLOAD: flatbuffers::VOffsetT = 6;
+  pub const VT_DATA: flatbuffers::VOffsetT = 8;
+  pub const VT_CHUNK_INDEX: flatbuffers::VOffsetT = 10;
+  pub const VT_CHUNK_COUNT: flatbuffers::VOffsetT = 12;
 
   #[inline]
   pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
@@ -49,8 +52,11 @@ impl<'a> Message<'a> {
     args: &'args MessageArgs<'args>
   ) -> flatbuffers::WIPOffset<Message<'bldr>> {
     let mut builder = MessageBuilder::new(_fbb);
+    if let Some(x) = args.data { builder.add_data(x); }
     if let Some(x) = args.payload { builder.add_payload(x); }
     if let Some(x) = args.app { builder.add_app(x); }
+    builder.add_chunk_count(args.chunk_count);
+    builder.add_chunk_index(args.chunk_index);
     builder.finish()
   }
 
@@ -69,6 +75,27 @@ impl<'a> Message<'a> {
     // which contains a valid value in this slot
     unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(Message::VT_PAYLOAD, None)}
   }
+  #[inline]
+  pub fn data(&self) -> Option<flatbuffers::Vector<'a, u8>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u8>>>(Message::VT_DATA, None)}
+  }
+  #[inline]
+  pub fn chunk_index(&self) -> u16 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u16>(Message::VT_CHUNK_INDEX, Some(0)).unwrap()}
+  }
+  #[inline]
+  pub fn chunk_count(&self) -> u16 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u16>(Message::VT_CHUNK_COUNT, Some(0)).unwrap()}
+  }
 }
 
 impl flatbuffers::Verifiable for Message<'_> {
@@ -80,6 +107,9 @@ impl flatbuffers::Verifiable for Message<'_> {
     v.visit_table(pos)?
      .visit_field::<flatbuffers::ForwardsUOffset<&str>>("app", Self::VT_APP, false)?
      .visit_field::<flatbuffers::ForwardsUOffset<&str>>("payload", Self::VT_PAYLOAD, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u8>>>("data", Self::VT_DATA, false)?
+     .visit_field::<u16>("chunk_index", Self::VT_CHUNK_INDEX, false)?
+     .visit_field::<u16>("chunk_count", Self::VT_CHUNK_COUNT, false)?
      .finish();
     Ok(())
   }
@@ -87,6 +117,9 @@ impl flatbuffers::Verifiable for Message<'_> {
 pub struct MessageArgs<'a> {
     pub app: Option<flatbuffers::WIPOffset<&'a str>>,
     pub payload: Option<flatbuffers::WIPOffset<&'a str>>,
+    pub data: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u8>>>,
+    pub chunk_index: u16,
+    pub chunk_count: u16,
 }
 impl<'a> Default for MessageArgs<'a> {
   #[inline]
@@ -94,6 +127,9 @@ impl<'a> Default for MessageArgs<'a> {
     MessageArgs {
       app: None,
       payload: None,
+      data: None,
+      chunk_index: 0,
+      chunk_count: 0,
     }
   }
 }
@@ -112,6 +148,18 @@ impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> MessageBuilder<'a, 'b, A> {
     self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(Message::VT_PAYLOAD, payload);
   }
   #[inline]
+  pub fn add_data(&mut self, data: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u8>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(Message::VT_DATA, data);
+  }
+  #[inline]
+  pub fn add_chunk_index(&mut self, chunk_index: u16) {
+    self.fbb_.push_slot::<u16>(Message::VT_CHUNK_INDEX, chunk_index, 0);
+  }
+  #[inline]
+  pub fn add_chunk_count(&mut self, chunk_count: u16) {
+    self.fbb_.push_slot::<u16>(Message::VT_CHUNK_COUNT, chunk_count, 0);
+  }
+  #[inline]
   pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> MessageBuilder<'a, 'b, A> {
     let start = _fbb.start_table();
     MessageBuilder {
@@ -131,6 +179,9 @@ impl core::fmt::Debug for Message<'_> {
     let mut ds = f.debug_struct("Message");
       ds.field("app", &self.app());
       ds.field("payload", &self.payload());
+      ds.field("data", &self.data());
+      ds.field("chunk_index", &self.chunk_index());
+      ds.field("chunk_count", &self.chunk_count());
       ds.finish()
   }
 }
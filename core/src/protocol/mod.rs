@@ -1,2 +1,2 @@
 mod message_generated;
-pub use message_generated::screen_io_t::{Message, MessageArgs, MessageBuilder};
\ No newline at end of file
+pub use message_generated::screen_io_t::{root_as_message, Message, MessageArgs, MessageBuilder};
\ No newline at end of file
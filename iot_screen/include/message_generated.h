@@ -22,7 +22,10 @@ struct Message FLATBUFFERS_FINAL_CLASS : private ::flatbuffers::Table {
   typedef MessageBuilder Builder;
   enum FlatBuffersVTableOffset FLATBUFFERS_VTABLE_UNDERLYING_TYPE {
     VT_APP = 4,
-    VT_PAYLOAD = 6
+    VT_PAYLOAD = 6,
+    VT_DATA = 8,
+    VT_CHUNK_INDEX = 10,
+    VT_CHUNK_COUNT = 12
   };
   const ::flatbuffers::String *app() const {
     return GetPointer<const ::flatbuffers::String *>(VT_APP);
@@ -30,12 +33,25 @@ struct Message FLATBUFFERS_FINAL_CLASS : private ::flatbuffers::Table {
   const ::flatbuffers::String *payload() const {
     return GetPointer<const ::flatbuffers::String *>(VT_PAYLOAD);
   }
+  const ::flatbuffers::Vector<uint8_t> *data() const {
+    return GetPointer<const ::flatbuffers::Vector<uint8_t> *>(VT_DATA);
+  }
+  uint16_t chunk_index() const {
+    return GetField<uint16_t>(VT_CHUNK_INDEX, 0);
+  }
+  uint16_t chunk_count() const {
+    return GetField<uint16_t>(VT_CHUNK_COUNT, 0);
+  }
   bool Verify(::flatbuffers::Verifier &verifier) const {
     return VerifyTableStart(verifier) &&
            VerifyOffset(verifier, VT_APP) &&
            verifier.VerifyString(app()) &&
            VerifyOffset(verifier, VT_PAYLOAD) &&
            verifier.VerifyString(payload()) &&
+           VerifyOffset(verifier, VT_DATA) &&
+           verifier.VerifyVector(data()) &&
+           VerifyField<uint16_t>(verifier, VT_CHUNK_INDEX, 2) &&
+           VerifyField<uint16_t>(verifier, VT_CHUNK_COUNT, 2) &&
            verifier.EndTable();
   }
 };
@@ -50,6 +66,15 @@ struct MessageBuilder {
   void add_payload(::flatbuffers::Offset<::flatbuffers::String> payload) {
     fbb_.AddOffset(Message::VT_PAYLOAD, payload);
   }
+  void add_data(::flatbuffers::Offset<::flatbuffers::Vector<uint8_t>> data) {
+    fbb_.AddOffset(Message::VT_DATA, data);
+  }
+  void add_chunk_index(uint16_t chunk_index) {
+    fbb_.AddElement<uint16_t>(Message::VT_CHUNK_INDEX, chunk_index, 0);
+  }
+  void add_chunk_count(uint16_t chunk_count) {
+    fbb_.AddElement<uint16_t>(Message::VT_CHUNK_COUNT, chunk_count, 0);
+  }
   explicit MessageBuilder(::flatbuffers::FlatBufferBuilder &_fbb)
         : fbb_(_fbb) {
     start_ = fbb_.StartTable();
@@ -64,23 +89,36 @@ struct MessageBuilder {
 inline ::flatbuffers::Offset<Message> CreateMessage(
     ::flatbuffers::FlatBufferBuilder &_fbb,
     ::flatbuffers::Offset<::flatbuffers::String> app = 0,
-    ::flatbuffers::Offset<::flatbuffers::String> payload = 0) {
+    ::flatbuffers::Offset<::flatbuffers::String> payload = 0,
+    ::flatbuffers::Offset<::flatbuffers::Vector<uint8_t>> data = 0,
+    uint16_t chunk_index = 0,
+    uint16_t chunk_count = 0) {
   MessageBuilder builder_(_fbb);
+  builder_.add_data(data);
   builder_.add_payload(payload);
   builder_.add_app(app);
+  builder_.add_chunk_count(chunk_count);
+  builder_.add_chunk_index(chunk_index);
   return builder_.Finish();
 }
 
 inline ::flatbuffers::Offset<Message> CreateMessageDirect(
     ::flatbuffers::FlatBufferBuilder &_fbb,
     const char *app = nullptr,
-    const char *payload = nullptr) {
+    const char *payload = nullptr,
+    const std::vector<uint8_t> *data = nullptr,
+    uint16_t chunk_index = 0,
+    uint16_t chunk_count = 0) {
   auto app__ = app ? _fbb.CreateString(app) : 0;
   auto payload__ = payload ? _fbb.CreateString(payload) : 0;
+  auto data__ = data ? _fbb.CreateVector<uint8_t>(*data) : 0;
   return ScreenIoT::CreateMessage(
       _fbb,
       app__,
-      payload__);
+      payload__,
+      data__,
+      chunk_index,
+      chunk_count);
 }
 
 inline const ScreenIoT::Message *GetMessage(const void *buf) {
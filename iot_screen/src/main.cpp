@@ -7,6 +7,7 @@
 #include <Adafruit_ILI9341.h>
 #include <JPEGDecoder.h>
 #include <ESP8266WiFi.h>
+#include <ArduinoJson.h>
 #include <message_generated.h>
 
 constexpr int SCREEN_WIDTH = 128;
@@ -17,6 +18,10 @@ constexpr int OLED_I2C_ADDRESS = 0x3C;
 #define TFT_RST D3
 #define TFT_DC D4
 
+// Active-low push buttons to ground, using the internal pull-ups.
+#define BUTTON_TOGGLE D1
+#define BUTTON_NEXT D2
+
 const char *ssid = "spider-worse";
 const char *password = "pierniktokot";
 
@@ -25,6 +30,89 @@ const int server_port = 2699;
 
 Adafruit_ILI9341 tft = Adafruit_ILI9341(TFT_CS, TFT_DC);
 WiFiClient client;
+
+// Last Spotify state from the server. Progress is interpolated locally between updates.
+struct NowPlaying
+{
+    bool active = false;
+    bool playing = false;
+    String track;
+    String artists;
+    String album;
+    String album_id;
+    String device;
+    bool shuffle = false;
+    String repeat;
+    uint32_t duration_ms = 0;
+    uint32_t progress_ms = 0;
+    uint32_t received_at = 0;
+};
+
+NowPlaying now_playing;
+bool spotify_on_screen = false;
+
+// Album art arrives in chunks of ALBUM_ART_CHUNK_SIZE bytes (MAX_CHUNK_SIZE in core). The last
+// complete cover is kept so it can be redrawn with the track.
+struct AlbumArt
+{
+    String album_id;
+    String format;
+    uint16_t width = 0;
+    uint16_t height = 0;
+    uint8_t *data = nullptr;
+    size_t size = 0;
+    uint16_t next_chunk = 0;
+    bool complete = false;
+};
+
+AlbumArt album_art;
+
+constexpr size_t ALBUM_ART_CHUNK_SIZE = 768;
+// MAX_IMAGE_SIZE in core; the server skips covers that are larger.
+constexpr size_t MAX_ALBUM_ART_BYTES = 32 * 1024;
+constexpr int ALBUM_ART_MARGIN = 10;
+constexpr int ALBUM_ART_Y = 40;
+
+// Apps like the weather send one page per configured location, cycled through while on screen.
+constexpr uint8_t MAX_PAGES = 5;
+constexpr uint32_t PAGE_MS = 10000;
+
+struct Pages
+{
+    void (*draw)(JsonDocument &doc);
+    String pages[MAX_PAGES];
+    uint8_t count = 0;
+    uint8_t current = 0;
+    uint32_t shown_at = 0;
+};
+
+void draw_weather_page(JsonDocument &doc);
+void draw_air_quality_page(JsonDocument &doc);
+void draw_xtb_page(JsonDocument &doc);
+
+Pages weather_pages{draw_weather_page};
+Pages air_quality_pages{draw_air_quality_page};
+// The portfolio, the watchlist and the P/L history, each replaced by the next message with its
+// view; pages are added in the order their views first arrive.
+Pages xtb_pages{draw_xtb_page};
+String xtb_page_views[MAX_PAGES];
+Pages *pages_on_screen = nullptr;
+
+struct Button
+{
+    uint8_t pin;
+    const char *app;
+    const char *event;
+    bool pressed;
+    uint32_t changed_at;
+};
+
+Button buttons[] = {
+    {BUTTON_TOGGLE, "Spotify", "toggle", false, 0},
+    {BUTTON_NEXT, "Spotify", "next", false, 0},
+};
+
+constexpr uint32_t BUTTON_DEBOUNCE_MS = 50;
 void init_wifi()
 {
     Serial.println("init wifi");
@@ -70,28 +158,12 @@ bool connect_to_tcp()
     return true;
 }
 
-void draw_jpg(const char* filename, int xpos, int ypos) {
-    if (!LittleFS.exists(filename)) {
-        Serial.printf("File %s does not exist\n", filename);
-        return;
-    }
-
-    fs::File jpgFile = LittleFS.open(filename, "r");
-    if (!jpgFile) {
-        Serial.printf("Failed to open file %s\n", filename);
-        return;
-    }
-    
+// Draws the image JpegDec has just decoded, MCU by MCU.
+void render_jpeg(int xpos, int ypos) {
     uint16_t *pImage;
     int16_t mcu_x, mcu_y;
     uint16_t mcu_w, mcu_h;
 
-    int ret = JpegDec.decodeFsFile(jpgFile);
-    if (ret != 1) {
-        Serial.println("Failed to decode JPEG");
-        return;
-    }
-
     while (JpegDec.read()) {
         mcu_x = JpegDec.MCUx * JpegDec.MCUWidth + xpos;
         mcu_y = JpegDec.MCUy * JpegDec.MCUHeight + ypos;
@@ -106,7 +178,27 @@ void draw_jpg(const char* filename, int xpos, int ypos) {
 
         tft.drawRGBBitmap(mcu_x, mcu_y, pImage, NULL, mcu_w, mcu_h);
     }
+}
+
+void draw_jpg(const char* filename, int xpos, int ypos) {
+    if (!LittleFS.exists(filename)) {
+        Serial.printf("File %s does not exist\n", filename);
+        return;
+    }
+
+    fs::File jpgFile = LittleFS.open(filename, "r");
+    if (!jpgFile) {
+        Serial.printf("Failed to open file %s\n", filename);
+        return;
+    }
     
+    int ret = JpegDec.decodeFsFile(jpgFile);
+    if (ret != 1) {
+        Serial.println("Failed to decode JPEG");
+        return;
+    }
+
+    render_jpeg(xpos, ypos);
 
     jpgFile.close();
 }
@@ -142,6 +234,40 @@ void print_fs_files()
 }
 
 
+// Sends an event to the server in the same flatbuffers envelope it uses for updates.
+void send_event(const char *app, const char *event)
+{
+    if (!client.connected())
+    {
+        Serial.printf("Not connected; dropping %s event %s\n", app, event);
+        return;
+    }
+
+    flatbuffers::FlatBufferBuilder builder(128);
+    builder.Finish(ScreenIoT::CreateMessageDirect(builder, app, event));
+    client.write(builder.GetBufferPointer(), builder.GetSize());
+    Serial.printf("Sent %s event %s\n", app, event);
+}
+
+void poll_buttons()
+{
+    for (Button &button : buttons)
+    {
+        bool pressed = digitalRead(button.pin) == LOW;
+        if (pressed == button.pressed || millis() - button.changed_at < BUTTON_DEBOUNCE_MS)
+        {
+            continue;
+        }
+
+        button.pressed = pressed;
+        button.changed_at = millis();
+        if (pressed)
+        {
+            send_event(button.app, button.event);
+        }
+    }
+}
+
 void setup()
 {
     Serial.begin(9600);
@@ -153,6 +279,11 @@ void setup()
 
     print_fs_files();
 
+    for (Button &button : buttons)
+    {
+        pinMode(button.pin, INPUT_PULLUP);
+    }
+
     Serial.println("setup oled");
 
     tft.begin();
@@ -184,6 +315,754 @@ void try_to_reconnect()
     }
 }
 
+uint32_t current_progress_ms()
+{
+    if (!now_playing.playing)
+    {
+        return now_playing.progress_ms;
+    }
+
+    uint32_t progress = now_playing.progress_ms + (millis() - now_playing.received_at);
+    return min(progress, now_playing.duration_ms);
+}
+
+void format_time(uint32_t ms, char *out, size_t len)
+{
+    snprintf(out, len, "%u:%02u", (unsigned)(ms / 60000), (unsigned)((ms / 1000) % 60));
+}
+
+void draw_progress()
+{
+    const int bar_x = 10;
+    const int bar_y = 200;
+    const int bar_w = tft.width() - 2 * bar_x;
+    const int bar_h = 8;
+
+    uint32_t progress = current_progress_ms();
+    int filled = now_playing.duration_ms > 0 ? (uint64_t)bar_w * progress / now_playing.duration_ms : 0;
+    tft.fillRect(bar_x, bar_y, filled, bar_h, ILI9341_GREEN);
+    tft.fillRect(bar_x + filled, bar_y, bar_w - filled, bar_h, ILI9341_DARKGREY);
+
+    char elapsed[8];
+    char total[8];
+    format_time(progress, elapsed, sizeof(elapsed));
+    format_time(now_playing.duration_ms, total, sizeof(total));
+
+    tft.fillRect(bar_x, bar_y + 12, bar_w, 8, ILI9341_BLACK);
+    tft.setTextSize(1);
+    tft.setTextColor(ILI9341_WHITE);
+    tft.setCursor(bar_x, bar_y + 12);
+    tft.print(elapsed);
+    tft.setCursor(bar_x + bar_w - 6 * strlen(total), bar_y + 12);
+    tft.print(total);
+}
+
+void draw_album_art()
+{
+    if (!album_art.complete || album_art.album_id != now_playing.album_id)
+    {
+        return;
+    }
+
+    int x = tft.width() - album_art.width - ALBUM_ART_MARGIN;
+    if (album_art.format == "rgb565")
+    {
+        tft.drawRGBBitmap(x, ALBUM_ART_Y, (uint16_t *)album_art.data, album_art.width, album_art.height);
+        return;
+    }
+
+    if (JpegDec.decodeArray(album_art.data, album_art.size) != 1)
+    {
+        Serial.println("Failed to decode album art");
+        return;
+    }
+    render_jpeg(x, ALBUM_ART_Y);
+}
+
+void draw_now_playing()
+{
+    tft.fillScreen(ILI9341_BLACK);
+    tft.setCursor(0, 0);
+    tft.setTextColor(ILI9341_WHITE);
+    tft.setTextSize(2);
+    tft.println("Spotify");
+    tft.println();
+
+    if (!now_playing.active)
+    {
+        tft.println("Nothing playing");
+        return;
+    }
+
+    tft.println(now_playing.track);
+    tft.setTextSize(1);
+    tft.println(now_playing.artists);
+    tft.println(now_playing.album);
+    tft.println();
+
+    if (!now_playing.playing)
+    {
+        tft.setTextSize(2);
+        tft.setTextColor(ILI9341_YELLOW);
+        tft.println("Paused");
+        tft.setTextColor(ILI9341_WHITE);
+        tft.setTextSize(1);
+    }
+
+    tft.printf("Shuffle: %s  Repeat: %s\n", now_playing.shuffle ? "on" : "off", now_playing.repeat.c_str());
+    if (now_playing.device.length() > 0)
+    {
+        tft.printf("On %s\n", now_playing.device.c_str());
+    }
+
+    draw_album_art();
+    draw_progress();
+}
+
+void handle_spotify_message(const char *payload)
+{
+    JsonDocument doc;
+    DeserializationError error = deserializeJson(doc, payload);
+    if (error)
+    {
+        Serial.printf("Invalid Spotify payload: %s\n", error.c_str());
+        return;
+    }
+
+    const char *state = doc["state"] | "nothing_playing";
+    now_playing.active = strcmp(state, "nothing_playing") != 0;
+    now_playing.playing = strcmp(state, "playing") == 0;
+    now_playing.track = doc["track"] | "";
+    now_playing.album = doc["album"] | "";
+    now_playing.album_id = doc["albumId"] | "";
+    now_playing.device = doc["device"] | "";
+    now_playing.shuffle = doc["shuffle"] | false;
+    now_playing.repeat = doc["repeat"] | "off";
+    now_playing.duration_ms = doc["durationMs"] | 0;
+    now_playing.progress_ms = doc["progressMs"] | 0;
+    now_playing.received_at = millis();
+
+    now_playing.artists = "";
+    for (JsonVariant artist : doc["artists"].as<JsonArray>())
+    {
+        if (now_playing.artists.length() > 0)
+        {
+            now_playing.artists += ", ";
+        }
+        now_playing.artists += artist.as<const char *>();
+    }
+
+    draw_now_playing();
+}
+
+// Icons named like WeatherIcon on the server, drawn into a size x size box.
+void draw_cloud(int x, int y, int size, uint16_t color)
+{
+    int r = size / 5;
+    tft.fillCircle(x + size / 3, y + size / 2, r, color);
+    tft.fillCircle(x + size / 2, y + size / 2 - r / 2, r + r / 3, color);
+    tft.fillCircle(x + 2 * size / 3, y + size / 2, r, color);
+    tft.fillRect(x + size / 3, y + size / 2, size / 3, r, color);
+}
+
+void draw_sun(int cx, int cy, int r)
+{
+    tft.fillCircle(cx, cy, r, ILI9341_YELLOW);
+    for (int i = 0; i < 8; i++)
+    {
+        float angle = i * PI / 4;
+        tft.drawLine(cx + cos(angle) * (r + 2), cy + sin(angle) * (r + 2),
+                     cx + cos(angle) * (r + r / 2 + 2), cy + sin(angle) * (r + r / 2 + 2), ILI9341_YELLOW);
+    }
+}
+
+void draw_moon(int cx, int cy, int r)
+{
+    tft.fillCircle(cx, cy, r, ILI9341_LIGHTGREY);
+    tft.fillCircle(cx + r / 2, cy - r / 3, r, ILI9341_BLACK);
+}
+
+void draw_weather_icon(const char *icon, int x, int y, int size)
+{
+    tft.fillRect(x, y, size, size, ILI9341_BLACK);
+    int bottom = y + 3 * size / 4;
+
+    if (strcmp(icon, "clear") == 0)
+    {
+        draw_sun(x + size / 2, y + size / 2, size / 5);
+    }
+    else if (strcmp(icon, "clear_night") == 0)
+    {
+        draw_moon(x + size / 2, y + size / 2, size / 4);
+    }
+    else if (strcmp(icon, "partly_cloudy") == 0 || strcmp(icon, "partly_cloudy_night") == 0)
+    {
+        if (strcmp(icon, "partly_cloudy") == 0)
+        {
+            draw_sun(x + size / 3, y + size / 3, size / 6);
+        }
+        else
+        {
+            draw_moon(x + size / 3, y + size / 3, size / 5);
+        }
+        draw_cloud(x + size / 8, y + size / 8, size, ILI9341_WHITE);
+    }
+    else if (strcmp(icon, "fog") == 0)
+    {
+        for (int i = 0; i < 4; i++)
+        {
+            int line_y = y + size / 4 + i * size / 7;
+            tft.drawFastHLine(x + (i % 2) * size / 8, line_y, size - size / 8, ILI9341_LIGHTGREY);
+        }
+    }
+    else
+    {
+        bool thunder = strcmp(icon, "thunderstorm") == 0;
+        draw_cloud(x, y - size / 8, size, thunder ? ILI9341_DARKGREY : ILI9341_LIGHTGREY);
+
+        if (strcmp(icon, "drizzle") == 0 || strcmp(icon, "rain") == 0)
+        {
+            int drops = strcmp(icon, "rain") == 0 ? 4 : 2;
+            for (int i = 0; i < drops; i++)
+            {
+                int drop_x = x + size / 4 + i * size / (2 * drops) + size / 8;
+                tft.drawLine(drop_x, bottom, drop_x - size / 12, bottom + size / 6, ILI9341_CYAN);
+            }
+        }
+        else if (strcmp(icon, "snow") == 0)
+        {
+            for (int i = 0; i < 3; i++)
+            {
+                tft.fillCircle(x + size / 4 + i * size / 4, bottom + size / 10 + (i % 2) * size / 10, max(1, size / 24), ILI9341_WHITE);
+            }
+        }
+        else if (thunder)
+        {
+            int bolt_x = x + size / 2;
+            tft.drawLine(bolt_x, bottom - size / 12, bolt_x - size / 8, bottom + size / 8, ILI9341_YELLOW);
+            tft.drawLine(bolt_x - size / 8, bottom + size / 8, bolt_x, bottom + size / 8, ILI9341_YELLOW);
+            tft.drawLine(bolt_x, bottom + size / 8, bolt_x - size / 8, bottom + size / 4, ILI9341_YELLOW);
+        }
+    }
+}
+
+// Up, down or flat arrow at (x, y) for the server's temperature trend.
+void draw_trend_arrow(const char *trend, int x, int y)
+{
+    if (strcmp(trend, "rising") == 0)
+    {
+        tft.fillTriangle(x, y + 10, x + 10, y + 10, x + 5, y, ILI9341_ORANGE);
+    }
+    else if (strcmp(trend, "falling") == 0)
+    {
+        tft.fillTriangle(x, y, x + 10, y, x + 5, y + 10, ILI9341_CYAN);
+    }
+    else
+    {
+        tft.fillTriangle(x, y, x, y + 10, x + 10, y + 5, ILI9341_LIGHTGREY);
+    }
+}
+
+// Points scaled to the box, like the temperatures of the last day; gaps in the history break
+// the line.
+void draw_sparkline(JsonArray points, int x, int y, int w, int h)
+{
+    float lowest = 1e9, highest = -1e9;
+    for (JsonVariant point : points)
+    {
+        if (!point.isNull())
+        {
+            lowest = min(lowest, point.as<float>());
+            highest = max(highest, point.as<float>());
+        }
+    }
+    if (points.size() < 2 || lowest > highest)
+    {
+        return;
+    }
+
+    float range = max(highest - lowest, 1.0f);
+    int step = w / (points.size() - 1);
+    int previous_x = -1, previous_y = -1;
+    for (size_t i = 0; i < points.size(); i++)
+    {
+        if (points[i].isNull())
+        {
+            previous_x = -1;
+            continue;
+        }
+
+        int point_x = x + i * step;
+        int point_y = y + h - (points[i].as<float>() - lowest) / range * h;
+        if (previous_x >= 0)
+        {
+            tft.drawLine(previous_x, previous_y, point_x, point_y, ILI9341_GREEN);
+        }
+        tft.fillCircle(point_x, point_y, 1, ILI9341_GREEN);
+        previous_x = point_x;
+        previous_y = point_y;
+    }
+}
+
+// The GFX font has no UTF-8; its code page 437 has the degree sign at 0xF7.
+String screen_text(const char *text)
+{
+    String converted = text;
+    converted.replace("\xC2\xB0", "\xF7");
+    return converted;
+}
+
+// Clears the screen, titles it with the page's location and lets `pages.draw` fill in the rest.
+void draw_page(Pages &pages)
+{
+    if (pages.count == 0)
+    {
+        return;
+    }
+
+    JsonDocument doc;
+    DeserializationError error = deserializeJson(doc, pages.pages[pages.current]);
+    if (error)
+    {
+        Serial.printf("Invalid page: %s\n", error.c_str());
+        return;
+    }
+    pages.shown_at = millis();
+
+    tft.fillScreen(ILI9341_BLACK);
+    tft.cp437(true);
+    tft.setTextColor(ILI9341_WHITE);
+    tft.setTextSize(2);
+    tft.setCursor(0, 0);
+    tft.print(screen_text(doc["location"] | ""));
+    if (pages.count > 1)
+    {
+        tft.setTextSize(1);
+        tft.setCursor(tft.width() - 24, 4);
+        tft.printf("%u/%u", pages.current + 1, pages.count);
+    }
+
+    pages.draw(doc);
+    tft.setTextColor(ILI9341_WHITE);
+    tft.cp437(false);
+}
+
+void draw_weather_page(JsonDocument &doc)
+{
+    String temp_unit = screen_text(doc["units"]["temp"] | "");
+    const char *wind_unit = doc["units"]["wind"] | "";
+    const char *precip_unit = doc["units"]["precip"] | "";
+
+    draw_weather_icon(doc["icon"] | "cloudy", 4, 24, 56);
+    tft.setTextSize(3);
+    tft.setCursor(70, 28);
+    tft.print(doc["temp"].as<float>(), 1);
+    tft.print(temp_unit);
+    if (!doc["trend"].isNull())
+    {
+        draw_trend_arrow(doc["trend"], tft.getCursorX() + 4, 32);
+        draw_sparkline(doc["spark"].as<JsonArray>(), 220, 28, 96, 32);
+    }
+    tft.setTextSize(1);
+    tft.setCursor(70, 56);
+    tft.print(doc["condition"] | "");
+    if (!doc["min"].isNull())
+    {
+        tft.setCursor(70, 68);
+        tft.printf("Today %.1f", doc["min"].as<float>());
+        tft.print(temp_unit);
+        tft.printf(" - %.1f", doc["max"].as<float>());
+        tft.print(temp_unit);
+    }
+
+    tft.setCursor(0, 86);
+    tft.printf("Feels %.1f", doc["feels"].as<float>());
+    tft.print(temp_unit);
+    tft.printf("  Humidity %d%%\n", doc["humidity"].as<int>());
+    tft.printf("Wind %.1f %s %s  Precip %.1f %s\n", doc["wind"].as<float>(), wind_unit, doc["windDir"] | "",
+               doc["precip"].as<float>(), precip_unit);
+    tft.printf("Sunrise %s  Sunset %s", doc["sunrise"] | "--:--", doc["sunset"] | "--:--");
+
+    JsonArray hours = doc["hours"].as<JsonArray>();
+    int column = hours.size() > 0 ? tft.width() / hours.size() : 0;
+    int i = 0;
+    for (JsonObject hour : hours)
+    {
+        int x = i++ * column;
+        tft.setCursor(x + 4, 118);
+        tft.printf("%s:00", hour["hour"] | "--");
+        draw_weather_icon(hour["icon"] | "cloudy", x + 6, 128, 24);
+        tft.setCursor(x + 4, 154);
+        tft.printf("%d", hour["temp"].as<int>());
+        tft.print(temp_unit);
+        if (!hour["pop"].isNull())
+        {
+            tft.printf(" %d%%", hour["pop"].as<int>());
+        }
+    }
+
+    JsonArray days = doc["days"].as<JsonArray>();
+    column = days.size() > 0 ? tft.width() / days.size() : 0;
+    i = 0;
+    for (JsonObject day : days)
+    {
+        int x = i++ * column;
+        tft.setCursor(x + 4, 172);
+        tft.print(day["day"] | "");
+        draw_weather_icon(day["icon"] | "cloudy", x + 4, 182, 28);
+        tft.setCursor(x + 4, 214);
+        tft.printf("%d/%d", day["hi"].as<int>(), day["lo"].as<int>());
+    }
+}
+
+// Colors of the European AQI bands, as the server names them.
+uint16_t band_color(const char *band)
+{
+    if (strcmp(band, "good") == 0)
+        return ILI9341_GREEN;
+    if (strcmp(band, "fair") == 0)
+        return ILI9341_GREENYELLOW;
+    if (strcmp(band, "moderate") == 0)
+        return ILI9341_YELLOW;
+    if (strcmp(band, "poor") == 0)
+        return ILI9341_ORANGE;
+    if (strcmp(band, "very_poor") == 0)
+        return ILI9341_RED;
+    if (strcmp(band, "extremely_poor") == 0)
+        return ILI9341_MAGENTA;
+    return ILI9341_WHITE;
+}
+
+// "PM2.5  12.3 ug/m3" in the color of its band.
+void draw_air_quality_level(const char *label, JsonObject level, const char *unit)
+{
+    if (level.isNull())
+    {
+        return;
+    }
+
+    tft.setTextColor(band_color(level["band"] | ""));
+    tft.printf("%-8s %6.1f %s\n", label, level["value"].as<float>(), unit);
+}
+
+void draw_air_quality_page(JsonDocument &doc)
+{
+    JsonObject aqi = doc["aqi"];
+    if (!aqi.isNull())
+    {
+        uint16_t color = band_color(aqi["band"] | "");
+        tft.fillRoundRect(4, 28, 96, 56, 6, color);
+        tft.setTextColor(ILI9341_BLACK);
+        tft.setTextSize(3);
+        tft.setCursor(16, 36);
+        tft.print(aqi["value"].as<int>());
+        tft.setTextSize(1);
+        tft.setCursor(16, 70);
+        tft.print("European AQI");
+
+        String band = aqi["band"] | "";
+        band.replace("_", " ");
+        tft.setTextColor(color);
+        tft.setTextSize(2);
+        tft.setCursor(112, 44);
+        tft.print(band);
+    }
+
+    tft.setTextSize(2);
+    tft.setCursor(0, 96);
+    draw_air_quality_level("PM2.5", doc["pm25"], "ug/m3");
+    draw_air_quality_level("PM10", doc["pm10"], "ug/m3");
+
+    tft.setTextSize(1);
+    tft.println();
+    JsonArray pollen = doc["pollen"].as<JsonArray>();
+    tft.setTextColor(ILI9341_WHITE);
+    tft.println(pollen.size() > 0 ? "Pollen (grains/m3)" : "No pollen");
+    for (JsonObject level : pollen)
+    {
+        String kind = level["kind"] | "";
+        kind[0] = toupper(kind[0]);
+        draw_air_quality_level(kind.c_str(), level, "");
+    }
+}
+
+// Redraws only when the page is on screen or its app wasn't.
+void store_page(Pages &pages, uint8_t index, uint8_t count, const char *payload)
+{
+    if (index >= MAX_PAGES)
+    {
+        return;
+    }
+
+    pages.pages[index] = payload;
+    pages.count = min(count, MAX_PAGES);
+    if (pages.current >= pages.count)
+    {
+        pages.current = 0;
+    }
+
+    if (pages_on_screen != &pages || index == pages.current)
+    {
+        pages_on_screen = &pages;
+        draw_page(pages);
+    }
+}
+
+// The server formats P/L with a leading '-' on losses and '+' on gains.
+uint16_t profit_color(const char *profit)
+{
+    return profit[0] == '-' ? ILI9341_RED : profit[0] == '+' ? ILI9341_GREEN : ILI9341_WHITE;
+}
+
+// The account, its floating P/L and the symbols with the largest P/L, one row each. Amounts come
+// formatted for the account's locale, in `currency`.
+void draw_portfolio_page(JsonDocument &doc)
+{
+    tft.print("XTB");
+    bool demo = strcmp(doc["account"] | "real", "demo") == 0;
+    tft.fillRoundRect(tft.width() - 52, 0, 52, 18, 4, demo ? ILI9341_ORANGE : ILI9341_BLUE);
+    tft.setTextSize(1);
+    tft.setCursor(tft.width() - 42, 5);
+    tft.print(demo ? "DEMO" : "REAL");
+
+    tft.setCursor(0, 28);
+    const char *currency = doc["currency"] | "";
+    tft.printf("Balance %12s   Equity %12s %s\n", (const char *)(doc["balance"] | "-"), (const char *)(doc["equity"] | "-"), currency);
+    tft.printf("Margin level %.1f%%\n", doc["marginLevel"].as<float>());
+
+    const char *profit = doc["profit"] | "-";
+    tft.setTextSize(3);
+    tft.setCursor(0, 52);
+    tft.setTextColor(profit_color(profit));
+    tft.printf("%s %s", profit, currency);
+    tft.setTextSize(1);
+    tft.setTextColor(ILI9341_WHITE);
+    tft.setCursor(0, 80);
+    tft.printf("%u open positions", doc["positions"].as<unsigned>());
+
+    int y = 100;
+    for (JsonObject symbol : doc["symbols"].as<JsonArray>())
+    {
+        const char *symbol_profit = symbol["profit"] | "-";
+        tft.setTextSize(2);
+        tft.setTextColor(ILI9341_WHITE);
+        tft.setCursor(0, y);
+        tft.printf("%-10s x%u", (const char *)(symbol["symbol"] | ""), symbol["positions"].as<unsigned>());
+        tft.setTextColor(profit_color(symbol_profit));
+        tft.setCursor(tft.width() - 120, y);
+        tft.printf("%10s", symbol_profit);
+        y += 17;
+    }
+}
+
+uint16_t quote_color(const char *color)
+{
+    if (strcmp(color, "green") == 0)
+        return ILI9341_GREEN;
+    if (strcmp(color, "red") == 0)
+        return ILI9341_RED;
+    return ILI9341_WHITE;
+}
+
+// One row per instrument: bid and ask, then the daily change in the color the server hinted.
+void draw_watchlist_page(JsonDocument &doc)
+{
+    tft.print("Watchlist");
+    tft.setTextSize(1);
+    tft.setCursor(0, 24);
+    tft.printf("%-12s %12s %12s %10s %8s", "Symbol", "Bid", "Ask", "Change", "%");
+
+    int y = 40;
+    for (JsonObject quote : doc["quotes"].as<JsonArray>())
+    {
+        tft.setTextColor(ILI9341_WHITE);
+        tft.setCursor(0, y);
+        tft.printf("%-12s %12g %12g ", (const char *)(quote["symbol"] | ""), quote["bid"].as<double>(), quote["ask"].as<double>());
+        tft.setTextColor(quote_color(quote["color"] | ""));
+        if (quote["change"].isNull())
+        {
+            tft.printf("%10s %8s", "-", "-");
+        }
+        else
+        {
+            tft.printf("%+10g %+7.2f%%", quote["change"].as<double>(), quote["changePercent"].as<double>());
+        }
+        y += 22;
+    }
+}
+
+// Daily P/L of the last 30 days as a sparkline, with their total and the latest day.
+void draw_history_page(JsonDocument &doc)
+{
+    tft.print("P/L 30 days");
+
+    // Formatted with the currency, which the points of `pnl` are converted to.
+    const char *total = doc["total"] | "-";
+    tft.setTextSize(3);
+    tft.setCursor(0, 28);
+    tft.setTextColor(profit_color(total));
+    tft.print(total);
+
+    JsonArray pnl = doc["pnl"].as<JsonArray>();
+    JsonVariant today = pnl[pnl.size() - 1];
+    tft.setTextSize(1);
+    tft.setTextColor(ILI9341_WHITE);
+    tft.setCursor(0, 58);
+    if (today.isNull())
+    {
+        tft.print("Today -");
+    }
+    else
+    {
+        tft.printf("Today %+.2f", today.as<float>());
+    }
+
+    draw_sparkline(pnl, 8, 80, tft.width() - 16, tft.height() - 100);
+}
+
+void draw_xtb_page(JsonDocument &doc)
+{
+    if (strcmp(doc["view"] | "", "watchlist") == 0)
+    {
+        draw_watchlist_page(doc);
+    }
+    else if (strcmp(doc["view"] | "", "history") == 0)
+    {
+        draw_history_page(doc);
+    }
+    else
+    {
+        draw_portfolio_page(doc);
+    }
+}
+
+// Pages arrive one message per location; `count` drops pages for removed locations.
+void handle_page_message(Pages &pages, const char *payload)
+{
+    JsonDocument doc;
+    DeserializationError error = deserializeJson(doc, payload);
+    if (error)
+    {
+        Serial.printf("Invalid page payload: %s\n", error.c_str());
+        return;
+    }
+
+    store_page(pages, doc["index"] | 0, doc["count"] | 1, payload);
+}
+
+// The portfolio comes first; the watchlist and the history are added once they are first sent.
+void handle_xtb_message(const char *payload)
+{
+    JsonDocument doc;
+    DeserializationError error = deserializeJson(doc, payload);
+    if (error)
+    {
+        Serial.printf("Invalid XTB payload: %s\n", error.c_str());
+        return;
+    }
+
+    String view = doc["view"] | "portfolio";
+    uint8_t index = 0;
+    while (index < xtb_pages.count && xtb_page_views[index] != view)
+    {
+        index++;
+    }
+    if (index >= MAX_PAGES)
+    {
+        return;
+    }
+
+    xtb_page_views[index] = view;
+    store_page(xtb_pages, index, max<uint8_t>(xtb_pages.count, index + 1), payload);
+}
+
+// A banner over the bottom of whatever is on screen, until it is next redrawn.
+void handle_alert_message(const char *payload)
+{
+    JsonDocument doc;
+    DeserializationError error = deserializeJson(doc, payload);
+    if (error)
+    {
+        Serial.printf("Invalid alert: %s\n", error.c_str());
+        return;
+    }
+
+    const char *level = doc["level"] | "info";
+    uint16_t color = strcmp(level, "severe") == 0    ? ILI9341_RED
+                     : strcmp(level, "warning") == 0 ? ILI9341_ORANGE
+                                                     : ILI9341_BLUE;
+    Serial.printf("Alert: %s\n", doc["title"] | "");
+
+    int y = tft.height() - 36;
+    tft.fillRect(0, y, tft.width(), 36, color);
+    tft.setTextColor(ILI9341_WHITE);
+    tft.setTextSize(1);
+    tft.setCursor(4, y + 6);
+    tft.println(screen_text(doc["title"] | ""));
+    tft.setCursor(4, y + 20);
+    tft.println(screen_text(doc["message"] | ""));
+}
+
+// The first chunk starts a new cover; a missed chunk drops it until the next one.
+void handle_album_art_chunk(const char *header, const ScreenIoT::Message *message)
+{
+    uint16_t index = message->chunk_index();
+    if (index == 0)
+    {
+        JsonDocument doc;
+        DeserializationError error = deserializeJson(doc, header);
+        if (error)
+        {
+            Serial.printf("Invalid album art header: %s\n", error.c_str());
+            return;
+        }
+
+        size_t size = doc["size"] | 0;
+        if (size == 0 || size > MAX_ALBUM_ART_BYTES)
+        {
+            Serial.printf("Album art of %u bytes doesn't fit\n", (unsigned)size);
+            return;
+        }
+
+        free(album_art.data);
+        album_art.data = (uint8_t *)malloc(size);
+        album_art.size = album_art.data ? size : 0;
+        album_art.album_id = doc["albumId"] | "";
+        album_art.format = doc["format"] | "jpeg";
+        album_art.width = doc["width"] | 0;
+        album_art.height = doc["height"] | 0;
+        album_art.next_chunk = 0;
+        album_art.complete = false;
+    }
+
+    if (album_art.data == nullptr || index != album_art.next_chunk)
+    {
+        Serial.printf("Missed album art chunk %u; waiting for the next cover\n", album_art.next_chunk);
+        return;
+    }
+
+    auto data = message->data();
+    size_t offset = index * ALBUM_ART_CHUNK_SIZE;
+    if (offset + data->size() > album_art.size)
+    {
+        Serial.println("Album art chunk is larger than announced");
+        return;
+    }
+
+    memcpy(album_art.data + offset, data->data(), data->size());
+    album_art.next_chunk++;
+
+    if (album_art.next_chunk == message->chunk_count())
+    {
+        album_art.complete = true;
+        Serial.printf("Received album art for %s\n", album_art.album_id.c_str());
+        if (spotify_on_screen)
+        {
+            draw_album_art();
+        }
+    }
+}
+
 void process_message()
 {
     uint8_t buffer[1056];
@@ -214,6 +1093,48 @@ void process_message()
     }
 
     Serial.printf("App: %s\n", app);
+    if (message->data() != nullptr)
+    {
+        if (strcmp(app, "Spotify") == 0)
+        {
+            handle_album_art_chunk(payload, message);
+        }
+        return;
+    }
+
+    if (strcmp(app, "Alert") == 0)
+    {
+        handle_alert_message(payload);
+        return;
+    }
+
+    spotify_on_screen = strcmp(app, "Spotify") == 0;
+    if (spotify_on_screen)
+    {
+        pages_on_screen = nullptr;
+        handle_spotify_message(payload);
+        return;
+    }
+
+    if (strcmp(app, "Weather") == 0)
+    {
+        handle_page_message(weather_pages, payload);
+        return;
+    }
+
+    if (strcmp(app, "AirQuality") == 0)
+    {
+        handle_page_message(air_quality_pages, payload);
+        return;
+    }
+
+    if (strcmp(app, "XTB") == 0)
+    {
+        handle_xtb_message(payload);
+        return;
+    }
+    pages_on_screen = nullptr;
+
     tft.fillScreen(ILI9341_BLACK);
     tft.setCursor(0, 0);
     tft.setTextColor(ILI9341_WHITE);
@@ -235,6 +1156,7 @@ void loop()
     }
 
     try_to_reconnect();
+    poll_buttons();
 
     tft.drawCircle(SCREEN_WIDTH / 2, SCREEN_HEIGHT / 2, 32, ILI9341_GREEN);
 
@@ -242,6 +1164,17 @@ void loop()
       Serial.println("Processing message");
       process_message();
     }
+
+    if (spotify_on_screen && now_playing.playing)
+    {
+        draw_progress();
+    }
+
+    if (pages_on_screen && pages_on_screen->count > 1 && millis() - pages_on_screen->shown_at > PAGE_MS)
+    {
+        pages_on_screen->current = (pages_on_screen->current + 1) % pages_on_screen->count;
+        draw_page(*pages_on_screen);
+    }
     
 
     delay(500);
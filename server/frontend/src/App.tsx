@@ -1,26 +1,65 @@
+import { useEffect, useState } from 'react';
 import Dashboard from './components/dashboard';
+import LoginCard from './components/login-card';
 import Navbar from './components/navbar';
 import OAuthConfiguration from './components/oauth-configurations';
 import OAuthTokenExchanger from './components/oauth-token-exchanger';
+import ProviderStatusCard from './components/provider-status-card';
+import SpotifyPlayerCard from './components/spotify-player-card';
 import { ThemeProvider } from './components/theme-provider';
+import WeatherCard from './components/weather-card';
 import { Toaster } from '@/components/ui/sonner';
 import XtbLoginScreen from './components/xtb-login-card';
+import XtbWatchlistCard from './components/xtb-watchlist-card';
+import XtbAlertsCard from './components/xtb-alerts-card';
+import XtbMoneyCard from './components/xtb-money-card';
+import { getSession, logout, Session } from './lib/api/auth';
 
 function App() {
+  const [session, setSession] = useState<Session | null>(null);
+  const [loading, setLoading] = useState(true);
+
+  useEffect(() => {
+    getSession().then((session) => {
+      setSession(session);
+      setLoading(false);
+    });
+  }, []);
+
+  const handleLogout = async () => {
+    await logout();
+    setSession(null);
+  };
+
   return (
     <ThemeProvider defaultTheme="dark" storageKey="vite-ui-theme">
       <main className="w-full h-full min-h-screen flex flex-col">
-        <Navbar />
-        <section className="grid grid-cols-2 w-full h-full p-8">
-          <div className="flex flex-col gap-4">
-            <OAuthConfiguration />
-            <XtbLoginScreen />
-          </div>
-          <Dashboard />
-        </section>
+        <Navbar
+          username={session?.username}
+          onLogout={session ? handleLogout : undefined}
+        />
+        {loading ? null : session ? (
+          <section className="grid grid-cols-2 w-full h-full p-8">
+            <div className="flex flex-col gap-4">
+              <OAuthConfiguration />
+              <SpotifyPlayerCard />
+              <WeatherCard />
+              <XtbLoginScreen />
+              <XtbWatchlistCard />
+              <XtbAlertsCard />
+              <XtbMoneyCard />
+              <ProviderStatusCard />
+            </div>
+            <Dashboard />
+          </section>
+        ) : (
+          <section className="flex justify-center w-full h-full p-8">
+            <LoginCard onLogin={setSession} />
+          </section>
+        )}
       </main>
       <Toaster />
-      <OAuthTokenExchanger />
+      {session && <OAuthTokenExchanger />}
     </ThemeProvider>
   );
 }
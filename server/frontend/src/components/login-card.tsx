@@ -0,0 +1,65 @@
+import { useState } from 'react';
+import { toast } from 'sonner';
+import { login, Session } from '@/lib/api/auth';
+import { Button } from './ui/button';
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from './ui/card';
+import { Input } from './ui/input';
+import { Label } from './ui/label';
+
+interface Props {
+  onLogin: (session: Session) => void;
+}
+
+const LoginCard = ({ onLogin }: Props) => {
+  const [username, setUsername] = useState('');
+  const [password, setPassword] = useState('');
+
+  const handleLogin = async (e: React.FormEvent) => {
+    e.preventDefault();
+
+    const session = await login(username, password);
+    if (session) {
+      setPassword('');
+      onLogin(session);
+    } else {
+      toast.error('Invalid username or password', { position: 'top-right' });
+    }
+  };
+
+  return (
+    <Card className="w-[450px] h-fit p-4">
+      <CardHeader>
+        <CardTitle>Sign in</CardTitle>
+        <CardDescription>
+          Sign in with the admin account to configure your screen.
+        </CardDescription>
+      </CardHeader>
+      <CardContent>
+        <form className="flex flex-col gap-4" onSubmit={handleLogin}>
+          <Label>Username</Label>
+          <Input
+            value={username}
+            autoComplete="username"
+            onChange={(e) => setUsername(e.target.value)}
+          />
+          <Label>Password</Label>
+          <Input
+            type="password"
+            value={password}
+            autoComplete="current-password"
+            onChange={(e) => setPassword(e.target.value)}
+          />
+          <Button type="submit">Sign in</Button>
+        </form>
+      </CardContent>
+    </Card>
+  );
+};
+
+export default LoginCard;
@@ -1,10 +1,22 @@
 import { ModeToggle } from './mode-toggle';
+import { Button } from './ui/button';
 
-const Navbar = () => {
+interface Props {
+  username?: string;
+  onLogout?: () => void;
+}
+
+const Navbar = ({ username, onLogout }: Props) => {
   return (
-    <nav className="flex justify-between w-full p-4 sticky top-0 z-10 bg-green-100 drop-shadow-md dark:bg-gray-800">
+    <nav className="flex justify-between items-center gap-4 w-full p-4 sticky top-0 z-10 bg-green-100 drop-shadow-md dark:bg-gray-800">
       <h1 className="text-2xl font-bold">IoT Screen Dashboard</h1>
       <span className="flex-1" />
+      {username && <span>{username}</span>}
+      {onLogout && (
+        <Button variant="outline" onClick={onLogout}>
+          Sign out
+        </Button>
+      )}
       <ModeToggle />
     </nav>
   );
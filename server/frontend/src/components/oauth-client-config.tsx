@@ -10,35 +10,34 @@ import { Input } from './ui/input';
 import { Label } from './ui/label';
 import { Button } from './ui/button';
 import { toast } from 'sonner';
+import {
+  callbackUrl,
+  saveOAuth2Client,
+  startAuthorization,
+} from '@/lib/api/oauth2';
 
 interface Props extends React.HTMLAttributes<HTMLDivElement> {
   appName: string;
+  provider: string;
 
   clientId: string;
   clientSecret: string;
   redirectUri: string;
-  authorizeUrl: string;
-  getTokenUrl: string;
 
   onClientIdChange: (clientId: string) => void;
   onClientSecretChange: (clientSecret: string) => void;
   onRedirectUriChange: (redirectUri: string) => void;
-  onAuthorizeUrlChange: (authorizeUrl: string) => void;
-  onGetTokenUrlChange: (getTokenUrl: string) => void;
 }
 
 const OAuthClientConfig = ({
   appName,
+  provider,
   clientId,
   clientSecret,
   redirectUri,
-  authorizeUrl,
-  getTokenUrl,
   onClientIdChange,
   onClientSecretChange,
   onRedirectUriChange,
-  onAuthorizeUrlChange,
-  onGetTokenUrlChange,
   ...props
 }: Props) => {
   const handleClientIdChange = (e: React.ChangeEvent<HTMLInputElement>) => {
@@ -53,21 +52,25 @@ const OAuthClientConfig = ({
     onRedirectUriChange(e.target.value);
   };
 
-  const handleAuthorizeUrlChange = (e: React.ChangeEvent<HTMLInputElement>) => {
-    onAuthorizeUrlChange(e.target.value);
-  };
-
-  const handleGetTokenUrlChange = (e: React.ChangeEvent<HTMLInputElement>) => {
-    onGetTokenUrlChange(e.target.value);
+  const handleSave = async () => {
+    if (
+      await saveOAuth2Client(provider, { clientId, clientSecret, redirectUri })
+    ) {
+      toast.success('Client saved; connect to authorize it', {
+        position: 'top-right',
+      });
+    } else {
+      toast.error('Failed to save client', { position: 'top-right' });
+    }
   };
 
-  const handleAuthorize = () => {
-    toast('Opening authorization URL in a new tab');
-    if (!authorizeUrl) {
-      return;
+  const handleAuthorize = async () => {
+    const url = await startAuthorization(provider);
+    if (url) {
+      window.location.href = url;
+    } else {
+      toast.error('Failed to start authorization', { position: 'top-right' });
     }
-
-    window.open(authorizeUrl);
   };
 
   return (
@@ -86,34 +89,29 @@ const OAuthClientConfig = ({
           />
           <Label>Client Secret</Label>
           <Input
+            type="password"
             placeholder="Client Secret"
             value={clientSecret}
             onChange={handleClientSecretChange}
           />
           <Label>Redirect URI</Label>
           <Input
-            placeholder="Redirect URI"
+            placeholder={callbackUrl(provider)}
             value={redirectUri}
             onChange={handleRedirectUriChange}
           />
-          <Label>Get Token URL</Label>
-          <Input
-            placeholder="Get Token URL"
-            value={getTokenUrl}
-            onChange={handleGetTokenUrlChange}
-          />
         </div>
 
         <div className="flex flex-col gap-4 mt-4">
           <p className="leading-7 [&:not(:first-child)]:mt-6">
-            To authorize your OAuth client, you need to provide the
-            Authorization URL. This is the URL where the user will be redirected
-            to authorize your app.
+            Register the redirect URI with {appName}. It must point at this
+            server's callback, which finishes the authorization for you.
           </p>
-          <Label>Authorization URL</Label>
-          <Input value={authorizeUrl} onChange={handleAuthorizeUrlChange} />
+          <Button onClick={handleSave} type="button" variant="outline">
+            Save client
+          </Button>
           <Button onClick={handleAuthorize} type="button">
-            Authorize
+            Connect
           </Button>
         </div>
       </CardContent>
@@ -0,0 +1,100 @@
+import { useCallback, useEffect, useState } from 'react';
+import { toast } from 'sonner';
+import {
+  getProviders,
+  ProviderStatus,
+  setProviderRunning,
+} from '@/lib/api/providers';
+import { Badge } from './ui/badge';
+import { Button } from './ui/button';
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from './ui/card';
+
+const REFRESH_INTERVAL_MS = 10000;
+
+const stateLabels: Record<ProviderStatus['state'], string> = {
+  running: 'Running',
+  backing_off: 'Restarting',
+  stopped: 'Stopped',
+};
+
+const ProviderStatusCard = () => {
+  const [providers, setProviders] = useState<ProviderStatus[]>([]);
+
+  const refresh = useCallback(async () => {
+    const providers = await getProviders();
+    if (providers) {
+      setProviders(providers);
+    }
+  }, []);
+
+  useEffect(() => {
+    refresh();
+    const interval = setInterval(refresh, REFRESH_INTERVAL_MS);
+    return () => clearInterval(interval);
+  }, [refresh]);
+
+  const toggle = async (provider: ProviderStatus) => {
+    const running = provider.state === 'stopped';
+    if (!(await setProviderRunning(provider.name, running))) {
+      toast.error(`Failed to ${running ? 'start' : 'stop'} ${provider.name}`, {
+        position: 'top-right',
+      });
+    }
+    await refresh();
+  };
+
+  return (
+    <Card className="w-[450px]">
+      <CardHeader>
+        <CardTitle>Providers</CardTitle>
+        <CardDescription>
+          Background tasks that feed the screen
+        </CardDescription>
+      </CardHeader>
+      <CardContent className="flex flex-col gap-4">
+        {providers.map((provider) => (
+          <div key={provider.name} className="flex items-center gap-2">
+            <div className="flex flex-col flex-1">
+              <div className="flex items-center gap-2">
+                <span className="font-medium capitalize">{provider.name}</span>
+                <Badge
+                  variant={
+                    provider.state === 'running' ? 'default' : 'secondary'
+                  }
+                >
+                  {stateLabels[provider.state]}
+                </Badge>
+              </div>
+              {provider.health.status !== 'healthy' && (
+                <span className="text-sm text-muted-foreground">
+                  {provider.health.reason}
+                </span>
+              )}
+              {provider.lastPollError && (
+                <span className="text-sm text-muted-foreground">
+                  {provider.lastPollError}
+                </span>
+              )}
+              {provider.lastError && (
+                <span className="text-sm text-muted-foreground">
+                  {provider.lastError} ({provider.restarts} restarts)
+                </span>
+              )}
+            </div>
+            <Button variant="outline" onClick={() => toggle(provider)}>
+              {provider.state === 'stopped' ? 'Start' : 'Stop'}
+            </Button>
+          </div>
+        ))}
+      </CardContent>
+    </Card>
+  );
+};
+
+export default ProviderStatusCard;
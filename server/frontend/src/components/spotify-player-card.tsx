@@ -0,0 +1,68 @@
+import { useState } from 'react';
+import { toast } from 'sonner';
+import {
+  PlayerCommand,
+  sendPlayerCommand,
+  setVolume,
+} from '@/lib/api/spotify';
+import { Button } from './ui/button';
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from './ui/card';
+import { Slider } from './ui/slider';
+
+const SpotifyPlayerCard = () => {
+  const [volume, setVolumeValue] = useState(50);
+
+  const send = async (command: PlayerCommand) => {
+    if (!(await sendPlayerCommand(command))) {
+      toast.error('Spotify did not accept the command', {
+        position: 'top-right',
+      });
+    }
+  };
+
+  const commitVolume = async ([value]: number[]) => {
+    if (!(await setVolume(value))) {
+      toast.error('Failed to change volume', { position: 'top-right' });
+    }
+  };
+
+  return (
+    <Card className="w-[450px]">
+      <CardHeader>
+        <CardTitle>Spotify</CardTitle>
+        <CardDescription>Control playback on your active device</CardDescription>
+      </CardHeader>
+      <CardContent className="flex flex-col gap-4">
+        <div className="flex gap-2">
+          <Button variant="outline" onClick={() => send('previous')}>
+            Previous
+          </Button>
+          <Button onClick={() => send('toggle')}>Play / Pause</Button>
+          <Button variant="outline" onClick={() => send('next')}>
+            Next
+          </Button>
+          <Button variant="outline" onClick={() => send('like')}>
+            Like
+          </Button>
+        </div>
+        <p>Volume</p>
+        <Slider
+          min={0}
+          max={100}
+          step={5}
+          value={[volume]}
+          onValueChange={([value]) => setVolumeValue(value)}
+          onValueCommit={commitVolume}
+        />
+      </CardContent>
+    </Card>
+  );
+};
+
+export default SpotifyPlayerCard;
@@ -0,0 +1,230 @@
+import { Fragment, useCallback, useEffect, useState } from 'react';
+import { toast } from 'sonner';
+import {
+  addWeatherLocation,
+  AlertKind,
+  AlertRule,
+  deleteWeatherLocation,
+  getWeatherAlertRules,
+  getWeatherLocations,
+  getWeatherSettings,
+  saveWeatherAlertRule,
+  saveWeatherSettings,
+  Units,
+  WeatherLocation,
+  WeatherSettings,
+  WindUnit,
+} from '@/lib/api/weather';
+import { Button } from './ui/button';
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from './ui/card';
+import { Input } from './ui/input';
+import { Label } from './ui/label';
+import {
+  Select,
+  SelectContent,
+  SelectTrigger,
+  SelectValue,
+  SelectItem,
+} from './ui/select';
+import { Switch } from './ui/switch';
+
+const ALERT_LABELS: Record<AlertKind, string> = {
+  rain: 'Rain (mm)',
+  storm: 'Thunderstorm',
+  frost: 'Frost (°C)',
+  heat: 'Heat (°C)',
+};
+
+const WeatherCard = () => {
+  const [locations, setLocations] = useState<WeatherLocation[]>([]);
+  const [settings, setSettings] = useState<WeatherSettings>({
+    units: 'metric',
+    windUnit: 'kmh',
+  });
+  const [name, setName] = useState('');
+  const [rules, setRules] = useState<AlertRule[]>([]);
+
+  const refresh = useCallback(async () => {
+    const locations = await getWeatherLocations();
+    if (locations) {
+      setLocations(locations);
+    }
+  }, []);
+
+  useEffect(() => {
+    refresh();
+    getWeatherSettings().then((settings) => {
+      if (settings) {
+        setSettings(settings);
+      }
+    });
+    getWeatherAlertRules().then((rules) => {
+      if (rules) {
+        setRules(rules);
+      }
+    });
+  }, [refresh]);
+
+  const handleAdd = async () => {
+    if (!name.trim()) {
+      return;
+    }
+
+    if (await addWeatherLocation(name)) {
+      setName('');
+      await refresh();
+    } else {
+      toast.error(`Could not find ${name}`, { position: 'top-right' });
+    }
+  };
+
+  const handleDelete = async (location: WeatherLocation) => {
+    if (!(await deleteWeatherLocation(location.id))) {
+      toast.error(`Failed to remove ${location.name}`, {
+        position: 'top-right',
+      });
+    }
+    await refresh();
+  };
+
+  const updateSettings = async (settings: WeatherSettings) => {
+    setSettings(settings);
+    if (!(await saveWeatherSettings(settings))) {
+      toast.error('Failed to save weather settings', {
+        position: 'top-right',
+      });
+    }
+  };
+
+  const editRule = (rule: AlertRule) =>
+    setRules(rules.map((r) => (r.kind === rule.kind ? rule : r)));
+
+  const updateRule = async (rule: AlertRule) => {
+    editRule(rule);
+    if (!(await saveWeatherAlertRule(rule))) {
+      toast.error(`Failed to save the ${rule.kind} alert`, {
+        position: 'top-right',
+      });
+    }
+  };
+
+  return (
+    <Card className="w-[450px]">
+      <CardHeader>
+        <CardTitle>Weather</CardTitle>
+        <CardDescription>
+          Locations, units and alerts shown on the screen
+        </CardDescription>
+      </CardHeader>
+      <CardContent className="flex flex-col gap-4">
+        {locations.map((location) => (
+          <div key={location.id} className="flex items-center gap-2">
+            <span className="flex-1">
+              {location.name}
+              {location.country && (
+                <span className="text-sm text-muted-foreground">
+                  {' '}
+                  {location.country}
+                </span>
+              )}
+            </span>
+            <Button variant="outline" onClick={() => handleDelete(location)}>
+              Remove
+            </Button>
+          </div>
+        ))}
+        <div className="flex gap-2">
+          <Input
+            placeholder="City name"
+            value={name}
+            onChange={(e) => setName(e.target.value)}
+          />
+          <Button onClick={handleAdd}>Add</Button>
+        </div>
+        <Label>Units</Label>
+        <Select
+          value={settings.units}
+          onValueChange={(units) =>
+            updateSettings({ ...settings, units: units as Units })
+          }
+        >
+          <SelectTrigger>
+            <SelectValue placeholder="Select units" />
+          </SelectTrigger>
+          <SelectContent>
+            <SelectItem value="metric">Metric</SelectItem>
+            <SelectItem value="imperial">Imperial</SelectItem>
+          </SelectContent>
+        </Select>
+        <Label>Wind speed</Label>
+        <Select
+          value={settings.windUnit}
+          onValueChange={(windUnit) =>
+            updateSettings({ ...settings, windUnit: windUnit as WindUnit })
+          }
+        >
+          <SelectTrigger>
+            <SelectValue placeholder="Select wind unit" />
+          </SelectTrigger>
+          <SelectContent>
+            <SelectItem value="kmh">km/h</SelectItem>
+            <SelectItem value="ms">m/s</SelectItem>
+            <SelectItem value="mph">mph</SelectItem>
+            <SelectItem value="kn">Knots</SelectItem>
+          </SelectContent>
+        </Select>
+        <Label>Alerts</Label>
+        <div className="grid grid-cols-[auto_1fr_5rem_5rem_5rem] items-center gap-2 text-sm">
+          <span />
+          <span />
+          <span className="text-muted-foreground">Threshold</span>
+          <span className="text-muted-foreground">Within min</span>
+          <span className="text-muted-foreground">Quiet min</span>
+          {rules.map((rule) => (
+            <Fragment key={rule.kind}>
+              <Switch
+                checked={rule.enabled}
+                onCheckedChange={(enabled) => updateRule({ ...rule, enabled })}
+              />
+              <span>{ALERT_LABELS[rule.kind]}</span>
+              <Input
+                type="number"
+                value={rule.threshold}
+                disabled={rule.kind === 'storm'}
+                onChange={(e) =>
+                  editRule({ ...rule, threshold: Number(e.target.value) })
+                }
+                onBlur={() => updateRule(rule)}
+              />
+              <Input
+                type="number"
+                value={rule.withinMinutes}
+                onChange={(e) =>
+                  editRule({ ...rule, withinMinutes: Number(e.target.value) })
+                }
+                onBlur={() => updateRule(rule)}
+              />
+              <Input
+                type="number"
+                min={1}
+                value={rule.cooldownMinutes}
+                onChange={(e) =>
+                  editRule({ ...rule, cooldownMinutes: Number(e.target.value) })
+                }
+                onBlur={() => updateRule(rule)}
+              />
+            </Fragment>
+          ))}
+        </div>
+      </CardContent>
+    </Card>
+  );
+};
+
+export default WeatherCard;
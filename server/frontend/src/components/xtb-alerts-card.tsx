@@ -0,0 +1,211 @@
+import { Fragment, useCallback, useEffect, useState } from 'react';
+import { toast } from 'sonner';
+import {
+  addXtbAlertRule,
+  deleteXtbAlertRule,
+  getXtbAlertHistory,
+  getXtbAlertRules,
+  saveXtbAlertRule,
+  XtbAlert,
+  XtbAlertKind,
+  XtbAlertRule,
+} from '@/lib/api/xtb-alerts';
+import { Button } from './ui/button';
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from './ui/card';
+import { Input } from './ui/input';
+import { Label } from './ui/label';
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from './ui/select';
+import { Switch } from './ui/switch';
+
+const KIND_LABELS: Record<XtbAlertKind, string> = {
+  profit_below: 'P/L below',
+  profit_above: 'P/L above',
+  price_below: 'Price below',
+  price_above: 'Price above',
+  price_move: 'Price moves %',
+};
+
+const isPriceRule = (kind: XtbAlertKind) => kind.startsWith('price_');
+
+const NEW_RULE: Omit<XtbAlertRule, 'id'> = {
+  kind: 'profit_below',
+  symbol: null,
+  threshold: -200,
+  windowMinutes: 15,
+  cooldownMinutes: 60,
+  enabled: true,
+};
+
+const XtbAlertsCard = () => {
+  const [rules, setRules] = useState<XtbAlertRule[]>([]);
+  const [history, setHistory] = useState<XtbAlert[]>([]);
+  const [rule, setRule] = useState(NEW_RULE);
+
+  const refresh = useCallback(async () => {
+    const rules = await getXtbAlertRules();
+    if (rules) {
+      setRules(rules);
+    }
+    const history = await getXtbAlertHistory();
+    if (history) {
+      setHistory(history);
+    }
+  }, []);
+
+  useEffect(() => {
+    refresh();
+  }, [refresh]);
+
+  const handleAdd = async () => {
+    if (await addXtbAlertRule(rule)) {
+      setRule(NEW_RULE);
+      await refresh();
+    } else {
+      toast.error('Could not add the alert', { position: 'top-right' });
+    }
+  };
+
+  const editRule = (rule: XtbAlertRule) =>
+    setRules(rules.map((r) => (r.id === rule.id ? rule : r)));
+
+  const updateRule = async (rule: XtbAlertRule) => {
+    editRule(rule);
+    if (!(await saveXtbAlertRule(rule))) {
+      toast.error('Failed to save the alert', { position: 'top-right' });
+    }
+  };
+
+  const handleDelete = async (rule: XtbAlertRule) => {
+    if (!(await deleteXtbAlertRule(rule.id))) {
+      toast.error('Failed to remove the alert', { position: 'top-right' });
+    }
+    await refresh();
+  };
+
+  return (
+    <Card className="w-[450px]">
+      <CardHeader>
+        <CardTitle>XTB Alerts</CardTitle>
+        <CardDescription>
+          Price and P/L thresholds that alert on the screen
+        </CardDescription>
+      </CardHeader>
+      <CardContent className="flex flex-col gap-4">
+        <div className="grid grid-cols-[auto_1fr_5rem_5rem_auto] items-center gap-2 text-sm">
+          <span />
+          <span />
+          <span className="text-muted-foreground">Threshold</span>
+          <span className="text-muted-foreground">Quiet min</span>
+          <span />
+          {rules.map((rule) => (
+            <Fragment key={rule.id}>
+              <Switch
+                checked={rule.enabled}
+                onCheckedChange={(enabled) => updateRule({ ...rule, enabled })}
+              />
+              <span>
+                {KIND_LABELS[rule.kind]} {rule.symbol}
+                {rule.kind === 'price_move' && ` in ${rule.windowMinutes} min`}
+              </span>
+              <Input
+                type="number"
+                value={rule.threshold}
+                onChange={(e) =>
+                  editRule({ ...rule, threshold: Number(e.target.value) })
+                }
+                onBlur={() => updateRule(rule)}
+              />
+              <Input
+                type="number"
+                min={1}
+                value={rule.cooldownMinutes}
+                onChange={(e) =>
+                  editRule({ ...rule, cooldownMinutes: Number(e.target.value) })
+                }
+                onBlur={() => updateRule(rule)}
+              />
+              <Button variant="outline" onClick={() => handleDelete(rule)}>
+                Remove
+              </Button>
+            </Fragment>
+          ))}
+        </div>
+        <Label>New alert</Label>
+        <Select
+          value={rule.kind}
+          onValueChange={(kind) =>
+            setRule({ ...rule, kind: kind as XtbAlertKind })
+          }
+        >
+          <SelectTrigger>
+            <SelectValue placeholder="Select alert" />
+          </SelectTrigger>
+          <SelectContent>
+            {Object.entries(KIND_LABELS).map(([kind, label]) => (
+              <SelectItem key={kind} value={kind}>
+                {label}
+              </SelectItem>
+            ))}
+          </SelectContent>
+        </Select>
+        <div className="flex gap-2">
+          {isPriceRule(rule.kind) && (
+            <Input
+              placeholder="Symbol"
+              value={rule.symbol ?? ''}
+              onChange={(e) => setRule({ ...rule, symbol: e.target.value })}
+            />
+          )}
+          <Input
+            type="number"
+            placeholder="Threshold"
+            value={rule.threshold}
+            onChange={(e) =>
+              setRule({ ...rule, threshold: Number(e.target.value) })
+            }
+          />
+          {rule.kind === 'price_move' && (
+            <Input
+              type="number"
+              placeholder="Within min"
+              value={rule.windowMinutes}
+              onChange={(e) =>
+                setRule({ ...rule, windowMinutes: Number(e.target.value) })
+              }
+            />
+          )}
+          <Button onClick={handleAdd}>Add</Button>
+        </div>
+        {history.length > 0 && (
+          <>
+            <Label>Recent alerts</Label>
+            <ul className="flex flex-col gap-1 text-sm">
+              {history.slice(0, 10).map((alert) => (
+                <li key={alert.id}>
+                  <span className="text-muted-foreground">
+                    {new Date(`${alert.firedAt}Z`).toLocaleString()}
+                  </span>{' '}
+                  {alert.title}: {alert.message}
+                </li>
+              ))}
+            </ul>
+          </>
+        )}
+      </CardContent>
+    </Card>
+  );
+};
+
+export default XtbAlertsCard;
@@ -1,3 +1,4 @@
+import { useEffect, useState } from 'react';
 import useAppStore from '@/lib/store/store';
 import { Button } from './ui/button';
 import {
@@ -9,14 +10,37 @@ import {
 } from './ui/card';
 import { Input } from './ui/input';
 import { Label } from './ui/label';
-import { sendXtbCredentials } from '@/lib/api/send-xtb-credentials';
+import {
+  getXtbStatus,
+  sendXtbCredentials,
+  XtbAccountType,
+  XtbStatus,
+} from '@/lib/api/send-xtb-credentials';
 import { toast } from 'sonner';
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from './ui/select';
 
 const XtbLoginScreen = () => {
   const xtbUserId = useAppStore((state) => state.xtbUserId);
   const xtbPassword = useAppStore((state) => state.xtbPassword);
   const setXtbUserId = useAppStore((state) => state.setXtbUserId);
   const setXtbPassword = useAppStore((state) => state.setXtbPassword);
+  const [accountType, setAccountType] = useState<XtbAccountType>('demo');
+  const [status, setStatus] = useState<XtbStatus | null>(null);
+
+  useEffect(() => {
+    getXtbStatus().then((status) => {
+      setStatus(status);
+      if (status?.accountType) {
+        setAccountType(status.accountType);
+      }
+    });
+  }, []);
 
   const handleSaveCredentials = async () => {
     if (!xtbUserId || !xtbPassword) {
@@ -27,11 +51,16 @@ const XtbLoginScreen = () => {
     }
 
     if (
-      await sendXtbCredentials({ userId: xtbUserId, password: xtbPassword })
+      await sendXtbCredentials({
+        userId: xtbUserId,
+        password: xtbPassword,
+        accountType,
+      })
     ) {
       toast.success('Credentials saved successfully', {
         position: 'top-right',
       });
+      setStatus(await getXtbStatus());
     } else {
       toast.error('Failed to save credentials', { position: 'top-right' });
     }
@@ -44,6 +73,12 @@ const XtbLoginScreen = () => {
         <CardDescription>
           Enter your XTB credentials to see your account information.
         </CardDescription>
+        {status?.configured && (
+          <p className="text-sm text-muted-foreground">
+            Using the {status.accountType} account {status.userId}
+            {status.health?.reason && ` (${status.health.reason})`}
+          </p>
+        )}
       </CardHeader>
       <CardContent>
         <div className="flex flex-col gap-4 mb-4">
@@ -59,6 +94,19 @@ const XtbLoginScreen = () => {
             value={xtbPassword}
             onChange={(e) => setXtbPassword(e.target.value)}
           />
+          <Label>Account</Label>
+          <Select
+            value={accountType}
+            onValueChange={(value) => setAccountType(value as XtbAccountType)}
+          >
+            <SelectTrigger>
+              <SelectValue placeholder="Select account type" />
+            </SelectTrigger>
+            <SelectContent>
+              <SelectItem value="demo">Demo</SelectItem>
+              <SelectItem value="real">Real</SelectItem>
+            </SelectContent>
+          </Select>
         </div>
         <Button onClick={handleSaveCredentials}>Save credentials</Button>
       </CardContent>
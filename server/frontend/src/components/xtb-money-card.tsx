@@ -0,0 +1,175 @@
+import { useCallback, useEffect, useState } from 'react';
+import { toast } from 'sonner';
+import { Button } from './ui/button';
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from './ui/card';
+import { Input } from './ui/input';
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from './ui/select';
+import {
+  deleteXtbFxRate,
+  getXtbFxRates,
+  getXtbMoneySettings,
+  saveXtbFxRate,
+  saveXtbMoneySettings,
+  XtbFxRate,
+  XtbMoneySettings,
+} from '@/lib/api/xtb-money';
+
+const LOCALE_LABELS: Record<XtbMoneySettings['locale'], string> = {
+  en: 'English (1,234.56)',
+  pl: 'Polish (1 234,56)',
+};
+
+const XtbMoneyCard = () => {
+  const [settings, setSettings] = useState<XtbMoneySettings>({
+    locale: 'en',
+    displayCurrency: null,
+  });
+  const [rates, setRates] = useState<XtbFxRate[]>([]);
+  const [rate, setRate] = useState({ from: '', to: '', rate: '' });
+
+  const refresh = useCallback(async () => {
+    const [settings, rates] = await Promise.all([
+      getXtbMoneySettings(),
+      getXtbFxRates(),
+    ]);
+    if (settings) {
+      setSettings(settings);
+    }
+    if (rates) {
+      setRates(rates);
+    }
+  }, []);
+
+  useEffect(() => {
+    refresh();
+  }, [refresh]);
+
+  const handleSave = async () => {
+    const saved = await saveXtbMoneySettings(settings);
+    if (saved) {
+      setSettings(saved);
+      toast.success('Money settings saved', { position: 'top-right' });
+    } else {
+      toast.error('Could not save the money settings', {
+        position: 'top-right',
+      });
+    }
+  };
+
+  const handleAddRate = async () => {
+    const value = Number(rate.rate);
+    if (!rate.from.trim() || !rate.to.trim() || !(value > 0)) {
+      return;
+    }
+
+    if (await saveXtbFxRate({ from: rate.from, to: rate.to, rate: value })) {
+      setRate({ from: '', to: '', rate: '' });
+    } else {
+      toast.error(`Could not save the ${rate.from}/${rate.to} rate`, {
+        position: 'top-right',
+      });
+    }
+    await refresh();
+  };
+
+  const handleDeleteRate = async ({ from, to }: XtbFxRate) => {
+    if (!(await deleteXtbFxRate(from, to))) {
+      toast.error(`Failed to remove the ${from}/${to} rate`, {
+        position: 'top-right',
+      });
+    }
+    await refresh();
+  };
+
+  return (
+    <Card className="w-[450px]">
+      <CardHeader>
+        <CardTitle>XTB Money</CardTitle>
+        <CardDescription>
+          How amounts are written on the screen, and the rates converting them
+        </CardDescription>
+      </CardHeader>
+      <CardContent className="flex flex-col gap-4">
+        <div className="flex gap-2">
+          <Select
+            value={settings.locale}
+            onValueChange={(locale) =>
+              setSettings({
+                ...settings,
+                locale: locale as XtbMoneySettings['locale'],
+              })
+            }
+          >
+            <SelectTrigger>
+              <SelectValue placeholder="Select locale" />
+            </SelectTrigger>
+            <SelectContent>
+              {Object.entries(LOCALE_LABELS).map(([locale, label]) => (
+                <SelectItem key={locale} value={locale}>
+                  {label}
+                </SelectItem>
+              ))}
+            </SelectContent>
+          </Select>
+          <Input
+            placeholder="Display currency"
+            value={settings.displayCurrency ?? ''}
+            onChange={(e) =>
+              setSettings({
+                ...settings,
+                displayCurrency: e.target.value || null,
+              })
+            }
+          />
+          <Button onClick={handleSave}>Save</Button>
+        </div>
+        {rates.map((rate) => (
+          <div
+            key={`${rate.from}/${rate.to}`}
+            className="flex items-center gap-2"
+          >
+            <span className="flex-1">
+              1 {rate.from} = {rate.rate} {rate.to}
+            </span>
+            <Button variant="outline" onClick={() => handleDeleteRate(rate)}>
+              Remove
+            </Button>
+          </div>
+        ))}
+        <div className="flex gap-2">
+          <Input
+            placeholder="From"
+            value={rate.from}
+            onChange={(e) => setRate({ ...rate, from: e.target.value })}
+          />
+          <Input
+            placeholder="To"
+            value={rate.to}
+            onChange={(e) => setRate({ ...rate, to: e.target.value })}
+          />
+          <Input
+            type="number"
+            placeholder="Rate"
+            value={rate.rate}
+            onChange={(e) => setRate({ ...rate, rate: e.target.value })}
+          />
+          <Button onClick={handleAddRate}>Add</Button>
+        </div>
+      </CardContent>
+    </Card>
+  );
+};
+
+export default XtbMoneyCard;
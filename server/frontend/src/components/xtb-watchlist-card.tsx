@@ -0,0 +1,84 @@
+import { useCallback, useEffect, useState } from 'react';
+import { toast } from 'sonner';
+import { Button } from './ui/button';
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from './ui/card';
+import { Input } from './ui/input';
+import {
+  addXtbWatchlistSymbol,
+  deleteXtbWatchlistSymbol,
+  getXtbWatchlist,
+} from '@/lib/api/xtb-watchlist';
+
+const XtbWatchlistCard = () => {
+  const [symbols, setSymbols] = useState<string[]>([]);
+  const [symbol, setSymbol] = useState('');
+
+  const refresh = useCallback(async () => {
+    const symbols = await getXtbWatchlist();
+    if (symbols) {
+      setSymbols(symbols);
+    }
+  }, []);
+
+  useEffect(() => {
+    refresh();
+  }, [refresh]);
+
+  const handleAdd = async () => {
+    if (!symbol.trim()) {
+      return;
+    }
+
+    const symbols = await addXtbWatchlistSymbol(symbol);
+    if (symbols) {
+      setSymbol('');
+      setSymbols(symbols);
+    } else {
+      toast.error(`Could not add ${symbol}`, { position: 'top-right' });
+    }
+  };
+
+  const handleDelete = async (symbol: string) => {
+    if (!(await deleteXtbWatchlistSymbol(symbol))) {
+      toast.error(`Failed to remove ${symbol}`, { position: 'top-right' });
+    }
+    await refresh();
+  };
+
+  return (
+    <Card className="w-[450px]">
+      <CardHeader>
+        <CardTitle>XTB Watchlist</CardTitle>
+        <CardDescription>
+          Instruments whose live prices are shown on the screen
+        </CardDescription>
+      </CardHeader>
+      <CardContent className="flex flex-col gap-4">
+        {symbols.map((symbol) => (
+          <div key={symbol} className="flex items-center gap-2">
+            <span className="flex-1">{symbol}</span>
+            <Button variant="outline" onClick={() => handleDelete(symbol)}>
+              Remove
+            </Button>
+          </div>
+        ))}
+        <div className="flex gap-2">
+          <Input
+            placeholder="Symbol, e.g. EURPLN"
+            value={symbol}
+            onChange={(e) => setSymbol(e.target.value)}
+          />
+          <Button onClick={handleAdd}>Add</Button>
+        </div>
+      </CardContent>
+    </Card>
+  );
+};
+
+export default XtbWatchlistCard;
@@ -0,0 +1,46 @@
+import { base, setCsrfToken } from './base';
+
+export type Session = {
+  username: string;
+  csrfToken: string;
+};
+
+export const login = async (username: string, password: string) => {
+  try {
+    const response = await base.post<Session>(
+      '/auth/login',
+      { username, password },
+      {
+        headers: {
+          'Content-Type': 'application/json',
+        },
+      }
+    );
+    setCsrfToken(response.data.csrfToken);
+    return response.data;
+  } catch (error) {
+    console.error('Error during login:', error);
+    return null;
+  }
+};
+
+export const logout = async () => {
+  try {
+    await base.post('/auth/logout');
+  } catch (error) {
+    console.error('Error during logout:', error);
+  } finally {
+    setCsrfToken(null);
+  }
+};
+
+export const getSession = async () => {
+  try {
+    const response = await base.get<Session>('/auth/session');
+    setCsrfToken(response.data.csrfToken);
+    return response.data;
+  } catch {
+    setCsrfToken(null);
+    return null;
+  }
+};
@@ -5,7 +5,17 @@ if (!BASE_URL) {
   throw new Error('VITE_BASE_URL is not set');
 }
 
+export const CSRF_HEADER = 'X-CSRF-Token';
+
 export const base = axios.create({
   baseURL: BASE_URL,
-  withCredentials: false,
+  withCredentials: true,
 });
+
+export const setCsrfToken = (csrfToken: string | null) => {
+  if (csrfToken) {
+    base.defaults.headers.common[CSRF_HEADER] = csrfToken;
+  } else {
+    delete base.defaults.headers.common[CSRF_HEADER];
+  }
+};
@@ -0,0 +1,50 @@
+import { base } from './base';
+
+export type SaveOAuth2ClientPayload = {
+  clientId: string;
+  clientSecret: string;
+  redirectUri: string;
+  // Override the provider's defaults; required for the `generic` provider.
+  authorizeUrl?: string;
+  getTokenUrl?: string;
+  scopes?: string;
+};
+
+export const saveOAuth2Client = async (
+  provider: string,
+  payload: SaveOAuth2ClientPayload
+) => {
+  try {
+    const response = await base.put(`/oauth2/${provider}/client`, payload, {
+      headers: {
+        'Content-Type': 'application/json',
+      },
+    });
+    if (response.status === 200) {
+      return true;
+    } else {
+      console.error('Unexpected response:', response);
+      return false;
+    }
+  } catch (error) {
+    console.error('Error during PUT request:', error);
+    return false;
+  }
+};
+
+// The server builds the consent URL (state + PKCE); the browser is sent there. A POST, so it
+// carries the session's CSRF token.
+export const startAuthorization = async (provider: string) => {
+  try {
+    const response = await base.post<{ url: string }>(
+      `/oauth2/${provider}/authorize`
+    );
+    return response.data.url;
+  } catch (error) {
+    console.error('Error during POST request:', error);
+    return null;
+  }
+};
+
+export const callbackUrl = (provider: string) =>
+  `${base.defaults.baseURL}/oauth2/${provider}/callback`;
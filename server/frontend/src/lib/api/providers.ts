@@ -0,0 +1,52 @@
+import { base } from './base';
+
+export type ProviderState = 'running' | 'backing_off' | 'stopped';
+
+export type ConfigField = {
+  key: string;
+  label: string;
+  kind: 'text' | 'secret' | 'number' | 'url';
+  required: boolean;
+};
+
+export type SourceHealth =
+  | { status: 'healthy' }
+  | { status: 'unconfigured'; reason: string }
+  | { status: 'degraded'; reason: string };
+
+export type ProviderStatus = {
+  name: string;
+  state: ProviderState;
+  restarts: number;
+  consecutiveFailures: number;
+  lastError: string | null;
+  lastFailureAt: string | null;
+  nextRestartAt: string | null;
+  app: string;
+  mode: 'poll' | 'stream';
+  intervalSecs: number | null;
+  configSchema: ConfigField[];
+  health: SourceHealth;
+  lastUpdateAt: string | null;
+  lastPollError: string | null;
+};
+
+export const getProviders = async (): Promise<ProviderStatus[] | null> => {
+  try {
+    const response = await base.get<ProviderStatus[]>('/providers');
+    return response.data;
+  } catch (error) {
+    console.error('Error during GET request:', error);
+    return null;
+  }
+};
+
+export const setProviderRunning = async (name: string, running: boolean) => {
+  try {
+    await base.post(`/providers/${name}/${running ? 'start' : 'stop'}`);
+    return true;
+  } catch (error) {
+    console.error('Error during POST request:', error);
+    return false;
+  }
+};
@@ -1,8 +1,28 @@
 import { base } from './base';
 
+export type XtbAccountType = 'demo' | 'real';
+
 export type SendXtbCredentialsPayload = {
   userId: string;
   password: string;
+  accountType: XtbAccountType;
+};
+
+export type XtbStatus = {
+  configured: boolean;
+  userId: string | null;
+  accountType: XtbAccountType | null;
+  health: { status: string; reason?: string } | null;
+};
+
+export const getXtbStatus = async (): Promise<XtbStatus | null> => {
+  try {
+    const response = await base.get<XtbStatus>('/xtb/status');
+    return response.data;
+  } catch (error) {
+    console.error('Error during GET request:', error);
+    return null;
+  }
 };
 
 export const sendXtbCredentials = async (
@@ -0,0 +1,39 @@
+import { base } from './base';
+
+export type PlayerCommand =
+  | 'play'
+  | 'pause'
+  | 'toggle'
+  | 'next'
+  | 'previous'
+  | 'like'
+  | 'volume_up'
+  | 'volume_down';
+
+export const sendPlayerCommand = async (command: PlayerCommand) => {
+  try {
+    await base.post(`/spotify/player/${command}`);
+    return true;
+  } catch (error) {
+    console.error('Error during POST request:', error);
+    return false;
+  }
+};
+
+export const setVolume = async (volumePercent: number) => {
+  try {
+    await base.put(
+      '/spotify/player/volume',
+      { volumePercent },
+      {
+        headers: {
+          'Content-Type': 'application/json',
+        },
+      }
+    );
+    return true;
+  } catch (error) {
+    console.error('Error during PUT request:', error);
+    return false;
+  }
+};
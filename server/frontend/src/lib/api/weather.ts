@@ -0,0 +1,120 @@
+import { base } from './base';
+
+export type WeatherLocation = {
+  id: number;
+  name: string;
+  country: string;
+  latitude: number;
+  longitude: number;
+  timezone: string;
+};
+
+export type Units = 'metric' | 'imperial';
+export type WindUnit = 'kmh' | 'ms' | 'mph' | 'kn';
+
+export type WeatherSettings = {
+  units: Units;
+  windUnit: WindUnit;
+};
+
+export const getWeatherLocations = async (): Promise<
+  WeatherLocation[] | null
+> => {
+  try {
+    const response = await base.get<WeatherLocation[]>('/weather/locations');
+    return response.data;
+  } catch (error) {
+    console.error('Error during GET request:', error);
+    return null;
+  }
+};
+
+export const addWeatherLocation = async (
+  name: string
+): Promise<WeatherLocation | null> => {
+  try {
+    const response = await base.post<WeatherLocation>(
+      '/weather/locations',
+      { name },
+      {
+        headers: {
+          'Content-Type': 'application/json',
+        },
+      }
+    );
+    return response.data;
+  } catch (error) {
+    console.error('Error during POST request:', error);
+    return null;
+  }
+};
+
+export const deleteWeatherLocation = async (id: number) => {
+  try {
+    await base.delete(`/weather/locations/${id}`);
+    return true;
+  } catch (error) {
+    console.error('Error during DELETE request:', error);
+    return false;
+  }
+};
+
+export const getWeatherSettings =
+  async (): Promise<WeatherSettings | null> => {
+    try {
+      const response = await base.get<WeatherSettings>('/weather/settings');
+      return response.data;
+    } catch (error) {
+      console.error('Error during GET request:', error);
+      return null;
+    }
+  };
+
+export const saveWeatherSettings = async (settings: WeatherSettings) => {
+  try {
+    await base.put('/weather/settings', settings, {
+      headers: {
+        'Content-Type': 'application/json',
+      },
+    });
+    return true;
+  } catch (error) {
+    console.error('Error during PUT request:', error);
+    return false;
+  }
+};
+
+export type AlertKind = 'rain' | 'storm' | 'frost' | 'heat';
+
+/** Thresholds are metric: mm for rain, °C for frost and heat. */
+export type AlertRule = {
+  kind: AlertKind;
+  enabled: boolean;
+  threshold: number;
+  withinMinutes: number;
+  cooldownMinutes: number;
+};
+
+export const getWeatherAlertRules = async (): Promise<AlertRule[] | null> => {
+  try {
+    const response = await base.get<AlertRule[]>('/weather/alert-rules');
+    return response.data;
+  } catch (error) {
+    console.error('Error during GET request:', error);
+    return null;
+  }
+};
+
+export const saveWeatherAlertRule = async ({ kind, ...rule }: AlertRule) => {
+  try {
+    await base.put(`/weather/alert-rules/${kind}`, rule, {
+      headers: {
+        'Content-Type': 'application/json',
+      },
+    });
+    return true;
+  } catch (error) {
+    console.error('Error during PUT request:', error);
+    return false;
+  }
+};
@@ -0,0 +1,88 @@
+import { base } from './base';
+
+export type XtbAlertKind =
+  | 'profit_below'
+  | 'profit_above'
+  | 'price_below'
+  | 'price_above'
+  | 'price_move';
+
+/** Profits are in the account currency; `price_move` thresholds are percent. */
+export type XtbAlertRule = {
+  id: number;
+  kind: XtbAlertKind;
+  symbol: string | null;
+  threshold: number;
+  windowMinutes: number;
+  cooldownMinutes: number;
+  enabled: boolean;
+};
+
+export type XtbAlert = {
+  id: number;
+  ruleId: number | null;
+  firedAt: string;
+  level: 'info' | 'warning' | 'severe';
+  title: string;
+  message: string;
+};
+
+export const getXtbAlertRules = async (): Promise<XtbAlertRule[] | null> => {
+  try {
+    const response = await base.get<XtbAlertRule[]>('/xtb/alert-rules');
+    return response.data;
+  } catch (error) {
+    console.error('Error during GET request:', error);
+    return null;
+  }
+};
+
+export const addXtbAlertRule = async (
+  rule: Omit<XtbAlertRule, 'id'>
+): Promise<XtbAlertRule | null> => {
+  try {
+    const response = await base.post<XtbAlertRule>('/xtb/alert-rules', rule, {
+      headers: {
+        'Content-Type': 'application/json',
+      },
+    });
+    return response.data;
+  } catch (error) {
+    console.error('Error during POST request:', error);
+    return null;
+  }
+};
+
+export const saveXtbAlertRule = async ({ id, ...rule }: XtbAlertRule) => {
+  try {
+    await base.put(`/xtb/alert-rules/${id}`, rule, {
+      headers: {
+        'Content-Type': 'application/json',
+      },
+    });
+    return true;
+  } catch (error) {
+    console.error('Error during PUT request:', error);
+    return false;
+  }
+};
+
+export const deleteXtbAlertRule = async (id: number) => {
+  try {
+    await base.delete(`/xtb/alert-rules/${id}`);
+    return true;
+  } catch (error) {
+    console.error('Error during DELETE request:', error);
+    return false;
+  }
+};
+
+export const getXtbAlertHistory = async (): Promise<XtbAlert[] | null> => {
+  try {
+    const response = await base.get<XtbAlert[]>('/xtb/alert-history');
+    return response.data;
+  } catch (error) {
+    console.error('Error during GET request:', error);
+    return null;
+  }
+};
@@ -0,0 +1,81 @@
+import { base } from './base';
+
+export type XtbMoneySettings = {
+  locale: 'en' | 'pl';
+  /** Amounts stay in the account currency when null or without a rate. */
+  displayCurrency: string | null;
+};
+
+/** One `from` buys `rate` of `to`. */
+export type XtbFxRate = {
+  from: string;
+  to: string;
+  rate: number;
+};
+
+export const getXtbMoneySettings =
+  async (): Promise<XtbMoneySettings | null> => {
+    try {
+      const response = await base.get<XtbMoneySettings>('/xtb/money');
+      return response.data;
+    } catch (error) {
+      console.error('Error during GET request:', error);
+      return null;
+    }
+  };
+
+export const saveXtbMoneySettings = async (
+  settings: XtbMoneySettings
+): Promise<XtbMoneySettings | null> => {
+  try {
+    const response = await base.put<XtbMoneySettings>('/xtb/money', settings, {
+      headers: {
+        'Content-Type': 'application/json',
+      },
+    });
+    return response.data;
+  } catch (error) {
+    console.error('Error during PUT request:', error);
+    return null;
+  }
+};
+
+export const getXtbFxRates = async (): Promise<XtbFxRate[] | null> => {
+  try {
+    const response = await base.get<XtbFxRate[]>('/xtb/fx-rates');
+    return response.data;
+  } catch (error) {
+    console.error('Error during GET request:', error);
+    return null;
+  }
+};
+
+export const saveXtbFxRate = async ({ from, to, rate }: XtbFxRate) => {
+  try {
+    await base.put(
+      `/xtb/fx-rates/${encodeURIComponent(from)}/${encodeURIComponent(to)}`,
+      { rate },
+      {
+        headers: {
+          'Content-Type': 'application/json',
+        },
+      }
+    );
+    return true;
+  } catch (error) {
+    console.error('Error during PUT request:', error);
+    return false;
+  }
+};
+
+export const deleteXtbFxRate = async (from: string, to: string) => {
+  try {
+    await base.delete(
+      `/xtb/fx-rates/${encodeURIComponent(from)}/${encodeURIComponent(to)}`
+    );
+    return true;
+  } catch (error) {
+    console.error('Error during DELETE request:', error);
+    return false;
+  }
+};
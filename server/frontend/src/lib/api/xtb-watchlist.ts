@@ -0,0 +1,41 @@
+import { base } from './base';
+
+export const getXtbWatchlist = async (): Promise<string[] | null> => {
+  try {
+    const response = await base.get<string[]>('/xtb/watchlist');
+    return response.data;
+  } catch (error) {
+    console.error('Error during GET request:', error);
+    return null;
+  }
+};
+
+export const addXtbWatchlistSymbol = async (
+  symbol: string
+): Promise<string[] | null> => {
+  try {
+    const response = await base.post<string[]>(
+      '/xtb/watchlist',
+      { symbol },
+      {
+        headers: {
+          'Content-Type': 'application/json',
+        },
+      }
+    );
+    return response.data;
+  } catch (error) {
+    console.error('Error during POST request:', error);
+    return null;
+  }
+};
+
+export const deleteXtbWatchlistSymbol = async (symbol: string) => {
+  try {
+    await base.delete(`/xtb/watchlist/${encodeURIComponent(symbol)}`);
+    return true;
+  } catch (error) {
+    console.error('Error during DELETE request:', error);
+    return false;
+  }
+};
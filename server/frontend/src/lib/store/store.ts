@@ -3,12 +3,9 @@ import { persist } from 'zustand/middleware';
 import { Widget } from '../types';
 
 export interface AppSlice {
-  spotifyCode: string;
   spotifyCallbackUrl: string;
   spotifyClientId: string;
   spotifyClientSecret: string;
-  spotifyAuthorizeUrl: string;
-  spotifyGetTokenUrl: string;
 
   xtbUserId: string;
   xtbPassword: string;
@@ -22,12 +19,9 @@ export interface AppSlice {
   accentColor: string;
   charactersPerSecond: number;
 
-  setSpotifyCode: (code: string) => void;
   setSpotifyCallbackUrl: (url: string) => void;
   setSpotifyClientId: (clientId: string) => void;
   setSpotifyClientSecret: (clientSecret: string) => void;
-  setSpotifyAuthorizeUrl: (authorizeUrl: string) => void;
-  setSpotifyGetTokenUrl: (getTokenUrl: string) => void;
 
   setXtbUserId: (email: string) => void;
   setXtbPassword: (password: string) => void;
@@ -50,12 +44,9 @@ export type StoreSlice<T> = (
 ) => T;
 
 const createAppSlice: StoreSlice<AppSlice> = (set) => ({
-  spotifyCode: '',
   spotifyCallbackUrl: '',
   spotifyClientId: '',
   spotifyClientSecret: '',
-  spotifyAuthorizeUrl: '',
-  spotifyGetTokenUrl: '',
 
   xtbUserId: '',
   xtbPassword: '',
@@ -69,9 +60,6 @@ const createAppSlice: StoreSlice<AppSlice> = (set) => ({
   accentColor: '#22C55E',
   charactersPerSecond: 2,
 
-  setSpotifyCode: (code: string) => {
-    set({ spotifyCode: code });
-  },
   setSpotifyCallbackUrl: (url: string) => {
     set({ spotifyCallbackUrl: url });
   },
@@ -81,12 +69,6 @@ const createAppSlice: StoreSlice<AppSlice> = (set) => ({
   setSpotifyClientSecret: (clientSecret: string) => {
     set({ spotifyClientSecret: clientSecret });
   },
-  setSpotifyAuthorizeUrl: (authorizeUrl: string) => {
-    set({ spotifyAuthorizeUrl: authorizeUrl });
-  },
-  setSpotifyGetTokenUrl: (getTokenUrl: string) => {
-    set({ spotifyGetTokenUrl: getTokenUrl });
-  },
 
   setXtbUserId: (email: string) => {
     set({ xtbUserId: email });
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS oauth2_states (
+    state TEXT PRIMARY KEY,
+    app_name TEXT NOT NULL,
+    code_verifier TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,3 @@
+-- Consent-screen URL and scopes per client; empty means "use the provider registry's default".
+ALTER TABLE oauth2_tokens ADD COLUMN authorize_url TEXT NOT NULL DEFAULT '';
+ALTER TABLE oauth2_tokens ADD COLUMN scopes TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,4 @@
+ALTER TABLE oauth2_tokens ADD COLUMN refresh_failures INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE oauth2_tokens ADD COLUMN last_refresh_error TEXT;
+-- Set once refreshing keeps failing; cleared when the integration is authorized again.
+ALTER TABLE oauth2_tokens ADD COLUMN broken BOOLEAN NOT NULL DEFAULT FALSE;
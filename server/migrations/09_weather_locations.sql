@@ -0,0 +1,21 @@
+CREATE TABLE IF NOT EXISTS weather_locations (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    country TEXT NOT NULL DEFAULT '',
+    latitude REAL NOT NULL,
+    longitude REAL NOT NULL,
+    timezone TEXT NOT NULL
+);
+
+-- The location that used to be hardcoded, so existing screens keep showing it.
+INSERT INTO weather_locations (name, country, latitude, longitude, timezone)
+VALUES ('Gdańsk', 'Poland', 54.3523, 18.6491, 'Europe/Warsaw');
+
+-- A single row of display preferences for every location.
+CREATE TABLE IF NOT EXISTS weather_settings (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    units TEXT NOT NULL DEFAULT 'metric',
+    wind_unit TEXT NOT NULL DEFAULT 'kmh'
+);
+
+INSERT INTO weather_settings (id) VALUES (1);
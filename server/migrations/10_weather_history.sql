@@ -0,0 +1,16 @@
+-- The old table only ever held a single reading without a location, so the series starts fresh.
+DROP TABLE IF EXISTS weather;
+
+-- One row per Open-Meteo observation, always stored in metric units.
+CREATE TABLE weather (
+    id INTEGER PRIMARY KEY,
+    location_id INTEGER NOT NULL REFERENCES weather_locations(id) ON DELETE CASCADE,
+    recorded_at TIMESTAMP NOT NULL,
+    temperature REAL NOT NULL,
+    apparent_temperature REAL NOT NULL,
+    humidity REAL NOT NULL,
+    wind_speed REAL NOT NULL,
+    precipitation REAL NOT NULL,
+    weather_code INTEGER NOT NULL,
+    UNIQUE (location_id, recorded_at)
+);
@@ -0,0 +1,14 @@
+-- One rule per kind of weather alert. Thresholds are metric: mm for rain, °C for frost and heat.
+CREATE TABLE IF NOT EXISTS weather_alert_rules (
+    kind TEXT PRIMARY KEY,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    threshold REAL NOT NULL,
+    within_minutes INTEGER NOT NULL,
+    cooldown_minutes INTEGER NOT NULL
+);
+
+INSERT INTO weather_alert_rules (kind, threshold, within_minutes, cooldown_minutes) VALUES
+    ('rain', 0.1, 60, 60),
+    ('storm', 0, 180, 120),
+    ('frost', 0, 720, 720),
+    ('heat', 30, 720, 720);
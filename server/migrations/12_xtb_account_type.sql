@@ -0,0 +1,2 @@
+-- Credentials saved before account types existed are for real accounts.
+ALTER TABLE xtb_credentials ADD COLUMN account_type TEXT NOT NULL DEFAULT 'real';
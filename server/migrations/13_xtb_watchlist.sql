@@ -0,0 +1,5 @@
+-- Instruments whose tick prices the screen shows, in the order they were added.
+CREATE TABLE IF NOT EXISTS xtb_watchlist (
+    id INTEGER PRIMARY KEY,
+    symbol TEXT NOT NULL UNIQUE
+);
@@ -0,0 +1,22 @@
+-- Thresholds checked against the XTB streams. `symbol` is only set for price rules.
+CREATE TABLE IF NOT EXISTS xtb_alert_rules (
+    id INTEGER PRIMARY KEY,
+    kind TEXT NOT NULL,
+    symbol TEXT,
+    threshold REAL NOT NULL,
+    window_minutes INTEGER NOT NULL DEFAULT 0,
+    cooldown_minutes INTEGER NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE
+);
+
+-- Alerts the rules raised; kept when their rule is deleted.
+CREATE TABLE IF NOT EXISTS xtb_alert_history (
+    id INTEGER PRIMARY KEY,
+    rule_id INTEGER,
+    fired_at TIMESTAMP NOT NULL,
+    level TEXT NOT NULL,
+    title TEXT NOT NULL,
+    message TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS xtb_alert_history_fired_at ON xtb_alert_history (fired_at);
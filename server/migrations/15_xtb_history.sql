@@ -0,0 +1,20 @@
+-- The account as the XTB streams reported it, every few minutes while a session is up.
+CREATE TABLE IF NOT EXISTS xtb_snapshots (
+    id INTEGER PRIMARY KEY,
+    recorded_at TIMESTAMP NOT NULL,
+    balance REAL NOT NULL,
+    equity REAL NOT NULL,
+    profit REAL NOT NULL,
+    positions INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS xtb_snapshots_recorded_at ON xtb_snapshots (recorded_at);
+
+-- One row per UTC day, updated by every snapshot, so the last one of the day closes it.
+CREATE TABLE IF NOT EXISTS xtb_daily (
+    date DATE PRIMARY KEY,
+    open_equity REAL NOT NULL,
+    balance REAL NOT NULL,
+    equity REAL NOT NULL,
+    profit REAL NOT NULL
+);
@@ -0,0 +1,16 @@
+-- A single row of how money values are shown on the screens.
+CREATE TABLE IF NOT EXISTS money_settings (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    locale TEXT NOT NULL DEFAULT 'en',
+    display_currency TEXT
+);
+
+INSERT INTO money_settings (id) VALUES (1);
+
+-- Entered by hand; a pair also converts the other way with the inverse rate.
+CREATE TABLE IF NOT EXISTS fx_rates (
+    from_currency TEXT NOT NULL,
+    to_currency TEXT NOT NULL,
+    rate REAL NOT NULL,
+    PRIMARY KEY (from_currency, to_currency)
+);
@@ -0,0 +1,41 @@
+use serde::Serialize;
+
+use crate::tcp::StateMessage;
+
+/// Screen app that shows alerts on top of whatever else is on screen.
+pub const APP: &str = "Alert";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum AlertLevel {
+    /// Something went back to normal.
+    Info,
+    Warning,
+    Severe,
+}
+
+/// A notice raised by a source, e.g. when a reading crosses a threshold.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Alert {
+    /// Name of the source that raised it, e.g. `air_quality`.
+    pub source: &'static str,
+    pub level: AlertLevel,
+    pub title: String,
+    pub message: String,
+}
+
+impl Alert {
+    pub fn new(source: &'static str, level: AlertLevel, title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            source,
+            level,
+            title: title.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn to_message(&self) -> anyhow::Result<StateMessage> {
+        Ok(StateMessage::data(APP, serde_json::to_string(self)?))
+    }
+}
@@ -0,0 +1,289 @@
+use std::sync::OnceLock;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    XChaCha20Poly1305, XNonce,
+};
+use sha2::{Digest, Sha256};
+
+// Envelope encryption for secrets stored in SQLite.
+//
+// Every value gets its own random data key. The value is sealed with the data key, and the data
+// key is sealed with the master key, so rotating the master key only re-wraps data keys:
+//
+//   enc:v1:<master key id>:<base64 nonce + wrapped data key>:<base64 nonce + ciphertext>
+
+const PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 24;
+
+static MASTER_KEY: OnceLock<MasterKey> = OnceLock::new();
+
+#[derive(Clone)]
+pub struct MasterKey {
+    id: String,
+    cipher: XChaCha20Poly1305,
+}
+
+impl MasterKey {
+    /// Parses a base64-encoded 32 byte key, e.g. the output of `openssl rand -base64 32`.
+    pub fn from_base64(encoded: &str) -> anyhow::Result<Self> {
+        let bytes = STANDARD
+            .decode(encoded.trim())
+            .map_err(|e| anyhow::anyhow!("Master key is not valid base64: {}", e))?;
+
+        if bytes.len() != 32 {
+            return Err(anyhow::anyhow!(
+                "Master key must be 32 bytes, got {}",
+                bytes.len()
+            ));
+        }
+
+        let id = format!("{:x}", Sha256::digest(&bytes))[..8].to_string();
+        let cipher = XChaCha20Poly1305::new_from_slice(&bytes)
+            .map_err(|_| anyhow::anyhow!("Invalid master key length"))?;
+
+        Ok(Self { id, cipher })
+    }
+
+    /// Reads the key from `<name>`, or from the file named by `<name>_FILE`.
+    pub fn from_env(name: &str) -> anyhow::Result<Option<Self>> {
+        if let Ok(encoded) = std::env::var(name) {
+            return Self::from_base64(&encoded).map(Some);
+        }
+
+        if let Ok(path) = std::env::var(format!("{}_FILE", name)) {
+            let encoded = std::fs::read_to_string(&path)
+                .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path, e))?;
+            return Self::from_base64(&encoded).map(Some);
+        }
+
+        Ok(None)
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+/// Installs the process-wide master key used by the `db` module.
+pub fn init(key: MasterKey) -> anyhow::Result<()> {
+    MASTER_KEY
+        .set(key)
+        .map_err(|_| anyhow::anyhow!("Master key is already initialized"))
+}
+
+pub fn init_from_env() -> anyhow::Result<()> {
+    match MasterKey::from_env("MASTER_KEY")? {
+        Some(key) => init(key),
+        None => Err(anyhow::anyhow!(
+            "MASTER_KEY is not set; generate one with `openssl rand -base64 32`"
+        )),
+    }
+}
+
+/// Installs a fixed all-zero master key; safe to call from every test.
+#[cfg(test)]
+pub fn init_test_key() {
+    MASTER_KEY.get_or_init(|| {
+        MasterKey::from_base64("AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=")
+            .expect("test key is valid")
+    });
+}
+
+fn master_key() -> anyhow::Result<&'static MasterKey> {
+    MASTER_KEY
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("Master key is not initialized"))
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(PREFIX)
+}
+
+/// Returns the id of the master key that wrapped `value`, if it is encrypted.
+pub fn key_id_of(value: &str) -> Option<&str> {
+    value.strip_prefix(PREFIX)?.split(':').next()
+}
+
+fn seal(cipher: &XChaCha20Poly1305, plaintext: &[u8]) -> anyhow::Result<String> {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| anyhow::anyhow!("Encryption failed"))?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    Ok(STANDARD.encode(sealed))
+}
+
+fn open(cipher: &XChaCha20Poly1305, sealed: &str) -> anyhow::Result<Vec<u8>> {
+    let sealed = STANDARD
+        .decode(sealed)
+        .map_err(|e| anyhow::anyhow!("Encrypted value is not valid base64: {}", e))?;
+
+    if sealed.len() < NONCE_LEN {
+        return Err(anyhow::anyhow!("Encrypted value is truncated"));
+    }
+
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    cipher
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow::anyhow!("Decryption failed; wrong master key or corrupted value"))
+}
+
+struct Envelope<'a> {
+    key_id: &'a str,
+    wrapped_key: &'a str,
+    ciphertext: &'a str,
+}
+
+fn parse_envelope(value: &str) -> anyhow::Result<Envelope<'_>> {
+    let rest = value
+        .strip_prefix(PREFIX)
+        .ok_or_else(|| anyhow::anyhow!("Value is not encrypted"))?;
+
+    let mut parts = rest.splitn(3, ':');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(key_id), Some(wrapped_key), Some(ciphertext)) => Ok(Envelope {
+            key_id,
+            wrapped_key,
+            ciphertext,
+        }),
+        _ => Err(anyhow::anyhow!("Malformed encrypted value")),
+    }
+}
+
+fn unwrap_data_key(master: &MasterKey, envelope: &Envelope) -> anyhow::Result<XChaCha20Poly1305> {
+    if envelope.key_id != master.id {
+        return Err(anyhow::anyhow!(
+            "Value was encrypted with master key {}, but the active key is {}",
+            envelope.key_id,
+            master.id
+        ));
+    }
+
+    let data_key = open(&master.cipher, envelope.wrapped_key)?;
+    XChaCha20Poly1305::new_from_slice(&data_key)
+        .map_err(|_| anyhow::anyhow!("Invalid data key length"))
+}
+
+pub fn encrypt_with(master: &MasterKey, plaintext: &str) -> anyhow::Result<String> {
+    let data_key = XChaCha20Poly1305::generate_key(&mut OsRng);
+    let data_cipher = XChaCha20Poly1305::new(&data_key);
+
+    let wrapped_key = seal(&master.cipher, &data_key)?;
+    let ciphertext = seal(&data_cipher, plaintext.as_bytes())?;
+
+    Ok(format!("{}{}:{}:{}", PREFIX, master.id, wrapped_key, ciphertext))
+}
+
+pub fn decrypt_with(master: &MasterKey, value: &str) -> anyhow::Result<String> {
+    let envelope = parse_envelope(value)?;
+    let data_cipher = unwrap_data_key(master, &envelope)?;
+    let plaintext = open(&data_cipher, envelope.ciphertext)?;
+
+    String::from_utf8(plaintext).map_err(|_| anyhow::anyhow!("Decrypted value is not UTF-8"))
+}
+
+/// Re-wraps the data key of `value` from `old` to `new` without touching the ciphertext.
+pub fn rewrap(old: &MasterKey, new: &MasterKey, value: &str) -> anyhow::Result<String> {
+    let envelope = parse_envelope(value)?;
+    let data_key = open(&old.cipher, envelope.wrapped_key)
+        .map_err(|e| anyhow::anyhow!("Failed to unwrap data key with the old master key: {}", e))?;
+    let wrapped_key = seal(&new.cipher, &data_key)?;
+
+    Ok(format!("{}{}:{}:{}", PREFIX, new.id, wrapped_key, envelope.ciphertext))
+}
+
+/// Encrypts `plaintext` with the process-wide master key.
+pub fn encrypt(plaintext: &str) -> anyhow::Result<String> {
+    encrypt_with(master_key()?, plaintext)
+}
+
+/// Decrypts a value written by `encrypt`. Rows that predate encryption are returned as-is;
+/// `db::encrypt_existing_secrets` converts them at startup.
+pub fn decrypt(value: &str) -> anyhow::Result<String> {
+    if !is_encrypted(value) {
+        return Ok(value.to_string());
+    }
+
+    decrypt_with(master_key()?, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+
+    use super::{decrypt, decrypt_with, encrypt_with, is_encrypted, key_id_of, rewrap, MasterKey};
+
+    fn key(byte: u8) -> MasterKey {
+        MasterKey::from_base64(&STANDARD.encode([byte; 32])).unwrap()
+    }
+
+    #[test]
+    fn values_round_trip_with_fresh_data_keys() {
+        let master = key(1);
+
+        let first = encrypt_with(&master, "hunter2").unwrap();
+        let second = encrypt_with(&master, "hunter2").unwrap();
+
+        assert!(is_encrypted(&first));
+        assert!(!first.contains("hunter2"));
+        assert_ne!(first, second);
+        assert_eq!(key_id_of(&first), Some(master.id()));
+        assert_eq!(decrypt_with(&master, &first).unwrap(), "hunter2");
+        assert_eq!(decrypt_with(&master, &second).unwrap(), "hunter2");
+    }
+
+    #[test]
+    fn the_wrong_key_fails() {
+        let value = encrypt_with(&key(1), "hunter2").unwrap();
+
+        let error = decrypt_with(&key(2), &value).unwrap_err();
+        assert!(error.to_string().contains("was encrypted with master key"));
+
+        // A different key that claims the same id still can't open the data key.
+        let mut impostor = key(2);
+        impostor.id = key(1).id;
+        assert!(decrypt_with(&impostor, &value).is_err());
+    }
+
+    #[test]
+    fn tampered_values_fail() {
+        let master = key(1);
+        let value = encrypt_with(&master, "hunter2").unwrap();
+        let (head, ciphertext) = value.rsplit_once(':').unwrap();
+
+        let mut bytes = STANDARD.decode(ciphertext).unwrap();
+        *bytes.last_mut().unwrap() ^= 1;
+        let tampered = format!("{}:{}", head, STANDARD.encode(bytes));
+        assert!(decrypt_with(&master, &tampered).is_err());
+
+        let truncated = format!("{}:{}", head, STANDARD.encode([0u8; 8]));
+        assert!(decrypt_with(&master, &truncated).is_err());
+        assert!(decrypt_with(&master, "enc:v1:garbage").is_err());
+    }
+
+    #[test]
+    fn plaintext_rows_pass_through() {
+        assert!(!is_encrypted("hunter2"));
+        assert_eq!(key_id_of("hunter2"), None);
+        assert_eq!(decrypt("hunter2").unwrap(), "hunter2");
+    }
+
+    #[test]
+    fn rewrap_moves_the_data_key_to_the_new_master() {
+        let (old, new) = (key(1), key(2));
+        let value = encrypt_with(&old, "hunter2").unwrap();
+
+        let rewrapped = rewrap(&old, &new, &value).unwrap();
+
+        assert_eq!(key_id_of(&rewrapped), Some(new.id()));
+        // Only the data key is re-wrapped; the ciphertext stays as it was.
+        assert_eq!(rewrapped.rsplit_once(':').unwrap().1, value.rsplit_once(':').unwrap().1);
+        assert_eq!(decrypt_with(&new, &rewrapped).unwrap(), "hunter2");
+        assert!(decrypt_with(&old, &rewrapped).is_err());
+        assert!(rewrap(&new, &old, &value).is_err());
+    }
+}
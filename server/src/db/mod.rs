@@ -1,4 +1,10 @@
-use crate::web::weather::WeatherResponse;
+use crate::alerts::AlertLevel;
+use crate::crypto::{self, MasterKey};
+use crate::money::{FxRate, MoneySettings};
+use crate::web::{
+    weather::{alert_rules::AlertRule, WeatherSettings},
+    xtb::{alert_rules::AlertRule as XtbAlertRule, AccountType},
+};
 use sqlx::{migrate::MigrateDatabase, Sqlite, SqlitePool};
 
 #[allow(dead_code, unused)]
@@ -19,15 +25,161 @@ pub async fn initialize_db() -> anyhow::Result<SqlitePool> {
         Err(e) => return Err(e.into()),
     }
 
+    encrypt_existing_secrets(&pool).await?;
+
     Ok(pool)
 }
 
-#[derive(Debug, sqlx::FromRow)]
-pub struct WeatherRow {
-    pub id: i64,
-    pub time: i64,
-    pub interval: i64,
-    pub temperature: f64,
+/// Fresh in-memory database with all migrations applied.
+#[cfg(test)]
+pub async fn initialize_test_db() -> SqlitePool {
+    crypto::init_test_key();
+
+    // A single connection, since every connection to `sqlite::memory:` is its own database.
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .expect("failed to open in-memory database");
+    sqlx::migrate!("./migrations")
+        .run(&pool)
+        .await
+        .expect("failed to migrate in-memory database");
+
+    pool
+}
+
+#[derive(sqlx::FromRow)]
+struct OAuth2SecretsRow {
+    id: i64,
+    client_secret: String,
+    access_token: String,
+    refresh_token: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct XtbSecretsRow {
+    user_id: String,
+    password: String,
+}
+
+/// Encrypts secrets that were stored in plaintext before encryption at rest was introduced.
+pub async fn encrypt_existing_secrets(pool: &SqlitePool) -> anyhow::Result<()> {
+    let encrypt_if_plain = |value: String| -> anyhow::Result<String> {
+        if crypto::is_encrypted(&value) {
+            Ok(value)
+        } else {
+            crypto::encrypt(&value)
+        }
+    };
+
+    let mut tx = pool.begin().await?;
+    let mut migrated = 0;
+
+    let oauth2_rows = sqlx::query_as::<_, OAuth2SecretsRow>(
+        "SELECT id, client_secret, access_token, refresh_token FROM oauth2_tokens",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for row in oauth2_rows {
+        if crypto::is_encrypted(&row.client_secret)
+            && crypto::is_encrypted(&row.access_token)
+            && crypto::is_encrypted(&row.refresh_token)
+        {
+            continue;
+        }
+
+        sqlx::query(
+            "UPDATE oauth2_tokens SET client_secret = ?, access_token = ?, refresh_token = ? WHERE id = ?",
+        )
+        .bind(encrypt_if_plain(row.client_secret)?)
+        .bind(encrypt_if_plain(row.access_token)?)
+        .bind(encrypt_if_plain(row.refresh_token)?)
+        .bind(row.id)
+        .execute(&mut *tx)
+        .await?;
+        migrated += 1;
+    }
+
+    let xtb_rows = sqlx::query_as::<_, XtbSecretsRow>("SELECT user_id, password FROM xtb_credentials")
+        .fetch_all(&mut *tx)
+        .await?;
+
+    for row in xtb_rows {
+        if crypto::is_encrypted(&row.password) {
+            continue;
+        }
+
+        sqlx::query("UPDATE xtb_credentials SET password = ? WHERE user_id = ?")
+            .bind(encrypt_if_plain(row.password)?)
+            .bind(row.user_id)
+            .execute(&mut *tx)
+            .await?;
+        migrated += 1;
+    }
+
+    tx.commit().await?;
+
+    if migrated > 0 {
+        println!("Encrypted secrets in {} existing rows", migrated);
+    }
+
+    Ok(())
+}
+
+/// Re-wraps every stored secret from the `old` master key to the `new` one in one transaction.
+pub async fn rotate_master_key(
+    pool: &SqlitePool,
+    old: &MasterKey,
+    new: &MasterKey,
+) -> anyhow::Result<usize> {
+    let rewrap = |value: String| -> anyhow::Result<String> {
+        match crypto::key_id_of(&value) {
+            Some(key_id) if key_id == new.id() => Ok(value),
+            Some(_) => crypto::rewrap(old, new, &value),
+            None => crypto::encrypt_with(new, &value),
+        }
+    };
+
+    let mut tx = pool.begin().await?;
+    let mut rotated = 0;
+
+    let oauth2_rows = sqlx::query_as::<_, OAuth2SecretsRow>(
+        "SELECT id, client_secret, access_token, refresh_token FROM oauth2_tokens",
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for row in oauth2_rows {
+        sqlx::query(
+            "UPDATE oauth2_tokens SET client_secret = ?, access_token = ?, refresh_token = ? WHERE id = ?",
+        )
+        .bind(rewrap(row.client_secret)?)
+        .bind(rewrap(row.access_token)?)
+        .bind(rewrap(row.refresh_token)?)
+        .bind(row.id)
+        .execute(&mut *tx)
+        .await?;
+        rotated += 1;
+    }
+
+    let xtb_rows = sqlx::query_as::<_, XtbSecretsRow>("SELECT user_id, password FROM xtb_credentials")
+        .fetch_all(&mut *tx)
+        .await?;
+
+    for row in xtb_rows {
+        sqlx::query("UPDATE xtb_credentials SET password = ? WHERE user_id = ?")
+            .bind(rewrap(row.password)?)
+            .bind(row.user_id)
+            .execute(&mut *tx)
+            .await?;
+        rotated += 1;
+    }
+
+    tx.commit().await?;
+
+    Ok(rotated)
 }
 
 #[derive(Debug, sqlx::FromRow)]
@@ -37,51 +189,193 @@ pub struct ApiKeyRow {
     pub key: String,
 }
 
-pub async fn get_latest_weather_from_db(pool: &SqlitePool) -> anyhow::Result<Option<WeatherRow>> {
-    let row = sqlx::query_as::<_, WeatherRow>(
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct WeatherLocation {
+    pub id: i64,
+    pub name: String,
+    pub country: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub timezone: String,
+}
+
+pub async fn get_weather_locations(pool: &SqlitePool) -> anyhow::Result<Vec<WeatherLocation>> {
+    let rows = sqlx::query_as::<_, WeatherLocation>(
         r#"
-        SELECT id, time, interval, temperature
-        FROM weather
-        ORDER BY id DESC
-        LIMIT 1
+        SELECT id, name, country, latitude, longitude, timezone
+        FROM weather_locations
+        ORDER BY id
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Stores `location` and returns it with its new `id`; the given `id` is ignored.
+pub async fn add_weather_location(pool: &SqlitePool, location: WeatherLocation) -> anyhow::Result<WeatherLocation> {
+    let query = r#"
+        INSERT INTO weather_locations (name, country, latitude, longitude, timezone)
+        VALUES (?, ?, ?, ?, ?)
+    "#;
+
+    let result = sqlx::query(query)
+        .bind(&location.name)
+        .bind(&location.country)
+        .bind(location.latitude)
+        .bind(location.longitude)
+        .bind(&location.timezone)
+        .execute(pool)
+        .await?;
+
+    Ok(WeatherLocation {
+        id: result.last_insert_rowid(),
+        ..location
+    })
+}
+
+/// Returns whether a location with `id` existed.
+pub async fn delete_weather_location(pool: &SqlitePool, id: i64) -> anyhow::Result<bool> {
+    let result = sqlx::query("DELETE FROM weather_locations WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn get_weather_settings(pool: &SqlitePool) -> anyhow::Result<WeatherSettings> {
+    let row = sqlx::query_as::<_, WeatherSettings>(
+        r#"
+        SELECT units, wind_unit
+        FROM weather_settings
+        WHERE id = 1
         "#,
     )
     .fetch_optional(pool)
     .await?;
 
-    Ok(row)
+    Ok(row.unwrap_or_default())
+}
+
+pub async fn save_weather_settings(pool: &SqlitePool, settings: &WeatherSettings) -> anyhow::Result<()> {
+    let query = r#"
+        INSERT INTO weather_settings (id, units, wind_unit)
+        VALUES (1, ?, ?)
+        ON CONFLICT (id) DO UPDATE SET units = excluded.units, wind_unit = excluded.wind_unit
+    "#;
+
+    sqlx::query(query)
+        .bind(settings.units)
+        .bind(settings.wind_unit)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// One observation in metric units: °C, km/h and mm.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct WeatherReading {
+    pub location_id: i64,
+    /// UTC.
+    pub recorded_at: chrono::NaiveDateTime,
+    pub temperature: f64,
+    pub apparent_temperature: f64,
+    pub humidity: f64,
+    pub wind_speed: f64,
+    pub precipitation: f64,
+    pub weather_code: i64,
+}
+
+/// Returns whether the reading was new; Open-Meteo repeats an observation until the next one.
+pub async fn save_weather_reading(pool: &SqlitePool, reading: &WeatherReading) -> anyhow::Result<bool> {
+    let query = r#"
+        INSERT INTO weather (location_id, recorded_at, temperature, apparent_temperature, humidity, wind_speed, precipitation, weather_code)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT (location_id, recorded_at) DO NOTHING
+    "#;
+
+    let result = sqlx::query(query)
+        .bind(reading.location_id)
+        .bind(reading.recorded_at)
+        .bind(reading.temperature)
+        .bind(reading.apparent_temperature)
+        .bind(reading.humidity)
+        .bind(reading.wind_speed)
+        .bind(reading.precipitation)
+        .bind(reading.weather_code)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
 }
 
-pub async fn save_weather_to_database(
+/// Readings of a location since `since`, oldest first.
+pub async fn get_weather_history(
     pool: &SqlitePool,
-    weather_data: WeatherResponse,
-) -> anyhow::Result<()> {
-    let exists = get_latest_weather_from_db(pool).await?;
-    if let Some(weather) = exists {
-        let query = r#"
-            UPDATE weather
-            SET temperature = ?
-            WHERE id = ?
-        "#;
+    location_id: i64,
+    since: chrono::NaiveDateTime,
+) -> anyhow::Result<Vec<WeatherReading>> {
+    let rows = sqlx::query_as::<_, WeatherReading>(
+        r#"
+        SELECT location_id, recorded_at, temperature, apparent_temperature, humidity, wind_speed, precipitation, weather_code
+        FROM weather
+        WHERE location_id = ? AND recorded_at >= ?
+        ORDER BY recorded_at
+        "#,
+    )
+    .bind(location_id)
+    .bind(since)
+    .fetch_all(pool)
+    .await?;
 
-        sqlx::query(query)
-            .bind(weather_data.current.temperature_2m)
-            .bind(weather.id)
-            .execute(pool)
-            .await?;
-    } else {
-        let query = r#"
-        INSERT INTO weather (time, interval, temperature)
-        VALUES (?, ?, ?)
+    Ok(rows)
+}
+
+pub async fn delete_weather_readings_before(pool: &SqlitePool, before: chrono::NaiveDateTime) -> anyhow::Result<u64> {
+    let result = sqlx::query("DELETE FROM weather WHERE recorded_at < ?")
+        .bind(before)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub async fn get_weather_alert_rules(pool: &SqlitePool) -> anyhow::Result<Vec<AlertRule>> {
+    let rows = sqlx::query_as::<_, AlertRule>(
+        r#"
+        SELECT kind, enabled, threshold, within_minutes, cooldown_minutes
+        FROM weather_alert_rules
+        ORDER BY rowid
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+pub async fn save_weather_alert_rule(pool: &SqlitePool, rule: &AlertRule) -> anyhow::Result<()> {
+    let query = r#"
+        INSERT INTO weather_alert_rules (kind, enabled, threshold, within_minutes, cooldown_minutes)
+        VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT (kind) DO UPDATE SET
+            enabled = excluded.enabled,
+            threshold = excluded.threshold,
+            within_minutes = excluded.within_minutes,
+            cooldown_minutes = excluded.cooldown_minutes
     "#;
 
-        sqlx::query(query)
-            .bind(weather_data.current.time)
-            .bind(weather_data.current.interval)
-            .bind(weather_data.current.temperature_2m)
-            .execute(pool)
-            .await?;
-    }
+    sqlx::query(query)
+        .bind(rule.kind)
+        .bind(rule.enabled)
+        .bind(rule.threshold)
+        .bind(rule.within_minutes)
+        .bind(rule.cooldown_minutes)
+        .execute(pool)
+        .await?;
 
     Ok(())
 }
@@ -162,6 +456,24 @@ pub struct OAuth2Token {
     pub code: String,
     pub get_token_url: String,
     pub created_at: chrono::NaiveDateTime,
+    /// Empty when the provider's default consent URL applies.
+    pub authorize_url: String,
+    /// Empty when the provider's default scopes apply.
+    pub scopes: String,
+    /// Consecutive failed refreshes since the last successful one.
+    pub refresh_failures: i64,
+    pub last_refresh_error: Option<String>,
+    /// Refreshing gave up; the integration has to be authorized again.
+    pub broken: bool,
+}
+
+fn decrypt_oauth2_token(token: OAuth2Token) -> anyhow::Result<OAuth2Token> {
+    Ok(OAuth2Token {
+        client_secret: crypto::decrypt(&token.client_secret)?,
+        access_token: crypto::decrypt(&token.access_token)?,
+        refresh_token: crypto::decrypt(&token.refresh_token)?,
+        ..token
+    })
 }
 
 pub async fn get_token_from_db(
@@ -170,7 +482,7 @@ pub async fn get_token_from_db(
 ) -> anyhow::Result<Option<OAuth2Token>> {
     let row = sqlx::query_as::<_, OAuth2Token>(
         r#"
-        SELECT app_name, client_secret, client_id, redirect_uri, access_token, refresh_token, expires_at, code, get_token_url, created_at
+        SELECT app_name, client_secret, client_id, redirect_uri, access_token, refresh_token, expires_at, code, get_token_url, created_at, authorize_url, scopes, refresh_failures, last_refresh_error, broken
         FROM oauth2_tokens
         WHERE app_name = ?
         "#,
@@ -179,7 +491,21 @@ pub async fn get_token_from_db(
     .fetch_optional(pool)
     .await?;
 
-    Ok(row)
+    row.map(decrypt_oauth2_token).transpose()
+}
+
+pub async fn get_oauth2_tokens(pool: &SqlitePool) -> anyhow::Result<Vec<OAuth2Token>> {
+    let rows = sqlx::query_as::<_, OAuth2Token>(
+        r#"
+        SELECT app_name, client_secret, client_id, redirect_uri, access_token, refresh_token, expires_at, code, get_token_url, created_at, authorize_url, scopes, refresh_failures, last_refresh_error, broken
+        FROM oauth2_tokens
+        ORDER BY app_name
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter().map(decrypt_oauth2_token).collect()
 }
 
 pub async fn add_new_oauth2_token_to_db(
@@ -187,43 +513,56 @@ pub async fn add_new_oauth2_token_to_db(
     data: OAuth2Token,
 ) -> anyhow::Result<()> {
     let exists = get_token_from_db(pool, data.app_name.clone()).await?;
+    let client_secret = crypto::encrypt(&data.client_secret)?;
+    let access_token = crypto::encrypt(&data.access_token)?;
+    let refresh_token = crypto::encrypt(&data.refresh_token)?;
     if let Some(_) = exists {
         let query = r#"
             UPDATE oauth2_tokens
-            SET access_token = ?, refresh_token = ?, expires_at = ?, code = ?, get_token_url = ?, created_at = ?, client_secret = ?, client_id = ?, redirect_uri = ?
+            SET access_token = ?, refresh_token = ?, expires_at = ?, code = ?, get_token_url = ?, created_at = ?, client_secret = ?, client_id = ?, redirect_uri = ?, authorize_url = ?, scopes = ?, refresh_failures = ?, last_refresh_error = ?, broken = ?
             WHERE app_name = ?
         "#;
 
         sqlx::query(query)
-            .bind(data.access_token)
-            .bind(data.refresh_token)
+            .bind(access_token)
+            .bind(refresh_token)
             .bind(data.expires_at)
             .bind(data.code)
             .bind(data.get_token_url)
             .bind(data.created_at)
-            .bind(data.client_secret)
+            .bind(client_secret)
             .bind(data.client_id)
             .bind(data.redirect_uri)
+            .bind(data.authorize_url)
+            .bind(data.scopes)
+            .bind(data.refresh_failures)
+            .bind(data.last_refresh_error)
+            .bind(data.broken)
             .bind(data.app_name)
             .execute(pool)
             .await?;
     } else {
         let query = r#"
-        INSERT INTO oauth2_tokens (app_name, client_secret, client_id, redirect_uri, access_token, refresh_token, expires_at, code, get_token_url, created_at)
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        INSERT INTO oauth2_tokens (app_name, client_secret, client_id, redirect_uri, access_token, refresh_token, expires_at, code, get_token_url, created_at, authorize_url, scopes, refresh_failures, last_refresh_error, broken)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
     "#;
 
         sqlx::query(query)
             .bind(data.app_name)
-            .bind(data.client_secret)
+            .bind(client_secret)
             .bind(data.client_id)
             .bind(data.redirect_uri)
-            .bind(data.access_token)
-            .bind(data.refresh_token)
+            .bind(access_token)
+            .bind(refresh_token)
             .bind(data.expires_at)
             .bind(data.code)
             .bind(data.get_token_url)
             .bind(data.created_at)
+            .bind(data.authorize_url)
+            .bind(data.scopes)
+            .bind(data.refresh_failures)
+            .bind(data.last_refresh_error)
+            .bind(data.broken)
             .execute(pool)
             .await?;
     }
@@ -231,6 +570,7 @@ pub async fn add_new_oauth2_token_to_db(
     Ok(())
 }
 
+/// Stores freshly issued tokens and clears any recorded refresh failures.
 pub async fn update_oauth2_access_and_refresh_tokens(
     pool: &SqlitePool,
     app_name: String,
@@ -240,13 +580,13 @@ pub async fn update_oauth2_access_and_refresh_tokens(
 ) -> anyhow::Result<()> {
     let query = r#"
         UPDATE oauth2_tokens
-        SET access_token = ?, refresh_token = ?, expires_at = ?
+        SET access_token = ?, refresh_token = ?, expires_at = ?, refresh_failures = 0, last_refresh_error = NULL, broken = FALSE
         WHERE app_name = ?
     "#;
 
     sqlx::query(query)
-        .bind(access_token)
-        .bind(refresh_token)
+        .bind(crypto::encrypt(&access_token)?)
+        .bind(crypto::encrypt(&refresh_token)?)
         .bind(expires_at)
         .bind(app_name)
         .execute(pool)
@@ -255,6 +595,35 @@ pub async fn update_oauth2_access_and_refresh_tokens(
     Ok(())
 }
 
+/// Counts a failed refresh and marks the link broken when `give_up` is set or after
+/// `max_failures` consecutive failures. Returns whether the link is now broken.
+pub async fn record_oauth2_refresh_failure(
+    pool: &SqlitePool,
+    app_name: &str,
+    error: &str,
+    give_up: bool,
+    max_failures: i64,
+) -> anyhow::Result<bool> {
+    let query = r#"
+        UPDATE oauth2_tokens
+        SET refresh_failures = refresh_failures + 1,
+            last_refresh_error = ?,
+            broken = broken OR ? OR refresh_failures + 1 >= ?
+        WHERE app_name = ?
+        RETURNING broken
+    "#;
+
+    let broken = sqlx::query_scalar::<_, bool>(query)
+        .bind(error)
+        .bind(give_up)
+        .bind(max_failures)
+        .bind(app_name)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(broken.unwrap_or(false))
+}
+
 pub async fn delete_oauth2_token_from_db(
     pool: &SqlitePool,
     app_name: String,
@@ -269,51 +638,121 @@ pub async fn delete_oauth2_token_from_db(
     Ok(())
 }
 
+#[derive(Debug, sqlx::FromRow)]
+pub struct OAuth2State {
+    pub app_name: String,
+    pub code_verifier: String,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+pub async fn save_oauth2_state(
+    pool: &SqlitePool,
+    state: &str,
+    app_name: &str,
+    code_verifier: &str,
+) -> anyhow::Result<()> {
+    let query = r#"
+        INSERT INTO oauth2_states (state, app_name, code_verifier, created_at)
+        VALUES (?, ?, ?, ?)
+    "#;
+
+    sqlx::query(query)
+        .bind(state)
+        .bind(app_name)
+        .bind(code_verifier)
+        .bind(chrono::Utc::now().naive_utc())
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Removes and returns a pending authorization, so every `state` can be redeemed only once.
+pub async fn take_oauth2_state(pool: &SqlitePool, state: &str) -> anyhow::Result<Option<OAuth2State>> {
+    let row = sqlx::query_as::<_, OAuth2State>(
+        r#"
+        DELETE FROM oauth2_states
+        WHERE state = ?
+        RETURNING app_name, code_verifier, created_at
+        "#,
+    )
+    .bind(state)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row)
+}
+
+pub async fn delete_expired_oauth2_states(
+    pool: &SqlitePool,
+    older_than: chrono::NaiveDateTime,
+) -> anyhow::Result<()> {
+    let query = r#"
+        DELETE FROM oauth2_states
+        WHERE created_at < ?
+    "#;
+
+    sqlx::query(query).bind(older_than).execute(pool).await?;
+
+    Ok(())
+}
+
 #[derive(Debug, sqlx::FromRow)]
 pub struct XtbCredentials {
     pub user_id: String,
     pub password: String,
+    pub account_type: AccountType,
 }
 
 pub async fn get_xtb_credentials(pool: &SqlitePool) -> anyhow::Result<Option<XtbCredentials>> {
     let row = sqlx::query_as::<_, XtbCredentials>(
         r#"
-        SELECT user_id, password
+        SELECT user_id, password, account_type
         FROM xtb_credentials
         "#,
     )
     .fetch_optional(pool)
     .await?;
 
-    Ok(row)
+    row.map(|credentials| -> anyhow::Result<XtbCredentials> {
+        Ok(XtbCredentials {
+            password: crypto::decrypt(&credentials.password)?,
+            ..credentials
+        })
+    })
+    .transpose()
 }
 
 pub async fn save_xtb_credentials(
     pool: &SqlitePool,
     user_id: String,
     password: String,
+    account_type: AccountType,
 ) -> anyhow::Result<()> {
     let exists = get_xtb_credentials(pool).await?;
+    let password = crypto::encrypt(&password)?;
     if let Some(_) = exists {
         let query = r#"
             UPDATE xtb_credentials
-            SET user_id = ?, password = ?
+            SET user_id = ?, password = ?, account_type = ?
         "#;
 
         sqlx::query(query)
             .bind(user_id)
             .bind(password)
+            .bind(account_type)
             .execute(pool)
             .await?;
     } else {
         let query = r#"
-        INSERT INTO xtb_credentials (user_id, password)
-        VALUES (?, ?)
+        INSERT INTO xtb_credentials (user_id, password, account_type)
+        VALUES (?, ?, ?)
     "#;
 
         sqlx::query(query)
             .bind(user_id)
             .bind(password)
+            .bind(account_type)
             .execute(pool)
             .await?;
     }
@@ -329,4 +768,670 @@ pub async fn delete_xtb_credentials(pool: &SqlitePool) -> anyhow::Result<()> {
     sqlx::query(query).execute(pool).await?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+pub async fn get_xtb_watchlist(pool: &SqlitePool) -> anyhow::Result<Vec<String>> {
+    let symbols = sqlx::query_scalar::<_, String>("SELECT symbol FROM xtb_watchlist ORDER BY id")
+        .fetch_all(pool)
+        .await?;
+
+    Ok(symbols)
+}
+
+/// Returns whether `symbol` was added; it isn't when it already is on the watchlist.
+pub async fn add_xtb_watchlist_symbol(pool: &SqlitePool, symbol: &str) -> anyhow::Result<bool> {
+    let result = sqlx::query("INSERT OR IGNORE INTO xtb_watchlist (symbol) VALUES (?)")
+        .bind(symbol)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Returns whether `symbol` was on the watchlist.
+pub async fn delete_xtb_watchlist_symbol(pool: &SqlitePool, symbol: &str) -> anyhow::Result<bool> {
+    let result = sqlx::query("DELETE FROM xtb_watchlist WHERE symbol = ?")
+        .bind(symbol)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn get_xtb_alert_rules(pool: &SqlitePool) -> anyhow::Result<Vec<XtbAlertRule>> {
+    let rows = sqlx::query_as::<_, XtbAlertRule>(
+        r#"
+        SELECT id, kind, symbol, threshold, window_minutes, cooldown_minutes, enabled
+        FROM xtb_alert_rules
+        ORDER BY id
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Stores `rule` and returns it with its new `id`; the given `id` is ignored.
+pub async fn add_xtb_alert_rule(pool: &SqlitePool, rule: XtbAlertRule) -> anyhow::Result<XtbAlertRule> {
+    let query = r#"
+        INSERT INTO xtb_alert_rules (kind, symbol, threshold, window_minutes, cooldown_minutes, enabled)
+        VALUES (?, ?, ?, ?, ?, ?)
+    "#;
+
+    let result = sqlx::query(query)
+        .bind(rule.kind)
+        .bind(&rule.symbol)
+        .bind(rule.threshold)
+        .bind(rule.window_minutes)
+        .bind(rule.cooldown_minutes)
+        .bind(rule.enabled)
+        .execute(pool)
+        .await?;
+
+    Ok(XtbAlertRule {
+        id: result.last_insert_rowid(),
+        ..rule
+    })
+}
+
+/// Returns whether a rule with `rule.id` existed.
+pub async fn update_xtb_alert_rule(pool: &SqlitePool, rule: &XtbAlertRule) -> anyhow::Result<bool> {
+    let query = r#"
+        UPDATE xtb_alert_rules
+        SET kind = ?, symbol = ?, threshold = ?, window_minutes = ?, cooldown_minutes = ?, enabled = ?
+        WHERE id = ?
+    "#;
+
+    let result = sqlx::query(query)
+        .bind(rule.kind)
+        .bind(&rule.symbol)
+        .bind(rule.threshold)
+        .bind(rule.window_minutes)
+        .bind(rule.cooldown_minutes)
+        .bind(rule.enabled)
+        .bind(rule.id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Returns whether a rule with `id` existed.
+pub async fn delete_xtb_alert_rule(pool: &SqlitePool, id: i64) -> anyhow::Result<bool> {
+    let result = sqlx::query("DELETE FROM xtb_alert_rules WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// An alert an XTB rule raised.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, sqlx::FromRow)]
+pub struct XtbAlertRecord {
+    pub id: i64,
+    /// `None` once the rule was deleted.
+    #[serde(rename = "ruleId")]
+    pub rule_id: Option<i64>,
+    /// UTC.
+    #[serde(rename = "firedAt")]
+    pub fired_at: chrono::NaiveDateTime,
+    pub level: AlertLevel,
+    pub title: String,
+    pub message: String,
+}
+
+pub async fn save_xtb_alert(pool: &SqlitePool, record: &XtbAlertRecord) -> anyhow::Result<()> {
+    let query = r#"
+        INSERT INTO xtb_alert_history (rule_id, fired_at, level, title, message)
+        VALUES (?, ?, ?, ?, ?)
+    "#;
+
+    sqlx::query(query)
+        .bind(record.rule_id)
+        .bind(record.fired_at)
+        .bind(record.level)
+        .bind(&record.title)
+        .bind(&record.message)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// The latest `limit` alerts, newest first.
+pub async fn get_xtb_alert_history(pool: &SqlitePool, limit: i64) -> anyhow::Result<Vec<XtbAlertRecord>> {
+    let rows = sqlx::query_as::<_, XtbAlertRecord>(
+        r#"
+        SELECT id, rule_id, fired_at, level, title, message
+        FROM xtb_alert_history
+        ORDER BY fired_at DESC, id DESC
+        LIMIT ?
+        "#,
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+pub async fn delete_xtb_alerts_before(pool: &SqlitePool, before: chrono::NaiveDateTime) -> anyhow::Result<u64> {
+    let result = sqlx::query("DELETE FROM xtb_alert_history WHERE fired_at < ?")
+        .bind(before)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// The XTB account at one point in time; amounts are in the account currency.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, sqlx::FromRow)]
+pub struct XtbSnapshot {
+    /// UTC.
+    #[serde(rename = "recordedAt")]
+    pub recorded_at: chrono::NaiveDateTime,
+    pub balance: f64,
+    pub equity: f64,
+    /// Floating P/L of the open positions.
+    pub profit: f64,
+    pub positions: i64,
+}
+
+/// The XTB account at the end of a UTC day, or as of the latest snapshot for today.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct XtbDay {
+    pub date: chrono::NaiveDate,
+    /// Equity of the day's first snapshot.
+    pub open_equity: f64,
+    pub balance: f64,
+    pub equity: f64,
+    pub profit: f64,
+}
+
+/// Records the snapshot and makes it the end-of-day record of its UTC date.
+pub async fn save_xtb_snapshot(pool: &SqlitePool, snapshot: &XtbSnapshot) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO xtb_snapshots (recorded_at, balance, equity, profit, positions)
+        VALUES (?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(snapshot.recorded_at)
+    .bind(snapshot.balance)
+    .bind(snapshot.equity)
+    .bind(snapshot.profit)
+    .bind(snapshot.positions)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO xtb_daily (date, open_equity, balance, equity, profit)
+        VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT (date) DO UPDATE SET
+            balance = excluded.balance,
+            equity = excluded.equity,
+            profit = excluded.profit
+        "#,
+    )
+    .bind(snapshot.recorded_at.date())
+    .bind(snapshot.equity)
+    .bind(snapshot.balance)
+    .bind(snapshot.equity)
+    .bind(snapshot.profit)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Snapshots since `since`, oldest first.
+pub async fn get_xtb_snapshots(pool: &SqlitePool, since: chrono::NaiveDateTime) -> anyhow::Result<Vec<XtbSnapshot>> {
+    let rows = sqlx::query_as::<_, XtbSnapshot>(
+        r#"
+        SELECT recorded_at, balance, equity, profit, positions
+        FROM xtb_snapshots
+        WHERE recorded_at >= ?
+        ORDER BY recorded_at
+        "#,
+    )
+    .bind(since)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Days since `since`, oldest first.
+pub async fn get_xtb_days(pool: &SqlitePool, since: chrono::NaiveDate) -> anyhow::Result<Vec<XtbDay>> {
+    let rows = sqlx::query_as::<_, XtbDay>(
+        r#"
+        SELECT date, open_equity, balance, equity, profit
+        FROM xtb_daily
+        WHERE date >= ?
+        ORDER BY date
+        "#,
+    )
+    .bind(since)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+pub async fn delete_xtb_snapshots_before(pool: &SqlitePool, before: chrono::NaiveDateTime) -> anyhow::Result<u64> {
+    let result = sqlx::query("DELETE FROM xtb_snapshots WHERE recorded_at < ?")
+        .bind(before)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub async fn delete_xtb_days_before(pool: &SqlitePool, before: chrono::NaiveDate) -> anyhow::Result<u64> {
+    let result = sqlx::query("DELETE FROM xtb_daily WHERE date < ?")
+        .bind(before)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub async fn get_money_settings(pool: &SqlitePool) -> anyhow::Result<MoneySettings> {
+    let row = sqlx::query_as::<_, MoneySettings>(
+        r#"
+        SELECT locale, display_currency
+        FROM money_settings
+        WHERE id = 1
+        "#,
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.unwrap_or_default())
+}
+
+pub async fn save_money_settings(pool: &SqlitePool, settings: &MoneySettings) -> anyhow::Result<()> {
+    let query = r#"
+        INSERT INTO money_settings (id, locale, display_currency)
+        VALUES (1, ?, ?)
+        ON CONFLICT (id) DO UPDATE SET locale = excluded.locale, display_currency = excluded.display_currency
+    "#;
+
+    sqlx::query(query)
+        .bind(settings.locale)
+        .bind(&settings.display_currency)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_fx_rates(pool: &SqlitePool) -> anyhow::Result<Vec<FxRate>> {
+    let rows = sqlx::query_as::<_, FxRate>(
+        r#"
+        SELECT from_currency AS "from", to_currency AS "to", rate
+        FROM fx_rates
+        ORDER BY from_currency, to_currency
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+pub async fn save_fx_rate(pool: &SqlitePool, rate: &FxRate) -> anyhow::Result<()> {
+    let query = r#"
+        INSERT INTO fx_rates (from_currency, to_currency, rate)
+        VALUES (?, ?, ?)
+        ON CONFLICT (from_currency, to_currency) DO UPDATE SET rate = excluded.rate
+    "#;
+
+    sqlx::query(query)
+        .bind(&rate.from)
+        .bind(&rate.to)
+        .bind(rate.rate)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn delete_fx_rate(pool: &SqlitePool, from: &str, to: &str) -> anyhow::Result<bool> {
+    let result = sqlx::query("DELETE FROM fx_rates WHERE from_currency = ? AND to_currency = ?")
+        .bind(from)
+        .bind(to)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct AdminUser {
+    pub id: i64,
+    pub username: String,
+    pub password_hash: String,
+}
+
+pub async fn count_admin_users(pool: &SqlitePool) -> anyhow::Result<i64> {
+    let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM admin_users")
+        .fetch_one(pool)
+        .await?;
+
+    Ok(count.0)
+}
+
+pub async fn get_admin_user_by_username(
+    pool: &SqlitePool,
+    username: &str,
+) -> anyhow::Result<Option<AdminUser>> {
+    let row = sqlx::query_as::<_, AdminUser>(
+        r#"
+        SELECT id, username, password_hash
+        FROM admin_users
+        WHERE username = ?
+        "#,
+    )
+    .bind(username)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row)
+}
+
+pub async fn create_admin_user(
+    pool: &SqlitePool,
+    username: &str,
+    password_hash: &str,
+) -> anyhow::Result<()> {
+    let query = r#"
+        INSERT INTO admin_users (username, password_hash)
+        VALUES (?, ?)
+    "#;
+
+    sqlx::query(query)
+        .bind(username)
+        .bind(password_hash)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn update_admin_password(
+    pool: &SqlitePool,
+    user_id: i64,
+    password_hash: &str,
+) -> anyhow::Result<()> {
+    let query = r#"
+        UPDATE admin_users
+        SET password_hash = ?
+        WHERE id = ?
+    "#;
+
+    sqlx::query(query)
+        .bind(password_hash)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct Session {
+    pub user_id: i64,
+    pub username: String,
+    pub csrf_token: String,
+    pub expires_at: chrono::NaiveDateTime,
+}
+
+pub async fn create_session(
+    pool: &SqlitePool,
+    token_hash: &str,
+    user_id: i64,
+    csrf_token: &str,
+    expires_at: chrono::NaiveDateTime,
+) -> anyhow::Result<()> {
+    let query = r#"
+        INSERT INTO sessions (token_hash, user_id, csrf_token, expires_at)
+        VALUES (?, ?, ?, ?)
+    "#;
+
+    sqlx::query(query)
+        .bind(token_hash)
+        .bind(user_id)
+        .bind(csrf_token)
+        .bind(expires_at)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_session(pool: &SqlitePool, token_hash: &str) -> anyhow::Result<Option<Session>> {
+    let row = sqlx::query_as::<_, Session>(
+        r#"
+        SELECT sessions.user_id, admin_users.username, sessions.csrf_token, sessions.expires_at
+        FROM sessions
+        JOIN admin_users ON admin_users.id = sessions.user_id
+        WHERE sessions.token_hash = ?
+        "#,
+    )
+    .bind(token_hash)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row)
+}
+
+pub async fn delete_session(pool: &SqlitePool, token_hash: &str) -> anyhow::Result<()> {
+    let query = r#"
+        DELETE FROM sessions
+        WHERE token_hash = ?
+    "#;
+
+    sqlx::query(query).bind(token_hash).execute(pool).await?;
+
+    Ok(())
+}
+
+pub async fn delete_sessions_for_user(pool: &SqlitePool, user_id: i64) -> anyhow::Result<()> {
+    let query = r#"
+        DELETE FROM sessions
+        WHERE user_id = ?
+    "#;
+
+    sqlx::query(query).bind(user_id).execute(pool).await?;
+
+    Ok(())
+}
+
+pub async fn delete_expired_sessions(pool: &SqlitePool) -> anyhow::Result<()> {
+    let query = r#"
+        DELETE FROM sessions
+        WHERE expires_at < ?
+    "#;
+
+    sqlx::query(query)
+        .bind(chrono::Utc::now().naive_utc())
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct ApiToken {
+    pub id: i64,
+    pub name: String,
+    pub scopes: String,
+    pub created_at: chrono::NaiveDateTime,
+    pub last_used_at: Option<chrono::NaiveDateTime>,
+}
+
+pub async fn create_api_token(
+    pool: &SqlitePool,
+    name: &str,
+    token_hash: &str,
+    scopes: &str,
+) -> anyhow::Result<i64> {
+    let query = r#"
+        INSERT INTO api_tokens (name, token_hash, scopes)
+        VALUES (?, ?, ?)
+    "#;
+
+    let result = sqlx::query(query)
+        .bind(name)
+        .bind(token_hash)
+        .bind(scopes)
+        .execute(pool)
+        .await?;
+
+    Ok(result.last_insert_rowid())
+}
+
+pub async fn get_api_token_by_hash(
+    pool: &SqlitePool,
+    token_hash: &str,
+) -> anyhow::Result<Option<ApiToken>> {
+    let row = sqlx::query_as::<_, ApiToken>(
+        r#"
+        SELECT id, name, scopes, created_at, last_used_at
+        FROM api_tokens
+        WHERE token_hash = ?
+        "#,
+    )
+    .bind(token_hash)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row)
+}
+
+pub async fn get_api_tokens(pool: &SqlitePool) -> anyhow::Result<Vec<ApiToken>> {
+    let rows = sqlx::query_as::<_, ApiToken>(
+        r#"
+        SELECT id, name, scopes, created_at, last_used_at
+        FROM api_tokens
+        ORDER BY id
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+pub async fn touch_api_token(pool: &SqlitePool, id: i64) -> anyhow::Result<()> {
+    let query = r#"
+        UPDATE api_tokens
+        SET last_used_at = ?
+        WHERE id = ?
+    "#;
+
+    sqlx::query(query)
+        .bind(chrono::Utc::now().naive_utc())
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn delete_api_token(pool: &SqlitePool, id: i64) -> anyhow::Result<bool> {
+    let query = r#"
+        DELETE FROM api_tokens
+        WHERE id = ?
+    "#;
+
+    let result = sqlx::query(query).bind(id).execute(pool).await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use sqlx::SqlitePool;
+
+    use super::{encrypt_existing_secrets, initialize_test_db, rotate_master_key};
+    use crate::crypto::{self, MasterKey};
+
+    async fn insert_plaintext_secrets(db: &SqlitePool) {
+        sqlx::query(
+            r#"
+            INSERT INTO oauth2_tokens
+                (app_name, client_secret, client_id, redirect_uri, access_token, refresh_token, expires_at, code, get_token_url)
+            VALUES ('spotify', 'client-secret', 'client-id', 'http://localhost', 'access', 'refresh', '2026-01-01 00:00:00', '', '')
+            "#,
+        )
+        .execute(db)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO xtb_credentials (user_id, password) VALUES ('12345', 'hunter2')")
+            .execute(db)
+            .await
+            .unwrap();
+    }
+
+    async fn secrets(db: &SqlitePool) -> Vec<String> {
+        let (client_secret, access_token, refresh_token): (String, String, String) =
+            sqlx::query_as("SELECT client_secret, access_token, refresh_token FROM oauth2_tokens")
+                .fetch_one(db)
+                .await
+                .unwrap();
+        let (password,): (String,) = sqlx::query_as("SELECT password FROM xtb_credentials")
+            .fetch_one(db)
+            .await
+            .unwrap();
+        vec![client_secret, access_token, refresh_token, password]
+    }
+
+    #[tokio::test]
+    async fn existing_plaintext_secrets_are_encrypted() {
+        let db = initialize_test_db().await;
+        insert_plaintext_secrets(&db).await;
+
+        encrypt_existing_secrets(&db).await.unwrap();
+
+        let secrets = secrets(&db).await;
+        assert!(secrets.iter().all(|secret| crypto::is_encrypted(secret)));
+        let plaintext: Vec<_> = secrets.iter().map(|secret| crypto::decrypt(secret).unwrap()).collect();
+        assert_eq!(plaintext, ["client-secret", "access", "refresh", "hunter2"]);
+
+        // Running it again leaves encrypted rows alone.
+        encrypt_existing_secrets(&db).await.unwrap();
+        assert_eq!(self::secrets(&db).await, secrets);
+    }
+
+    #[tokio::test]
+    async fn rotation_re_encrypts_every_secret_column() {
+        let db = initialize_test_db().await;
+        insert_plaintext_secrets(&db).await;
+        encrypt_existing_secrets(&db).await.unwrap();
+        let old = MasterKey::from_base64("AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=").unwrap();
+        let new = MasterKey::from_base64(&STANDARD.encode([7u8; 32])).unwrap();
+
+        assert_eq!(rotate_master_key(&db, &old, &new).await.unwrap(), 2);
+
+        let plaintext: Vec<_> = secrets(&db)
+            .await
+            .iter()
+            .map(|secret| {
+                assert_eq!(crypto::key_id_of(secret), Some(new.id()));
+                assert!(crypto::decrypt_with(&old, secret).is_err());
+                crypto::decrypt_with(&new, secret).unwrap()
+            })
+            .collect();
+        assert_eq!(plaintext, ["client-secret", "access", "refresh", "hunter2"]);
+    }
+}
@@ -0,0 +1,470 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use rand::Rng;
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    Client, Method, RequestBuilder, Response, StatusCode, Url,
+};
+use tokio::time::sleep;
+
+// Outbound HTTP for every integration (Spotify, weather, OAuth2 token endpoints).
+//
+// Requests that fail transiently are retried with exponential backoff, honoring Retry-After.
+// Hosts that keep failing get a circuit breaker, and hosts that rate limit us are paused, so
+// pollers fail fast instead of hammering an API that is already struggling.
+
+static SHARED: OnceLock<HttpClient> = OnceLock::new();
+
+/// The process-wide client; one connection pool and one set of breakers for all integrations.
+pub fn shared() -> &'static HttpClient {
+    SHARED.get_or_init(|| HttpClient::new(HttpPolicy::default()))
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HttpPolicy {
+    pub connect_timeout: Duration,
+    /// For the whole request, including reading the body.
+    pub timeout: Duration,
+    pub max_attempts: u32,
+    pub backoff_base: Duration,
+    pub backoff_max: Duration,
+    /// A longer Retry-After isn't waited out inline; the host is paused instead.
+    pub max_retry_after: Duration,
+    /// How long a host is paused after a 429 without Retry-After.
+    pub rate_limit_pause: Duration,
+    /// Consecutive failed requests (after retries) that open a host's circuit.
+    pub failure_threshold: u32,
+    pub open_duration: Duration,
+}
+
+impl Default for HttpPolicy {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            timeout: Duration::from_secs(15),
+            max_attempts: 3,
+            backoff_base: Duration::from_millis(500),
+            backoff_max: Duration::from_secs(8),
+            max_retry_after: Duration::from_secs(10),
+            rate_limit_pause: Duration::from_secs(30),
+            failure_threshold: 5,
+            open_duration: Duration::from_secs(60),
+        }
+    }
+}
+
+impl HttpPolicy {
+    /// Exponential backoff with ±25% jitter before retry number `attempt`.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        let delay = self
+            .backoff_base
+            .saturating_mul(1 << exponent)
+            .min(self.backoff_max);
+
+        delay.mul_f64(rand::thread_rng().gen_range(0.75..=1.25))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum HttpError {
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+    #[error("{host} is rate limiting requests; retry in {}s", .retry_in.as_secs())]
+    RateLimited { host: String, retry_in: Duration },
+    #[error("{host} keeps failing; requests are paused for {}s", .retry_in.as_secs())]
+    CircuitOpen { host: String, retry_in: Duration },
+}
+
+impl HttpError {
+    /// When the host accepts requests again, if it is paused.
+    pub fn retry_in(&self) -> Option<Duration> {
+        match self {
+            Self::Request(_) => None,
+            Self::RateLimited { retry_in, .. } | Self::CircuitOpen { retry_in, .. } => Some(*retry_in),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct HostState {
+    failures: u32,
+    /// The circuit is open until then; afterwards requests go through again and the next
+    /// failure reopens it right away.
+    open_until: Option<Instant>,
+    rate_limited_until: Option<Instant>,
+}
+
+pub struct HttpClient {
+    client: Client,
+    policy: HttpPolicy,
+    hosts: Mutex<HashMap<String, HostState>>,
+}
+
+impl HttpClient {
+    pub fn new(policy: HttpPolicy) -> Self {
+        let client = Client::builder()
+            .connect_timeout(policy.connect_timeout)
+            .timeout(policy.timeout)
+            .build()
+            .expect("failed to build HTTP client");
+
+        Self {
+            client,
+            policy,
+            hosts: Mutex::default(),
+        }
+    }
+
+    pub fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        self.client.request(method, url)
+    }
+
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        self.request(Method::GET, url)
+    }
+
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        self.request(Method::POST, url)
+    }
+
+    /// Sends a request built by this client, retrying transient failures.
+    ///
+    /// 429 and 503 mean the request wasn't processed, so they are retried for every method;
+    /// other server errors and timeouts only for idempotent ones. Error statuses that are left
+    /// once retries run out are returned as responses, except 429, which pauses the host.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, HttpError> {
+        let request = request.build()?;
+        let host = host_key(request.url());
+        self.check_host(&host)?;
+
+        let idempotent = matches!(
+            *request.method(),
+            Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS
+        );
+
+        let mut attempt = 1;
+        loop {
+            let Some(current) = request.try_clone() else {
+                // Streaming bodies can only be sent once.
+                let outcome = self.client.execute(request).await;
+                return self.record(&host, outcome, None);
+            };
+
+            let outcome = self.client.execute(current).await;
+            let retry_after = outcome
+                .as_ref()
+                .ok()
+                .and_then(|response| retry_after(response.headers()));
+            let retryable = match &outcome {
+                Ok(response) => match response.status() {
+                    StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => true,
+                    status => idempotent && status.is_server_error(),
+                },
+                // Nothing was sent when connecting failed.
+                Err(e) => e.is_connect() || (idempotent && e.is_timeout()),
+            };
+
+            if !retryable || attempt >= self.policy.max_attempts {
+                return self.record(&host, outcome, retry_after);
+            }
+
+            let delay = match retry_after {
+                Some(delay) if delay > self.policy.max_retry_after => {
+                    return self.record(&host, outcome, retry_after);
+                }
+                Some(delay) => delay,
+                None => self.policy.backoff(attempt),
+            };
+
+            let reason = match &outcome {
+                Ok(response) => response.status().to_string(),
+                Err(e) => e.to_string(),
+            };
+            println!(
+                "{} {} failed ({}), retrying in {}ms",
+                request.method(),
+                host,
+                reason,
+                delay.as_millis()
+            );
+
+            sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    fn check_host(&self, host: &str) -> Result<(), HttpError> {
+        let hosts = self.hosts.lock().unwrap();
+        let Some(state) = hosts.get(host) else {
+            return Ok(());
+        };
+
+        let now = Instant::now();
+        let remaining = |until: Option<Instant>| until.filter(|until| *until > now).map(|until| until - now);
+
+        if let Some(retry_in) = remaining(state.rate_limited_until) {
+            return Err(HttpError::RateLimited { host: host.to_string(), retry_in });
+        }
+        if let Some(retry_in) = remaining(state.open_until) {
+            return Err(HttpError::CircuitOpen { host: host.to_string(), retry_in });
+        }
+
+        Ok(())
+    }
+
+    /// Updates the host's breaker with the final outcome of a request.
+    fn record(
+        &self,
+        host: &str,
+        outcome: Result<Response, reqwest::Error>,
+        retry_after: Option<Duration>,
+    ) -> Result<Response, HttpError> {
+        let mut hosts = self.hosts.lock().unwrap();
+        let state = hosts.entry(host.to_string()).or_default();
+
+        let failed = match &outcome {
+            Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => {
+                let retry_in = retry_after.unwrap_or(self.policy.rate_limit_pause);
+                state.rate_limited_until = Some(Instant::now() + retry_in);
+                println!("{} is rate limiting requests, pausing for {}s", host, retry_in.as_secs());
+
+                return Err(HttpError::RateLimited { host: host.to_string(), retry_in });
+            }
+            Ok(response) => response.status().is_server_error(),
+            Err(e) => e.is_connect() || e.is_timeout(),
+        };
+
+        if failed {
+            state.failures += 1;
+            if state.failures >= self.policy.failure_threshold {
+                state.open_until = Some(Instant::now() + self.policy.open_duration);
+                println!(
+                    "{} failed {} times in a row, pausing requests for {}s",
+                    host,
+                    state.failures,
+                    self.policy.open_duration.as_secs()
+                );
+            }
+        } else if outcome.is_ok() {
+            *state = HostState::default();
+        }
+
+        outcome.map_err(HttpError::from)
+    }
+}
+
+fn host_key(url: &Url) -> String {
+    match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => url.to_string(),
+    }
+}
+
+/// Parses `Retry-After` as either delay seconds or an HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(delay.to_std().unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+
+    use axum::{
+        extract::State,
+        http::{header, HeaderMap, HeaderValue, StatusCode},
+        response::{IntoResponse, Response},
+        Router,
+    };
+
+    use super::{retry_after, HttpClient, HttpError, HttpPolicy};
+    use crate::test_utils::spawn_mock_server;
+
+    type Script = Arc<Mutex<(VecDeque<Response>, usize)>>;
+
+    fn fast_policy() -> HttpPolicy {
+        HttpPolicy {
+            timeout: Duration::from_millis(500),
+            backoff_base: Duration::from_millis(10),
+            backoff_max: Duration::from_millis(40),
+            max_retry_after: Duration::from_secs(2),
+            open_duration: Duration::from_millis(200),
+            failure_threshold: 2,
+            ..HttpPolicy::default()
+        }
+    }
+
+    fn status(status: StatusCode) -> Response {
+        status.into_response()
+    }
+
+    fn rate_limited(retry_after: &'static str) -> Response {
+        (StatusCode::TOO_MANY_REQUESTS, [(header::RETRY_AFTER, retry_after)]).into_response()
+    }
+
+    /// Answers with `responses` in order, then 200; returns the URL and a hit counter.
+    async fn spawn_scripted_server(responses: Vec<Response>) -> (String, Script) {
+        async fn respond(State(script): State<Script>) -> Response {
+            let mut script = script.lock().unwrap();
+            script.1 += 1;
+            script.0.pop_front().unwrap_or_else(|| "ok".into_response())
+        }
+
+        let script: Script = Arc::new(Mutex::new((responses.into(), 0)));
+        let router = Router::new().fallback(respond).with_state(script.clone());
+
+        (format!("{}/resource", spawn_mock_server(router).await), script)
+    }
+
+    fn hits(script: &Script) -> usize {
+        script.lock().unwrap().1
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retried() {
+        let (url, script) = spawn_scripted_server(vec![
+            status(StatusCode::BAD_GATEWAY),
+            status(StatusCode::SERVICE_UNAVAILABLE),
+        ])
+        .await;
+        let client = HttpClient::new(fast_policy());
+
+        let response = client.send(client.get(&url)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(hits(&script), 3);
+    }
+
+    #[tokio::test]
+    async fn post_is_only_retried_when_it_was_not_processed() {
+        let (url, script) = spawn_scripted_server(vec![
+            status(StatusCode::SERVICE_UNAVAILABLE),
+            status(StatusCode::INTERNAL_SERVER_ERROR),
+        ])
+        .await;
+        let client = HttpClient::new(fast_policy());
+
+        let response = client.send(client.post(&url)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(hits(&script), 2);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let (url, script) = spawn_scripted_server(vec![status(StatusCode::NOT_FOUND)]).await;
+        let client = HttpClient::new(fast_policy());
+
+        let response = client.send(client.get(&url)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(hits(&script), 1);
+    }
+
+    #[tokio::test]
+    async fn short_retry_after_is_waited_out() {
+        let (url, script) = spawn_scripted_server(vec![rate_limited("1")]).await;
+        let client = HttpClient::new(fast_policy());
+        let started = Instant::now();
+
+        let response = client.send(client.get(&url)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(hits(&script), 2);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn long_retry_after_pauses_the_host() {
+        let (url, script) = spawn_scripted_server(vec![rate_limited("120")]).await;
+        let client = HttpClient::new(fast_policy());
+
+        let error = client.send(client.get(&url)).await.unwrap_err();
+        assert!(matches!(error, HttpError::RateLimited { .. }));
+        assert!(error.retry_in().unwrap() > Duration::from_secs(100));
+
+        let error = client.send(client.get(&url)).await.unwrap_err();
+        assert!(matches!(error, HttpError::RateLimited { .. }));
+        assert_eq!(hits(&script), 1);
+    }
+
+    #[tokio::test]
+    async fn failing_host_opens_the_circuit_until_it_recovers() {
+        let failures = (0..6).map(|_| status(StatusCode::INTERNAL_SERVER_ERROR)).collect();
+        let (url, script) = spawn_scripted_server(failures).await;
+        let client = HttpClient::new(HttpPolicy { max_attempts: 1, ..fast_policy() });
+
+        for _ in 0..2 {
+            let response = client.send(client.get(&url)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        }
+        let error = client.send(client.get(&url)).await.unwrap_err();
+        assert!(matches!(error, HttpError::CircuitOpen { .. }));
+        assert_eq!(hits(&script), 2);
+
+        // Half open: one more failure reopens the circuit right away.
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        client.send(client.get(&url)).await.unwrap();
+        assert!(matches!(client.send(client.get(&url)).await, Err(HttpError::CircuitOpen { .. })));
+
+        // A success closes it again.
+        script.lock().unwrap().0.clear();
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(client.send(client.get(&url)).await.unwrap().status(), StatusCode::OK);
+        client.send(client.get(&url)).await.unwrap();
+        assert_eq!(hits(&script), 5);
+    }
+
+    #[tokio::test]
+    async fn slow_responses_time_out() {
+        async fn slow() -> &'static str {
+            tokio::time::sleep(Duration::from_secs(2)).await;
+            "too late"
+        }
+
+        let url = spawn_mock_server(Router::new().fallback(slow)).await;
+        let client = HttpClient::new(HttpPolicy {
+            timeout: Duration::from_millis(50),
+            max_attempts: 1,
+            ..fast_policy()
+        });
+
+        match client.send(client.get(&url)).await {
+            Err(HttpError::Request(e)) => assert!(e.is_timeout()),
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn retry_after_accepts_seconds_and_dates() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RETRY_AFTER, HeaderValue::from_static("7"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(7)));
+
+        headers.insert(header::RETRY_AFTER, HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+
+        let in_a_minute = (chrono::Utc::now() + chrono::Duration::seconds(60)).to_rfc2822();
+        headers.insert(header::RETRY_AFTER, HeaderValue::from_str(&in_a_minute).unwrap());
+        assert!(retry_after(&headers).unwrap() > Duration::from_secs(55));
+
+        headers.insert(header::RETRY_AFTER, HeaderValue::from_static("soon"));
+        assert_eq!(retry_after(&headers), None);
+    }
+}
@@ -2,27 +2,44 @@ use std::collections::HashMap;
 
 use std::sync::Arc;
 
-use db::initialize_db;
+use crypto::MasterKey;
+use db::{initialize_db, rotate_master_key};
+
+use supervisor::Supervisor;
 
 use tcp::{broadcast_new_data, handle_client, heartbeat_task, StateMessage};
 use tokio::net::TcpListener;
 use tokio::sync::{mpsc, RwLock};
+use web::auth::ensure_admin_user;
 use web::initialize_axum_server;
-use web::spotify::spotify_polling_task;
-use web::weather::weather_polling_task;
-use web::xtb::initialize_xtb_websocket;
+use web::oauth2::refresher::TokenRefresher;
+use web::spotify::SpotifyPlayer;
+use web::weather::OpenMeteo;
 
 type Clients = Arc<RwLock<HashMap<String, mpsc::Sender<Vec<u8>>>>>;
 
 pub mod web;
 pub mod db;
 pub mod tcp;
+pub mod crypto;
+pub mod http;
+pub mod supervisor;
+pub mod sources;
+pub mod alerts;
+pub mod money;
+#[cfg(test)]
+mod test_utils;
 
 
 #[tokio::main]
 async fn main() {
     dotenv::dotenv().ok();
 
+    if let Err(e) = crypto::init_from_env() {
+        eprintln!("Failed to load master key: {}", e);
+        return;
+    }
+
     let db = match initialize_db().await {
         Ok(db) => db,
         Err(e) => {
@@ -31,17 +48,34 @@ async fn main() {
         }
     };
 
+    if std::env::args().nth(1).as_deref() == Some("rotate-master-key") {
+        if let Err(e) = run_master_key_rotation(&db).await {
+            eprintln!("Failed to rotate master key: {}", e);
+        }
+        return;
+    }
+
+    if let Err(e) = ensure_admin_user(&db).await {
+        eprintln!("Failed to create admin account: {}", e);
+        return;
+    }
+
     let clients: Clients = Arc::new(RwLock::new(HashMap::new()));
     let listener = TcpListener::bind("0.0.0.0:2699").await.unwrap();
     println!("Listening on port 2699");
 
+    let token_refresher = TokenRefresher::new(db.clone());
+    let spotify_player = SpotifyPlayer::new(token_refresher.clone());
+
     let (state_sender, state_receiver) = mpsc::channel::<StateMessage>(100);
-    tokio::spawn(initialize_axum_server(db.clone()));
+    let open_meteo = OpenMeteo::new();
+    let sources = sources::default_sources(db.clone(), spotify_player.clone(), open_meteo.clone(), Supervisor::new());
+    tokio::spawn(initialize_axum_server(db.clone(), spotify_player.clone(), open_meteo, sources.clone()));
     tokio::spawn(broadcast_new_data(clients.clone(), state_receiver));
     tokio::spawn(heartbeat_task(state_sender.clone()));
-    tokio::spawn(spotify_polling_task(db.clone(), state_sender.clone()));
-    tokio::spawn(weather_polling_task(state_sender.clone()));
-    tokio::spawn(initialize_xtb_websocket(db.clone(), state_sender.clone()));
+    tokio::spawn(token_refresher.run());
+
+    sources.start(state_sender.clone());
 
     loop {
         if let Ok((stream, addr)) = listener.accept().await {
@@ -51,7 +85,20 @@ async fn main() {
             clients.write().await.insert(peer_addr.clone(), sender);
 
             let clients_clone = clients.clone();
-            tokio::spawn(handle_client(stream, peer_addr, clients_clone, receiver));
+            tokio::spawn(handle_client(stream, peer_addr, clients_clone, receiver, sources.clone()));
         }
     }
 }
+
+/// `server rotate-master-key`: re-wraps every stored secret from `OLD_MASTER_KEY` to `MASTER_KEY`.
+async fn run_master_key_rotation(db: &sqlx::SqlitePool) -> anyhow::Result<()> {
+    let old = MasterKey::from_env("OLD_MASTER_KEY")?
+        .ok_or_else(|| anyhow::anyhow!("OLD_MASTER_KEY is not set"))?;
+    let new = MasterKey::from_env("MASTER_KEY")?
+        .ok_or_else(|| anyhow::anyhow!("MASTER_KEY is not set"))?;
+
+    let rotated = rotate_master_key(db, &old, &new).await?;
+    println!("Re-encrypted {} rows from master key {} to {}", rotated, old.id(), new.id());
+
+    Ok(())
+}
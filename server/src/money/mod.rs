@@ -0,0 +1,238 @@
+use serde::{Deserialize, Serialize};
+
+/// How amounts are written on the screens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum Locale {
+    /// `1,234.56`
+    #[default]
+    En,
+    /// `1 234,56`, and `1234,56` since Polish doesn't group four digits.
+    Pl,
+}
+
+impl Locale {
+    fn separators(self) -> (char, char) {
+        match self {
+            Self::En => (',', '.'),
+            Self::Pl => (' ', ','),
+        }
+    }
+
+    /// Digits the integer part needs before it is grouped at all.
+    fn min_grouped_digits(self) -> usize {
+        match self {
+            Self::En => 4,
+            Self::Pl => 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct MoneySettings {
+    pub locale: Locale,
+    /// ISO 4217 code amounts are converted to; `None` keeps the account currency.
+    #[serde(rename = "displayCurrency")]
+    pub display_currency: Option<String>,
+}
+
+/// How many units of `to` one unit of `from` buys.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct FxRate {
+    pub from: String,
+    pub to: String,
+    pub rate: f64,
+}
+
+/// Upper-cases `currency` as ISO 4217 writes it, or says why it can't be a currency code.
+pub fn normalize_currency(currency: &str) -> Result<String, String> {
+    let currency = currency.trim().to_uppercase();
+    if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(format!("{} is not a currency code", currency));
+    }
+
+    Ok(currency)
+}
+
+/// Rounds half away from zero on the decimal digits `amount` prints as, so 1.005 is 1.01 rather
+/// than the 1.00 its binary value would round to.
+fn to_cents(amount: f64) -> i64 {
+    if !amount.is_finite() {
+        return 0;
+    }
+
+    // Floats print without an exponent, so this is always `digits[.digits]`.
+    let text = amount.abs().to_string();
+    let (whole, fraction) = text.split_once('.').unwrap_or((&text, ""));
+    let fraction = format!("{:0<3}", fraction);
+    let whole: i64 = whole.parse().unwrap_or(i64::MAX / 100);
+    let mut cents = whole.saturating_mul(100).saturating_add(fraction[..2].parse().unwrap_or(0));
+    if fraction.as_bytes()[2] >= b'5' {
+        cents = cents.saturating_add(1);
+    }
+
+    if amount < 0.0 {
+        -cents
+    } else {
+        cents
+    }
+}
+
+/// Formats amounts of one account in its currency, or in the display currency when a stored rate
+/// converts to it. Every money value a provider shows goes through here.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoneyFormat {
+    locale: Locale,
+    /// `None` when the provider didn't say; amounts are then written without a code.
+    currency: Option<String>,
+    rate: f64,
+}
+
+impl Default for MoneyFormat {
+    fn default() -> Self {
+        Self::new(&MoneySettings::default(), None, &[])
+    }
+}
+
+impl MoneyFormat {
+    /// Falls back to `currency` when no rate, nor its inverse, converts it to the display
+    /// currency.
+    pub fn new(settings: &MoneySettings, currency: Option<&str>, rates: &[FxRate]) -> Self {
+        let mut format = Self {
+            locale: settings.locale,
+            currency: currency.map(str::to_string),
+            rate: 1.0,
+        };
+
+        let (Some(from), Some(to)) = (currency, settings.display_currency.as_deref()) else {
+            return format;
+        };
+        let direct = rates.iter().find(|rate| rate.from == from && rate.to == to).map(|rate| rate.rate);
+        let inverse = rates.iter().find(|rate| rate.from == to && rate.to == from).map(|rate| 1.0 / rate.rate);
+        match direct.or(inverse).filter(|rate| rate.is_finite() && *rate > 0.0) {
+            Some(rate) => {
+                format.currency = Some(to.to_string());
+                format.rate = rate;
+            }
+            None if from != to => println!("No FX rate from {} to {}; showing {}", from, to, from),
+            None => {}
+        }
+
+        format
+    }
+
+    /// The currency amounts are shown in.
+    pub fn currency(&self) -> Option<&str> {
+        self.currency.as_deref()
+    }
+
+    /// `amount` in the display currency, rounded to cents.
+    pub fn convert(&self, amount: f64) -> f64 {
+        to_cents(amount * self.rate) as f64 / 100.0
+    }
+
+    /// `amount` with the locale's separators and without a currency code, e.g. `-1 234,56`.
+    pub fn amount(&self, amount: f64) -> String {
+        let cents = to_cents(amount * self.rate);
+        let (group, decimal) = self.locale.separators();
+        let digits = (cents.unsigned_abs() / 100).to_string();
+
+        let mut whole = String::new();
+        for (i, digit) in digits.chars().enumerate() {
+            let remaining = digits.len() - i;
+            if i > 0 && remaining.is_multiple_of(3) && digits.len() >= self.locale.min_grouped_digits() {
+                whole.push(group);
+            }
+            whole.push(digit);
+        }
+
+        let sign = if cents < 0 { "-" } else { "" };
+        format!("{}{}{}{:02}", sign, whole, decimal, cents.unsigned_abs() % 100)
+    }
+
+    /// Like `amount`, with a `+` on gains, as P/L is written.
+    pub fn signed(&self, amount: f64) -> String {
+        let text = self.amount(amount);
+        if to_cents(amount * self.rate) > 0 {
+            format!("+{}", text)
+        } else {
+            text
+        }
+    }
+
+    /// `amount` followed by the currency code, e.g. `1 234,56 PLN`.
+    pub fn format(&self, amount: f64) -> String {
+        self.with_currency(self.amount(amount))
+    }
+
+    pub fn format_signed(&self, amount: f64) -> String {
+        self.with_currency(self.signed(amount))
+    }
+
+    fn with_currency(&self, text: String) -> String {
+        match &self.currency {
+            Some(currency) => format!("{} {}", text, currency),
+            None => text,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_currency, FxRate, Locale, MoneyFormat, MoneySettings};
+
+    fn settings(locale: Locale, display_currency: Option<&str>) -> MoneySettings {
+        MoneySettings {
+            locale,
+            display_currency: display_currency.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn amounts_use_the_locale_separators() {
+        let en = MoneyFormat::new(&settings(Locale::En, None), Some("USD"), &[]);
+        let pl = MoneyFormat::new(&settings(Locale::Pl, None), Some("PLN"), &[]);
+
+        assert_eq!(en.format(1234567.891), "1,234,567.89 USD");
+        assert_eq!(en.amount(-999.5), "-999.50");
+        assert_eq!(pl.format(-1234567.891), "-1 234 567,89 PLN");
+        assert_eq!(pl.amount(1234.5), "1234,50");
+        assert_eq!(pl.amount(12345.0), "12 345,00");
+        assert_eq!(pl.format_signed(30.0), "+30,00 PLN");
+        assert_eq!(pl.signed(-0.001), "0,00");
+        assert_eq!(MoneyFormat::default().format(5.0), "5.00");
+    }
+
+    #[test]
+    fn halves_round_away_from_zero() {
+        let format = MoneyFormat::default();
+
+        assert_eq!(format.amount(1.005), "1.01");
+        assert_eq!(format.amount(-2.675), "-2.68");
+        assert_eq!(format.amount(0.994), "0.99");
+        assert_eq!(format.amount(f64::NAN), "0.00");
+    }
+
+    #[test]
+    fn amounts_are_converted_with_a_stored_rate_or_its_inverse() {
+        let rates = [FxRate { from: "EUR".to_string(), to: "PLN".to_string(), rate: 4.25 }];
+
+        let direct = MoneyFormat::new(&settings(Locale::Pl, Some("PLN")), Some("EUR"), &rates);
+        assert_eq!(direct.format(100.0), "425,00 PLN");
+        assert_eq!(direct.convert(1.0), 4.25);
+
+        let inverse = MoneyFormat::new(&settings(Locale::En, Some("EUR")), Some("PLN"), &rates);
+        assert_eq!(inverse.format(425.0), "100.00 EUR");
+
+        let missing = MoneyFormat::new(&settings(Locale::En, Some("USD")), Some("PLN"), &rates);
+        assert_eq!((missing.currency(), missing.format(10.0).as_str()), (Some("PLN"), "10.00 PLN"));
+    }
+
+    #[test]
+    fn currencies_are_normalized() {
+        assert_eq!(normalize_currency(" pln "), Ok("PLN".to_string()));
+        assert!(normalize_currency("ZŁ").is_err());
+        assert!(normalize_currency("EURO").is_err());
+    }
+}
@@ -0,0 +1,443 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tokio::{
+    sync::{mpsc, Notify},
+    time::{interval, sleep},
+};
+
+use crate::{
+    supervisor::{ProviderStatus, Supervisor},
+    tcp::StateMessage,
+    web::{
+        spotify::{SpotifyPlayer, SpotifySource},
+        weather::{air_quality::AirQualitySource, OpenMeteo, WeatherSource},
+        xtb::XtbSource,
+    },
+};
+
+/// Sources woken through `poll_now` wait this long first; upstream APIs apply commands
+/// asynchronously, so polling right away often returns the old state.
+const POLL_NOW_SETTLE_MS: u64 = 500;
+/// Consecutive failed polls after which a source fails, so the supervisor backs it off and counts
+/// the restart; single failures are retried on the next interval.
+const MAX_POLL_FAILURES: u32 = 3;
+
+/// Every source the screen can show, in one place. Adding a widget means adding it here.
+pub fn default_sources(db: SqlitePool, player: SpotifyPlayer, open_meteo: OpenMeteo, supervisor: Supervisor) -> Sources {
+    Sources::new(
+        supervisor,
+        vec![
+            Arc::new(SpotifySource::new(player)),
+            Arc::new(WeatherSource::new(db.clone(), open_meteo.clone())),
+            Arc::new(AirQualitySource::new(db.clone(), open_meteo)),
+            Arc::new(XtbSource::new(db)),
+        ],
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceMode {
+    /// `poll` is called every `interval`.
+    Poll { interval: Duration },
+    /// `stream` runs for as long as the source is up and sends updates as they come.
+    Stream,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldKind {
+    Text,
+    Secret,
+    Number,
+    Url,
+}
+
+/// A setting the dashboard collects for a source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigField {
+    pub key: &'static str,
+    pub label: &'static str,
+    pub kind: FieldKind,
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Health {
+    Healthy,
+    /// Missing configuration; restarting won't help until it is provided.
+    Unconfigured { reason: String },
+    Degraded { reason: String },
+}
+
+/// A provider of data for one app on the screen.
+#[async_trait]
+pub trait DataSource: Send + Sync {
+    /// Supervisor and API name, e.g. `weather`.
+    fn name(&self) -> &'static str;
+
+    /// The screen app this source's messages are for, e.g. `Weather`.
+    fn app(&self) -> &'static str;
+
+    fn mode(&self) -> SourceMode;
+
+    fn config_schema(&self) -> Vec<ConfigField> {
+        Vec::new()
+    }
+
+    async fn health(&self) -> Health {
+        Health::Healthy
+    }
+
+    /// What to send to the screens now; empty when nothing changed.
+    async fn poll(&self) -> anyhow::Result<Vec<StateMessage>> {
+        Err(anyhow::anyhow!("{} does not poll", self.name()))
+    }
+
+    async fn stream(&self, _sender: mpsc::Sender<StateMessage>) -> anyhow::Result<()> {
+        Err(anyhow::anyhow!("{} does not stream", self.name()))
+    }
+
+    /// Notified when a polling source should poll before its interval is up, e.g. after a
+    /// command changed what it would report.
+    fn poll_now(&self) -> Option<Arc<Notify>> {
+        None
+    }
+
+    /// Notified when settings the source reads changed, so it picks them up without a restart;
+    /// sources without one are restarted instead.
+    fn reload(&self) -> Option<Arc<Notify>> {
+        None
+    }
+
+    /// Handles an event sent by the screen for this source's app, e.g. a button press.
+    async fn handle_event(&self, event: &str) -> anyhow::Result<()> {
+        Err(anyhow::anyhow!("{} does not accept events, got {}", self.name(), event))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct SourceStats {
+    last_update_at: Option<DateTime<Utc>>,
+    last_poll_error: Option<String>,
+}
+
+/// What `GET /providers` reports for every source.
+#[derive(Debug, Serialize)]
+pub struct SourceStatus {
+    #[serde(flatten)]
+    pub provider: ProviderStatus,
+    pub app: &'static str,
+    pub mode: &'static str,
+    #[serde(rename = "intervalSecs")]
+    pub interval_secs: Option<u64>,
+    #[serde(rename = "configSchema")]
+    pub config_schema: Vec<ConfigField>,
+    pub health: Health,
+    #[serde(rename = "lastUpdateAt")]
+    pub last_update_at: Option<DateTime<Utc>>,
+    #[serde(rename = "lastPollError")]
+    pub last_poll_error: Option<String>,
+}
+
+/// The registered sources and the supervisor that runs them.
+#[derive(Clone)]
+pub struct Sources {
+    supervisor: Supervisor,
+    sources: Arc<Vec<Arc<dyn DataSource>>>,
+    stats: Arc<Mutex<HashMap<&'static str, SourceStats>>>,
+}
+
+impl Sources {
+    pub fn new(supervisor: Supervisor, sources: Vec<Arc<dyn DataSource>>) -> Self {
+        Self {
+            supervisor,
+            sources: Arc::new(sources),
+            stats: Arc::default(),
+        }
+    }
+
+    pub fn supervisor(&self) -> &Supervisor {
+        &self.supervisor
+    }
+
+    /// Hands every source to the supervisor, which starts it.
+    pub fn start(&self, sender: mpsc::Sender<StateMessage>) {
+        for source in self.sources.iter() {
+            let (this, source, sender) = (self.clone(), source.clone(), sender.clone());
+            self.supervisor.register(source.name(), move || {
+                this.clone().run(source.clone(), sender.clone())
+            });
+        }
+    }
+
+    async fn run(self, source: Arc<dyn DataSource>, sender: mpsc::Sender<StateMessage>) -> anyhow::Result<()> {
+        let period = match source.mode() {
+            SourceMode::Poll { interval } => interval,
+            SourceMode::Stream => return source.stream(sender).await,
+        };
+
+        let mut interval = interval(period);
+        let poll_now = source.poll_now().unwrap_or_default();
+        let mut failures = 0;
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = poll_now.notified() => {
+                    sleep(Duration::from_millis(POLL_NOW_SETTLE_MS)).await;
+                    interval.reset();
+                }
+            }
+
+            let messages = match source.poll().await {
+                Ok(messages) => messages,
+                Err(e) => {
+                    println!("Failed to poll {}: {}", source.name(), e);
+                    self.stats.lock().unwrap().entry(source.name()).or_default().last_poll_error = Some(e.to_string());
+                    failures += 1;
+                    if failures >= MAX_POLL_FAILURES {
+                        return Err(anyhow::anyhow!("{} polls in a row failed, the last with: {}", failures, e));
+                    }
+                    continue;
+                }
+            };
+            failures = 0;
+
+            if !messages.is_empty() {
+                let mut stats = self.stats.lock().unwrap();
+                let stats = stats.entry(source.name()).or_default();
+                stats.last_update_at = Some(Utc::now());
+                stats.last_poll_error = None;
+            }
+            for message in messages {
+                sender.send(message).await?;
+            }
+        }
+    }
+
+    /// Has a source pick up its changed settings: in place where it can, otherwise by restarting
+    /// it if it is running.
+    pub fn reload(&self, name: &str) {
+        match self.sources.iter().find(|source| source.name() == name).and_then(|source| source.reload()) {
+            Some(reload) => reload.notify_one(),
+            None => {
+                self.supervisor.restart(name);
+            }
+        }
+    }
+
+    /// Routes an event from the screen to the source of its app.
+    pub async fn handle_event(&self, app: &str, event: &str) -> anyhow::Result<()> {
+        let source = self
+            .sources
+            .iter()
+            .find(|source| source.app() == app)
+            .ok_or_else(|| anyhow::anyhow!("No source for app {}", app))?;
+
+        source.handle_event(event).await
+    }
+
+    pub async fn statuses(&self) -> Vec<SourceStatus> {
+        let mut statuses = Vec::new();
+        for source in self.sources.iter() {
+            let (mode, interval_secs) = match source.mode() {
+                SourceMode::Poll { interval } => ("poll", Some(interval.as_secs())),
+                SourceMode::Stream => ("stream", None),
+            };
+            let stats = self.stats.lock().unwrap().get(source.name()).cloned().unwrap_or_default();
+
+            statuses.push(SourceStatus {
+                provider: self
+                    .supervisor
+                    .status(source.name())
+                    .unwrap_or_else(|| ProviderStatus::stopped(source.name())),
+                app: source.app(),
+                mode,
+                interval_secs,
+                config_schema: source.config_schema(),
+                health: source.health().await,
+                last_update_at: stats.last_update_at,
+                last_poll_error: stats.last_poll_error,
+            });
+        }
+        statuses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+    use tokio::{sync::mpsc, time::timeout};
+
+    use super::{DataSource, Health, SourceMode, Sources};
+    use crate::{supervisor::Supervisor, tcp::StateMessage};
+
+    /// Counts polls, reporting a change on odd ones and failing on every third.
+    #[derive(Default)]
+    struct Counter {
+        polls: AtomicU32,
+        events: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl DataSource for Counter {
+        fn name(&self) -> &'static str {
+            "counter"
+        }
+
+        fn app(&self) -> &'static str {
+            "Counter"
+        }
+
+        fn mode(&self) -> SourceMode {
+            SourceMode::Poll { interval: Duration::from_millis(10) }
+        }
+
+        async fn health(&self) -> Health {
+            Health::Degraded { reason: "counting".to_string() }
+        }
+
+        async fn poll(&self) -> anyhow::Result<Vec<StateMessage>> {
+            let poll = self.polls.fetch_add(1, Ordering::SeqCst) + 1;
+            if poll.is_multiple_of(3) {
+                return Err(anyhow::anyhow!("poll {} failed", poll));
+            }
+            if poll.is_multiple_of(2) {
+                return Ok(Vec::new());
+            }
+            Ok(vec![StateMessage::data(self.app(), poll.to_string())])
+        }
+
+        async fn handle_event(&self, event: &str) -> anyhow::Result<()> {
+            self.events.lock().unwrap().push(event.to_string());
+            Ok(())
+        }
+    }
+
+    fn sources(counter: &Arc<Counter>) -> Sources {
+        Sources::new(Supervisor::new(), vec![counter.clone()])
+    }
+
+    async fn next_payload(receiver: &mut mpsc::Receiver<StateMessage>) -> String {
+        match timeout(Duration::from_secs(1), receiver.recv()).await.unwrap().unwrap() {
+            StateMessage::Data { app, payload } => {
+                assert_eq!(app, "Counter");
+                payload
+            }
+            _ => panic!("expected a data message"),
+        }
+    }
+
+    #[tokio::test]
+    async fn polling_sources_only_send_changes() {
+        let counter = Arc::new(Counter::default());
+        let sources = sources(&counter);
+        let (sender, mut receiver) = mpsc::channel(10);
+
+        sources.start(sender);
+
+        assert_eq!(next_payload(&mut receiver).await, "1");
+        assert_eq!(next_payload(&mut receiver).await, "5");
+        assert_eq!(next_payload(&mut receiver).await, "7");
+        sources.supervisor().stop("counter");
+    }
+
+    #[tokio::test]
+    async fn statuses_describe_sources() {
+        let counter = Arc::new(Counter::default());
+        let sources = sources(&counter);
+        let (sender, mut receiver) = mpsc::channel(10);
+
+        let statuses = sources.statuses().await;
+        assert_eq!(statuses[0].provider.name, "counter");
+        assert_eq!(statuses[0].mode, "poll");
+        assert_eq!(statuses[0].interval_secs, Some(0));
+        assert_eq!(statuses[0].health, Health::Degraded { reason: "counting".to_string() });
+        assert!(statuses[0].last_update_at.is_none());
+
+        sources.start(sender);
+        next_payload(&mut receiver).await;
+        next_payload(&mut receiver).await;
+        sources.supervisor().stop("counter");
+
+        let status = sources.statuses().await.remove(0);
+        assert!(status.last_update_at.is_some());
+        // The failed third poll is cleared by the fifth, which sent an update.
+        assert_eq!(status.last_poll_error, None);
+    }
+
+    /// Fails every poll.
+    struct Broken;
+
+    #[async_trait]
+    impl DataSource for Broken {
+        fn name(&self) -> &'static str {
+            "broken"
+        }
+
+        fn app(&self) -> &'static str {
+            "Broken"
+        }
+
+        fn mode(&self) -> SourceMode {
+            SourceMode::Poll { interval: Duration::from_millis(10) }
+        }
+
+        async fn poll(&self) -> anyhow::Result<Vec<StateMessage>> {
+            Err(anyhow::anyhow!("upstream is down"))
+        }
+    }
+
+    #[tokio::test]
+    async fn repeated_poll_failures_fail_the_source() {
+        let sources = Sources::new(
+            Supervisor::with_backoff(Duration::from_millis(10), Duration::from_millis(20)),
+            vec![Arc::new(Broken)],
+        );
+        let (sender, _receiver) = mpsc::channel(10);
+
+        sources.start(sender);
+
+        let status = timeout(Duration::from_secs(1), async {
+            loop {
+                let status = sources.supervisor().status("broken").unwrap();
+                if status.restarts > 0 {
+                    break status;
+                }
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+        sources.supervisor().stop("broken");
+        assert_eq!(status.last_error.as_deref(), Some("3 polls in a row failed, the last with: upstream is down"));
+    }
+
+    #[tokio::test]
+    async fn events_are_routed_by_app() {
+        let counter = Arc::new(Counter::default());
+        let sources = sources(&counter);
+
+        sources.handle_event("Counter", "next").await.unwrap();
+        let unknown = sources.handle_event("Clock", "next").await.unwrap_err();
+
+        assert_eq!(*counter.events.lock().unwrap(), vec!["next".to_string()]);
+        assert_eq!(unknown.to_string(), "No source for app Clock");
+    }
+}
@@ -0,0 +1,490 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::Serialize;
+use tokio::{
+    task::{AbortHandle, JoinHandle},
+    time::sleep,
+};
+
+const BACKOFF_BASE_SECS: u64 = 1;
+const BACKOFF_MAX_SECS: u64 = 5 * 60;
+/// A provider that ran this long before failing is considered to have recovered in between, so
+/// its next restart starts the backoff over.
+const HEALTHY_RUN_SECS: u64 = 60;
+
+type ProviderFuture = Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send>>;
+type ProviderFactory = Arc<dyn Fn() -> ProviderFuture + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderState {
+    Running,
+    /// Failed and waiting to be restarted.
+    BackingOff,
+    Stopped,
+}
+
+/// What `GET /providers` reports for every provider.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderStatus {
+    pub name: String,
+    pub state: ProviderState,
+    pub restarts: u32,
+    #[serde(rename = "consecutiveFailures")]
+    pub consecutive_failures: u32,
+    #[serde(rename = "lastError")]
+    pub last_error: Option<String>,
+    #[serde(rename = "lastFailureAt")]
+    pub last_failure_at: Option<DateTime<Utc>>,
+    #[serde(rename = "nextRestartAt")]
+    pub next_restart_at: Option<DateTime<Utc>>,
+}
+
+impl ProviderStatus {
+    pub fn stopped(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            state: ProviderState::Stopped,
+            restarts: 0,
+            consecutive_failures: 0,
+            last_error: None,
+            last_failure_at: None,
+            next_restart_at: None,
+        }
+    }
+}
+
+struct Provider {
+    factory: ProviderFactory,
+    status: ProviderStatus,
+    /// The restart loop, and the provider task it is currently waiting on.
+    supervisor: Option<JoinHandle<()>>,
+    task: Option<AbortHandle>,
+    /// Bumped by every start and stop. A restart loop only spawns tasks for the generation it
+    /// was started in, so one aborted too late can't spawn a task nothing tracks.
+    generation: u64,
+}
+
+/// Owns the long-running provider tasks (Spotify, weather, XTB), restarting them with backoff
+/// when they fail and starting or stopping them on request.
+#[derive(Clone)]
+pub struct Supervisor {
+    providers: Arc<Mutex<HashMap<String, Provider>>>,
+    backoff_base: Duration,
+    backoff_max: Duration,
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        Self::with_backoff(Duration::from_secs(BACKOFF_BASE_SECS), Duration::from_secs(BACKOFF_MAX_SECS))
+    }
+
+    pub fn with_backoff(backoff_base: Duration, backoff_max: Duration) -> Self {
+        Self {
+            providers: Arc::default(),
+            backoff_base,
+            backoff_max,
+        }
+    }
+
+    /// Adds a provider and starts it. `task` is called again for every restart.
+    pub fn register<F, Fut>(&self, name: &str, task: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        let factory: ProviderFactory = Arc::new(move || Box::pin(task()));
+        let status = ProviderStatus::stopped(name);
+
+        let mut providers = self.providers.lock().unwrap();
+        let generation = providers.get(name).map_or(0, |provider| provider.generation + 1);
+        let previous = providers.insert(
+            name.to_string(),
+            Provider {
+                factory,
+                status,
+                supervisor: None,
+                task: None,
+                generation,
+            },
+        );
+        drop(providers);
+        if let Some(previous) = previous {
+            abort(previous);
+        }
+
+        self.start(name);
+    }
+
+    /// Starts a stopped provider; returns `false` if there is no such provider.
+    pub fn start(&self, name: &str) -> bool {
+        let mut providers = self.providers.lock().unwrap();
+        let Some(provider) = providers.get_mut(name) else {
+            return false;
+        };
+
+        if provider.supervisor.is_none() {
+            provider.status.state = ProviderState::Running;
+            provider.status.consecutive_failures = 0;
+            provider.status.next_restart_at = None;
+            provider.generation += 1;
+            let supervise = self.clone().supervise(name.to_string(), provider.generation);
+            provider.supervisor = Some(tokio::spawn(supervise));
+            println!("Started provider {}", name);
+        }
+
+        true
+    }
+
+    /// Stops a provider until it is started again; returns `false` if there is no such provider.
+    pub fn stop(&self, name: &str) -> bool {
+        let mut providers = self.providers.lock().unwrap();
+        let Some(provider) = providers.get_mut(name) else {
+            return false;
+        };
+
+        if let Some(supervisor) = provider.supervisor.take() {
+            supervisor.abort();
+            println!("Stopped provider {}", name);
+        }
+        if let Some(task) = provider.task.take() {
+            task.abort();
+        }
+        provider.generation += 1;
+        provider.status.state = ProviderState::Stopped;
+        provider.status.next_restart_at = None;
+
+        true
+    }
+
+    /// Restarts a running provider right away, e.g. after its configuration changed. One that
+    /// was stopped stays stopped; returns `false` if there is no such provider.
+    pub fn restart(&self, name: &str) -> bool {
+        let running = match self.providers.lock().unwrap().get(name) {
+            Some(provider) => provider.supervisor.is_some(),
+            None => return false,
+        };
+
+        if running {
+            self.stop(name);
+            self.start(name);
+        }
+        true
+    }
+
+    pub fn status(&self, name: &str) -> Option<ProviderStatus> {
+        self.providers
+            .lock()
+            .unwrap()
+            .get(name)
+            .map(|provider| provider.status.clone())
+    }
+
+    pub fn statuses(&self) -> Vec<ProviderStatus> {
+        let mut statuses: Vec<ProviderStatus> = self
+            .providers
+            .lock()
+            .unwrap()
+            .values()
+            .map(|provider| provider.status.clone())
+            .collect();
+        statuses.sort_by(|a, b| a.name.cmp(&b.name));
+        statuses
+    }
+
+    async fn supervise(self, name: String, generation: u64) {
+        loop {
+            let Some(task) = self.spawn_task(&name, generation) else {
+                return;
+            };
+
+            let started_at = Instant::now();
+            let error = match task.await {
+                Ok(Ok(())) => "Exited".to_string(),
+                Ok(Err(e)) => e.to_string(),
+                Err(e) if e.is_panic() => format!("Panicked: {}", panic_message(e.into_panic())),
+                Err(_) => return,
+            };
+
+            let delay = {
+                let mut providers = self.providers.lock().unwrap();
+                let Some(provider) = providers.get_mut(&name) else {
+                    return;
+                };
+                if provider.generation != generation {
+                    return;
+                }
+                let status = &mut provider.status;
+
+                if started_at.elapsed() >= Duration::from_secs(HEALTHY_RUN_SECS) {
+                    status.consecutive_failures = 0;
+                }
+                status.consecutive_failures += 1;
+                let delay = self.backoff(status.consecutive_failures);
+
+                status.state = ProviderState::BackingOff;
+                status.last_error = Some(error.clone());
+                status.last_failure_at = Some(Utc::now());
+                status.next_restart_at = chrono::Duration::from_std(delay).ok().map(|delay| Utc::now() + delay);
+                provider.task = None;
+
+                delay
+            };
+
+            println!("Provider {} failed ({}), restarting in {}s", name, error, delay.as_secs());
+            sleep(delay).await;
+        }
+    }
+
+    /// Exponential backoff with ±25% jitter, so providers that fail together don't restart in
+    /// lockstep.
+    fn backoff(&self, failures: u32) -> Duration {
+        let exponent = failures.saturating_sub(1).min(16);
+        let delay = self.backoff_base.saturating_mul(1 << exponent).min(self.backoff_max);
+
+        delay.mul_f64(rand::thread_rng().gen_range(0.75..=1.25))
+    }
+
+    /// `None` once the provider was stopped or restarted since `generation` started.
+    fn spawn_task(&self, name: &str, generation: u64) -> Option<JoinHandle<anyhow::Result<()>>> {
+        let mut providers = self.providers.lock().unwrap();
+        let provider = providers.get_mut(name).filter(|provider| provider.generation == generation)?;
+
+        if provider.status.state == ProviderState::BackingOff {
+            provider.status.restarts += 1;
+        }
+        provider.status.state = ProviderState::Running;
+        provider.status.next_restart_at = None;
+
+        let task = tokio::spawn((provider.factory)());
+        provider.task = Some(task.abort_handle());
+        Some(task)
+    }
+}
+
+fn abort(provider: Provider) {
+    if let Some(supervisor) = provider.supervisor {
+        supervisor.abort();
+    }
+    if let Some(task) = provider.task {
+        task.abort();
+    }
+}
+
+fn panic_message(panic: Box<dyn std::any::Any + Send>) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use tokio::time::sleep;
+
+    use super::{ProviderState, ProviderStatus, Supervisor};
+
+    fn test_supervisor() -> Supervisor {
+        Supervisor::with_backoff(Duration::from_millis(10), Duration::from_millis(20))
+    }
+
+    /// Waits up to a second for `condition`, which depends on tasks running in the background.
+    async fn eventually(condition: impl Fn() -> bool) {
+        for _ in 0..100 {
+            if condition() {
+                return;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+        panic!("condition not met in time");
+    }
+
+    fn status(supervisor: &Supervisor, name: &str) -> ProviderStatus {
+        supervisor
+            .statuses()
+            .into_iter()
+            .find(|status| status.name == name)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn failing_provider_is_restarted_and_failures_are_recorded() {
+        let supervisor = test_supervisor();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let task_runs = runs.clone();
+
+        supervisor.register("weather", move || {
+            let runs = task_runs.clone();
+            async move {
+                runs.fetch_add(1, Ordering::SeqCst);
+                Err(anyhow::anyhow!("Open-Meteo is down"))
+            }
+        });
+        eventually(|| runs.load(Ordering::SeqCst) >= 3).await;
+
+        let status = status(&supervisor, "weather");
+        assert!(status.restarts >= 2);
+        assert!(status.consecutive_failures >= 2);
+        assert_eq!(status.last_error.as_deref(), Some("Open-Meteo is down"));
+        assert!(status.last_failure_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn panicking_provider_is_restarted() {
+        let supervisor = test_supervisor();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let task_runs = runs.clone();
+
+        supervisor.register("xtb", move || {
+            let runs = task_runs.clone();
+            async move {
+                if runs.fetch_add(1, Ordering::SeqCst) == 0 {
+                    panic!("lost the socket");
+                }
+                std::future::pending::<()>().await;
+                Ok(())
+            }
+        });
+        eventually(|| status(&supervisor, "xtb").restarts == 1).await;
+
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        let status = status(&supervisor, "xtb");
+        assert_eq!(status.state, ProviderState::Running);
+        assert_eq!(status.restarts, 1);
+        assert_eq!(status.last_error.as_deref(), Some("Panicked: lost the socket"));
+    }
+
+    #[tokio::test]
+    async fn stopped_provider_stays_stopped_until_started() {
+        let supervisor = test_supervisor();
+        let ticks = Arc::new(AtomicUsize::new(0));
+        let task_ticks = ticks.clone();
+
+        supervisor.register("spotify", move || {
+            let ticks = task_ticks.clone();
+            async move {
+                loop {
+                    ticks.fetch_add(1, Ordering::SeqCst);
+                    sleep(Duration::from_millis(5)).await;
+                }
+            }
+        });
+        eventually(|| ticks.load(Ordering::SeqCst) > 0).await;
+
+        assert!(supervisor.stop("spotify"));
+        let stopped_at = ticks.load(Ordering::SeqCst);
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(ticks.load(Ordering::SeqCst), stopped_at);
+        assert_eq!(status(&supervisor, "spotify").state, ProviderState::Stopped);
+
+        assert!(supervisor.start("spotify"));
+        eventually(|| ticks.load(Ordering::SeqCst) > stopped_at).await;
+        assert_eq!(status(&supervisor, "spotify").state, ProviderState::Running);
+    }
+
+    #[tokio::test]
+    async fn restart_runs_the_provider_again() {
+        let supervisor = test_supervisor();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let task_runs = runs.clone();
+
+        supervisor.register("xtb", move || {
+            let runs = task_runs.clone();
+            async move {
+                runs.fetch_add(1, Ordering::SeqCst);
+                std::future::pending::<()>().await;
+                Ok(())
+            }
+        });
+        eventually(|| runs.load(Ordering::SeqCst) == 1).await;
+        assert!(supervisor.restart("xtb"));
+
+        eventually(|| runs.load(Ordering::SeqCst) == 2).await;
+        assert_eq!(status(&supervisor, "xtb").restarts, 0);
+    }
+
+    #[tokio::test]
+    async fn restart_leaves_a_stopped_provider_stopped() {
+        let supervisor = test_supervisor();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let task_runs = runs.clone();
+
+        supervisor.register("xtb", move || {
+            let runs = task_runs.clone();
+            async move {
+                runs.fetch_add(1, Ordering::SeqCst);
+                std::future::pending::<()>().await;
+                Ok(())
+            }
+        });
+        eventually(|| runs.load(Ordering::SeqCst) == 1).await;
+        assert!(supervisor.stop("xtb"));
+
+        assert!(supervisor.restart("xtb"));
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!(status(&supervisor, "xtb").state, ProviderState::Stopped);
+        assert!(!supervisor.restart("myspace"));
+    }
+
+    #[tokio::test]
+    async fn stopped_generation_cannot_spawn_a_task() {
+        let supervisor = test_supervisor();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let task_runs = runs.clone();
+
+        supervisor.register("weather", move || {
+            let runs = task_runs.clone();
+            async move {
+                runs.fetch_add(1, Ordering::SeqCst);
+                std::future::pending::<()>().await;
+                Ok(())
+            }
+        });
+        eventually(|| runs.load(Ordering::SeqCst) == 1).await;
+        let generation = supervisor.providers.lock().unwrap()["weather"].generation;
+        assert!(supervisor.stop("weather"));
+
+        // As a restart loop that was past its backoff sleep when `stop` aborted it would.
+        assert!(supervisor.spawn_task("weather", generation).is_none());
+        sleep(Duration::from_millis(20)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!(status(&supervisor, "weather").state, ProviderState::Stopped);
+
+        assert!(supervisor.start("weather"));
+        eventually(|| runs.load(Ordering::SeqCst) == 2).await;
+        assert!(supervisor.spawn_task("weather", generation).is_none());
+    }
+
+    #[tokio::test]
+    async fn unknown_provider_cannot_be_started_or_stopped() {
+        let supervisor = test_supervisor();
+
+        assert!(!supervisor.start("myspace"));
+        assert!(!supervisor.stop("myspace"));
+        assert!(supervisor.statuses().is_empty());
+    }
+}
@@ -1,27 +1,47 @@
-use core::send_message;
-use std::{collections::HashMap, sync::Arc, time::{Duration, Instant}};
+use screen_core::{read_message, send_chunk, send_message};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
 
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
     net::TcpStream,
     sync::{mpsc, RwLock},
-    time::interval,
+    time::{interval, MissedTickBehavior},
 };
 
+use crate::{alerts, sources::Sources};
+
 pub type Clients = Arc<RwLock<HashMap<String, mpsc::Sender<Vec<u8>>>>>;
 
 pub enum StateMessage {
-    TrackData(String),
-    WeatherData(String),
-    XtbData(String),
+    /// An update for the screen app `app`, usually JSON.
+    Data { app: &'static str, payload: String },
+    /// Part of a binary transfer such as album art; `header` describes the whole transfer.
+    Chunk {
+        app: &'static str,
+        header: String,
+        data: Vec<u8>,
+        index: u16,
+        count: u16,
+    },
     Ping,
 }
 
+impl StateMessage {
+    pub fn data(app: &'static str, payload: impl Into<String>) -> Self {
+        Self::Data { app, payload: payload.into() }
+    }
+}
+
 pub async fn handle_client(
     stream: TcpStream,
     peer_addr: String,
     clients: Clients,
     mut receiver: mpsc::Receiver<Vec<u8>>,
+    sources: Sources,
 ) {
     let (mut reader, mut writer) = stream.into_split();
 
@@ -35,10 +55,21 @@ pub async fn handle_client(
                     println!("Client {} disconnected", peer_addr_clone);
                     break;
                 }
-                Ok(size) => {
-                    let received = String::from_utf8_lossy(&buffer[..size]);
-                    println!("Received from {}: {}", peer_addr_clone, received);
-                }
+                Ok(size) => match read_message(&buffer[..size]) {
+                    Ok((app, payload)) => {
+                        println!("Event from {}: {} {}", peer_addr_clone, app, payload);
+                        let sources = sources.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = sources.handle_event(&app, &payload).await {
+                                println!("Failed to handle {} event {}: {}", app, payload, e);
+                            }
+                        });
+                    }
+                    Err(_) => {
+                        let received = String::from_utf8_lossy(&buffer[..size]);
+                        println!("Received from {}: {}", peer_addr_clone, received);
+                    }
+                },
                 Err(e) => {
                     println!("Error reading from client {}: {}", peer_addr_clone, e);
                     break;
@@ -59,6 +90,55 @@ pub async fn handle_client(
     println!("Client {} disconnected", peer_addr);
 }
 
+/// Most updates waiting to be broadcast; past it, the oldest update of the same app is dropped.
+const MAX_QUEUED_UPDATES: usize = 32;
+
+/// Messages waiting to be broadcast. Chunks have their own queue, so a cover being sent doesn't
+/// hold back the updates of other apps.
+#[derive(Default)]
+struct Outbox {
+    updates: Vec<(&'static str, Vec<u8>)>,
+    chunks: VecDeque<(&'static str, Vec<u8>)>,
+}
+
+impl Outbox {
+    fn push_update(&mut self, app: &'static str, payload: Vec<u8>) {
+        if self.updates.len() >= MAX_QUEUED_UPDATES {
+            // Drops an update that a newer one of its app supersedes, otherwise the oldest one.
+            let superseded = |i: usize| {
+                let queued = self.updates[i].0;
+                queued == app || self.updates[i + 1..].iter().any(|(later, _)| *later == queued)
+            };
+            let stale = (0..self.updates.len())
+                .find(|&i| self.updates[i].0 != alerts::APP && superseded(i))
+                .or_else(|| self.updates.iter().position(|(queued, _)| *queued != alerts::APP))
+                .unwrap_or(0);
+            let (dropped, _) = self.updates.remove(stale);
+            println!("Too many queued updates; dropped one for {}", dropped);
+        }
+
+        if app == alerts::APP {
+            // Alerts jump the queue, but stay in order.
+            let position = self.updates.iter().take_while(|(queued, _)| *queued == alerts::APP).count();
+            self.updates.insert(position, (app, payload));
+        } else {
+            self.updates.push((app, payload));
+        }
+    }
+
+    /// The first chunk of a transfer replaces what is left of the app's previous one.
+    fn push_chunk(&mut self, app: &'static str, index: u16, payload: Vec<u8>) {
+        if index == 0 {
+            self.chunks.retain(|(queued, _)| *queued != app);
+        }
+        self.chunks.push_back((app, payload));
+    }
+
+    fn next_updates(&mut self, count: usize) -> Vec<(&'static str, Vec<u8>)> {
+        self.updates.drain(..count.min(self.updates.len())).collect()
+    }
+}
+
 pub async fn broadcast_new_data(
     clients: Clients,
     mut state_receiver: mpsc::Receiver<StateMessage>,
@@ -79,39 +159,54 @@ pub async fn broadcast_new_data(
 
     const MAX_MESSAGES_PER_SECOND: usize = 2;
     const BATCH_INTERVAL: Duration = Duration::from_secs(1);
+    // The screen reads one message at a time, so chunks are still spaced out.
+    const CHUNK_INTERVAL: Duration = Duration::from_millis(100);
+
+    let mut outbox = Outbox::default();
+    // Buffered messages also go out when nothing new arrives.
+    let mut flush = interval(BATCH_INTERVAL);
+    let mut chunk_flush = interval(CHUNK_INTERVAL);
+    chunk_flush.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        let state = tokio::select! {
+            state = state_receiver.recv() => match state {
+                Some(state) => state,
+                None => break,
+            },
+            _ = flush.tick() => {
+                let batch = outbox.next_updates(MAX_MESSAGES_PER_SECOND);
+                if batch.is_empty() {
+                    continue;
+                }
 
-    let mut last_sent = Instant::now();
-    let mut batch_buffer: Vec<(String, Vec<u8>) > = Vec::new();
+                for (data_type, payload) in batch {
+                    broadcast_to_clients(&clients, payload, data_type).await;
+                }
 
-    while let Some(state) = state_receiver.recv().await {
-        let (data_type, payload) = match state {
-            StateMessage::TrackData(track_data) => ("Spotify", send_message("Spotify", &track_data)),
-            StateMessage::WeatherData(weather_data) => ("Weather", send_message("Weather", &weather_data)),
-            StateMessage::XtbData(xtb_data) => ("XTB", send_message("XTB", &xtb_data)),
-            _ => {
-                println!("Unknown message");
+                println!("Sent batch of messages");
+                continue;
+            }
+            _ = chunk_flush.tick(), if !outbox.chunks.is_empty() => {
+                if let Some((data_type, payload)) = outbox.chunks.pop_front() {
+                    broadcast_to_clients(&clients, payload, data_type).await;
+                }
                 continue;
             }
         };
 
-        match payload {
-            Ok(payload) => {
-                batch_buffer.push((data_type.to_string(), payload));
-
-                if last_sent.elapsed() >= BATCH_INTERVAL {
-                    let batch = batch_buffer.drain(..MAX_MESSAGES_PER_SECOND.min(batch_buffer.len())).collect::<Vec<_>>();
-
-                    for (data_type, payload) in batch {
-                        broadcast_to_clients(&clients, payload, &data_type).await;
-                    }
-
-                    last_sent = Instant::now();
-                    println!("Sent batch of messages");
+        match state {
+            StateMessage::Data { app, payload } => match send_message(app, &payload) {
+                Ok(payload) => outbox.push_update(app, payload),
+                Err(e) => eprintln!("Failed to send message for {}: {:?}", app, e),
+            },
+            StateMessage::Chunk { app, header, data, index, count } => {
+                match send_chunk(app, &header, &data, index, count) {
+                    Ok(payload) => outbox.push_chunk(app, index, payload),
+                    Err(e) => eprintln!("Failed to send chunk for {}: {:?}", app, e),
                 }
             }
-            Err(e) => {
-                eprintln!("Failed to send message for {}: {:?}", data_type, e);
-            }
+            StateMessage::Ping => continue,
         }
     }
 }
@@ -125,3 +220,49 @@ pub async fn heartbeat_task(sender: mpsc::Sender<StateMessage>) -> anyhow::Resul
         sender.send(StateMessage::Ping).await?;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Outbox, MAX_QUEUED_UPDATES};
+    use crate::alerts;
+
+    fn apps(queue: &[(&'static str, Vec<u8>)]) -> Vec<&'static str> {
+        queue.iter().map(|(app, _)| *app).collect()
+    }
+
+    #[test]
+    fn a_new_cover_replaces_the_queued_chunks_of_the_last_one() {
+        let mut outbox = Outbox::default();
+        for index in 0..3 {
+            outbox.push_chunk("Spotify", index, vec![1, index as u8]);
+        }
+        outbox.push_chunk("Other", 0, vec![9]);
+        outbox.chunks.pop_front();
+
+        outbox.push_chunk("Spotify", 0, vec![2, 0]);
+
+        let chunks: Vec<_> = outbox.chunks.iter().map(|(_, payload)| payload.clone()).collect();
+        assert_eq!(chunks, [vec![9], vec![2, 0]]);
+        assert!(outbox.updates.is_empty());
+    }
+
+    #[test]
+    fn updates_are_bounded_and_drop_their_stale_ones() {
+        let mut outbox = Outbox::default();
+        outbox.push_update("Weather", vec![0]);
+        for i in 1..MAX_QUEUED_UPDATES {
+            outbox.push_update("XTB", vec![i as u8]);
+        }
+
+        outbox.push_update(alerts::APP, vec![100]);
+        assert_eq!(outbox.updates.len(), MAX_QUEUED_UPDATES);
+        assert_eq!(apps(&outbox.updates[..2]), [alerts::APP, "Weather"]);
+
+        outbox.push_update("Weather", vec![101]);
+        assert_eq!(outbox.updates.len(), MAX_QUEUED_UPDATES);
+        assert_eq!(apps(&outbox.updates).iter().filter(|app| **app == "Weather").count(), 1);
+        assert_eq!(outbox.updates.last().unwrap().1, vec![101]);
+
+        assert_eq!(apps(&outbox.next_updates(2)), [alerts::APP, "XTB"]);
+    }
+}
@@ -0,0 +1,51 @@
+use axum::Router;
+use sqlx::SqlitePool;
+use tokio::net::TcpListener;
+
+use crate::db::{add_new_oauth2_token_to_db, OAuth2Token};
+
+/// Serves `router` on an ephemeral local port and returns its base URL, e.g. `http://127.0.0.1:41234`.
+pub async fn spawn_mock_server(router: Router) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("failed to bind mock server");
+    let addr = listener.local_addr().expect("mock server has no address");
+
+    tokio::spawn(async move {
+        axum::serve(listener, router).await.expect("mock server failed");
+    });
+
+    format!("http://{}", addr)
+}
+
+/// Stores a connected OAuth2 integration whose access token expires in `expires_in`.
+pub async fn insert_oauth2_token(
+    db: &SqlitePool,
+    app_name: &str,
+    token_url: &str,
+    expires_in: chrono::Duration,
+) {
+    let now = chrono::Utc::now().naive_utc();
+    add_new_oauth2_token_to_db(
+        db,
+        OAuth2Token {
+            app_name: app_name.to_string(),
+            client_secret: "client-secret".to_string(),
+            client_id: "client-id".to_string(),
+            redirect_uri: format!("http://localhost:2700/oauth2/{}/callback", app_name),
+            access_token: "access-old".to_string(),
+            refresh_token: "refresh-old".to_string(),
+            expires_at: now + expires_in,
+            code: "".to_string(),
+            get_token_url: token_url.to_string(),
+            created_at: now,
+            authorize_url: "".to_string(),
+            scopes: "".to_string(),
+            refresh_failures: 0,
+            last_refresh_error: None,
+            broken: false,
+        },
+    )
+    .await
+    .expect("failed to insert OAuth2 token");
+}
@@ -0,0 +1,723 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use axum::{
+    extract::{ConnectInfo, Path, Request, State},
+    http::{header, Extensions, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use rand::{distributions::Alphanumeric, rngs::OsRng, Rng};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+
+use super::error::{ApiError, ApiJson, ApiResult};
+use crate::db::{
+    count_admin_users, create_admin_user, create_api_token, create_session, delete_api_token,
+    delete_expired_sessions, delete_session, delete_sessions_for_user, get_admin_user_by_username,
+    get_api_token_by_hash, get_api_tokens, get_session, touch_api_token, update_admin_password,
+};
+
+pub const SESSION_COOKIE: &str = "iot_screen_session";
+pub const CSRF_HEADER: &str = "x-csrf-token";
+const SESSION_TTL_HOURS: i64 = 12;
+/// Failed logins allowed per username and per client address within the window.
+const MAX_FAILED_LOGINS: u32 = 5;
+const FAILED_LOGIN_WINDOW_SECS: u64 = 15 * 60;
+
+// Scopes an API token can be granted. Admin sessions implicitly hold all of them.
+pub const SCOPE_OAUTH2: &str = "oauth2";
+pub const SCOPE_XTB: &str = "xtb";
+pub const SCOPE_SPOTIFY: &str = "spotify";
+pub const SCOPE_WEATHER: &str = "weather";
+pub const SCOPE_ADMIN: &str = "admin";
+pub const ALL_SCOPES: [&str; 5] = [SCOPE_OAUTH2, SCOPE_XTB, SCOPE_SPOTIFY, SCOPE_WEATHER, SCOPE_ADMIN];
+
+/// Who made the request, inserted into the request extensions by `require_scope`.
+#[derive(Debug, Clone)]
+pub enum Principal {
+    Admin { user_id: i64, username: String },
+    ApiToken { name: String },
+}
+
+/// State for the `require_scope` middleware: every route behind it needs `scope`.
+#[derive(Clone)]
+pub struct ScopeGuard {
+    pub db: SqlitePool,
+    pub scope: &'static str,
+}
+
+impl ScopeGuard {
+    pub fn new(db: SqlitePool, scope: &'static str) -> Self {
+        Self { db, scope }
+    }
+}
+
+/// Creates the admin account from `ADMIN_USERNAME`/`ADMIN_PASSWORD` when none exists yet.
+pub async fn ensure_admin_user(db: &SqlitePool) -> anyhow::Result<()> {
+    if count_admin_users(db).await? > 0 {
+        return Ok(());
+    }
+
+    let (Ok(username), Ok(password)) = (
+        std::env::var("ADMIN_USERNAME"),
+        std::env::var("ADMIN_PASSWORD"),
+    ) else {
+        println!("No admin account exists; set ADMIN_USERNAME and ADMIN_PASSWORD to create one");
+        return Ok(());
+    };
+
+    let password_hash = hash_password(&password)?;
+    create_admin_user(db, &username, &password_hash).await?;
+    println!("Created admin account {}", username);
+
+    Ok(())
+}
+
+pub fn hash_password(password: &str) -> anyhow::Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow::anyhow!("Failed to hash password: {}", e))?;
+
+    Ok(hash.to_string())
+}
+
+fn verify_password(password: &str, password_hash: &str) -> bool {
+    match PasswordHash::new(password_hash) {
+        Ok(hash) => Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+fn generate_token(length: usize) -> String {
+    OsRng
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+/// Session ids and API tokens are only ever stored hashed.
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+fn session_cookie_from_headers(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value.to_string())
+}
+
+fn bearer_token_from_headers(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(|token| token.trim().to_string())
+}
+
+fn session_cookie(value: &str, max_age_seconds: i64) -> HeaderValue {
+    // No `Secure` flag: the dashboard is served over plain HTTP on the LAN.
+    let cookie = format!(
+        "{}={}; Path=/; HttpOnly; SameSite=Strict; Max-Age={}",
+        SESSION_COOKIE, value, max_age_seconds
+    );
+    HeaderValue::from_str(&cookie).expect("session cookie is valid ASCII")
+}
+
+fn is_mutating(method: &Method) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+fn unauthorized() -> ApiError {
+    ApiError::Unauthorized("Sign in or provide an API token".to_string())
+}
+
+fn forbidden(detail: &str) -> ApiError {
+    ApiError::Forbidden(detail.to_string())
+}
+
+/// Authenticates the request with either an API token (`Authorization: Bearer ...`) or an
+/// admin session cookie. Session-authenticated requests that mutate state must also echo the
+/// session's CSRF token in the `X-CSRF-Token` header.
+pub async fn require_scope(
+    State(guard): State<ScopeGuard>,
+    mut request: Request,
+    next: Next,
+) -> ApiResult<Response> {
+    let headers = request.headers();
+
+    let principal = if let Some(token) = bearer_token_from_headers(headers) {
+        let api_token = get_api_token_by_hash(&guard.db, &hash_token(&token))
+            .await
+            .map_err(ApiError::Database)?
+            .ok_or_else(unauthorized)?;
+
+        if !api_token.scopes.split_whitespace().any(|scope| scope == guard.scope) {
+            return Err(forbidden(&format!("Token is missing the {} scope", guard.scope)));
+        }
+
+        if let Err(e) = touch_api_token(&guard.db, api_token.id).await {
+            eprintln!("Failed to update API token usage: {}", e);
+        }
+
+        if is_mutating(request.method()) {
+            println!(
+                "{} {} authorized by API token {}",
+                request.method(),
+                request.uri().path(),
+                api_token.name
+            );
+        }
+
+        Principal::ApiToken {
+            name: api_token.name,
+        }
+    } else if let Some(session_token) = session_cookie_from_headers(headers) {
+        let session = get_session(&guard.db, &hash_token(&session_token))
+            .await
+            .map_err(ApiError::Database)?
+            .ok_or_else(unauthorized)?;
+
+        if chrono::Utc::now().naive_utc() >= session.expires_at {
+            return Err(unauthorized());
+        }
+
+        if is_mutating(request.method()) {
+            let csrf_token = headers.get(CSRF_HEADER).and_then(|value| value.to_str().ok());
+            if csrf_token != Some(session.csrf_token.as_str()) {
+                return Err(forbidden("Missing or invalid CSRF token"));
+            }
+        }
+
+        Principal::Admin {
+            user_id: session.user_id,
+            username: session.username,
+        }
+    } else {
+        return Err(unauthorized());
+    };
+
+    request.extensions_mut().insert(principal);
+    Ok(next.run(request).await)
+}
+
+/// Counts failed logins per username and per client address, so passwords can't be guessed at
+/// full speed. Kept in memory; a restart forgets them.
+#[derive(Clone, Default)]
+pub struct LoginThrottle {
+    /// Failures and when the first of them was, by `user:` or `ip:` key.
+    failures: Arc<Mutex<HashMap<String, (u32, Instant)>>>,
+}
+
+impl LoginThrottle {
+    fn keys(username: &str, ip: Option<IpAddr>) -> Vec<String> {
+        let mut keys = vec![format!("user:{}", username)];
+        keys.extend(ip.map(|ip| format!("ip:{}", ip)));
+        keys
+    }
+
+    /// Whether any of `keys` used up its failed logins for the current window.
+    fn blocked(&self, keys: &[String]) -> bool {
+        let window = Duration::from_secs(FAILED_LOGIN_WINDOW_SECS);
+        let failures = self.failures.lock().unwrap();
+        keys.iter().any(|key| {
+            failures
+                .get(key)
+                .is_some_and(|&(count, since)| count >= MAX_FAILED_LOGINS && since.elapsed() < window)
+        })
+    }
+
+    fn record_failure(&self, keys: &[String]) {
+        let window = Duration::from_secs(FAILED_LOGIN_WINDOW_SECS);
+        let mut failures = self.failures.lock().unwrap();
+        // Expired windows are dropped here, so guessed usernames don't pile up.
+        failures.retain(|_, (_, since)| since.elapsed() < window);
+        for key in keys {
+            failures.entry(key.clone()).or_insert((0, Instant::now())).0 += 1;
+        }
+    }
+
+    fn clear(&self, keys: &[String]) {
+        let mut failures = self.failures.lock().unwrap();
+        for key in keys {
+            failures.remove(key);
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct LoginPayload {
+    username: String,
+    password: String,
+}
+
+#[derive(Serialize)]
+pub struct SessionResponse {
+    username: String,
+    #[serde(rename = "csrfToken")]
+    csrf_token: String,
+}
+
+/// Refuses to check passwords for a username or client address with too many recent failures.
+pub async fn login(
+    State((db, throttle)): State<(SqlitePool, LoginThrottle)>,
+    extensions: Extensions,
+    ApiJson(payload): ApiJson<LoginPayload>,
+) -> ApiResult<Response> {
+    // Missing when the server wasn't started with connection info, as in tests.
+    let ip = extensions.get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(address)| address.ip());
+    let keys = LoginThrottle::keys(&payload.username, ip);
+    if throttle.blocked(&keys) {
+        return Err(ApiError::TooManyRequests("Too many failed logins; try again later".to_string()));
+    }
+
+    let user = get_admin_user_by_username(&db, &payload.username)
+        .await
+        .map_err(ApiError::Database)?
+        .filter(|user| verify_password(&payload.password, &user.password_hash));
+    let Some(user) = user else {
+        throttle.record_failure(&keys);
+        return Err(ApiError::Unauthorized("Invalid username or password".to_string()));
+    };
+    throttle.clear(&keys);
+
+    if let Err(e) = delete_expired_sessions(&db).await {
+        eprintln!("Failed to clean up expired sessions: {}", e);
+    }
+
+    let session_token = generate_token(48);
+    let csrf_token = generate_token(32);
+    let expires_at = chrono::Utc::now().naive_utc() + chrono::Duration::hours(SESSION_TTL_HOURS);
+
+    create_session(&db, &hash_token(&session_token), user.id, &csrf_token, expires_at)
+        .await
+        .map_err(ApiError::Database)?;
+
+    let mut response = Json(SessionResponse {
+        username: user.username,
+        csrf_token,
+    })
+    .into_response();
+    response.headers_mut().insert(
+        header::SET_COOKIE,
+        session_cookie(&session_token, SESSION_TTL_HOURS * 3600),
+    );
+    Ok(response)
+}
+
+/// Needs the session's CSRF token like other session requests, so another site can't sign the
+/// admin out.
+pub async fn logout(State(db): State<SqlitePool>, headers: HeaderMap) -> ApiResult<Response> {
+    if let Some(session_token) = session_cookie_from_headers(&headers) {
+        let token_hash = hash_token(&session_token);
+        if let Some(session) = get_session(&db, &token_hash).await.map_err(ApiError::Database)? {
+            let csrf_token = headers.get(CSRF_HEADER).and_then(|value| value.to_str().ok());
+            if csrf_token != Some(session.csrf_token.as_str()) {
+                return Err(forbidden("Missing or invalid CSRF token"));
+            }
+
+            delete_session(&db, &token_hash)
+                .await
+                .map_err(ApiError::Database)?;
+        }
+    }
+
+    let mut response = StatusCode::NO_CONTENT.into_response();
+    response
+        .headers_mut()
+        .insert(header::SET_COOKIE, session_cookie("", 0));
+    Ok(response)
+}
+
+pub async fn current_session(
+    State(db): State<SqlitePool>,
+    headers: HeaderMap,
+) -> ApiResult<Json<SessionResponse>> {
+    let session_token = session_cookie_from_headers(&headers).ok_or_else(unauthorized)?;
+
+    let session = get_session(&db, &hash_token(&session_token))
+        .await
+        .map_err(ApiError::Database)?
+        .filter(|session| chrono::Utc::now().naive_utc() < session.expires_at)
+        .ok_or_else(unauthorized)?;
+
+    Ok(Json(SessionResponse {
+        username: session.username,
+        csrf_token: session.csrf_token,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordPayload {
+    #[serde(rename = "currentPassword")]
+    current_password: String,
+    #[serde(rename = "newPassword")]
+    new_password: String,
+}
+
+pub async fn change_password(
+    State(db): State<SqlitePool>,
+    Extension(principal): Extension<Principal>,
+    ApiJson(payload): ApiJson<ChangePasswordPayload>,
+) -> ApiResult<Response> {
+    let Principal::Admin { user_id, username } = principal else {
+        return Err(forbidden("Only the admin account can change its password"));
+    };
+
+    let user = get_admin_user_by_username(&db, &username)
+        .await
+        .map_err(ApiError::Database)?
+        .ok_or_else(unauthorized)?;
+
+    if !verify_password(&payload.current_password, &user.password_hash) {
+        return Err(ApiError::validation("Current password is incorrect"));
+    }
+
+    if payload.new_password.len() < 8 {
+        return Err(ApiError::validation("Password must be at least 8 characters"));
+    }
+
+    let password_hash = hash_password(&payload.new_password).map_err(ApiError::Internal)?;
+    update_admin_password(&db, user_id, &password_hash)
+        .await
+        .map_err(ApiError::Database)?;
+    // Changing the password signs out every other browser.
+    delete_sessions_for_user(&db, user_id)
+        .await
+        .map_err(ApiError::Database)?;
+
+    let mut response = StatusCode::NO_CONTENT.into_response();
+    response
+        .headers_mut()
+        .insert(header::SET_COOKIE, session_cookie("", 0));
+    Ok(response)
+}
+
+#[derive(Deserialize)]
+pub struct CreateApiTokenPayload {
+    name: String,
+    scopes: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct ApiTokenResponse {
+    id: i64,
+    name: String,
+    scopes: Vec<String>,
+    #[serde(rename = "createdAt")]
+    created_at: chrono::NaiveDateTime,
+    #[serde(rename = "lastUsedAt")]
+    last_used_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Serialize)]
+pub struct CreatedApiTokenResponse {
+    id: i64,
+    name: String,
+    scopes: Vec<String>,
+    /// Only returned once; the server keeps just its hash.
+    token: String,
+}
+
+pub async fn create_token(
+    State(db): State<SqlitePool>,
+    ApiJson(payload): ApiJson<CreateApiTokenPayload>,
+) -> ApiResult<(StatusCode, Json<CreatedApiTokenResponse>)> {
+    if payload.name.trim().is_empty() {
+        return Err(ApiError::validation("Token name must not be empty"));
+    }
+
+    if payload.scopes.is_empty() {
+        return Err(ApiError::validation("At least one scope is required"));
+    }
+
+    if let Some(scope) = payload
+        .scopes
+        .iter()
+        .find(|scope| !ALL_SCOPES.contains(&scope.as_str()))
+    {
+        return Err(ApiError::validation(format!("Unknown scope {}", scope)));
+    }
+
+    let token = generate_token(40);
+    let id = create_api_token(&db, &payload.name, &hash_token(&token), &payload.scopes.join(" "))
+        .await
+        .map_err(ApiError::Database)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedApiTokenResponse {
+            id,
+            name: payload.name,
+            scopes: payload.scopes,
+            token,
+        }),
+    ))
+}
+
+pub async fn list_tokens(State(db): State<SqlitePool>) -> ApiResult<Json<Vec<ApiTokenResponse>>> {
+    let tokens = get_api_tokens(&db).await.map_err(ApiError::Database)?;
+
+    Ok(Json(
+        tokens
+            .into_iter()
+            .map(|token| ApiTokenResponse {
+                id: token.id,
+                name: token.name,
+                scopes: token.scopes.split_whitespace().map(String::from).collect(),
+                created_at: token.created_at,
+                last_used_at: token.last_used_at,
+            })
+            .collect(),
+    ))
+}
+
+pub async fn revoke_token(State(db): State<SqlitePool>, Path(id): Path<i64>) -> ApiResult<StatusCode> {
+    if delete_api_token(&db, id).await.map_err(ApiError::Database)? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::not_found(format!("API token {} does not exist", id)))
+    }
+}
+
+/// Creates an API token directly in the database and returns its plaintext value.
+#[cfg(test)]
+pub async fn create_test_token(db: &SqlitePool, scopes: &[&str]) -> String {
+    let token = generate_token(40);
+    create_api_token(db, "test", &hash_token(&token), &scopes.join(" "))
+        .await
+        .expect("failed to create test token");
+    token
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use axum::{
+        body::{to_bytes, Body},
+        extract::ConnectInfo,
+        http::{header, HeaderMap, Request, StatusCode},
+        response::Response,
+    };
+    use serde_json::{json, Value};
+    use sqlx::SqlitePool;
+    use tower::ServiceExt;
+
+    use super::{
+        bearer_token_from_headers, create_test_token, hash_password, session_cookie_from_headers, verify_password,
+        CSRF_HEADER, MAX_FAILED_LOGINS, SCOPE_ADMIN, SCOPE_WEATHER, SCOPE_XTB,
+    };
+    use crate::{
+        db::{create_admin_user, initialize_test_db},
+        web::create_test_router,
+    };
+
+    async fn request(db: &SqlitePool, request: Request<Body>) -> Response {
+        create_test_router(db.clone()).oneshot(request).await.unwrap()
+    }
+
+    async fn body_json(response: Response) -> Value {
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    /// Signs in as a fresh admin, returning the session cookie and its CSRF token.
+    async fn login(db: &SqlitePool) -> (String, String) {
+        let password_hash = hash_password("correct horse").unwrap();
+        create_admin_user(db, "admin", &password_hash).await.unwrap();
+
+        let credentials = json!({ "username": "admin", "password": "correct horse" });
+        let response = request(
+            db,
+            Request::post("/auth/login")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(credentials.to_string()))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let cookie = response.headers()[header::SET_COOKIE]
+            .to_str()
+            .unwrap()
+            .split(';')
+            .next()
+            .unwrap()
+            .to_string();
+        let csrf_token = body_json(response).await["csrfToken"].as_str().unwrap().to_string();
+        (cookie, csrf_token)
+    }
+
+    #[test]
+    fn passwords_are_verified_against_their_hash() {
+        let password_hash = hash_password("correct horse").unwrap();
+
+        assert!(verify_password("correct horse", &password_hash));
+        assert!(!verify_password("battery staple", &password_hash));
+        assert!(!verify_password("correct horse", "not a hash"));
+    }
+
+    #[test]
+    fn credentials_are_read_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, "theme=dark; iot_screen_session=abc123".parse().unwrap());
+        headers.insert(header::AUTHORIZATION, "Bearer  token ".parse().unwrap());
+
+        assert_eq!(session_cookie_from_headers(&headers).as_deref(), Some("abc123"));
+        assert_eq!(bearer_token_from_headers(&headers).as_deref(), Some("token"));
+
+        headers.insert(header::AUTHORIZATION, "Basic dXNlcg==".parse().unwrap());
+        assert_eq!(bearer_token_from_headers(&headers), None);
+    }
+
+    #[tokio::test]
+    async fn session_requests_need_csrf_token() {
+        let db = initialize_test_db().await;
+        let (cookie, csrf_token) = login(&db).await;
+
+        let credentials = json!({ "userId": "12345", "password": "hunter2" });
+        let post = |csrf_token: Option<&str>| {
+            let mut request = Request::post("/xtb/credentials")
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::COOKIE, &cookie);
+            if let Some(csrf_token) = csrf_token {
+                request = request.header(CSRF_HEADER, csrf_token);
+            }
+            request.body(Body::from(credentials.to_string())).unwrap()
+        };
+
+        assert_eq!(request(&db, post(None)).await.status(), StatusCode::FORBIDDEN);
+        assert_eq!(request(&db, post(Some("wrong"))).await.status(), StatusCode::FORBIDDEN);
+        assert_eq!(request(&db, post(Some(&csrf_token))).await.status(), StatusCode::OK);
+
+        // Reads don't need the token.
+        let get = Request::get("/providers").header(header::COOKIE, &cookie).body(Body::empty()).unwrap();
+        assert_eq!(request(&db, get).await.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn login_rejects_wrong_password() {
+        let db = initialize_test_db().await;
+        let password_hash = hash_password("correct horse").unwrap();
+        create_admin_user(&db, "admin", &password_hash).await.unwrap();
+
+        let credentials = json!({ "username": "admin", "password": "battery staple" });
+        let response = request(
+            &db,
+            Request::post("/auth/login")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(credentials.to_string()))
+                .unwrap(),
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(response.headers().get(header::SET_COOKIE).is_none());
+    }
+
+    #[tokio::test]
+    async fn failed_logins_are_throttled_per_user_and_address() {
+        let db = initialize_test_db().await;
+        let password_hash = hash_password("correct horse").unwrap();
+        create_admin_user(&db, "admin", &password_hash).await.unwrap();
+        // One router, so its throttle sees every attempt.
+        let router = create_test_router(db.clone());
+        let attempt = |username: &str, password: &str, address: [u8; 4]| {
+            let credentials = json!({ "username": username, "password": password });
+            let request = Request::post("/auth/login")
+                .header(header::CONTENT_TYPE, "application/json")
+                .extension(ConnectInfo(SocketAddr::from((address, 40000))))
+                .body(Body::from(credentials.to_string()))
+                .unwrap();
+            let router = router.clone();
+            async move { router.oneshot(request).await.unwrap().status() }
+        };
+
+        for _ in 0..MAX_FAILED_LOGINS {
+            assert_eq!(attempt("admin", "battery staple", [10, 0, 0, 1]).await, StatusCode::UNAUTHORIZED);
+        }
+
+        // Even the right password is refused, from any address, and so is any user from that one.
+        assert_eq!(attempt("admin", "correct horse", [10, 0, 0, 2]).await, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(attempt("root", "toor", [10, 0, 0, 1]).await, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(attempt("root", "toor", [10, 0, 0, 2]).await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn logout_ends_the_session() {
+        let db = initialize_test_db().await;
+        let (cookie, csrf_token) = login(&db).await;
+
+        let session = || Request::get("/auth/session").header(header::COOKIE, &cookie).body(Body::empty()).unwrap();
+        let response = request(&db, session()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_json(response).await, json!({ "username": "admin", "csrfToken": csrf_token }));
+
+        let logout = |csrf_token: &str| {
+            Request::post("/auth/logout")
+                .header(header::COOKIE, &cookie)
+                .header(CSRF_HEADER, csrf_token)
+                .body(Body::empty())
+                .unwrap()
+        };
+        assert_eq!(request(&db, logout("wrong")).await.status(), StatusCode::FORBIDDEN);
+        assert_eq!(request(&db, session()).await.status(), StatusCode::OK);
+
+        let response = request(&db, logout(&csrf_token)).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(response.headers()[header::SET_COOKIE].to_str().unwrap().contains("Max-Age=0"));
+
+        assert_eq!(request(&db, session()).await.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn api_tokens_only_reach_their_scopes() {
+        let db = initialize_test_db().await;
+        let token = create_test_token(&db, &[SCOPE_XTB, SCOPE_WEATHER]).await;
+        let get = |uri: &str, token: &str| {
+            Request::get(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        assert_eq!(request(&db, get("/xtb/watchlist", &token)).await.status(), StatusCode::OK);
+        let response = request(&db, get("/providers", &token)).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(body_json(response).await["detail"], "Token is missing the admin scope");
+        assert_eq!(request(&db, get("/xtb/watchlist", "unknown")).await.status(), StatusCode::UNAUTHORIZED);
+        let anonymous = Request::get("/xtb/watchlist").body(Body::empty()).unwrap();
+        assert_eq!(request(&db, anonymous).await.status(), StatusCode::UNAUTHORIZED);
+
+        // Tokens don't need a CSRF token, and revoked ones stop working.
+        let admin = create_test_token(&db, &[SCOPE_ADMIN]).await;
+        let response = request(&db, get("/auth/tokens", &admin)).await;
+        let id = body_json(response).await[0]["id"].as_i64().unwrap();
+        let revoke = Request::delete(format!("/auth/tokens/{}", id))
+            .header(header::AUTHORIZATION, format!("Bearer {}", admin))
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(request(&db, revoke).await.status(), StatusCode::NO_CONTENT);
+        assert_eq!(request(&db, get("/xtb/watchlist", &token)).await.status(), StatusCode::UNAUTHORIZED);
+    }
+}
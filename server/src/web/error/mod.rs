@@ -0,0 +1,140 @@
+use axum::{
+    extract::{rejection::JsonRejection, FromRequest},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+
+/// Error returned by web handlers, rendered as an RFC 9457 problem-details body.
+#[derive(Debug)]
+pub enum ApiError {
+    /// The request was well-formed but its content was rejected.
+    Validation(String),
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
+    /// The client has to wait before trying again, e.g. after failed logins.
+    TooManyRequests(String),
+    /// Reading or writing SQLite failed.
+    Database(anyhow::Error),
+    /// A third-party API (token endpoint, Spotify, ...) failed or answered with an error.
+    Upstream(String),
+    Internal(anyhow::Error),
+}
+
+#[derive(Serialize)]
+struct ProblemDetails {
+    #[serde(rename = "type")]
+    problem_type: &'static str,
+    title: &'static str,
+    status: u16,
+    detail: String,
+}
+
+impl ApiError {
+    pub fn validation(detail: impl Into<String>) -> Self {
+        Self::Validation(detail.into())
+    }
+
+    pub fn not_found(detail: impl Into<String>) -> Self {
+        Self::NotFound(detail.into())
+    }
+
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::Validation(_) => StatusCode::BAD_REQUEST,
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::Forbidden(_) => StatusCode::FORBIDDEN,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::Database(_) | Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::Upstream(_) => StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    fn problem_type(&self) -> &'static str {
+        match self {
+            Self::Validation(_) => "validation-error",
+            Self::Unauthorized(_) => "unauthorized",
+            Self::Forbidden(_) => "forbidden",
+            Self::NotFound(_) => "not-found",
+            Self::TooManyRequests(_) => "too-many-requests",
+            Self::Database(_) => "database-error",
+            Self::Upstream(_) => "upstream-error",
+            Self::Internal(_) => "internal-error",
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            Self::Validation(_) => "Invalid request",
+            Self::Unauthorized(_) => "Authentication required",
+            Self::Forbidden(_) => "Forbidden",
+            Self::NotFound(_) => "Not found",
+            Self::TooManyRequests(_) => "Too many requests",
+            Self::Database(_) => "Database error",
+            Self::Upstream(_) => "Upstream service error",
+            Self::Internal(_) => "Internal server error",
+        }
+    }
+
+    fn detail(&self) -> String {
+        match self {
+            Self::Validation(detail)
+            | Self::Unauthorized(detail)
+            | Self::Forbidden(detail)
+            | Self::NotFound(detail)
+            | Self::TooManyRequests(detail)
+            | Self::Upstream(detail) => detail.clone(),
+            // Internal details are logged, not leaked to the client.
+            Self::Database(_) => "A database operation failed".to_string(),
+            Self::Internal(_) => "An unexpected error occurred".to_string(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        match &self {
+            Self::Database(e) => eprintln!("Database error: {:?}", e),
+            Self::Internal(e) => eprintln!("Internal error: {:?}", e),
+            Self::Upstream(detail) => eprintln!("Upstream error: {}", detail),
+            _ => {}
+        }
+
+        let status = self.status();
+        let body = ProblemDetails {
+            problem_type: self.problem_type(),
+            title: self.title(),
+            status: status.as_u16(),
+            detail: self.detail(),
+        };
+
+        (
+            status,
+            [(header::CONTENT_TYPE, "application/problem+json")],
+            serde_json::to_string(&body).unwrap_or_default(),
+        )
+            .into_response()
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::Validation(rejection.body_text())
+    }
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(error: reqwest::Error) -> Self {
+        Self::Upstream(error.to_string())
+    }
+}
+
+/// `Json` extractor whose rejections are reported as problem details.
+#[derive(FromRequest)]
+#[from_request(via(Json), rejection(ApiError))]
+pub struct ApiJson<T>(pub T);
+
+pub type ApiResult<T> = Result<T, ApiError>;
@@ -1,40 +1,130 @@
-use axum::{extract::{State, Json}, response::IntoResponse, routing::{get, post}, Router};
+use std::net::SocketAddr;
+
+use auth::{require_scope, LoginThrottle, ScopeGuard, CSRF_HEADER, SCOPE_ADMIN, SCOPE_OAUTH2, SCOPE_SPOTIFY, SCOPE_WEATHER, SCOPE_XTB};
+use axum::{extract::{Path, State}, http::{header, HeaderName, StatusCode}, middleware::from_fn_with_state, response::IntoResponse, routing::{delete, get, post, put}, Json, Router};
+use error::{ApiError, ApiJson, ApiResult};
 use serde::Deserialize;
+use spotify::SpotifyPlayer;
+use weather::OpenMeteo;
+use xtb::AccountType;
 use sqlx::SqlitePool;
 use tokio::net::TcpListener;
-use tower_http::{cors::{AllowHeaders, CorsLayer}, services::ServeDir};
+use tower_http::{cors::CorsLayer, services::ServeDir};
 
-use crate::db::{add_new_oauth2_token_to_db, save_xtb_credentials, OAuth2Token};
+use crate::{
+    db::save_xtb_credentials,
+    sources::{SourceStatus, Sources},
+};
 
+pub mod auth;
+pub mod error;
 pub mod weather;
 pub mod oauth2;
 pub mod spotify;
 pub mod xtb;
 
-pub async fn initialize_axum_server(
-    db: SqlitePool,
-) -> anyhow::Result<()> {
+pub fn create_router(db: SqlitePool, player: SpotifyPlayer, open_meteo: OpenMeteo, sources: Sources) -> Router {
     let origins = [
         "http://localhost:5173".parse().unwrap(),
         "http://localhost:8080".parse().unwrap(),
         "http://localhost:2700".parse().unwrap(),
     ];
 
-    let app = Router::new()
-    .route("/health", get(health_check))
-    .route("/oauth2/code", post(post_oauth2_code))
+    let admin_routes = Router::new()
+    .route("/auth/password", post(auth::change_password))
+    .route("/auth/tokens", get(auth::list_tokens).post(auth::create_token))
+    .route("/auth/tokens/{id}", delete(auth::revoke_token))
+    .route_layer(from_fn_with_state(ScopeGuard::new(db.clone(), SCOPE_ADMIN), require_scope));
+
+    let provider_routes = Router::new()
+    .route("/providers", get(list_providers))
+    .route("/providers/{name}/start", post(start_provider))
+    .route("/providers/{name}/stop", post(stop_provider))
+    .route_layer(from_fn_with_state(ScopeGuard::new(db.clone(), SCOPE_ADMIN), require_scope))
+    .with_state(sources.clone());
+
+    let login_routes = Router::new()
+    .route("/auth/login", post(auth::login))
+    .with_state((db.clone(), LoginThrottle::default()));
+
+    let oauth2_routes = Router::new()
+    .route("/oauth2/links", get(oauth2::list_links))
+    .route("/oauth2/{provider}/client", put(oauth2::put_oauth2_client))
+    .route("/oauth2/{provider}/authorize", post(oauth2::authorize))
+    .route_layer(from_fn_with_state(ScopeGuard::new(db.clone(), SCOPE_OAUTH2), require_scope));
+
+    let spotify_routes = Router::new()
+    .route("/spotify/player/volume", put(spotify::set_volume))
+    .route("/spotify/player/{command}", post(spotify::player_command))
+    .route_layer(from_fn_with_state(ScopeGuard::new(db.clone(), SCOPE_SPOTIFY), require_scope))
+    .with_state(player);
+
+    let weather_routes = Router::new()
+    .route("/weather/locations", get(weather::list_locations).post(weather::add_location))
+    .route("/weather/locations/{id}", delete(weather::delete_location))
+    .route("/weather/settings", get(weather::get_settings).put(weather::put_settings))
+    .route("/weather/history", get(weather::get_history))
+    .route("/weather/alert-rules", get(weather::list_alert_rules))
+    .route("/weather/alert-rules/{kind}", put(weather::put_alert_rule))
+    .route_layer(from_fn_with_state(ScopeGuard::new(db.clone(), SCOPE_WEATHER), require_scope))
+    .with_state((db.clone(), open_meteo));
+
+    let xtb_routes = Router::new()
     .route("/xtb/credentials", post(send_xtb_credentials))
+    .route("/xtb/status", get(xtb::get_status))
+    .route("/xtb/watchlist", get(xtb::list_watchlist).post(xtb::add_watchlist_symbol))
+    .route("/xtb/watchlist/{symbol}", delete(xtb::delete_watchlist_symbol))
+    .route("/xtb/alert-rules", get(xtb::list_alert_rules).post(xtb::add_alert_rule))
+    .route("/xtb/alert-rules/{id}", put(xtb::put_alert_rule).delete(xtb::delete_alert_rule))
+    .route("/xtb/alert-history", get(xtb::get_alert_history))
+    .route("/xtb/history", get(xtb::get_history))
+    .route("/xtb/money", get(xtb::get_money).put(xtb::put_money))
+    .route("/xtb/fx-rates", get(xtb::list_fx_rates))
+    .route("/xtb/fx-rates/{from}/{to}", put(xtb::put_fx_rate).delete(xtb::delete_fx_rate_pair))
+    .route_layer(from_fn_with_state(ScopeGuard::new(db.clone(), SCOPE_XTB), require_scope))
+    .with_state((db.clone(), sources));
+
+    Router::new()
+    .route("/health", get(health_check))
+    .route("/auth/logout", post(auth::logout))
+    .route("/auth/session", get(auth::current_session))
+    .route("/oauth2/{provider}/callback", get(oauth2::callback))
+    .merge(login_routes)
+    .merge(admin_routes)
+    .merge(provider_routes)
+    .merge(oauth2_routes)
+    .merge(spotify_routes)
+    .merge(weather_routes)
+    .merge(xtb_routes)
     .layer(
         CorsLayer::new()
         .allow_origin(origins)
-        .allow_headers(AllowHeaders::any())
-        .allow_methods([axum::http::Method::GET, axum::http::Method::POST])
+        .allow_credentials(true)
+        .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION, HeaderName::from_static(CSRF_HEADER)])
+        .allow_methods([axum::http::Method::GET, axum::http::Method::POST, axum::http::Method::PUT, axum::http::Method::DELETE])
     )
     .fallback_service(ServeDir::new("frontend/dist"))
-    .with_state(db);
+    .with_state(db)
+}
+
+/// Router for tests that don't talk to Spotify.
+#[cfg(test)]
+pub fn create_test_router(db: SqlitePool) -> Router {
+    let player = SpotifyPlayer::new(oauth2::refresher::TokenRefresher::new(db.clone()));
+    create_router(db, player, OpenMeteo::new(), Sources::new(crate::supervisor::Supervisor::new(), Vec::new()))
+}
+
+pub async fn initialize_axum_server(
+    db: SqlitePool,
+    player: SpotifyPlayer,
+    open_meteo: OpenMeteo,
+    sources: Sources,
+) -> anyhow::Result<()> {
+    let app = create_router(db, player, open_meteo, sources);
 
     let listener = TcpListener::bind("0.0.0.0:2700").await?;
-    axum::serve(listener, app.into_make_service()).await?;
+    // Connection info lets failed logins be throttled per client address.
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
 
     Ok(())
 }
@@ -43,64 +133,531 @@ async fn health_check() -> impl IntoResponse {
     "OK"
 }
 
-#[derive(Deserialize)]
-struct PostOAuth2Payload {
-    code: String,
-    #[serde(rename = "appName")]
-    app_name: String,
-    #[serde(rename = "clientSecret")]
-    client_secret: String,
-    #[serde(rename = "clientId")]
-    client_id: String,
-    #[serde(rename = "redirectUri")]
-    redirect_uri: String,
-    #[serde(rename = "getTokenUrl")]
-    get_token_url: String,
-}
-
-async fn post_oauth2_code(State(db): State<SqlitePool>, Json(payload): Json<PostOAuth2Payload>) -> impl IntoResponse {
-    let PostOAuth2Payload { code, app_name, client_secret, client_id, redirect_uri, get_token_url } = payload;
-
-    match app_name.as_str() {
-        "spotify" => {
-            let data = OAuth2Token {
-                app_name: app_name,
-                client_secret: client_secret,
-                client_id: client_id,
-                redirect_uri: redirect_uri,
-                access_token: "".to_string(),
-                refresh_token: "".to_string(),
-                expires_at: chrono::Utc::now().naive_utc(),
-                code: code,
-                get_token_url: get_token_url,
-                created_at: chrono::Utc::now().naive_utc(),
-            };
-            let _ = add_new_oauth2_token_to_db(
-                &db,
-                data,
-            ).await;
-        },
-        _ => return "Invalid app name".into_response(),
-    }
-
-    axum::http::StatusCode::OK.into_response()
-}
-
 #[derive(Deserialize)]
 struct SendXtbCredentialsPayload {
     #[serde(rename = "userId")]
     user_id: String,
     password: String,
+    #[serde(default, rename = "accountType")]
+    account_type: AccountType,
 }
 
-async fn send_xtb_credentials(State(db): State<SqlitePool>, Json(payload): Json<SendXtbCredentialsPayload>) -> impl IntoResponse {
-    let SendXtbCredentialsPayload { user_id, password } = payload;
+async fn send_xtb_credentials(
+    State((db, sources)): State<(SqlitePool, Sources)>,
+    ApiJson(payload): ApiJson<SendXtbCredentialsPayload>,
+) -> ApiResult<StatusCode> {
+    let SendXtbCredentialsPayload { user_id, password, account_type } = payload;
+
+    if user_id.trim().is_empty() || password.is_empty() {
+        return Err(ApiError::validation("userId and password are required"));
+    }
+
+    save_xtb_credentials(&db, user_id, password, account_type).await.map_err(ApiError::Database)?;
+    // Log in again with the new credentials instead of waiting for the next retry; a stopped
+    // provider stays stopped.
+    sources.supervisor().restart("xtb");
+
+    Ok(StatusCode::OK)
+}
+
+async fn list_providers(State(sources): State<Sources>) -> Json<Vec<SourceStatus>> {
+    Json(sources.statuses().await)
+}
+
+async fn start_provider(State(sources): State<Sources>, Path(name): Path<String>) -> ApiResult<StatusCode> {
+    if !sources.supervisor().start(&name) {
+        return Err(ApiError::not_found(format!("Unknown provider {}", name)));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn stop_provider(State(sources): State<Sources>, Path(name): Path<String>) -> ApiResult<StatusCode> {
+    if !sources.supervisor().stop(&name) {
+        return Err(ApiError::not_found(format!("Unknown provider {}", name)));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::{to_bytes, Body},
+        http::{header, Method, Request, StatusCode},
+        response::Response,
+    };
+    use serde_json::{json, Value};
+    use sqlx::SqlitePool;
+    use tower::ServiceExt;
+
+    use std::sync::Arc;
+
+    use super::{
+        auth, create_router, create_test_router, oauth2::refresher::TokenRefresher, spotify::SpotifyPlayer,
+        weather::{OpenMeteo, WeatherSource},
+        xtb::{AccountType, XtbSource},
+    };
+    use crate::{
+        db::{get_token_from_db, get_xtb_credentials, initialize_test_db, save_xtb_snapshot, XtbSnapshot},
+        sources::Sources,
+        supervisor::{ProviderState, Supervisor},
+    };
+
+    async fn send_json(db: &SqlitePool, method: Method, uri: &str, token: Option<&str>, body: Value) -> Response {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json");
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+
+        create_test_router(db.clone())
+            .oneshot(request.body(Body::from(body.to_string())).unwrap())
+            .await
+            .unwrap()
+    }
+
+    async fn problem(response: Response) -> Value {
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "application/problem+json"
+        );
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    async fn post_json(db: &SqlitePool, uri: &str, token: Option<&str>, body: Value) -> Response {
+        send_json(db, Method::POST, uri, token, body).await
+    }
+
+    async fn put_json(db: &SqlitePool, uri: &str, token: Option<&str>, body: Value) -> Response {
+        send_json(db, Method::PUT, uri, token, body).await
+    }
+
+    fn oauth2_client_payload() -> Value {
+        json!({
+            "clientSecret": "client-secret",
+            "clientId": "client-id",
+            "redirectUri": "http://localhost:2700/oauth2/spotify/callback",
+        })
+    }
+
+    #[tokio::test]
+    async fn health_check_is_public() {
+        let db = initialize_test_db().await;
+
+        let response = create_test_router(db)
+            .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn put_oauth2_client_requires_authentication() {
+        let db = initialize_test_db().await;
+
+        let response = put_json(&db, "/oauth2/spotify/client", None, oauth2_client_payload()).await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(problem(response).await["type"], "unauthorized");
+        assert!(get_token_from_db(&db, "spotify".to_string()).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn put_oauth2_client_requires_oauth2_scope() {
+        let db = initialize_test_db().await;
+        let token = auth::create_test_token(&db, &[auth::SCOPE_XTB]).await;
+
+        let response = put_json(&db, "/oauth2/spotify/client", Some(&token), oauth2_client_payload()).await;
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(problem(response).await["type"], "forbidden");
+    }
+
+    #[tokio::test]
+    async fn put_oauth2_client_stores_client_configuration() {
+        let db = initialize_test_db().await;
+        let token = auth::create_test_token(&db, &[auth::SCOPE_OAUTH2]).await;
+
+        let response = put_json(&db, "/oauth2/spotify/client", Some(&token), oauth2_client_payload()).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let stored = get_token_from_db(&db, "spotify".to_string()).await.unwrap().unwrap();
+        assert_eq!(stored.client_id, "client-id");
+        assert_eq!(stored.client_secret, "client-secret");
+        assert_eq!(stored.get_token_url, "https://accounts.spotify.com/api/token");
+        assert!(stored.access_token.is_empty());
+    }
+
+    #[tokio::test]
+    async fn put_oauth2_client_rejects_unknown_provider() {
+        let db = initialize_test_db().await;
+        let token = auth::create_test_token(&db, &[auth::SCOPE_OAUTH2]).await;
+
+        let response = put_json(&db, "/oauth2/myspace/client", Some(&token), oauth2_client_payload()).await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let problem = problem(response).await;
+        assert_eq!(problem["type"], "not-found");
+        assert_eq!(problem["status"], 404);
+        assert_eq!(problem["detail"], "Unknown OAuth2 provider myspace");
+    }
+
+    #[tokio::test]
+    async fn put_oauth2_client_rejects_relative_redirect_uri() {
+        let db = initialize_test_db().await;
+        let token = auth::create_test_token(&db, &[auth::SCOPE_OAUTH2]).await;
+        let mut payload = oauth2_client_payload();
+        payload["redirectUri"] = json!("/oauth2/spotify/callback");
 
-    let _ = save_xtb_credentials(
-        &db,
-        user_id,
-        password,
-    ).await;
+        let response = put_json(&db, "/oauth2/spotify/client", Some(&token), payload).await;
 
-    axum::http::StatusCode::OK.into_response()
-}
\ No newline at end of file
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(problem(response).await["type"], "validation-error");
+    }
+
+    #[tokio::test]
+    async fn put_oauth2_client_rejects_incomplete_payload() {
+        let db = initialize_test_db().await;
+        let token = auth::create_test_token(&db, &[auth::SCOPE_OAUTH2]).await;
+
+        let response = put_json(&db, "/oauth2/spotify/client", Some(&token), json!({ "clientId": "client-id" })).await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(problem(response).await["type"], "validation-error");
+    }
+
+    #[tokio::test]
+    async fn put_oauth2_client_reports_database_failure() {
+        let db = initialize_test_db().await;
+        let token = auth::create_test_token(&db, &[auth::SCOPE_OAUTH2]).await;
+        sqlx::query("DROP TABLE oauth2_tokens").execute(&db).await.unwrap();
+
+        let response = put_json(&db, "/oauth2/spotify/client", Some(&token), oauth2_client_payload()).await;
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let problem = problem(response).await;
+        assert_eq!(problem["type"], "database-error");
+        assert!(!problem["detail"].as_str().unwrap().contains("oauth2_tokens"));
+    }
+
+    #[tokio::test]
+    async fn send_xtb_credentials_stores_credentials() {
+        let db = initialize_test_db().await;
+        let token = auth::create_test_token(&db, &[auth::SCOPE_XTB]).await;
+
+        let response = post_json(
+            &db,
+            "/xtb/credentials",
+            Some(&token),
+            json!({ "userId": "12345", "password": "hunter2" }),
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let stored = get_xtb_credentials(&db).await.unwrap().unwrap();
+        assert_eq!(stored.user_id, "12345");
+        assert_eq!(stored.password, "hunter2");
+        assert_eq!(stored.account_type, AccountType::Real);
+    }
+
+    #[tokio::test]
+    async fn xtb_status_reports_the_account_type() {
+        let db = initialize_test_db().await;
+        let token = auth::create_test_token(&db, &[auth::SCOPE_XTB]).await;
+        let sources = Sources::new(Supervisor::new(), vec![Arc::new(XtbSource::new(db.clone()))]);
+
+        let response = provider_request(&db, &sources, Method::GET, "/xtb/status", auth::SCOPE_XTB).await;
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let status: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(status["configured"], false);
+        assert_eq!(status["health"]["status"], "unconfigured");
+
+        let credentials = json!({ "userId": "12345", "password": "hunter2", "accountType": "demo" });
+        let response = post_json(&db, "/xtb/credentials", Some(&token), credentials).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(get_xtb_credentials(&db).await.unwrap().unwrap().account_type, AccountType::Demo);
+
+        let response = provider_request(&db, &sources, Method::GET, "/xtb/status", auth::SCOPE_XTB).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let status: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(status, json!({ "configured": true, "userId": "12345", "accountType": "demo", "health": { "status": "healthy" } }));
+    }
+
+    #[tokio::test]
+    async fn xtb_watchlist_can_be_edited() {
+        let db = initialize_test_db().await;
+        let token = auth::create_test_token(&db, &[auth::SCOPE_XTB]).await;
+
+        for symbol in ["eurpln", "US500"] {
+            let response = post_json(&db, "/xtb/watchlist", Some(&token), json!({ "symbol": symbol })).await;
+            assert_eq!(response.status(), StatusCode::CREATED);
+        }
+        let response = post_json(&db, "/xtb/watchlist", Some(&token), json!({ "symbol": "EURPLN" })).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(problem(response).await["detail"], "EURPLN is already on the watchlist");
+        let response = post_json(&db, "/xtb/watchlist", Some(&token), json!({ "symbol": "US 500" })).await;
+        assert_eq!(problem(response).await["detail"], "US 500 is not a valid symbol");
+
+        let response = send_json(&db, Method::DELETE, "/xtb/watchlist/eurpln", Some(&token), json!({})).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let response = send_json(&db, Method::DELETE, "/xtb/watchlist/GOLD", Some(&token), json!({})).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = send_json(&db, Method::GET, "/xtb/watchlist", Some(&token), json!({})).await;
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let symbols: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(symbols, json!(["US500"]));
+    }
+
+    #[tokio::test]
+    async fn xtb_alert_rules_can_be_edited() {
+        let db = initialize_test_db().await;
+        let token = auth::create_test_token(&db, &[auth::SCOPE_XTB]).await;
+
+        let rule = json!({ "kind": "price_move", "symbol": "us500", "threshold": 1.0, "windowMinutes": 15, "cooldownMinutes": 30 });
+        let response = post_json(&db, "/xtb/alert-rules", Some(&token), rule).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let created: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(created["symbol"], "US500");
+        assert_eq!(created["enabled"], true);
+
+        let rule = json!({ "kind": "price_above", "threshold": 5000.0, "cooldownMinutes": 30 });
+        let response = post_json(&db, "/xtb/alert-rules", Some(&token), rule).await;
+        assert_eq!(problem(response).await["detail"], "symbol is required for price rules");
+        let rule = json!({ "kind": "price_move", "symbol": "US500", "threshold": 1.0, "windowMinutes": 0, "cooldownMinutes": 30 });
+        let response = post_json(&db, "/xtb/alert-rules", Some(&token), rule).await;
+        assert_eq!(problem(response).await["detail"], "windowMinutes must be between 1 and 240");
+        let rule = json!({ "kind": "price_below", "symbol": "US500", "threshold": 5000.0, "cooldownMinutes": 0 });
+        let response = post_json(&db, "/xtb/alert-rules", Some(&token), rule).await;
+        assert_eq!(problem(response).await["detail"], "cooldownMinutes must be between 1 and 10080");
+
+        let id = created["id"].as_i64().unwrap();
+        let rule = json!({ "kind": "profit_below", "symbol": "US500", "threshold": -200.0, "cooldownMinutes": 60, "enabled": false });
+        let response = put_json(&db, &format!("/xtb/alert-rules/{}", id), Some(&token), rule.clone()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = put_json(&db, "/xtb/alert-rules/999", Some(&token), rule).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = send_json(&db, Method::GET, "/xtb/alert-rules", Some(&token), json!({})).await;
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let rules: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            rules,
+            json!([{ "id": id, "kind": "profit_below", "symbol": null, "threshold": -200.0, "windowMinutes": 0, "cooldownMinutes": 60, "enabled": false }])
+        );
+
+        let uri = format!("/xtb/alert-rules/{}", id);
+        let response = send_json(&db, Method::DELETE, &uri, Some(&token), json!({})).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let response = send_json(&db, Method::GET, "/xtb/alert-history?limit=0", Some(&token), json!({})).await;
+        assert_eq!(problem(response).await["detail"], "limit must be between 1 and 500");
+    }
+
+    #[tokio::test]
+    async fn xtb_money_settings_and_fx_rates_can_be_edited() {
+        let db = initialize_test_db().await;
+        let token = auth::create_test_token(&db, &[auth::SCOPE_XTB]).await;
+
+        let response = send_json(&db, Method::GET, "/xtb/money", Some(&token), json!({})).await;
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let settings: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(settings, json!({ "locale": "en", "displayCurrency": null }));
+
+        let response = put_json(&db, "/xtb/money", Some(&token), json!({ "locale": "pl", "displayCurrency": "pln" })).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = put_json(&db, "/xtb/money", Some(&token), json!({ "locale": "pl", "displayCurrency": "zloty" })).await;
+        assert_eq!(problem(response).await["detail"], "ZLOTY is not a currency code");
+        let response = send_json(&db, Method::GET, "/xtb/money", Some(&token), json!({})).await;
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let settings: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(settings, json!({ "locale": "pl", "displayCurrency": "PLN" }));
+
+        let response = put_json(&db, "/xtb/fx-rates/eur/pln", Some(&token), json!({ "rate": 4.25 })).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = put_json(&db, "/xtb/fx-rates/EUR/PLN", Some(&token), json!({ "rate": 4.3 })).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = put_json(&db, "/xtb/fx-rates/USD/PLN", Some(&token), json!({ "rate": 0.0 })).await;
+        assert_eq!(problem(response).await["detail"], "rate must be a positive number");
+
+        let response = send_json(&db, Method::GET, "/xtb/fx-rates", Some(&token), json!({})).await;
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let rates: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(rates, json!([{ "from": "EUR", "to": "PLN", "rate": 4.3 }]));
+
+        let response = send_json(&db, Method::DELETE, "/xtb/fx-rates/eur/pln", Some(&token), json!({})).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let response = send_json(&db, Method::DELETE, "/xtb/fx-rates/EUR/PLN", Some(&token), json!({})).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn xtb_history_has_daily_pnl_and_snapshots() {
+        let db = initialize_test_db().await;
+        let token = auth::create_test_token(&db, &[auth::SCOPE_XTB]).await;
+        let now = chrono::Utc::now().naive_utc();
+        for (recorded_at, equity) in [(now - chrono::Duration::days(1), 1000.0), (now, 1010.0), (now, 1025.5)] {
+            let snapshot = XtbSnapshot { recorded_at, balance: 1000.0, equity, profit: equity - 1000.0, positions: 1 };
+            save_xtb_snapshot(&db, &snapshot).await.unwrap();
+        }
+
+        let response = send_json(&db, Method::GET, "/xtb/history?days=1", Some(&token), json!({})).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let history: Value = serde_json::from_slice(&body).unwrap();
+        let today = now.date().to_string();
+        assert_eq!(
+            history["days"],
+            json!([{ "date": today, "balance": 1000.0, "equity": 1025.5, "profit": 25.5, "pnl": 25.5 }])
+        );
+        assert_eq!(history["snapshots"].as_array().unwrap().len(), 2);
+
+        let response = send_json(&db, Method::GET, "/xtb/history?days=0", Some(&token), json!({})).await;
+        assert_eq!(problem(response).await["detail"], "days must be between 1 and 365");
+    }
+
+    #[tokio::test]
+    async fn send_xtb_credentials_leaves_a_stopped_provider_stopped() {
+        let db = initialize_test_db().await;
+        let token = auth::create_test_token(&db, &[auth::SCOPE_XTB]).await;
+        let supervisor = Supervisor::new();
+        supervisor.register("xtb", std::future::pending);
+        supervisor.stop("xtb");
+        let sources = Sources::new(supervisor, vec![Arc::new(XtbSource::new(db.clone()))]);
+        let player = SpotifyPlayer::new(TokenRefresher::new(db.clone()));
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/xtb/credentials")
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(json!({ "userId": "12345", "password": "hunter2" }).to_string()))
+            .unwrap();
+
+        let response = create_router(db.clone(), player, OpenMeteo::new(), sources.clone())
+            .oneshot(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(sources.supervisor().statuses()[0].state, ProviderState::Stopped);
+    }
+
+    #[tokio::test]
+    async fn send_xtb_credentials_rejects_empty_credentials() {
+        let db = initialize_test_db().await;
+        let token = auth::create_test_token(&db, &[auth::SCOPE_XTB]).await;
+
+        let response = post_json(
+            &db,
+            "/xtb/credentials",
+            Some(&token),
+            json!({ "userId": " ", "password": "" }),
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(problem(response).await["type"], "validation-error");
+        assert!(get_xtb_credentials(&db).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn send_xtb_credentials_reports_database_failure() {
+        let db = initialize_test_db().await;
+        let token = auth::create_test_token(&db, &[auth::SCOPE_XTB]).await;
+        sqlx::query("DROP TABLE xtb_credentials").execute(&db).await.unwrap();
+
+        let response = post_json(
+            &db,
+            "/xtb/credentials",
+            Some(&token),
+            json!({ "userId": "12345", "password": "hunter2" }),
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(problem(response).await["type"], "database-error");
+    }
+
+    async fn provider_request(db: &SqlitePool, sources: &Sources, method: Method, uri: &str, scope: &str) -> Response {
+        let token = auth::create_test_token(db, &[scope]).await;
+        let player = SpotifyPlayer::new(TokenRefresher::new(db.clone()));
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .body(Body::empty())
+            .unwrap();
+
+        create_router(db.clone(), player, OpenMeteo::new(), sources.clone())
+            .oneshot(request)
+            .await
+            .unwrap()
+    }
+
+    /// Weather source whose supervised task idles instead of calling the weather API.
+    fn sources_with_weather(db: &SqlitePool) -> Sources {
+        let supervisor = Supervisor::new();
+        supervisor.register("weather", std::future::pending);
+        Sources::new(supervisor, vec![Arc::new(WeatherSource::new(db.clone(), OpenMeteo::new()))])
+    }
+
+    #[tokio::test]
+    async fn providers_route_lists_provider_status() {
+        let db = initialize_test_db().await;
+        let sources = sources_with_weather(&db);
+
+        let response = provider_request(&db, &sources, Method::GET, "/providers", auth::SCOPE_ADMIN).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let statuses: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(statuses[0]["name"], "weather");
+        assert_eq!(statuses[0]["state"], "running");
+        assert_eq!(statuses[0]["restarts"], 0);
+        assert_eq!(statuses[0]["app"], "Weather");
+        assert_eq!(statuses[0]["mode"], "poll");
+        assert_eq!(statuses[0]["health"]["status"], "healthy");
+    }
+
+    #[tokio::test]
+    async fn providers_can_be_stopped_and_started() {
+        let db = initialize_test_db().await;
+        let sources = sources_with_weather(&db);
+
+        let response = provider_request(&db, &sources, Method::POST, "/providers/weather/stop", auth::SCOPE_ADMIN).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(sources.supervisor().statuses()[0].state, ProviderState::Stopped);
+
+        let response = provider_request(&db, &sources, Method::POST, "/providers/weather/start", auth::SCOPE_ADMIN).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(sources.supervisor().statuses()[0].state, ProviderState::Running);
+    }
+
+    #[tokio::test]
+    async fn unknown_provider_is_not_found() {
+        let db = initialize_test_db().await;
+
+        let sources = Sources::new(Supervisor::new(), Vec::new());
+        let response = provider_request(&db, &sources, Method::POST, "/providers/myspace/stop", auth::SCOPE_ADMIN).await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(problem(response).await["detail"], "Unknown provider myspace");
+    }
+
+    #[tokio::test]
+    async fn providers_route_requires_admin_scope() {
+        let db = initialize_test_db().await;
+        let sources = sources_with_weather(&db);
+
+        let response = provider_request(&db, &sources, Method::POST, "/providers/weather/stop", auth::SCOPE_XTB).await;
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(sources.supervisor().statuses()[0].state, ProviderState::Running);
+    }
+}
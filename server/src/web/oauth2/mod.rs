@@ -1,6 +1,80 @@
-use reqwest::Client;
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::Redirect,
+    Json,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::{distributions::Alphanumeric, rngs::OsRng, Rng};
+use reqwest::Url;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
 
+use self::providers::Provider;
+use super::error::{ApiError, ApiJson, ApiResult};
+use crate::db::{
+    add_new_oauth2_token_to_db, delete_expired_oauth2_states, get_oauth2_tokens, get_token_from_db,
+    save_oauth2_state, take_oauth2_state, update_oauth2_access_and_refresh_tokens, OAuth2Token,
+};
+use crate::http::{self, HttpError};
+
+pub mod providers;
+pub mod refresher;
+
+/// How long a user has to finish the provider's consent screen.
+const STATE_TTL_MINUTES: i64 = 10;
+
+/// Tokens without an `expires_in` (e.g. GitHub OAuth apps) are treated as valid this long.
+const NON_EXPIRING_TOKEN_DAYS: i64 = 3650;
+
+/// Why a token endpoint call failed.
+#[derive(Debug, thiserror::Error)]
+pub enum OAuth2Error {
+    #[error("Could not reach the token endpoint: {0}")]
+    Http(#[from] HttpError),
+    #[error("Token endpoint rejected the request: {error}{}", describe(.description))]
+    Rejected {
+        error: String,
+        description: Option<String>,
+    },
+    #[error("Token endpoint answered HTTP {status} with an unexpected body: {body}")]
+    InvalidResponse { status: u16, body: String },
+    #[error("No refresh token is stored; authorize the integration again")]
+    MissingRefreshToken,
+}
+
+impl OAuth2Error {
+    /// Retrying won't help: the grant was revoked or there is nothing to refresh with.
+    pub fn is_permanent(&self) -> bool {
+        match self {
+            Self::Rejected { error, .. } => {
+                matches!(error.as_str(), "invalid_grant" | "invalid_client" | "unauthorized_client")
+            }
+            Self::MissingRefreshToken => true,
+            Self::Http(_) | Self::InvalidResponse { .. } => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for OAuth2Error {
+    fn from(error: reqwest::Error) -> Self {
+        Self::Http(error.into())
+    }
+}
+
+fn describe(description: &Option<String>) -> String {
+    description
+        .as_deref()
+        .map(|description| format!(" ({})", description))
+        .unwrap_or_default()
+}
+
+impl From<OAuth2Error> for ApiError {
+    fn from(error: OAuth2Error) -> Self {
+        ApiError::Upstream(error.to_string())
+    }
+}
 
 pub struct ExchangeCodePayload {
     pub code: String,
@@ -8,68 +82,126 @@ pub struct ExchangeCodePayload {
     pub client_id: String,
     pub client_secret: String,
     pub get_token_url: String,
+    pub code_verifier: Option<String>,
+}
+
+pub struct RefreshTokenPayload {
+    pub client_id: String,
+    pub client_secret: String,
+    pub refresh_token: String,
+    pub get_token_url: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug)]
 pub struct OAuth2Tokens {
     pub access_token: String,
-    pub refresh_token: String,
-    pub expires_in: u64,
+    /// Often omitted on refresh, in which case the previous refresh token stays valid.
+    pub refresh_token: Option<String>,
+    pub expires_in: Option<u64>,
+}
+
+impl OAuth2Tokens {
+    pub fn expires_at(&self, now: chrono::NaiveDateTime) -> chrono::NaiveDateTime {
+        match self.expires_in {
+            Some(seconds) => now + chrono::Duration::seconds(seconds as i64),
+            None => now + chrono::Duration::days(NON_EXPIRING_TOKEN_DAYS),
+        }
+    }
+}
+
+/// Token endpoint answer; every field is optional so that quirky providers don't fail to parse.
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+    /// Some providers send the lifetime as a string.
+    expires_in: Option<serde_json::Value>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+fn parse_token_response(status: u16, body: &str) -> Result<OAuth2Tokens, OAuth2Error> {
+    let invalid = || OAuth2Error::InvalidResponse {
+        status,
+        body: body.chars().take(200).collect(),
+    };
+
+    let response: TokenResponse = serde_json::from_str(body).map_err(|_| invalid())?;
+
+    // GitHub reports errors with 200 OK, so an `error` field wins over the status code.
+    if let Some(error) = response.error {
+        return Err(OAuth2Error::Rejected {
+            error,
+            description: response.error_description,
+        });
+    }
+
+    if !(200..300).contains(&status) {
+        return Err(invalid());
+    }
+
+    let access_token = response
+        .access_token
+        .filter(|token| !token.is_empty())
+        .ok_or_else(invalid)?;
+
+    let expires_in = match response.expires_in {
+        Some(serde_json::Value::Number(seconds)) => seconds.as_u64(),
+        Some(serde_json::Value::String(seconds)) => seconds.parse().ok(),
+        _ => None,
+    };
+
+    Ok(OAuth2Tokens {
+        access_token,
+        refresh_token: response.refresh_token.filter(|token| !token.is_empty()),
+        expires_in,
+    })
+}
+
+async fn request_tokens(
+    provider: &Provider,
+    token_url: &str,
+    params: &[(&str, &str)],
+) -> Result<OAuth2Tokens, OAuth2Error> {
+    let http = http::shared();
+    let mut request = http.post(token_url).form(params);
+    if provider.requires_json_accept {
+        request = request.header(header::ACCEPT, "application/json");
+    }
+
+    let response = http.send(request).await?;
+    let status = response.status().as_u16();
+    let body = response.text().await?;
+
+    parse_token_response(status, &body)
 }
 
 pub async fn exchange_code_for_tokens(
+    provider: &Provider,
     payload: ExchangeCodePayload,
-) -> anyhow::Result<OAuth2Tokens> {
-    let client = Client::new();
-    let params = [
+) -> Result<OAuth2Tokens, OAuth2Error> {
+    let mut params = vec![
         ("grant_type", "authorization_code"),
         ("code", payload.code.as_str()),
         ("redirect_uri", payload.redirect_uri.as_str()),
         ("client_id", payload.client_id.as_str()),
         ("client_secret", payload.client_secret.as_str()),
     ];
-
-    let response = client
-        .post(payload.get_token_url.as_str())
-        .form(&params)
-        .send()
-        .await?;
-
-    if response.status().is_success() {
-        let json: serde_json::Value = response.json().await?;
-        let access_token = json.get("access_token").unwrap().as_str().unwrap();
-        let refresh_token = json.get("refresh_token").unwrap().as_str().unwrap();
-        let expires_in = json.get("expires_in").unwrap().as_u64().unwrap();
-
-        Ok(OAuth2Tokens {
-            access_token: access_token.to_string(),
-            refresh_token: refresh_token.to_string(),
-            expires_in: expires_in,
-        })
-    } else {
-        let error_text = response.text().await?;
-        println!("Failed to exchange code for tokens: {}", error_text);
-        Err(anyhow::anyhow!("Failed to exchange code for tokens: {}", error_text))
+    if let Some(code_verifier) = payload.code_verifier.as_deref() {
+        params.push(("code_verifier", code_verifier));
     }
-}
-
-pub struct RefreshTokenPayload {
-    pub client_id: String,
-    pub client_secret: String,
-    pub refresh_token: String,
-    pub get_token_url: String,
-}
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct RefreshedTokenResponse {
-    pub access_token: String,
-    pub expires_in: u64,
+    request_tokens(provider, &payload.get_token_url, &params).await
 }
 
 pub async fn refresh_access_token(
+    provider: &Provider,
     payload: RefreshTokenPayload,
-) -> anyhow::Result<RefreshedTokenResponse> {
-    let client = Client::new();
+) -> Result<OAuth2Tokens, OAuth2Error> {
+    if payload.refresh_token.is_empty() {
+        return Err(OAuth2Error::MissingRefreshToken);
+    }
+
     let params = [
         ("grant_type", "refresh_token"),
         ("refresh_token", payload.refresh_token.as_str()),
@@ -77,23 +209,802 @@ pub async fn refresh_access_token(
         ("client_secret", payload.client_secret.as_str()),
     ];
 
-   
-    let response = client
-        .post(payload.get_token_url.as_str())
-        .form(&params)
-        .send()
-        .await?;
-
-    if response.status().is_success() {
-        let json: serde_json::Value = response.json().await?;
-        let access_token = json.get("access_token").unwrap().as_str().unwrap();
-        let expires_in = json.get("expires_in").unwrap().as_u64().unwrap();
-
-        Ok(RefreshedTokenResponse {
-            access_token: access_token.to_string(),
-            expires_in: expires_in,
+    request_tokens(provider, &payload.get_token_url, &params).await
+}
+
+fn lookup_provider(name: &str) -> ApiResult<&'static Provider> {
+    providers::get(name).ok_or_else(|| ApiError::not_found(format!("Unknown OAuth2 provider {}", name)))
+}
+
+fn random_string(length: usize) -> String {
+    OsRng
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+/// PKCE S256 challenge for `code_verifier` (RFC 7636).
+pub fn code_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+pub fn build_authorize_url(
+    authorize_url: &str,
+    client_id: &str,
+    redirect_uri: &str,
+    scopes: &str,
+    state: &str,
+    code_verifier: &str,
+    extra_params: &[(&str, &str)],
+) -> anyhow::Result<Url> {
+    let challenge = code_challenge(code_verifier);
+    let mut url = Url::parse_with_params(
+        authorize_url,
+        &[
+            ("response_type", "code"),
+            ("client_id", client_id),
+            ("redirect_uri", redirect_uri),
+            ("state", state),
+            ("code_challenge_method", "S256"),
+            ("code_challenge", challenge.as_str()),
+        ],
+    )?;
+
+    {
+        let mut query = url.query_pairs_mut();
+        if !scopes.is_empty() {
+            query.append_pair("scope", scopes);
+        }
+        query.extend_pairs(extra_params);
+    }
+
+    Ok(url)
+}
+
+#[derive(Deserialize)]
+pub struct PutOAuth2ClientPayload {
+    #[serde(rename = "clientId")]
+    client_id: String,
+    #[serde(rename = "clientSecret")]
+    client_secret: String,
+    /// Must point at `/oauth2/{provider}/callback` on this server and be registered with the provider.
+    #[serde(rename = "redirectUri")]
+    redirect_uri: String,
+    /// The remaining fields override the provider's defaults and are required for `generic`.
+    #[serde(rename = "authorizeUrl")]
+    authorize_url: Option<String>,
+    #[serde(rename = "getTokenUrl")]
+    get_token_url: Option<String>,
+    scopes: Option<String>,
+}
+
+fn validate_url(name: &str, url: &str) -> ApiResult<()> {
+    Url::parse(url)
+        .map(|_| ())
+        .map_err(|_| ApiError::validation(format!("{} must be an absolute URL", name)))
+}
+
+/// Stores the OAuth2 client registration for `provider`. Existing tokens are discarded, so the
+/// integration has to be authorized again.
+pub async fn put_oauth2_client(
+    State(db): State<SqlitePool>,
+    Path(provider): Path<String>,
+    ApiJson(payload): ApiJson<PutOAuth2ClientPayload>,
+) -> ApiResult<StatusCode> {
+    let provider_info = lookup_provider(&provider)?;
+
+    if payload.client_id.is_empty() || payload.client_secret.is_empty() || payload.redirect_uri.is_empty() {
+        return Err(ApiError::validation("clientId, clientSecret and redirectUri are required"));
+    }
+    validate_url("redirectUri", &payload.redirect_uri)?;
+
+    let authorize_url = payload.authorize_url.unwrap_or_default();
+    let resolved_authorize_url = Provider::resolve(&authorize_url, provider_info.authorize_url)
+        .ok_or_else(|| ApiError::validation(format!("authorizeUrl is required for {}", provider)))?;
+    validate_url("authorizeUrl", resolved_authorize_url)?;
+
+    let get_token_url = Provider::resolve(payload.get_token_url.as_deref().unwrap_or_default(), provider_info.token_url)
+        .ok_or_else(|| ApiError::validation(format!("getTokenUrl is required for {}", provider)))?
+        .to_string();
+    validate_url("getTokenUrl", &get_token_url)?;
+
+    let data = OAuth2Token {
+        app_name: provider,
+        client_secret: payload.client_secret,
+        client_id: payload.client_id,
+        redirect_uri: payload.redirect_uri,
+        access_token: "".to_string(),
+        refresh_token: "".to_string(),
+        expires_at: chrono::Utc::now().naive_utc(),
+        code: "".to_string(),
+        get_token_url,
+        created_at: chrono::Utc::now().naive_utc(),
+        authorize_url,
+        scopes: payload.scopes.unwrap_or_default(),
+        refresh_failures: 0,
+        last_refresh_error: None,
+        broken: false,
+    };
+    add_new_oauth2_token_to_db(&db, data).await.map_err(ApiError::Database)?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Serialize)]
+pub struct AuthorizeResponse {
+    url: String,
+}
+
+/// Starts the authorization-code flow, returning the provider's consent screen for the browser
+/// to open. A POST, so sessions need their CSRF token and a link alone can't start a flow.
+pub async fn authorize(
+    State(db): State<SqlitePool>,
+    Path(provider): Path<String>,
+) -> ApiResult<Json<AuthorizeResponse>> {
+    let provider_info = lookup_provider(&provider)?;
+
+    let client = get_token_from_db(&db, provider.clone())
+        .await
+        .map_err(ApiError::Database)?
+        .ok_or_else(|| ApiError::not_found(format!("{} has no OAuth2 client configured", provider)))?;
+
+    let authorize_url = Provider::resolve(&client.authorize_url, provider_info.authorize_url)
+        .ok_or_else(|| ApiError::validation(format!("{} has no authorizeUrl configured", provider)))?;
+    let scopes = Provider::resolve(&client.scopes, provider_info.scopes).unwrap_or_default();
+
+    let now = chrono::Utc::now().naive_utc();
+    delete_expired_oauth2_states(&db, now - chrono::Duration::minutes(STATE_TTL_MINUTES))
+        .await
+        .map_err(ApiError::Database)?;
+
+    let state = random_string(32);
+    let code_verifier = random_string(64);
+    save_oauth2_state(&db, &state, &provider, &code_verifier)
+        .await
+        .map_err(ApiError::Database)?;
+
+    let url = build_authorize_url(
+        authorize_url,
+        &client.client_id,
+        &client.redirect_uri,
+        scopes,
+        &state,
+        &code_verifier,
+        provider_info.authorize_params,
+    )
+    .map_err(ApiError::Internal)?;
+
+    Ok(Json(AuthorizeResponse { url: url.to_string() }))
+}
+
+#[derive(Serialize)]
+pub struct OAuth2LinkResponse {
+    #[serde(rename = "appName")]
+    app_name: String,
+    connected: bool,
+    broken: bool,
+    #[serde(rename = "expiresAt")]
+    expires_at: chrono::NaiveDateTime,
+    #[serde(rename = "refreshFailures")]
+    refresh_failures: i64,
+    #[serde(rename = "lastRefreshError")]
+    last_refresh_error: Option<String>,
+}
+
+/// Lists the configured integrations and whether their tokens are still being refreshed.
+pub async fn list_links(State(db): State<SqlitePool>) -> ApiResult<Json<Vec<OAuth2LinkResponse>>> {
+    let tokens = get_oauth2_tokens(&db).await.map_err(ApiError::Database)?;
+
+    Ok(Json(
+        tokens
+            .into_iter()
+            .map(|token| OAuth2LinkResponse {
+                connected: !token.access_token.is_empty() && !token.broken,
+                app_name: token.app_name,
+                broken: token.broken,
+                expires_at: token.expires_at,
+                refresh_failures: token.refresh_failures,
+                last_refresh_error: token.last_refresh_error,
+            })
+            .collect(),
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct CallbackParams {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+fn frontend_redirect(provider: &str, status: &str) -> Redirect {
+    let frontend_url = std::env::var("FRONTEND_URL").unwrap_or_else(|_| "/".to_string());
+    Redirect::to(&format!("{}?oauth2={}&status={}", frontend_url, provider, status))
+}
+
+/// Redirect target registered with the provider. It is not behind authentication; the
+/// single-use `state` ties it to an `authorize` call made by a signed-in admin.
+pub async fn callback(
+    State(db): State<SqlitePool>,
+    Path(provider): Path<String>,
+    Query(params): Query<CallbackParams>,
+) -> ApiResult<Redirect> {
+    let state = params
+        .state
+        .ok_or_else(|| ApiError::validation("Missing state parameter"))?;
+
+    let pending = take_oauth2_state(&db, &state)
+        .await
+        .map_err(ApiError::Database)?
+        .filter(|pending| pending.app_name == provider)
+        .ok_or_else(|| ApiError::validation("Unknown or already used state"))?;
+
+    let age = chrono::Utc::now().naive_utc() - pending.created_at;
+    if age > chrono::Duration::minutes(STATE_TTL_MINUTES) {
+        return Err(ApiError::validation("Authorization took too long; please try again"));
+    }
+
+    if let Some(error) = params.error {
+        println!("{} authorization was not granted: {}", provider, error);
+        return Ok(frontend_redirect(&provider, "denied"));
+    }
+
+    let code = params
+        .code
+        .ok_or_else(|| ApiError::validation("Missing code parameter"))?;
+
+    let provider_info = lookup_provider(&provider)?;
+    let client = get_token_from_db(&db, provider.clone())
+        .await
+        .map_err(ApiError::Database)?
+        .ok_or_else(|| ApiError::not_found(format!("{} has no OAuth2 client configured", provider)))?;
+
+    let tokens = exchange_code_for_tokens(
+        provider_info,
+        ExchangeCodePayload {
+            code,
+            redirect_uri: client.redirect_uri,
+            client_id: client.client_id,
+            client_secret: client.client_secret,
+            get_token_url: client.get_token_url,
+            code_verifier: Some(pending.code_verifier),
+        },
+    )
+    .await?;
+
+    let expires_at = tokens.expires_at(chrono::Utc::now().naive_utc());
+    update_oauth2_access_and_refresh_tokens(
+        &db,
+        provider.clone(),
+        tokens.access_token,
+        tokens.refresh_token.unwrap_or(client.refresh_token),
+        expires_at,
+    )
+    .await
+    .map_err(ApiError::Database)?;
+
+    println!("Connected {}", provider);
+    Ok(frontend_redirect(&provider, "connected"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use axum::{
+        body::{to_bytes, Body},
+        extract::{Form, State},
+        http::{header, HeaderMap, Method, Request, StatusCode},
+        response::{IntoResponse, Response},
+        routing::post,
+        Json, Router,
+    };
+    use reqwest::Url;
+    use serde_json::{json, Value};
+    use sqlx::SqlitePool;
+    use tower::ServiceExt;
+
+    use super::{
+        code_challenge, exchange_code_for_tokens, parse_token_response, providers, refresh_access_token,
+        ExchangeCodePayload, OAuth2Error, RefreshTokenPayload,
+    };
+    use crate::{
+        db::{get_token_from_db, initialize_test_db},
+        test_utils::spawn_mock_server,
+        web::{auth, create_test_router},
+    };
+
+    type Received = Arc<Mutex<Vec<HashMap<String, String>>>>;
+
+    /// Token endpoint that records every form it receives and answers like Spotify does.
+    async fn spawn_token_endpoint() -> (String, Received) {
+        async fn token(
+            State(received): State<Received>,
+            Form(form): Form<HashMap<String, String>>,
+        ) -> Response {
+            let grant_type = form.get("grant_type").cloned().unwrap_or_default();
+            let code = form.get("code").cloned().unwrap_or_default();
+            received.lock().unwrap().push(form);
+
+            let body = match (grant_type.as_str(), code.as_str()) {
+                ("authorization_code", "good-code") => json!({
+                    "access_token": "access-1",
+                    "token_type": "Bearer",
+                    "refresh_token": "refresh-1",
+                    "expires_in": 3600,
+                }),
+                ("refresh_token", _) => json!({
+                    "access_token": "access-2",
+                    "token_type": "Bearer",
+                    "expires_in": 3600,
+                }),
+                _ => {
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        Json(json!({ "error": "invalid_grant" })),
+                    )
+                        .into_response()
+                }
+            };
+
+            Json(body).into_response()
+        }
+
+        let received = Received::default();
+        let router = Router::new()
+            .route("/token", post(token))
+            .with_state(received.clone());
+        let base_url = spawn_mock_server(router).await;
+
+        (format!("{}/token", base_url), received)
+    }
+
+    /// Token endpoint with GitHub's quirks: form-encoded unless JSON is requested, errors with
+    /// 200 OK, and tokens that neither expire nor come with a refresh token.
+    async fn spawn_github_token_endpoint() -> String {
+        async fn token(headers: HeaderMap, Form(form): Form<HashMap<String, String>>) -> Response {
+            let wants_json = headers
+                .get(header::ACCEPT)
+                .is_some_and(|accept| accept == "application/json");
+            if !wants_json {
+                return "access_token=gho_access&scope=read%3Auser&token_type=bearer".into_response();
+            }
+
+            match form.get("code").map(String::as_str) {
+                Some("good-code") => Json(json!({
+                    "access_token": "gho_access",
+                    "scope": "read:user",
+                    "token_type": "bearer",
+                }))
+                .into_response(),
+                _ => Json(json!({
+                    "error": "bad_verification_code",
+                    "error_description": "The code passed is incorrect or expired.",
+                }))
+                .into_response(),
+            }
+        }
+
+        let base_url = spawn_mock_server(Router::new().route("/token", post(token))).await;
+        format!("{}/token", base_url)
+    }
+
+    fn github_exchange(token_url: &str, code: &str) -> ExchangeCodePayload {
+        ExchangeCodePayload {
+            code: code.to_string(),
+            redirect_uri: "http://localhost:2700/oauth2/github/callback".to_string(),
+            client_id: "client-id".to_string(),
+            client_secret: "client-secret".to_string(),
+            get_token_url: token_url.to_string(),
+            code_verifier: None,
+        }
+    }
+
+    async fn request(db: &SqlitePool, method: Method, uri: &str, token: Option<&str>, body: Option<Value>) -> Response {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+
+        let body = match body {
+            Some(body) => {
+                request = request.header(header::CONTENT_TYPE, "application/json");
+                Body::from(body.to_string())
+            }
+            None => Body::empty(),
+        };
+
+        create_test_router(db.clone())
+            .oneshot(request.body(body).unwrap())
+            .await
+            .unwrap()
+    }
+
+    /// Configures the Spotify client against `token_url` and starts the flow, returning the
+    /// parsed consent-screen URL.
+    async fn start_authorization(db: &SqlitePool, token_url: &str) -> Url {
+        let api_token = auth::create_test_token(db, &[auth::SCOPE_OAUTH2]).await;
+
+        let response = request(
+            db,
+            Method::PUT,
+            "/oauth2/spotify/client",
+            Some(&api_token),
+            Some(json!({
+                "clientId": "client-id",
+                "clientSecret": "client-secret",
+                "redirectUri": "http://localhost:2700/oauth2/spotify/callback",
+                "getTokenUrl": token_url,
+            })),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        authorize(db, "spotify", &api_token).await
+    }
+
+    /// Starts the flow for `provider`, returning the parsed consent-screen URL.
+    async fn authorize(db: &SqlitePool, provider: &str, api_token: &str) -> Url {
+        let uri = format!("/oauth2/{}/authorize", provider);
+        let response = request(db, Method::POST, &uri, Some(api_token), None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        Url::parse(body["url"].as_str().unwrap()).unwrap()
+    }
+
+    fn query_param(url: &Url, name: &str) -> String {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+            .unwrap_or_else(|| panic!("{} is missing from {}", name, url))
+    }
+
+    #[tokio::test]
+    async fn authorize_redirects_to_consent_screen_with_pkce() {
+        let db = initialize_test_db().await;
+        let (token_url, _) = spawn_token_endpoint().await;
+
+        let url = start_authorization(&db, &token_url).await;
+
+        assert_eq!(url.host_str(), Some("accounts.spotify.com"));
+        assert_eq!(query_param(&url, "client_id"), "client-id");
+        assert_eq!(query_param(&url, "response_type"), "code");
+        assert_eq!(query_param(&url, "code_challenge_method"), "S256");
+        assert_eq!(
+            query_param(&url, "redirect_uri"),
+            "http://localhost:2700/oauth2/spotify/callback"
+        );
+        assert!(query_param(&url, "scope").contains("user-read-currently-playing"));
+    }
+
+    #[tokio::test]
+    async fn authorize_requires_client_configuration() {
+        let db = initialize_test_db().await;
+        let api_token = auth::create_test_token(&db, &[auth::SCOPE_OAUTH2]).await;
+
+        let response = request(&db, Method::POST, "/oauth2/spotify/authorize", Some(&api_token), None).await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn authorize_cannot_be_started_by_a_link() {
+        let db = initialize_test_db().await;
+        let api_token = auth::create_test_token(&db, &[auth::SCOPE_OAUTH2]).await;
+
+        let response = request(&db, Method::GET, "/oauth2/spotify/authorize", Some(&api_token), None).await;
+
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn callback_exchanges_code_with_verifier_and_stores_tokens() {
+        let db = initialize_test_db().await;
+        let (token_url, received) = spawn_token_endpoint().await;
+        let url = start_authorization(&db, &token_url).await;
+        let state = query_param(&url, "state");
+
+        let response = request(
+            &db,
+            Method::GET,
+            &format!("/oauth2/spotify/callback?code=good-code&state={}", state),
+            None,
+            None,
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert_eq!(
+            response.headers()[header::LOCATION],
+            "/?oauth2=spotify&status=connected"
+        );
+
+        let form = received.lock().unwrap()[0].clone();
+        assert_eq!(form["grant_type"], "authorization_code");
+        assert_eq!(form["client_id"], "client-id");
+        assert_eq!(code_challenge(&form["code_verifier"]), query_param(&url, "code_challenge"));
+
+        let stored = get_token_from_db(&db, "spotify".to_string()).await.unwrap().unwrap();
+        assert_eq!(stored.access_token, "access-1");
+        assert_eq!(stored.refresh_token, "refresh-1");
+        assert!(stored.expires_at > chrono::Utc::now().naive_utc());
+    }
+
+    #[tokio::test]
+    async fn callback_state_can_only_be_used_once() {
+        let db = initialize_test_db().await;
+        let (token_url, received) = spawn_token_endpoint().await;
+        let url = start_authorization(&db, &token_url).await;
+        let callback = format!(
+            "/oauth2/spotify/callback?code=good-code&state={}",
+            query_param(&url, "state")
+        );
+
+        let first = request(&db, Method::GET, &callback, None, None).await;
+        let second = request(&db, Method::GET, &callback, None, None).await;
+
+        assert_eq!(first.status(), StatusCode::SEE_OTHER);
+        assert_eq!(second.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(received.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn callback_rejects_unknown_state() {
+        let db = initialize_test_db().await;
+        let (token_url, received) = spawn_token_endpoint().await;
+        start_authorization(&db, &token_url).await;
+
+        let response = request(
+            &db,
+            Method::GET,
+            "/oauth2/spotify/callback?code=good-code&state=forged",
+            None,
+            None,
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(received.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn callback_reports_denied_consent() {
+        let db = initialize_test_db().await;
+        let (token_url, received) = spawn_token_endpoint().await;
+        let url = start_authorization(&db, &token_url).await;
+
+        let response = request(
+            &db,
+            Method::GET,
+            &format!(
+                "/oauth2/spotify/callback?error=access_denied&state={}",
+                query_param(&url, "state")
+            ),
+            None,
+            None,
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert_eq!(
+            response.headers()[header::LOCATION],
+            "/?oauth2=spotify&status=denied"
+        );
+        assert!(received.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn callback_reports_rejected_code_as_upstream_error() {
+        let db = initialize_test_db().await;
+        let (token_url, _) = spawn_token_endpoint().await;
+        let url = start_authorization(&db, &token_url).await;
+
+        let response = request(
+            &db,
+            Method::GET,
+            &format!(
+                "/oauth2/spotify/callback?code=bad-code&state={}",
+                query_param(&url, "state")
+            ),
+            None,
+            None,
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        let stored = get_token_from_db(&db, "spotify".to_string()).await.unwrap().unwrap();
+        assert!(stored.access_token.is_empty());
+    }
+
+    #[tokio::test]
+    async fn refresh_uses_refresh_token_grant() {
+        let (token_url, received) = spawn_token_endpoint().await;
+
+        let refreshed = refresh_access_token(&providers::SPOTIFY, RefreshTokenPayload {
+            client_id: "client-id".to_string(),
+            client_secret: "client-secret".to_string(),
+            refresh_token: "refresh-1".to_string(),
+            get_token_url: token_url,
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(refreshed.access_token, "access-2");
+        let form = received.lock().unwrap()[0].clone();
+        assert_eq!(form["grant_type"], "refresh_token");
+        assert_eq!(form["refresh_token"], "refresh-1");
+        assert!(!form.contains_key("code"));
+    }
+
+    #[tokio::test]
+    async fn refresh_keeps_missing_refresh_token_optional() {
+        let (token_url, _) = spawn_token_endpoint().await;
+
+        let refreshed = refresh_access_token(&providers::SPOTIFY, RefreshTokenPayload {
+            client_id: "client-id".to_string(),
+            client_secret: "client-secret".to_string(),
+            refresh_token: "refresh-1".to_string(),
+            get_token_url: token_url,
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(refreshed.refresh_token, None);
+        assert_eq!(refreshed.expires_in, Some(3600));
+    }
+
+    #[tokio::test]
+    async fn refresh_without_refresh_token_fails_without_calling_provider() {
+        let (token_url, received) = spawn_token_endpoint().await;
+
+        let error = refresh_access_token(&providers::GITHUB, RefreshTokenPayload {
+            client_id: "client-id".to_string(),
+            client_secret: "client-secret".to_string(),
+            refresh_token: "".to_string(),
+            get_token_url: token_url,
         })
-    } else {
-        Err(anyhow::anyhow!("Failed to refresh access token"))
+        .await
+        .unwrap_err();
+
+        assert!(matches!(error, OAuth2Error::MissingRefreshToken));
+        assert!(received.lock().unwrap().is_empty());
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn github_token_endpoint_is_asked_for_json() {
+        let token_url = spawn_github_token_endpoint().await;
+
+        let tokens = exchange_code_for_tokens(&providers::GITHUB, github_exchange(&token_url, "good-code"))
+            .await
+            .unwrap();
+
+        assert_eq!(tokens.access_token, "gho_access");
+        assert_eq!(tokens.refresh_token, None);
+        assert_eq!(tokens.expires_in, None);
+        let now = chrono::Utc::now().naive_utc();
+        assert!(tokens.expires_at(now) > now + chrono::Duration::days(365));
+    }
+
+    #[tokio::test]
+    async fn github_error_with_ok_status_is_rejected() {
+        let token_url = spawn_github_token_endpoint().await;
+
+        let error = exchange_code_for_tokens(&providers::GITHUB, github_exchange(&token_url, "bad-code"))
+            .await
+            .unwrap_err();
+
+        match error {
+            OAuth2Error::Rejected { error, description } => {
+                assert_eq!(error, "bad_verification_code");
+                assert!(description.unwrap().contains("incorrect or expired"));
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn form_encoded_token_response_is_reported_not_panicked() {
+        let token_url = spawn_github_token_endpoint().await;
+
+        // The generic provider doesn't ask for JSON, so this endpoint answers form-encoded.
+        let error = exchange_code_for_tokens(&providers::GENERIC, github_exchange(&token_url, "good-code"))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, OAuth2Error::InvalidResponse { status: 200, .. }));
+    }
+
+    #[test]
+    fn token_response_parsing_is_tolerant() {
+        let tokens = parse_token_response(200, r#"{"access_token":"a","expires_in":"3600"}"#).unwrap();
+        assert_eq!(tokens.expires_in, Some(3600));
+        assert_eq!(tokens.refresh_token, None);
+
+        let tokens = parse_token_response(200, r#"{"access_token":"a","refresh_token":"","expires_in":null}"#).unwrap();
+        assert_eq!(tokens.refresh_token, None);
+        assert_eq!(tokens.expires_in, None);
+
+        assert!(matches!(
+            parse_token_response(200, r#"{"token_type":"bearer"}"#),
+            Err(OAuth2Error::InvalidResponse { .. })
+        ));
+        assert!(matches!(
+            parse_token_response(500, "<html>Bad gateway</html>"),
+            Err(OAuth2Error::InvalidResponse { status: 500, .. })
+        ));
+        assert!(matches!(
+            parse_token_response(400, r#"{"error":"invalid_grant"}"#),
+            Err(OAuth2Error::Rejected { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn google_consent_screen_asks_for_offline_access() {
+        let db = initialize_test_db().await;
+        let api_token = auth::create_test_token(&db, &[auth::SCOPE_OAUTH2]).await;
+
+        let response = request(
+            &db,
+            Method::PUT,
+            "/oauth2/google/client",
+            Some(&api_token),
+            Some(json!({
+                "clientId": "client-id",
+                "clientSecret": "client-secret",
+                "redirectUri": "http://localhost:2700/oauth2/google/callback",
+            })),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let url = authorize(&db, "google", &api_token).await;
+
+        assert_eq!(url.host_str(), Some("accounts.google.com"));
+        assert_eq!(query_param(&url, "access_type"), "offline");
+        assert_eq!(query_param(&url, "prompt"), "consent");
+        let stored = get_token_from_db(&db, "google".to_string()).await.unwrap().unwrap();
+        assert_eq!(stored.get_token_url, "https://oauth2.googleapis.com/token");
+    }
+
+    #[tokio::test]
+    async fn generic_provider_uses_configured_endpoints() {
+        let db = initialize_test_db().await;
+        let api_token = auth::create_test_token(&db, &[auth::SCOPE_OAUTH2]).await;
+        let client = json!({
+            "clientId": "client-id",
+            "clientSecret": "client-secret",
+            "redirectUri": "http://localhost:2700/oauth2/generic/callback",
+        });
+
+        let response = request(&db, Method::PUT, "/oauth2/generic/client", Some(&api_token), Some(client.clone())).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let mut configured = client;
+        configured["authorizeUrl"] = json!("https://auth.example.com/authorize");
+        configured["getTokenUrl"] = json!("https://auth.example.com/token");
+        configured["scopes"] = json!("profile");
+        let response = request(&db, Method::PUT, "/oauth2/generic/client", Some(&api_token), Some(configured)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let url = authorize(&db, "generic", &api_token).await;
+
+        assert_eq!(url.host_str(), Some("auth.example.com"));
+        assert_eq!(query_param(&url, "scope"), "profile");
+    }
+
+    #[tokio::test]
+    async fn unknown_provider_is_not_found() {
+        let db = initialize_test_db().await;
+        let api_token = auth::create_test_token(&db, &[auth::SCOPE_OAUTH2]).await;
+
+        let response = request(&db, Method::POST, "/oauth2/myspace/authorize", Some(&api_token), None).await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,94 @@
+/// An OAuth2 authorization server the dashboard knows how to talk to.
+///
+/// The `{provider}` path segment of the `/oauth2` routes is looked up here, so adding an
+/// integration only needs a new entry in `PROVIDERS`.
+#[derive(Debug)]
+pub struct Provider {
+    pub name: &'static str,
+    /// Empty for `generic`, whose endpoints come from the client configuration.
+    pub authorize_url: &'static str,
+    pub token_url: &'static str,
+    pub scopes: &'static str,
+    /// Extra query parameters for the consent screen.
+    pub authorize_params: &'static [(&'static str, &'static str)],
+    /// The token endpoint answers form-encoded bodies unless JSON is asked for explicitly.
+    pub requires_json_accept: bool,
+}
+
+pub const SPOTIFY: Provider = Provider {
+    name: "spotify",
+    authorize_url: "https://accounts.spotify.com/authorize",
+    token_url: "https://accounts.spotify.com/api/token",
+    scopes: "user-read-currently-playing user-read-playback-state user-modify-playback-state user-library-modify",
+    authorize_params: &[],
+    requires_json_accept: false,
+};
+
+pub const GOOGLE: Provider = Provider {
+    name: "google",
+    authorize_url: "https://accounts.google.com/o/oauth2/v2/auth",
+    token_url: "https://oauth2.googleapis.com/token",
+    scopes: "openid email",
+    // Google only issues a refresh token for offline access, and only on a fresh consent.
+    authorize_params: &[("access_type", "offline"), ("prompt", "consent")],
+    requires_json_accept: false,
+};
+
+pub const GITHUB: Provider = Provider {
+    name: "github",
+    authorize_url: "https://github.com/login/oauth/authorize",
+    token_url: "https://github.com/login/oauth/access_token",
+    scopes: "read:user",
+    authorize_params: &[],
+    requires_json_accept: true,
+};
+
+/// Any RFC 6749 server; the client configuration has to supply its URLs and scopes.
+pub const GENERIC: Provider = Provider {
+    name: "generic",
+    authorize_url: "",
+    token_url: "",
+    scopes: "",
+    authorize_params: &[],
+    requires_json_accept: false,
+};
+
+pub const PROVIDERS: &[Provider] = &[SPOTIFY, GOOGLE, GITHUB, GENERIC];
+
+pub fn get(name: &str) -> Option<&'static Provider> {
+    PROVIDERS.iter().find(|provider| provider.name == name)
+}
+
+impl Provider {
+    /// Picks the configured value, falling back to the provider's default. `None` means neither
+    /// is set, which only happens for `generic`.
+    pub fn resolve<'a>(configured: &'a str, default: &'a str) -> Option<&'a str> {
+        [configured, default].into_iter().find(|value| !value.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get, Provider, PROVIDERS};
+
+    #[test]
+    fn providers_are_looked_up_by_name() {
+        assert_eq!(get("spotify").unwrap().token_url, "https://accounts.spotify.com/api/token");
+        assert!(get("github").unwrap().requires_json_accept);
+        assert!(get("myspace").is_none());
+    }
+
+    #[test]
+    fn provider_names_are_unique() {
+        for (i, provider) in PROVIDERS.iter().enumerate() {
+            assert!(PROVIDERS[i + 1..].iter().all(|other| other.name != provider.name));
+        }
+    }
+
+    #[test]
+    fn resolve_prefers_configured_value() {
+        assert_eq!(Provider::resolve("https://custom", "https://default"), Some("https://custom"));
+        assert_eq!(Provider::resolve("", "https://default"), Some("https://default"));
+        assert_eq!(Provider::resolve("", ""), None);
+    }
+}
@@ -0,0 +1,425 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use rand::Rng;
+use sqlx::SqlitePool;
+use tokio::{
+    sync::Mutex as AsyncMutex,
+    time::{interval, Instant},
+};
+
+use super::{providers, refresh_access_token, RefreshTokenPayload};
+use crate::db::{
+    get_oauth2_tokens, get_token_from_db, record_oauth2_refresh_failure,
+    update_oauth2_access_and_refresh_tokens, OAuth2Token,
+};
+
+/// Tokens are refreshed once they expire within this window.
+const REFRESH_MARGIN_SECS: i64 = 120;
+const SWEEP_INTERVAL_SECS: u64 = 30;
+/// Consecutive failed refreshes after which a link is marked broken.
+const MAX_REFRESH_FAILURES: i64 = 5;
+const BACKOFF_BASE_SECS: u64 = 15;
+const BACKOFF_MAX_SECS: u64 = 15 * 60;
+
+/// Keeps every `oauth2_tokens` row fresh and hands out valid access tokens.
+///
+/// Refreshes are serialized per app: callers that arrive while a refresh is running wait for it
+/// and reuse its result instead of spending the refresh token a second time.
+#[derive(Clone)]
+pub struct TokenRefresher {
+    db: SqlitePool,
+    state: Arc<Mutex<RefresherState>>,
+}
+
+#[derive(Default)]
+struct RefresherState {
+    locks: HashMap<String, Arc<AsyncMutex<()>>>,
+    /// Earliest time the next attempt may run after a failed refresh.
+    retry_at: HashMap<String, Instant>,
+}
+
+impl TokenRefresher {
+    pub fn new(db: SqlitePool) -> Self {
+        Self {
+            db,
+            state: Arc::default(),
+        }
+    }
+
+    /// Returns a usable access token for `app_name`, refreshing it first if it is about to expire.
+    pub async fn access_token(&self, app_name: &str) -> anyhow::Result<String> {
+        let token = self.load(app_name).await?;
+        if !needs_refresh(&token) {
+            return Ok(token.access_token);
+        }
+
+        self.refresh(app_name, &token.access_token).await
+    }
+
+    /// Refreshes after the provider rejected `rejected_token`. If another caller has already
+    /// replaced it, the new token is returned without contacting the provider.
+    pub async fn refresh(&self, app_name: &str, rejected_token: &str) -> anyhow::Result<String> {
+        let lock = self.lock_for(app_name);
+        let _guard = lock.lock().await;
+
+        let token = self.load(app_name).await?;
+        let replaced = token.access_token != rejected_token;
+        if replaced && !needs_refresh(&token) {
+            return Ok(token.access_token);
+        }
+
+        if self.backing_off(app_name) {
+            if replaced && token.expires_at > chrono::Utc::now().naive_utc() {
+                return Ok(token.access_token);
+            }
+            return Err(anyhow::anyhow!(
+                "Refreshing {} is backing off after a failure: {}",
+                app_name,
+                token.last_refresh_error.unwrap_or_default()
+            ));
+        }
+
+        self.try_refresh(&token).await
+    }
+
+    /// Refreshes every connected token that expires soon.
+    pub async fn refresh_due(&self) {
+        let tokens = match get_oauth2_tokens(&self.db).await {
+            Ok(tokens) => tokens,
+            Err(e) => {
+                println!("Failed to load OAuth2 tokens: {}", e);
+                return;
+            }
+        };
+
+        for token in tokens {
+            if token.broken
+                || token.access_token.is_empty()
+                || !needs_refresh(&token)
+                || self.backing_off(&token.app_name)
+            {
+                continue;
+            }
+
+            // Failures are logged and recorded by `try_refresh`.
+            let _ = self.refresh(&token.app_name, &token.access_token).await;
+        }
+    }
+
+    pub async fn run(self) -> anyhow::Result<()> {
+        let mut interval = interval(Duration::from_secs(SWEEP_INTERVAL_SECS));
+
+        loop {
+            interval.tick().await;
+            self.refresh_due().await;
+        }
+    }
+
+    fn lock_for(&self, app_name: &str) -> Arc<AsyncMutex<()>> {
+        let mut state = self.state.lock().unwrap();
+        state.locks.entry(app_name.to_string()).or_default().clone()
+    }
+
+    fn backing_off(&self, app_name: &str) -> bool {
+        let state = self.state.lock().unwrap();
+        state
+            .retry_at
+            .get(app_name)
+            .is_some_and(|retry_at| Instant::now() < *retry_at)
+    }
+
+    /// The stored token of a connected link that isn't broken, as it is; never refreshes it.
+    pub async fn load(&self, app_name: &str) -> anyhow::Result<OAuth2Token> {
+        let token = get_token_from_db(&self.db, app_name.to_string())
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("{} has no OAuth2 client configured", app_name))?;
+
+        if token.broken {
+            return Err(anyhow::anyhow!(
+                "{} needs to be authorized again: {}",
+                app_name,
+                token.last_refresh_error.unwrap_or_default()
+            ));
+        }
+
+        if token.access_token.is_empty() {
+            return Err(anyhow::anyhow!(
+                "{} is not connected yet; authorize it via /oauth2/{}/authorize",
+                app_name,
+                app_name
+            ));
+        }
+
+        Ok(token)
+    }
+
+    /// Runs one refresh against the provider and records the outcome. Callers hold the app's lock.
+    async fn try_refresh(&self, token: &OAuth2Token) -> anyhow::Result<String> {
+        let app_name = token.app_name.as_str();
+        let provider = providers::get(app_name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown OAuth2 provider {}", app_name))?;
+
+        let result = refresh_access_token(
+            provider,
+            RefreshTokenPayload {
+                client_id: token.client_id.clone(),
+                client_secret: token.client_secret.clone(),
+                refresh_token: token.refresh_token.clone(),
+                get_token_url: token.get_token_url.clone(),
+            },
+        )
+        .await;
+
+        match result {
+            Ok(refreshed) => {
+                let expires_at = refreshed.expires_at(chrono::Utc::now().naive_utc());
+                update_oauth2_access_and_refresh_tokens(
+                    &self.db,
+                    app_name.to_string(),
+                    refreshed.access_token.clone(),
+                    refreshed.refresh_token.unwrap_or_else(|| token.refresh_token.clone()),
+                    expires_at,
+                )
+                .await?;
+
+                self.state.lock().unwrap().retry_at.remove(app_name);
+                println!("Refreshed {} access token", app_name);
+                Ok(refreshed.access_token)
+            }
+            Err(e) => {
+                let broken = record_oauth2_refresh_failure(
+                    &self.db,
+                    app_name,
+                    &e.to_string(),
+                    e.is_permanent(),
+                    MAX_REFRESH_FAILURES,
+                )
+                .await?;
+
+                if broken {
+                    println!("Giving up on refreshing {}; authorize it again: {}", app_name, e);
+                } else {
+                    let delay = backoff(token.refresh_failures + 1);
+                    self.state
+                        .lock()
+                        .unwrap()
+                        .retry_at
+                        .insert(app_name.to_string(), Instant::now() + delay);
+                    println!(
+                        "Failed to refresh {}, retrying in {}s: {}",
+                        app_name,
+                        delay.as_secs(),
+                        e
+                    );
+                }
+
+                Err(e.into())
+            }
+        }
+    }
+}
+
+fn needs_refresh(token: &OAuth2Token) -> bool {
+    token.expires_at - chrono::Utc::now().naive_utc() <= chrono::Duration::seconds(REFRESH_MARGIN_SECS)
+}
+
+/// Exponential backoff with ±25% jitter, so links that fail together don't retry in lockstep.
+fn backoff(failures: i64) -> Duration {
+    let exponent = (failures - 1).clamp(0, 16) as u32;
+    let seconds = BACKOFF_BASE_SECS
+        .saturating_mul(1 << exponent)
+        .min(BACKOFF_MAX_SECS) as f64;
+
+    Duration::from_secs_f64(seconds * rand::thread_rng().gen_range(0.75..=1.25))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use axum::{
+        extract::{Form, State},
+        http::StatusCode,
+        response::{IntoResponse, Response},
+        routing::post,
+        Json, Router,
+    };
+    use serde_json::json;
+    use sqlx::SqlitePool;
+
+    use super::{backoff, TokenRefresher, MAX_REFRESH_FAILURES};
+    use crate::{
+        db::{get_token_from_db, initialize_test_db},
+        test_utils::{insert_oauth2_token, spawn_mock_server},
+    };
+
+    #[derive(Clone)]
+    struct TokenEndpoint {
+        calls: Arc<AtomicUsize>,
+        status: StatusCode,
+        body: serde_json::Value,
+    }
+
+    /// Token endpoint that answers every request with `status` and `body`, slowly enough for
+    /// concurrent callers to overlap.
+    async fn spawn_token_endpoint(status: StatusCode, body: serde_json::Value) -> (String, Arc<AtomicUsize>) {
+        async fn token(
+            State(endpoint): State<TokenEndpoint>,
+            Form(_): Form<HashMap<String, String>>,
+        ) -> Response {
+            endpoint.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            (endpoint.status, Json(endpoint.body)).into_response()
+        }
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let router = Router::new().route("/token", post(token)).with_state(TokenEndpoint {
+            calls: calls.clone(),
+            status,
+            body,
+        });
+        let base_url = spawn_mock_server(router).await;
+
+        (format!("{}/token", base_url), calls)
+    }
+
+    async fn insert_token(db: &SqlitePool, token_url: &str, expires_in: chrono::Duration) {
+        insert_oauth2_token(db, "spotify", token_url, expires_in).await;
+    }
+
+    fn refreshed_body() -> serde_json::Value {
+        json!({ "access_token": "access-new", "token_type": "Bearer", "expires_in": 3600 })
+    }
+
+    #[tokio::test]
+    async fn valid_token_is_returned_without_refreshing() {
+        let db = initialize_test_db().await;
+        let (token_url, calls) = spawn_token_endpoint(StatusCode::OK, refreshed_body()).await;
+        insert_token(&db, &token_url, chrono::Duration::hours(1)).await;
+
+        let token = TokenRefresher::new(db).access_token("spotify").await.unwrap();
+
+        assert_eq!(token, "access-old");
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn concurrent_callers_share_one_refresh() {
+        let db = initialize_test_db().await;
+        let (token_url, calls) = spawn_token_endpoint(StatusCode::OK, refreshed_body()).await;
+        insert_token(&db, &token_url, chrono::Duration::seconds(30)).await;
+        let refresher = TokenRefresher::new(db.clone());
+
+        let tokens = access_tokens_concurrently(&refresher, 5).await;
+
+        assert!(tokens.iter().all(|token| token == "access-new"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        let stored = get_token_from_db(&db, "spotify".to_string()).await.unwrap().unwrap();
+        assert_eq!(stored.refresh_token, "refresh-old");
+        assert!(stored.expires_at > chrono::Utc::now().naive_utc() + chrono::Duration::minutes(30));
+    }
+
+    async fn access_tokens_concurrently(refresher: &TokenRefresher, count: usize) -> Vec<String> {
+        let handles: Vec<_> = (0..count)
+            .map(|_| {
+                let refresher = refresher.clone();
+                tokio::spawn(async move { refresher.access_token("spotify").await.unwrap() })
+            })
+            .collect();
+
+        let mut tokens = Vec::new();
+        for handle in handles {
+            tokens.push(handle.await.unwrap());
+        }
+        tokens
+    }
+
+    #[tokio::test]
+    async fn rejected_token_is_only_refreshed_once() {
+        let db = initialize_test_db().await;
+        let (token_url, calls) = spawn_token_endpoint(StatusCode::OK, refreshed_body()).await;
+        insert_token(&db, &token_url, chrono::Duration::hours(1)).await;
+        let refresher = TokenRefresher::new(db);
+
+        let first = refresher.refresh("spotify", "access-old").await.unwrap();
+        let second = refresher.refresh("spotify", "access-old").await.unwrap();
+
+        assert_eq!(first, "access-new");
+        assert_eq!(second, "access-new");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn sweep_refreshes_tokens_about_to_expire() {
+        let db = initialize_test_db().await;
+        let (token_url, calls) = spawn_token_endpoint(StatusCode::OK, refreshed_body()).await;
+        insert_token(&db, &token_url, chrono::Duration::seconds(60)).await;
+
+        TokenRefresher::new(db.clone()).refresh_due().await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        let stored = get_token_from_db(&db, "spotify".to_string()).await.unwrap().unwrap();
+        assert_eq!(stored.access_token, "access-new");
+    }
+
+    #[tokio::test]
+    async fn failures_back_off_and_eventually_mark_link_broken() {
+        let db = initialize_test_db().await;
+        // Not a 503, which the HTTP layer already retries on its own.
+        let (token_url, calls) =
+            spawn_token_endpoint(StatusCode::INTERNAL_SERVER_ERROR, json!({ "message": "down" })).await;
+        insert_token(&db, &token_url, chrono::Duration::seconds(-10)).await;
+        let refresher = TokenRefresher::new(db.clone());
+
+        assert!(refresher.access_token("spotify").await.is_err());
+        // The second call lands inside the backoff window and doesn't reach the provider.
+        assert!(refresher.access_token("spotify").await.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        for _ in 1..MAX_REFRESH_FAILURES {
+            let token = get_token_from_db(&db, "spotify".to_string()).await.unwrap().unwrap();
+            let _ = refresher.try_refresh(&token).await;
+        }
+
+        let stored = get_token_from_db(&db, "spotify".to_string()).await.unwrap().unwrap();
+        assert!(stored.broken);
+        assert_eq!(stored.refresh_failures, MAX_REFRESH_FAILURES);
+        assert!(stored.last_refresh_error.unwrap().contains("500"));
+        let error = refresher.access_token("spotify").await.unwrap_err();
+        assert!(error.to_string().contains("authorized again"));
+    }
+
+    #[tokio::test]
+    async fn revoked_grant_marks_link_broken_immediately() {
+        let db = initialize_test_db().await;
+        let (token_url, _) =
+            spawn_token_endpoint(StatusCode::BAD_REQUEST, json!({ "error": "invalid_grant" })).await;
+        insert_token(&db, &token_url, chrono::Duration::seconds(-10)).await;
+
+        assert!(TokenRefresher::new(db.clone()).access_token("spotify").await.is_err());
+
+        let stored = get_token_from_db(&db, "spotify".to_string()).await.unwrap().unwrap();
+        assert!(stored.broken);
+        assert_eq!(stored.refresh_failures, 1);
+    }
+
+    #[test]
+    fn backoff_grows_with_jitter_and_is_capped() {
+        for failures in 1..=20 {
+            let delay = backoff(failures).as_secs_f64();
+            let expected = (15.0 * 2f64.powi(failures as i32 - 1)).min(900.0);
+            assert!(delay >= expected * 0.75 && delay <= expected * 1.25, "{} -> {}", failures, delay);
+        }
+    }
+}
@@ -0,0 +1,400 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, Rgb};
+use screen_core::{MAX_CHUNK_SIZE, MAX_IMAGE_SIZE};
+use serde::{Deserialize, Serialize};
+
+use crate::{http, tcp::StateMessage};
+
+const DEFAULT_SIZE: u32 = 120;
+const DEFAULT_JPEG_QUALITY: u8 = 75;
+/// The screen is 320x240; anything taller would not fit next to the track name.
+const MAX_SIZE: u32 = 240;
+/// 128x128 RGB565 pixels are exactly `MAX_IMAGE_SIZE`.
+const MAX_RGB565_SIZE: u32 = 128;
+const CACHE_CAPACITY: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtFormat {
+    /// Baseline JPEG; JPEGDecoder on the screen can't decode progressive images.
+    Jpeg,
+    /// Raw little-endian RGB565 pixels for `drawRGBBitmap`. No decoding, but ten times larger,
+    /// so only small sizes fit in the ESP8266's memory.
+    Rgb565,
+}
+
+impl ArtFormat {
+    /// Largest size whose covers still fit the screen's memory.
+    fn max_size(self) -> u32 {
+        match self {
+            Self::Jpeg => MAX_SIZE,
+            Self::Rgb565 => MAX_RGB565_SIZE,
+        }
+    }
+}
+
+impl FromStr for ArtFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> anyhow::Result<Self> {
+        match format.trim().to_lowercase().as_str() {
+            "jpeg" | "jpg" => Ok(Self::Jpeg),
+            "rgb565" => Ok(Self::Rgb565),
+            other => Err(anyhow::anyhow!("Unknown album art format {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArtConfig {
+    /// Longest side in pixels; covers are square, so usually both.
+    pub size: u32,
+    pub format: ArtFormat,
+    pub jpeg_quality: u8,
+}
+
+impl Default for ArtConfig {
+    fn default() -> Self {
+        Self {
+            size: DEFAULT_SIZE,
+            format: ArtFormat::Jpeg,
+            jpeg_quality: DEFAULT_JPEG_QUALITY,
+        }
+    }
+}
+
+impl ArtConfig {
+    /// Reads `ALBUM_ART_SIZE`, `ALBUM_ART_FORMAT` and `ALBUM_ART_JPEG_QUALITY`, keeping the
+    /// default for anything unset or invalid.
+    pub fn from_env() -> Self {
+        fn var<T: FromStr>(name: &str) -> Option<T> {
+            let value = std::env::var(name).ok()?;
+            let parsed = value.parse().ok();
+            if parsed.is_none() {
+                println!("Ignoring invalid {}={}", name, value);
+            }
+            parsed
+        }
+
+        let default = Self::default();
+        let format = var("ALBUM_ART_FORMAT").unwrap_or(default.format);
+        Self {
+            size: var("ALBUM_ART_SIZE").unwrap_or(default.size).clamp(16, format.max_size()),
+            format,
+            jpeg_quality: var("ALBUM_ART_JPEG_QUALITY")
+                .unwrap_or(default.jpeg_quality)
+                .clamp(1, 100),
+        }
+    }
+}
+
+/// One of the sizes Spotify offers a cover in (typically 640, 300 and 64 pixels).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AlbumImage {
+    pub url: String,
+    /// Null for some user-uploaded playlist covers.
+    pub width: Option<u32>,
+}
+
+/// The smallest image that is still at least `size` wide, so that it is only ever scaled down;
+/// the largest one when none is.
+pub fn pick_image(images: &[AlbumImage], size: u32) -> Option<&AlbumImage> {
+    let width = |image: &AlbumImage| image.width.unwrap_or(0);
+
+    images
+        .iter()
+        .filter(|image| width(image) >= size)
+        .min_by_key(|image| width(image))
+        .or_else(|| images.iter().max_by_key(|image| width(image)))
+}
+
+/// A cover resized and encoded for the screen.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AlbumArt {
+    pub album_id: String,
+    pub format: ArtFormat,
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+/// Describes the whole transfer; repeated in every chunk.
+#[derive(Serialize)]
+struct ArtHeader<'a> {
+    image: &'static str,
+    #[serde(rename = "albumId")]
+    album_id: &'a str,
+    format: ArtFormat,
+    width: u32,
+    height: u32,
+    size: usize,
+}
+
+impl AlbumArt {
+    pub fn encode(album_id: &str, image: &[u8], config: &ArtConfig) -> anyhow::Result<Self> {
+        let image = image::load_from_memory(image)?
+            .resize(config.size, config.size, FilterType::Triangle)
+            .to_rgb8();
+
+        let data = match config.format {
+            ArtFormat::Jpeg => {
+                let mut data = Vec::new();
+                JpegEncoder::new_with_quality(&mut data, config.jpeg_quality).encode_image(&image)?;
+                data
+            }
+            ArtFormat::Rgb565 => image
+                .pixels()
+                .flat_map(|&Rgb([r, g, b])| rgb565(r, g, b).to_le_bytes())
+                .collect(),
+        };
+
+        Ok(Self {
+            album_id: album_id.to_string(),
+            format: config.format,
+            width: image.width(),
+            height: image.height(),
+            data,
+        })
+    }
+
+    /// Splits the art into messages for `app`, small enough for the screen's read buffer. Art the
+    /// screen would throw away for its size is refused instead.
+    pub fn chunks(&self, app: &'static str) -> anyhow::Result<Vec<StateMessage>> {
+        if self.data.len() > MAX_IMAGE_SIZE {
+            return Err(anyhow::anyhow!(
+                "Album art of {} bytes is larger than the screen's {} bytes",
+                self.data.len(),
+                MAX_IMAGE_SIZE
+            ));
+        }
+
+        let header = serde_json::to_string(&ArtHeader {
+            image: "album_art",
+            album_id: &self.album_id,
+            format: self.format,
+            width: self.width,
+            height: self.height,
+            size: self.data.len(),
+        })?;
+
+        let count = self.data.len().div_ceil(MAX_CHUNK_SIZE) as u16;
+
+        Ok(self
+            .data
+            .chunks(MAX_CHUNK_SIZE)
+            .enumerate()
+            .map(|(index, data)| StateMessage::Chunk {
+                app,
+                header: header.clone(),
+                data: data.to_vec(),
+                index: index as u16,
+                count,
+            })
+            .collect())
+    }
+}
+
+fn rgb565(r: u8, g: u8, b: u8) -> u16 {
+    ((r as u16 & 0xF8) << 8) | ((g as u16 & 0xFC) << 3) | (b as u16 >> 3)
+}
+
+#[derive(Default)]
+struct Cache {
+    art: HashMap<String, Arc<AlbumArt>>,
+    /// Album ids, oldest first.
+    order: VecDeque<String>,
+}
+
+/// Encoded covers by album id, so going back and forth between tracks of an album doesn't
+/// download and re-encode its cover every time.
+#[derive(Clone)]
+pub struct AlbumArtCache {
+    config: ArtConfig,
+    cache: Arc<Mutex<Cache>>,
+}
+
+impl AlbumArtCache {
+    pub fn new(config: ArtConfig) -> Self {
+        Self {
+            config,
+            cache: Arc::default(),
+        }
+    }
+
+    pub fn config(&self) -> &ArtConfig {
+        &self.config
+    }
+
+    pub async fn get(&self, album_id: &str, image_url: &str) -> anyhow::Result<Arc<AlbumArt>> {
+        if let Some(art) = self.cache.lock().unwrap().art.get(album_id) {
+            return Ok(art.clone());
+        }
+
+        let http = http::shared();
+        let image = http
+            .send(http.get(image_url))
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+
+        let config = self.config;
+        let id = album_id.to_string();
+        let art = tokio::task::spawn_blocking(move || AlbumArt::encode(&id, &image, &config)).await??;
+        let art = Arc::new(art);
+
+        let mut cache = self.cache.lock().unwrap();
+        if cache.art.insert(album_id.to_string(), art.clone()).is_none() {
+            cache.order.push_back(album_id.to_string());
+        }
+        while cache.order.len() > CACHE_CAPACITY {
+            if let Some(oldest) = cache.order.pop_front() {
+                cache.art.remove(&oldest);
+            }
+        }
+
+        Ok(art)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Cursor,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use axum::{extract::State, http::header, response::IntoResponse, routing::get, Router};
+    use image::{ImageFormat, RgbImage};
+    use screen_core::{MAX_CHUNK_SIZE, MAX_IMAGE_SIZE};
+
+    use super::{pick_image, AlbumArt, AlbumArtCache, AlbumImage, ArtConfig, ArtFormat};
+    use crate::{tcp::StateMessage, test_utils::spawn_mock_server};
+
+    /// A 300x300 PNG cover, red on the left and blue on the right.
+    fn cover_png() -> Vec<u8> {
+        let image = RgbImage::from_fn(300, 300, |x, _| if x < 150 { [255, 0, 0].into() } else { [0, 0, 255].into() });
+        let mut png = Vec::new();
+        image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png).unwrap();
+        png
+    }
+
+    fn image(url: &str, width: Option<u32>) -> AlbumImage {
+        AlbumImage { url: url.to_string(), width }
+    }
+
+    #[test]
+    fn smallest_image_that_needs_no_upscaling_is_picked() {
+        let images = [image("640", Some(640)), image("300", Some(300)), image("64", Some(64))];
+
+        assert_eq!(pick_image(&images, 120).unwrap().url, "300");
+        assert_eq!(pick_image(&images, 320).unwrap().url, "640");
+        assert_eq!(pick_image(&images[2..], 120).unwrap().url, "64");
+        assert_eq!(pick_image(&[image("unknown", None)], 120).unwrap().url, "unknown");
+        assert!(pick_image(&[], 120).is_none());
+    }
+
+    #[test]
+    fn cover_is_resized_to_baseline_jpeg() {
+        let art = AlbumArt::encode("evolve", &cover_png(), &ArtConfig::default()).unwrap();
+
+        assert_eq!((art.width, art.height), (120, 120));
+        // SOI followed by a baseline (SOF0) frame, never a progressive one (SOF2).
+        assert_eq!(&art.data[..2], &[0xFF, 0xD8]);
+        assert!(art.data.windows(2).any(|marker| marker == [0xFF, 0xC0]));
+        assert!(!art.data.windows(2).any(|marker| marker == [0xFF, 0xC2]));
+
+        let decoded = image::load_from_memory(&art.data).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (120, 120));
+    }
+
+    #[test]
+    fn rgb565_is_little_endian_pixels() {
+        let config = ArtConfig { size: 32, format: ArtFormat::Rgb565, ..ArtConfig::default() };
+
+        let art = AlbumArt::encode("evolve", &cover_png(), &config).unwrap();
+
+        assert_eq!(art.data.len(), 32 * 32 * 2);
+        assert_eq!(&art.data[..2], &0xF800u16.to_le_bytes());
+        assert_eq!(&art.data[62..64], &0x001Fu16.to_le_bytes());
+    }
+
+    #[test]
+    fn art_larger_than_the_screen_accepts_is_refused() {
+        let config = ArtConfig { size: ArtFormat::Rgb565.max_size(), format: ArtFormat::Rgb565, ..ArtConfig::default() };
+        let art = AlbumArt::encode("evolve", &cover_png(), &config).unwrap();
+        assert_eq!(art.data.len(), MAX_IMAGE_SIZE);
+        assert!(art.chunks("Spotify").is_ok());
+
+        let art = AlbumArt { data: vec![0; MAX_IMAGE_SIZE + 1], ..art };
+        let Err(error) = art.chunks("Spotify") else {
+            panic!("oversized art was split into chunks");
+        };
+        assert_eq!(error.to_string(), "Album art of 32769 bytes is larger than the screen's 32768 bytes");
+    }
+
+    #[test]
+    fn art_is_split_into_chunks_with_a_header() {
+        let art = AlbumArt {
+            album_id: "evolve".to_string(),
+            format: ArtFormat::Jpeg,
+            width: 120,
+            height: 120,
+            data: (0..2000).map(|i| i as u8).collect(),
+        };
+
+        let chunks = art.chunks("Spotify").unwrap();
+
+        assert_eq!(chunks.len(), 3);
+        let mut reassembled = Vec::new();
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            let StateMessage::Chunk { app, header, data, index, count } = chunk else {
+                panic!("not an album art chunk");
+            };
+            assert_eq!(app, "Spotify");
+            let header: serde_json::Value = serde_json::from_str(&header).unwrap();
+            assert_eq!(header["albumId"], "evolve");
+            assert_eq!(header["format"], "jpeg");
+            assert_eq!(header["size"], 2000);
+            assert_eq!((index as usize, count), (i, 3));
+            assert!(data.len() <= MAX_CHUNK_SIZE);
+            reassembled.extend(data);
+        }
+        assert_eq!(reassembled, art.data);
+    }
+
+    #[tokio::test]
+    async fn covers_are_downloaded_once_per_album() {
+        async fn cover(State(downloads): State<Arc<AtomicUsize>>) -> impl IntoResponse {
+            downloads.fetch_add(1, Ordering::SeqCst);
+            ([(header::CONTENT_TYPE, "image/png")], cover_png())
+        }
+
+        let downloads = Arc::new(AtomicUsize::new(0));
+        let router = Router::new().route("/cover", get(cover)).with_state(downloads.clone());
+        let url = format!("{}/cover", spawn_mock_server(router).await);
+        let cache = AlbumArtCache::new(ArtConfig::default());
+
+        let first = cache.get("evolve", &url).await.unwrap();
+        let second = cache.get("evolve", &url).await.unwrap();
+        cache.get("origins", &url).await.unwrap();
+
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(downloads.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn failed_download_is_an_error() {
+        let url = format!("{}/missing", spawn_mock_server(Router::new()).await);
+
+        assert!(AlbumArtCache::new(ArtConfig::default()).get("evolve", &url).await.is_err());
+    }
+}
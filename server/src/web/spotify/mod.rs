@@ -1,127 +1,838 @@
-use std::time::Duration;
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
-use reqwest::Client;
-use sqlx::SqlitePool;
-use tokio::{sync::mpsc, time::interval};
+use async_trait::async_trait;
+use axum::extract::{Path, State};
+use reqwest::{Method, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
 
-use crate::{db::{get_token_from_db, update_oauth2_access_and_refresh_tokens}, tcp::StateMessage, web::oauth2::exchange_code_for_tokens};
+use crate::{
+    http,
+    sources::{ConfigField, DataSource, FieldKind, Health, SourceMode},
+    tcp::StateMessage,
+};
 
-use super::oauth2::{refresh_access_token, ExchangeCodePayload, RefreshTokenPayload};
+use super::{
+    error::{ApiError, ApiJson, ApiResult},
+    oauth2::refresher::TokenRefresher,
+};
+use album_art::{pick_image, AlbumArtCache, AlbumImage, ArtConfig};
 
-pub async fn fetch_current_playing_track(db: &SqlitePool) -> anyhow::Result<Option<String>> {
-    let client = Client::new();
-    let url = "https://api.spotify.com/v1/me/player/currently-playing";
+pub mod album_art;
 
-    let oauth2_token = match get_token_from_db(db, "spotify".to_string()).await? {
-        Some(token) => token,
-        None => return Ok(None),
-    };
+const API_URL: &str = "https://api.spotify.com/v1";
+const APP: &str = "Spotify";
+const POLL_INTERVAL_SECS: u64 = 10;
+/// The screen interpolates progress itself, so a new update is only needed when its estimate
+/// would be off by more than this (e.g. after a seek).
+const MAX_PROGRESS_DRIFT_MS: u64 = 3000;
+const VOLUME_STEP: i16 = 10;
 
-    let access_token = oauth2_token.access_token.clone();
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RepeatMode {
+    Off,
+    Track,
+    Context,
+}
 
-    let response = client
-        .get(url)
-        .header("Authorization", format!("Bearer {}", access_token))
-        .send()
-        .await?;
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NowPlaying {
+    pub track: String,
+    pub artists: Vec<String>,
+    pub album: String,
+    #[serde(rename = "durationMs")]
+    pub duration_ms: u64,
+    #[serde(rename = "progressMs")]
+    pub progress_ms: u64,
+    #[serde(rename = "isPlaying")]
+    pub is_playing: bool,
+    pub shuffle: bool,
+    pub repeat: RepeatMode,
+    pub device: Option<String>,
+    pub volume: Option<u8>,
+    /// Matches the album art chunks sent after the track.
+    #[serde(rename = "albumId")]
+    pub album_id: Option<String>,
+    /// Used to like the track; the screen doesn't need it.
+    #[serde(skip)]
+    pub track_id: Option<String>,
+    /// The screen gets the resized cover instead.
+    #[serde(skip)]
+    pub album_images: Vec<AlbumImage>,
+}
 
-    let mut track_name: Option<String> = None;
-    let mut artist_name: Option<String> = None;
+/// What the screen shows in the Spotify app, sent to it as JSON.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum PlaybackState {
+    /// No active device, or nothing is loaded on it.
+    NothingPlaying,
+    Playing(NowPlaying),
+    Paused(NowPlaying),
+}
 
-    if response.status().is_success() {
-        println!("Successfully fetched currently playing track");
-        let json: serde_json::Value = response.json().await?;
-        if let Some(item) = json.get("item") {
-            if let Some(track) = item.get("name") {
-                track_name = Some(track.to_string());
-            }
-            if let Some(artists) = item.get("artists") {
-                if let Some(artist) = artists.get(0) {
-                    if let Some(artist) = artist.get("name") {
-                        artist_name = Some(artist.to_string());
-                    }
+/// Response of `GET /v1/me/player`.
+#[derive(Debug, Deserialize)]
+struct PlayerResponse {
+    device: Option<Device>,
+    #[serde(default)]
+    shuffle_state: bool,
+    repeat_state: Option<RepeatMode>,
+    progress_ms: Option<u64>,
+    #[serde(default)]
+    is_playing: bool,
+    /// Null for ads and in private sessions.
+    item: Option<Item>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Device {
+    name: String,
+    volume_percent: Option<u8>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Item {
+    /// Null for local files.
+    id: Option<String>,
+    name: String,
+    duration_ms: u64,
+    /// Episodes have no artists or album.
+    #[serde(default)]
+    artists: Vec<Artist>,
+    album: Option<Album>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Artist {
+    name: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Album {
+    /// Null for local files.
+    id: Option<String>,
+    name: String,
+    #[serde(default)]
+    images: Vec<AlbumImage>,
+}
+
+impl PlaybackState {
+    fn from_response(response: PlayerResponse) -> Self {
+        let Some(item) = response.item else {
+            return Self::NothingPlaying;
+        };
+
+        let album = item.album.unwrap_or_default();
+
+        let now_playing = NowPlaying {
+            track: item.name,
+            artists: item.artists.into_iter().map(|artist| artist.name).collect(),
+            album: album.name,
+            duration_ms: item.duration_ms,
+            progress_ms: response.progress_ms.unwrap_or(0).min(item.duration_ms),
+            is_playing: response.is_playing,
+            shuffle: response.shuffle_state,
+            repeat: response.repeat_state.unwrap_or(RepeatMode::Off),
+            volume: response.device.as_ref().and_then(|device| device.volume_percent),
+            device: response.device.map(|device| device.name),
+            album_id: album.id,
+            track_id: item.id,
+            album_images: album.images,
+        };
+
+        if now_playing.is_playing {
+            Self::Playing(now_playing)
+        } else {
+            Self::Paused(now_playing)
+        }
+    }
+
+    fn now_playing(&self) -> Option<&NowPlaying> {
+        match self {
+            Self::NothingPlaying => None,
+            Self::Playing(now_playing) | Self::Paused(now_playing) => Some(now_playing),
+        }
+    }
+
+    /// Whether the screen, having received `self` `elapsed` ago, would now show something other
+    /// than `current`.
+    fn differs_from(&self, elapsed: Duration, current: &PlaybackState) -> bool {
+        match (self.now_playing(), current.now_playing()) {
+            (Some(previous), Some(current)) => {
+                let expected = if previous.is_playing {
+                    previous.progress_ms + elapsed.as_millis() as u64
+                } else {
+                    previous.progress_ms
                 }
+                .min(previous.duration_ms);
+
+                let same_except_progress = NowPlaying {
+                    progress_ms: current.progress_ms,
+                    ..previous.clone()
+                } == *current;
+
+                !same_except_progress || expected.abs_diff(current.progress_ms) > MAX_PROGRESS_DRIFT_MS
+            }
+            (None, None) => false,
+            _ => true,
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        match self {
+            Self::NothingPlaying => "Nothing playing".to_string(),
+            Self::Playing(now_playing) => {
+                format!("{} - {}", now_playing.artists.join(", "), now_playing.track)
+            }
+            Self::Paused(now_playing) => format!(
+                "Paused: {} - {}",
+                now_playing.artists.join(", "),
+                now_playing.track
+            ),
+        }
+    }
+}
+
+/// A playback command from the dashboard or a button on the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerCommand {
+    Play,
+    Pause,
+    TogglePlayback,
+    Next,
+    Previous,
+    SetVolume(u8),
+    /// Relative change in percentage points, for volume buttons.
+    ChangeVolume(i16),
+    LikeCurrentTrack,
+}
+
+impl FromStr for PlayerCommand {
+    type Err = anyhow::Error;
+
+    /// Parses the names used in `/spotify/player/{command}` and in screen events:
+    /// `play`, `pause`, `toggle`, `next`, `previous`, `like`, `volume_up`, `volume_down` and
+    /// `volume:<percent>`.
+    fn from_str(command: &str) -> anyhow::Result<Self> {
+        let command = match command.trim() {
+            "play" => Self::Play,
+            "pause" => Self::Pause,
+            "toggle" => Self::TogglePlayback,
+            "next" => Self::Next,
+            "previous" => Self::Previous,
+            "like" => Self::LikeCurrentTrack,
+            "volume_up" => Self::ChangeVolume(VOLUME_STEP),
+            "volume_down" => Self::ChangeVolume(-VOLUME_STEP),
+            other => match other.strip_prefix("volume:").map(str::parse::<u8>) {
+                Some(Ok(percent)) if percent <= 100 => Self::SetVolume(percent),
+                _ => return Err(anyhow::anyhow!("Unknown Spotify command {}", other)),
+            },
+        };
+
+        Ok(command)
+    }
+}
+
+/// Spotify Web API client for the connected account, shared by the polling task, the web
+/// routes and screen events.
+#[derive(Clone)]
+pub struct SpotifyPlayer {
+    refresher: TokenRefresher,
+    api_url: String,
+    /// Wakes the polling task so the screen reflects a command right away.
+    refresh_now: Arc<Notify>,
+    album_art: AlbumArtCache,
+}
+
+impl SpotifyPlayer {
+    pub fn new(refresher: TokenRefresher) -> Self {
+        Self::with_api_url(refresher, API_URL)
+    }
+
+    pub fn with_api_url(refresher: TokenRefresher, api_url: &str) -> Self {
+        Self {
+            refresher,
+            api_url: api_url.to_string(),
+            refresh_now: Arc::new(Notify::new()),
+            album_art: AlbumArtCache::new(ArtConfig::from_env()),
+        }
+    }
+
+    /// Sends an authorized request, refreshing the access token once if Spotify rejects it.
+    async fn request(&self, method: Method, path: &str, query: &[(&str, &str)]) -> anyhow::Result<Response> {
+        let http = http::shared();
+        let url = format!("{}{}", self.api_url, path);
+        let mut access_token = self.refresher.access_token("spotify").await?;
+
+        for attempt in 0..2 {
+            let request = http
+                .request(method.clone(), &url)
+                .bearer_auth(&access_token)
+                .query(query)
+                // Spotify rejects bodiless PUT/POST requests without a length.
+                .header(reqwest::header::CONTENT_LENGTH, 0);
+            let response = http.send(request).await?;
+
+            if response.status() != StatusCode::UNAUTHORIZED || attempt == 1 {
+                return Ok(response);
             }
-        } 
-    } else {
+
+            println!("Spotify rejected the access token, refreshing it");
+            access_token = self.refresher.refresh("spotify", &access_token).await?;
+        }
+
+        unreachable!("the second attempt always returns")
+    }
+
+    pub async fn playback_state(&self) -> anyhow::Result<PlaybackState> {
+        let response = self.request(Method::GET, "/me/player", &[]).await?;
+
+        match response.status() {
+            // Spotify answers 204 when no device is active.
+            StatusCode::NO_CONTENT => Ok(PlaybackState::NothingPlaying),
+            status if status.is_success() => {
+                let player: PlayerResponse = response.json().await?;
+                Ok(PlaybackState::from_response(player))
+            }
+            status => {
+                let error_text = response.text().await?;
+                Err(anyhow::anyhow!("Spotify answered {}: {}", status, error_text))
+            }
+        }
+    }
+
+    pub async fn send_command(&self, command: PlayerCommand) -> anyhow::Result<()> {
+        let (method, path, query) = match command {
+            PlayerCommand::Play => (Method::PUT, "/me/player/play", None),
+            PlayerCommand::Pause => (Method::PUT, "/me/player/pause", None),
+            PlayerCommand::TogglePlayback => match self.playback_state().await? {
+                PlaybackState::Playing(_) => (Method::PUT, "/me/player/pause", None),
+                _ => (Method::PUT, "/me/player/play", None),
+            },
+            PlayerCommand::Next => (Method::POST, "/me/player/next", None),
+            PlayerCommand::Previous => (Method::POST, "/me/player/previous", None),
+            PlayerCommand::SetVolume(percent) => (
+                Method::PUT,
+                "/me/player/volume",
+                Some(("volume_percent", percent.min(100).to_string())),
+            ),
+            PlayerCommand::ChangeVolume(delta) => {
+                let current = self
+                    .playback_state()
+                    .await?
+                    .now_playing()
+                    .and_then(|now_playing| now_playing.volume)
+                    .ok_or_else(|| anyhow::anyhow!("The active device doesn't report its volume"))?;
+                let percent = (current as i16 + delta).clamp(0, 100);
+
+                (Method::PUT, "/me/player/volume", Some(("volume_percent", percent.to_string())))
+            }
+            PlayerCommand::LikeCurrentTrack => {
+                let track_id = self
+                    .playback_state()
+                    .await?
+                    .now_playing()
+                    .and_then(|now_playing| now_playing.track_id.clone())
+                    .ok_or_else(|| anyhow::anyhow!("Nothing that can be liked is playing"))?;
+
+                (Method::PUT, "/me/tracks", Some(("ids", track_id)))
+            }
+        };
+
+        let query: Vec<(&str, &str)> = query.iter().map(|(key, value)| (*key, value.as_str())).collect();
+        let response = self.request(method, path, &query).await?;
         let status = response.status();
-        let error_text = response.text().await?;
-        println!("Failed to fetch currently playing track: {}", error_text);
-
-        if status == 401 {
-            let payload = ExchangeCodePayload {
-                code: oauth2_token.code.clone(),
-                redirect_uri: oauth2_token.redirect_uri.clone(),
-                client_id: oauth2_token.client_id.clone(),
-                client_secret: oauth2_token.client_secret.clone(),
-                get_token_url: oauth2_token.get_token_url.clone(),
+        if !status.is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow::anyhow!("Spotify answered {}: {}", status, error_text));
+        }
+
+        self.refresh_now.notify_one();
+        Ok(())
+    }
+
+    /// The cover of what is playing, as chunks for the screens.
+    async fn album_art_chunks(&self, now_playing: &NowPlaying) -> anyhow::Result<Vec<StateMessage>> {
+        let Some(album_id) = &now_playing.album_id else {
+            return Ok(Vec::new());
+        };
+        let Some(image) = pick_image(&now_playing.album_images, self.album_art.config().size) else {
+            return Ok(Vec::new());
+        };
+
+        let art = self.album_art.get(album_id, &image.url).await?;
+        art.chunks(APP)
+    }
+}
+
+/// `POST /spotify/player/{command}`, with the command names accepted by `PlayerCommand`.
+pub async fn player_command(
+    State(player): State<SpotifyPlayer>,
+    Path(command): Path<String>,
+) -> ApiResult<StatusCode> {
+    let command = command
+        .parse::<PlayerCommand>()
+        .map_err(|e| ApiError::validation(e.to_string()))?;
+
+    player
+        .send_command(command)
+        .await
+        .map_err(|e| ApiError::Upstream(e.to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+pub struct SetVolumePayload {
+    #[serde(rename = "volumePercent")]
+    volume_percent: u8,
+}
+
+pub async fn set_volume(
+    State(player): State<SpotifyPlayer>,
+    ApiJson(payload): ApiJson<SetVolumePayload>,
+) -> ApiResult<StatusCode> {
+    if payload.volume_percent > 100 {
+        return Err(ApiError::validation("volumePercent must be between 0 and 100"));
+    }
+
+    player
+        .send_command(PlayerCommand::SetVolume(payload.volume_percent))
+        .await
+        .map_err(|e| ApiError::Upstream(e.to_string()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Polls what is playing and sends it to the screens when it changed, followed by the album art
+/// when the album changed.
+pub struct SpotifySource {
+    player: SpotifyPlayer,
+    state: Mutex<PollState>,
+}
+
+#[derive(Default)]
+struct PollState {
+    last_sent: Option<(PlaybackState, Instant)>,
+    art_album_id: Option<String>,
+}
+
+impl SpotifySource {
+    pub fn new(player: SpotifyPlayer) -> Self {
+        Self {
+            player,
+            state: Mutex::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl DataSource for SpotifySource {
+    fn name(&self) -> &'static str {
+        "spotify"
+    }
+
+    fn app(&self) -> &'static str {
+        APP
+    }
+
+    fn mode(&self) -> SourceMode {
+        SourceMode::Poll {
+            interval: Duration::from_secs(POLL_INTERVAL_SECS),
+        }
+    }
+
+    /// Stored through `PUT /oauth2/spotify/client`.
+    fn config_schema(&self) -> Vec<ConfigField> {
+        vec![
+            ConfigField { key: "clientId", label: "Client ID", kind: FieldKind::Text, required: true },
+            ConfigField { key: "clientSecret", label: "Client secret", kind: FieldKind::Secret, required: true },
+            ConfigField { key: "redirectUri", label: "Redirect URI", kind: FieldKind::Url, required: true },
+        ]
+    }
+
+    /// From the stored token alone, so checking health never refreshes it.
+    async fn health(&self) -> Health {
+        match self.player.refresher.load("spotify").await {
+            Ok(token) if token.refresh_failures > 0 => Health::Degraded {
+                reason: format!("Refreshing the token failed: {}", token.last_refresh_error.unwrap_or_default()),
+            },
+            Ok(_) => Health::Healthy,
+            Err(e) => Health::Unconfigured { reason: e.to_string() },
+        }
+    }
+
+    async fn poll(&self) -> anyhow::Result<Vec<StateMessage>> {
+        let state = self.player.playback_state().await?;
+
+        let payload = serde_json::to_string(&state)?;
+        let new_album = {
+            let mut poll_state = self.state.lock().unwrap();
+            let changed = match &poll_state.last_sent {
+                Some((previous, sent_at)) => previous.differs_from(sent_at.elapsed(), &state),
+                None => true,
             };
-            let new_token = exchange_code_for_tokens(payload).await?;
-            let _ = update_oauth2_access_and_refresh_tokens(db, "spotify".to_string(), new_token.access_token, new_token.refresh_token, chrono::Utc::now().naive_utc() + chrono::Duration::seconds(new_token.expires_in as i64)).await?;
+            if !changed {
+                return Ok(Vec::new());
+            }
+
+            let new_album = state
+                .now_playing()
+                .filter(|now_playing| now_playing.album_id.is_some() && now_playing.album_id != poll_state.art_album_id)
+                .cloned();
+            if let Some(now_playing) = &new_album {
+                // Not retried until the album changes, a cover isn't worth hammering the CDN.
+                poll_state.art_album_id = now_playing.album_id.clone();
+            }
+            println!("Spotify: {}", state.summary());
+            poll_state.last_sent = Some((state, Instant::now()));
+            new_album
+        };
+
+        let mut messages = vec![StateMessage::data(APP, payload)];
+
+        if let Some(now_playing) = new_album {
+            match self.player.album_art_chunks(&now_playing).await {
+                Ok(chunks) => messages.extend(chunks),
+                Err(e) => println!("Skipping Spotify album art: {}", e),
+            }
         }
 
-        return Ok(None);
+        Ok(messages)
     }
 
-    let formatted = match (track_name, artist_name) {
-        (Some(track_name), Some(artist_name)) => Some(format!("{} - {}", artist_name, track_name)),
-        _ => None,
+    fn poll_now(&self) -> Option<Arc<Notify>> {
+        Some(self.player.refresh_now.clone())
+    }
+
+    /// Button presses on the screen, named like `PlayerCommand`.
+    async fn handle_event(&self, event: &str) -> anyhow::Result<()> {
+        self.player.send_command(event.parse()?).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
     };
 
-    Ok(formatted)
-}
-
-pub async fn spotify_polling_task(
-    db: SqlitePool,
-    sender: mpsc::Sender<StateMessage>,
-) -> anyhow::Result<()> {
-    let mut interval = interval(Duration::from_secs(30));
-    
-    loop {
-        interval.tick().await;
-
-        if let Ok(Some(oauth2_token)) = get_token_from_db(&db, "spotify".to_string()).await {
-            if oauth2_token.access_token.is_empty() || oauth2_token.refresh_token.is_empty() {
-                println!("We don't have an access token or refresh token for Spotify, so we need to exchange the code for tokens");
-                let payload = ExchangeCodePayload {
-                    code: oauth2_token.code.clone(),
-                    redirect_uri: oauth2_token.redirect_uri.clone(),
-                    client_id: oauth2_token.client_id.clone(),
-                    client_secret: oauth2_token.client_secret.clone(),
-                    get_token_url: oauth2_token.get_token_url.clone(),
-                };
-                match exchange_code_for_tokens(payload).await {
-                    Ok(tokens) => {
-                        let _ = update_oauth2_access_and_refresh_tokens(&db, "spotify".to_string(), tokens.access_token, tokens.refresh_token, chrono::Utc::now().naive_utc() + chrono::Duration::seconds(tokens.expires_in as i64)).await?;
-                        println!("Successfully exchanged code for tokens");
-                    },
-                    Err(e) => {
-                        println!("Failed to exchange code for tokens: {}", e);
-                    }
+    use axum::{
+        body::Body,
+        extract::State,
+        http::{header, Method, Request, StatusCode, Uri},
+        response::{IntoResponse, Response},
+        Json, Router,
+    };
+    use serde_json::{json, Value};
+    use sqlx::SqlitePool;
+    use tower::ServiceExt;
+
+    use super::{AlbumImage, NowPlaying, PlaybackState, PlayerCommand, RepeatMode, SpotifyPlayer, SpotifySource};
+    use crate::{
+        db::{get_token_from_db, initialize_test_db, record_oauth2_refresh_failure},
+        sources::{DataSource, Health, Sources},
+        supervisor::Supervisor,
+        test_utils::{insert_oauth2_token, spawn_mock_server},
+        web::{auth, create_router, oauth2::refresher::TokenRefresher, weather::OpenMeteo},
+    };
+
+    type Requests = Arc<Mutex<Vec<String>>>;
+
+    fn player_json(is_playing: bool) -> Value {
+        json!({
+            "device": { "id": "abc", "name": "Kitchen speaker", "type": "Speaker", "volume_percent": 40 },
+            "shuffle_state": true,
+            "repeat_state": "context",
+            "timestamp": 1700000000000u64,
+            "progress_ms": 42000,
+            "is_playing": is_playing,
+            "currently_playing_type": "track",
+            "item": {
+                "id": "0pqnGHJpmpxLKifKRmU6WP",
+                "name": "Believer",
+                "duration_ms": 204000,
+                "artists": [{ "name": "Imagine Dragons" }],
+                "album": {
+                    "id": "6ydUkTV5BzuUHYbqkKVRCh",
+                    "name": "Evolve",
+                    "images": [
+                        { "url": "https://i.scdn.co/image/640", "width": 640, "height": 640 },
+                        { "url": "https://i.scdn.co/image/300", "width": 300, "height": 300 }
+                    ]
                 }
             }
+        })
+    }
+
+    fn believer(is_playing: bool, progress_ms: u64) -> NowPlaying {
+        NowPlaying {
+            track: "Believer".to_string(),
+            artists: vec!["Imagine Dragons".to_string()],
+            album: "Evolve".to_string(),
+            duration_ms: 204000,
+            progress_ms,
+            is_playing,
+            shuffle: true,
+            repeat: RepeatMode::Context,
+            device: Some("Kitchen speaker".to_string()),
+            volume: Some(40),
+            album_id: Some("6ydUkTV5BzuUHYbqkKVRCh".to_string()),
+            track_id: Some("0pqnGHJpmpxLKifKRmU6WP".to_string()),
+            album_images: vec![
+                AlbumImage { url: "https://i.scdn.co/image/640".to_string(), width: Some(640) },
+                AlbumImage { url: "https://i.scdn.co/image/300".to_string(), width: Some(300) },
+            ],
+        }
+    }
 
-            if chrono::Utc::now().naive_utc() >= oauth2_token.expires_at {
-                println!("Access token has expired, refreshing token");
-                let payload = RefreshTokenPayload {
-                    client_id: oauth2_token.client_id.clone(),
-                    client_secret: oauth2_token.client_secret.clone(),
-                    refresh_token: oauth2_token.refresh_token.clone(),
-                    get_token_url: oauth2_token.get_token_url.clone(),
+    /// Spotify Web API stand-in: `GET /v1/me/player` answers `player` (or 204 when `None`), every
+    /// other request gets 204 and is recorded as `"<METHOD> <path and query>"`.
+    async fn spawn_spotify_api(player: Option<Value>) -> (String, Requests) {
+        async fn api(
+            State((player, requests)): State<(Option<Value>, Requests)>,
+            method: Method,
+            uri: Uri,
+        ) -> Response {
+            if method == Method::GET && uri.path() == "/v1/me/player" {
+                return match player {
+                    Some(player) => Json(player).into_response(),
+                    None => StatusCode::NO_CONTENT.into_response(),
                 };
-                if let Ok(refresh_response) = refresh_access_token(payload).await {
-                    let _ = update_oauth2_access_and_refresh_tokens(&db, "spotify".to_string(), refresh_response.access_token, oauth2_token.refresh_token, chrono::Utc::now().naive_utc() + chrono::Duration::seconds(refresh_response.expires_in as i64)).await?;
-                    println!("Successfully refreshed access token");
-                }   
-            }
-            
-            println!("Checking currently playing track");
-            if let Ok(Some(track)) = fetch_current_playing_track(&db).await {
-                println!("Currently playing: {}", track);
-                let payload = format!("Currently playing: {}", track);
-                sender.send(StateMessage::TrackData(payload)).await?;
             }
+
+            requests.lock().unwrap().push(format!("{} {}", method, uri));
+            StatusCode::NO_CONTENT.into_response()
         }
-        
+
+        let requests = Requests::default();
+        let router = Router::new()
+            .fallback(api)
+            .with_state((player, requests.clone()));
+        let base_url = spawn_mock_server(router).await;
+
+        (format!("{}/v1", base_url), requests)
+    }
+
+    /// A player for a connected Spotify account, talking to `spawn_spotify_api`.
+    async fn test_player_with_db(db: &SqlitePool, player: Option<Value>) -> (SpotifyPlayer, Requests) {
+        insert_oauth2_token(db, "spotify", "http://127.0.0.1:9/token", chrono::Duration::hours(1)).await;
+        let (api_url, requests) = spawn_spotify_api(player).await;
+
+        (SpotifyPlayer::with_api_url(TokenRefresher::new(db.clone()), &api_url), requests)
+    }
+
+    async fn test_player(player: Option<Value>) -> (SpotifyPlayer, Requests) {
+        test_player_with_db(&initialize_test_db().await, player).await
+    }
+
+    async fn post_command(db: &SqlitePool, player: SpotifyPlayer, uri: &str, scope: &str) -> StatusCode {
+        let token = auth::create_test_token(db, &[scope]).await;
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .body(Body::empty())
+            .unwrap();
+
+        create_router(db.clone(), player, OpenMeteo::new(), Sources::new(Supervisor::new(), Vec::new()))
+            .oneshot(request)
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn playing_track_is_parsed() {
+        let (player, _) = test_player(Some(player_json(true))).await;
+
+        let state = player.playback_state().await.unwrap();
+
+        assert_eq!(state, PlaybackState::Playing(believer(true, 42000)));
+        assert_eq!(state.summary(), "Imagine Dragons - Believer");
+    }
+
+    #[tokio::test]
+    async fn paused_playback_is_reported_as_paused() {
+        let (player, _) = test_player(Some(player_json(false))).await;
+
+        let state = player.playback_state().await.unwrap();
+
+        assert_eq!(state, PlaybackState::Paused(believer(false, 42000)));
+        assert_eq!(state.summary(), "Paused: Imagine Dragons - Believer");
+    }
+
+    #[tokio::test]
+    async fn no_content_means_nothing_playing() {
+        let (player, _) = test_player(None).await;
+
+        assert_eq!(player.playback_state().await.unwrap(), PlaybackState::NothingPlaying);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn missing_item_means_nothing_playing() {
+        let mut body = player_json(true);
+        body["item"] = Value::Null;
+        body["currently_playing_type"] = json!("ad");
+        let (player, _) = test_player(Some(body)).await;
+
+        assert_eq!(player.playback_state().await.unwrap(), PlaybackState::NothingPlaying);
+    }
+
+    #[test]
+    fn payload_is_tagged_json_with_progress() {
+        let payload = serde_json::to_value(PlaybackState::Playing(believer(true, 42000))).unwrap();
+
+        assert_eq!(payload["state"], "playing");
+        assert_eq!(payload["track"], "Believer");
+        assert_eq!(payload["progressMs"], 42000);
+        assert_eq!(payload["durationMs"], 204000);
+        assert_eq!(payload["repeat"], "context");
+        assert_eq!(payload["albumId"], "6ydUkTV5BzuUHYbqkKVRCh");
+        assert!(payload.get("track_id").is_none());
+        assert!(payload.get("album_images").is_none());
+        assert_eq!(
+            serde_json::to_value(PlaybackState::NothingPlaying).unwrap(),
+            json!({ "state": "nothing_playing" })
+        );
+    }
+
+    #[test]
+    fn interpolated_progress_does_not_trigger_an_update() {
+        let sent = PlaybackState::Playing(believer(true, 42000));
+
+        let on_time = PlaybackState::Playing(believer(true, 52000));
+        assert!(!sent.differs_from(Duration::from_secs(10), &on_time));
+
+        let seeked = PlaybackState::Playing(believer(true, 120000));
+        assert!(sent.differs_from(Duration::from_secs(10), &seeked));
+
+        let paused = PlaybackState::Paused(believer(false, 52000));
+        assert!(sent.differs_from(Duration::from_secs(10), &paused));
+
+        assert!(sent.differs_from(Duration::from_secs(10), &PlaybackState::NothingPlaying));
+        assert!(!PlaybackState::NothingPlaying
+            .differs_from(Duration::from_secs(10), &PlaybackState::NothingPlaying));
+    }
+
+    #[test]
+    fn commands_are_parsed_from_event_names() {
+        assert_eq!("toggle".parse::<PlayerCommand>().unwrap(), PlayerCommand::TogglePlayback);
+        assert_eq!("next\n".parse::<PlayerCommand>().unwrap(), PlayerCommand::Next);
+        assert_eq!("volume:35".parse::<PlayerCommand>().unwrap(), PlayerCommand::SetVolume(35));
+        assert_eq!("volume_down".parse::<PlayerCommand>().unwrap(), PlayerCommand::ChangeVolume(-10));
+        assert!("volume:150".parse::<PlayerCommand>().is_err());
+        assert!("rewind".parse::<PlayerCommand>().is_err());
+    }
+
+    #[tokio::test]
+    async fn toggle_pauses_while_playing() {
+        let (player, requests) = test_player(Some(player_json(true))).await;
+
+        player.send_command(PlayerCommand::TogglePlayback).await.unwrap();
+
+        assert_eq!(*requests.lock().unwrap(), vec!["PUT /v1/me/player/pause"]);
+    }
+
+    #[tokio::test]
+    async fn volume_buttons_step_from_current_volume() {
+        let (player, requests) = test_player(Some(player_json(true))).await;
+
+        player.send_command(PlayerCommand::ChangeVolume(10)).await.unwrap();
+        player.send_command(PlayerCommand::SetVolume(100)).await.unwrap();
+
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec![
+                "PUT /v1/me/player/volume?volume_percent=50",
+                "PUT /v1/me/player/volume?volume_percent=100",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn like_saves_the_current_track() {
+        let (player, requests) = test_player(Some(player_json(true))).await;
+
+        player.send_command(PlayerCommand::LikeCurrentTrack).await.unwrap();
+
+        assert_eq!(*requests.lock().unwrap(), vec!["PUT /v1/me/tracks?ids=0pqnGHJpmpxLKifKRmU6WP"]);
+    }
+
+    #[tokio::test]
+    async fn like_without_playback_fails() {
+        let (player, requests) = test_player(None).await;
+
+        assert!(player.send_command(PlayerCommand::LikeCurrentTrack).await.is_err());
+        assert!(requests.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn health_reads_the_stored_token_without_refreshing_it() {
+        let db = initialize_test_db().await;
+        let source = SpotifySource::new(SpotifyPlayer::new(TokenRefresher::new(db.clone())));
+        assert!(matches!(source.health().await, Health::Unconfigured { .. }));
+
+        // Expired, with a token URL nothing listens on: a refresh would fail and be recorded.
+        insert_oauth2_token(&db, "spotify", "http://127.0.0.1:9/token", chrono::Duration::hours(-1)).await;
+        assert_eq!(source.health().await, Health::Healthy);
+        assert_eq!(get_token_from_db(&db, "spotify".to_string()).await.unwrap().unwrap().refresh_failures, 0);
+
+        record_oauth2_refresh_failure(&db, "spotify", "invalid_grant", false, 5).await.unwrap();
+        assert_eq!(
+            source.health().await,
+            Health::Degraded { reason: "Refreshing the token failed: invalid_grant".to_string() }
+        );
+    }
+
+    #[tokio::test]
+    async fn commands_wake_the_polling_task() {
+        let (player, _) = test_player(Some(player_json(true))).await;
+
+        player.send_command(PlayerCommand::Next).await.unwrap();
+
+        tokio::time::timeout(Duration::from_millis(100), player.refresh_now.notified())
+            .await
+            .expect("command did not request a refresh");
+    }
+
+    #[tokio::test]
+    async fn player_route_sends_command() {
+        let db = initialize_test_db().await;
+        let (player, requests) = test_player_with_db(&db, Some(player_json(true))).await;
+
+        let status = post_command(&db, player, "/spotify/player/next", auth::SCOPE_SPOTIFY).await;
+
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert_eq!(*requests.lock().unwrap(), vec!["POST /v1/me/player/next"]);
+    }
+
+    #[tokio::test]
+    async fn player_route_rejects_unknown_command() {
+        let db = initialize_test_db().await;
+        let (player, requests) = test_player_with_db(&db, Some(player_json(true))).await;
+
+        let status = post_command(&db, player, "/spotify/player/rewind", auth::SCOPE_SPOTIFY).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(requests.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn player_route_requires_spotify_scope() {
+        let db = initialize_test_db().await;
+        let (player, requests) = test_player_with_db(&db, Some(player_json(true))).await;
+
+        let status = post_command(&db, player, "/spotify/player/next", auth::SCOPE_XTB).await;
+
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(requests.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,478 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use super::OpenMeteo;
+use crate::{
+    alerts::{Alert, AlertLevel},
+    db::{get_weather_locations, WeatherLocation},
+    http,
+    sources::{DataSource, Health, SourceMode},
+    tcp::StateMessage,
+};
+
+/// Open-Meteo updates air quality hourly.
+const POLL_INTERVAL_SECS: u64 = 10 * 60;
+const CURRENT_VARIABLES: &str =
+    "european_aqi,pm2_5,pm10,alder_pollen,birch_pollen,grass_pollen,mugwort_pollen,olive_pollen,ragweed_pollen";
+/// Readings reaching this band raise an alert; dropping below it again raises an all-clear.
+const ALERT_BAND: Band = Band::Poor;
+
+/// Bands of the European Air Quality Index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Band {
+    Good,
+    Fair,
+    Moderate,
+    Poor,
+    VeryPoor,
+    ExtremelyPoor,
+}
+
+impl Band {
+    const ALL: [Band; 6] = [
+        Self::Good,
+        Self::Fair,
+        Self::Moderate,
+        Self::Poor,
+        Self::VeryPoor,
+        Self::ExtremelyPoor,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Good => "good",
+            Self::Fair => "fair",
+            Self::Moderate => "moderate",
+            Self::Poor => "poor",
+            Self::VeryPoor => "very poor",
+            Self::ExtremelyPoor => "extremely poor",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pollutant {
+    Aqi,
+    Pm25,
+    Pm10,
+    Alder,
+    Birch,
+    Grass,
+    Mugwort,
+    Olive,
+    Ragweed,
+}
+
+impl Pollutant {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Aqi => "AQI",
+            Self::Pm25 => "PM2.5",
+            Self::Pm10 => "PM10",
+            Self::Alder => "Alder pollen",
+            Self::Birch => "Birch pollen",
+            Self::Grass => "Grass pollen",
+            Self::Mugwort => "Mugwort pollen",
+            Self::Olive => "Olive pollen",
+            Self::Ragweed => "Ragweed pollen",
+        }
+    }
+
+    fn unit(self) -> &'static str {
+        match self {
+            Self::Aqi => "",
+            Self::Pm25 | Self::Pm10 => "µg/m³",
+            _ => "grains/m³",
+        }
+    }
+
+    fn is_pollen(self) -> bool {
+        !matches!(self, Self::Aqi | Self::Pm25 | Self::Pm10)
+    }
+
+    /// Upper bounds of every band but the last. AQI and particulate matter follow the EEA's
+    /// European index; pollen has no official bands, these follow common allergy forecasts.
+    fn thresholds(self) -> [f64; 5] {
+        match self {
+            Self::Aqi => [20.0, 40.0, 60.0, 80.0, 100.0],
+            Self::Pm25 => [10.0, 20.0, 25.0, 50.0, 75.0],
+            Self::Pm10 => [20.0, 40.0, 50.0, 100.0, 150.0],
+            _ => [10.0, 30.0, 80.0, 200.0, 500.0],
+        }
+    }
+
+    pub fn band(self, value: f64) -> Band {
+        let index = self.thresholds().iter().take_while(|&&upper| value >= upper).count();
+        Band::ALL[index]
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+pub struct AirQualityResponse {
+    pub current: AirQualityCurrent,
+}
+
+/// Values are missing outside the area or season a pollutant is modelled for.
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+pub struct AirQualityCurrent {
+    pub time: String,
+    pub european_aqi: Option<f64>,
+    pub pm2_5: Option<f64>,
+    pub pm10: Option<f64>,
+    pub alder_pollen: Option<f64>,
+    pub birch_pollen: Option<f64>,
+    pub grass_pollen: Option<f64>,
+    pub mugwort_pollen: Option<f64>,
+    pub olive_pollen: Option<f64>,
+    pub ragweed_pollen: Option<f64>,
+}
+
+impl AirQualityCurrent {
+    pub fn readings(&self) -> Vec<(Pollutant, f64)> {
+        [
+            (Pollutant::Aqi, self.european_aqi),
+            (Pollutant::Pm25, self.pm2_5),
+            (Pollutant::Pm10, self.pm10),
+            (Pollutant::Alder, self.alder_pollen),
+            (Pollutant::Birch, self.birch_pollen),
+            (Pollutant::Grass, self.grass_pollen),
+            (Pollutant::Mugwort, self.mugwort_pollen),
+            (Pollutant::Olive, self.olive_pollen),
+            (Pollutant::Ragweed, self.ragweed_pollen),
+        ]
+        .into_iter()
+        .filter_map(|(pollutant, value)| Some((pollutant, value?)))
+        .collect()
+    }
+}
+
+impl OpenMeteo {
+    pub async fn air_quality(&self, location: &WeatherLocation) -> anyhow::Result<AirQualityResponse> {
+        let http = http::shared();
+        let request = http.get(&format!("{}/air-quality", self.air_quality_url)).query(&[
+            ("latitude", location.latitude.to_string()),
+            ("longitude", location.longitude.to_string()),
+            ("current", CURRENT_VARIABLES.to_string()),
+            ("timezone", location.timezone.clone()),
+        ]);
+        let response = http.send(request).await?.error_for_status()?;
+        let data = response.json::<AirQualityResponse>().await?;
+        Ok(data)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Level {
+    pub kind: Pollutant,
+    pub value: f64,
+    pub band: Band,
+}
+
+impl Level {
+    fn new(kind: Pollutant, value: f64) -> Self {
+        Self {
+            kind,
+            value: (value * 10.0).round() / 10.0,
+            band: kind.band(value),
+        }
+    }
+}
+
+/// One location's air-quality page on the screen.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AirQualityView {
+    pub view: &'static str,
+    pub location: String,
+    pub index: usize,
+    pub count: usize,
+    pub aqi: Option<Level>,
+    pub pm25: Option<Level>,
+    pub pm10: Option<Level>,
+    /// Only pollen that is in the air.
+    pub pollen: Vec<Level>,
+}
+
+impl AirQualityView {
+    pub fn new(location: &str, index: usize, count: usize, readings: &[(Pollutant, f64)]) -> Self {
+        let level = |kind: Pollutant| {
+            readings
+                .iter()
+                .find(|(pollutant, _)| *pollutant == kind)
+                .map(|&(kind, value)| Level::new(kind, value))
+        };
+
+        Self {
+            view: "air_quality",
+            location: location.to_string(),
+            index,
+            count,
+            aqi: level(Pollutant::Aqi),
+            pm25: level(Pollutant::Pm25),
+            pm10: level(Pollutant::Pm10),
+            pollen: readings
+                .iter()
+                .filter(|(pollutant, value)| pollutant.is_pollen() && *value > 0.0)
+                .map(|&(kind, value)| Level::new(kind, value))
+                .collect(),
+        }
+    }
+}
+
+/// PM2.5, PM10, the European AQI and pollen for every weather location.
+pub struct AirQualitySource {
+    db: SqlitePool,
+    open_meteo: OpenMeteo,
+    /// Last band of every location and pollutant, to notice threshold crossings.
+    bands: Mutex<HashMap<(i64, Pollutant), Band>>,
+}
+
+impl AirQualitySource {
+    pub fn new(db: SqlitePool, open_meteo: OpenMeteo) -> Self {
+        Self {
+            db,
+            open_meteo,
+            bands: Mutex::default(),
+        }
+    }
+
+    fn crossings(&self, location: &WeatherLocation, readings: &[(Pollutant, f64)]) -> Vec<Alert> {
+        let mut bands = self.bands.lock().unwrap();
+        let mut alerts = Vec::new();
+        for &(pollutant, value) in readings {
+            let band = pollutant.band(value);
+            let previous = bands.insert((location.id, pollutant), band);
+
+            let reading = format!("{:.1} {}", value, pollutant.unit());
+            let title = format!("{} is {} in {}", pollutant.label(), band.label(), location.name);
+            if band >= ALERT_BAND && previous.is_none_or(|previous| band > previous) {
+                let level = if band > ALERT_BAND { AlertLevel::Severe } else { AlertLevel::Warning };
+                alerts.push(Alert::new(self.name(), level, title, reading.trim_end()));
+            } else if band < ALERT_BAND && previous.is_some_and(|previous| previous >= ALERT_BAND) {
+                let title = format!("{} is {} again in {}", pollutant.label(), band.label(), location.name);
+                alerts.push(Alert::new(self.name(), AlertLevel::Info, title, reading.trim_end()));
+            }
+        }
+        alerts
+    }
+}
+
+#[async_trait]
+impl DataSource for AirQualitySource {
+    fn name(&self) -> &'static str {
+        "air_quality"
+    }
+
+    fn app(&self) -> &'static str {
+        "AirQuality"
+    }
+
+    fn mode(&self) -> SourceMode {
+        SourceMode::Poll {
+            interval: Duration::from_secs(POLL_INTERVAL_SECS),
+        }
+    }
+
+    async fn health(&self) -> Health {
+        match get_weather_locations(&self.db).await {
+            Ok(locations) if locations.is_empty() => Health::Unconfigured {
+                reason: "No weather locations configured".to_string(),
+            },
+            Ok(_) => Health::Healthy,
+            Err(e) => Health::Degraded { reason: e.to_string() },
+        }
+    }
+
+    /// An `AirQualityView` page per location, followed by alerts for bands that were crossed.
+    /// Locations that fail are skipped; the poll only fails when all do.
+    async fn poll(&self) -> anyhow::Result<Vec<StateMessage>> {
+        let locations = get_weather_locations(&self.db).await?;
+
+        let mut messages = Vec::new();
+        let mut alerts = Vec::new();
+        let mut failure = None;
+        for (index, location) in locations.iter().enumerate() {
+            let readings = match self.open_meteo.air_quality(location).await {
+                Ok(air_quality) => air_quality.current.readings(),
+                Err(e) => {
+                    println!("Failed to get the air quality for {}: {}", location.name, e);
+                    failure = Some(e);
+                    continue;
+                }
+            };
+            let view = AirQualityView::new(&location.name, index, locations.len(), &readings);
+            messages.push(StateMessage::data(self.app(), serde_json::to_string(&view)?));
+            alerts.extend(self.crossings(location, &readings));
+        }
+        if let (Some(e), true) = (failure, messages.is_empty()) {
+            return Err(e);
+        }
+
+        for alert in alerts {
+            messages.push(alert.to_message()?);
+        }
+
+        Ok(messages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use axum::{
+        extract::{Query, State},
+        http::StatusCode,
+        routing::get,
+        Json, Router,
+    };
+    use serde_json::{json, Value};
+
+    use super::{AirQualitySource, Band, Pollutant};
+    use crate::{
+        alerts::AlertLevel,
+        db::{add_weather_location, delete_weather_location, get_weather_locations, initialize_test_db, WeatherLocation},
+        sources::DataSource,
+        tcp::StateMessage,
+        test_utils::spawn_mock_server,
+        web::weather::OpenMeteo,
+    };
+
+    type Current = Arc<Mutex<Value>>;
+
+    async fn air_quality(
+        State(current): State<Current>,
+        Query(query): Query<HashMap<String, String>>,
+    ) -> Result<Json<Value>, StatusCode> {
+        // Null Island stands in for a location Open-Meteo rejects.
+        if query["latitude"] == "0" {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        Ok(Json(json!({
+            "latitude": 54.35,
+            "longitude": 18.65,
+            "current_units": { "pm2_5": "μg/m³", "pm10": "μg/m³", "european_aqi": "EAQI" },
+            "current": current.lock().unwrap().clone()
+        })))
+    }
+
+    /// An air-quality source for the seeded Gdańsk location, reading `current` from a mock.
+    async fn test_source() -> (AirQualitySource, Current) {
+        let current = Current::new(Mutex::new(Value::Null));
+        let router = Router::new()
+            .route("/v1/air-quality", get(air_quality))
+            .with_state(current.clone());
+        let base_url = format!("{}/v1", spawn_mock_server(router).await);
+        let open_meteo = OpenMeteo::with_base_urls(&base_url, &base_url, &base_url);
+
+        (AirQualitySource::new(initialize_test_db().await, open_meteo), current)
+    }
+
+    fn set_pm25(current: &Current, pm2_5: f64) {
+        *current.lock().unwrap() = json!({
+            "time": "2026-10-19T12:00",
+            "interval": 3600,
+            "european_aqi": 35,
+            "pm2_5": pm2_5,
+            "pm10": 18.44,
+            "alder_pollen": 0.0,
+            "birch_pollen": 42.0,
+            "grass_pollen": null
+        });
+    }
+
+    /// Pages and alerts sent by a poll, as JSON.
+    async fn poll(source: &AirQualitySource) -> (Vec<Value>, Vec<Value>) {
+        let (mut pages, mut alerts) = (Vec::new(), Vec::new());
+        for message in source.poll().await.unwrap() {
+            match message {
+                StateMessage::Data { app: "AirQuality", payload } => pages.push(serde_json::from_str(&payload).unwrap()),
+                StateMessage::Data { app: "Alert", payload } => alerts.push(serde_json::from_str(&payload).unwrap()),
+                _ => panic!("unexpected message"),
+            }
+        }
+        (pages, alerts)
+    }
+
+    #[test]
+    fn readings_are_banded() {
+        assert_eq!(Pollutant::Pm25.band(0.0), Band::Good);
+        assert_eq!(Pollutant::Pm25.band(10.0), Band::Fair);
+        assert_eq!(Pollutant::Pm25.band(24.9), Band::Moderate);
+        assert_eq!(Pollutant::Pm25.band(38.0), Band::Poor);
+        assert_eq!(Pollutant::Pm25.band(80.0), Band::ExtremelyPoor);
+        assert_eq!(Pollutant::Pm10.band(60.0), Band::Poor);
+        assert_eq!(Pollutant::Aqi.band(65.0), Band::Poor);
+        assert_eq!(Pollutant::Birch.band(42.0), Band::Moderate);
+    }
+
+    #[tokio::test]
+    async fn poll_sends_a_banded_page_per_location() {
+        let (source, current) = test_source().await;
+        set_pm25(&current, 12.34);
+
+        let (pages, alerts) = poll(&source).await;
+
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0]["location"], "Gdańsk");
+        assert_eq!(pages[0]["aqi"], json!({ "kind": "aqi", "value": 35.0, "band": "fair" }));
+        assert_eq!(pages[0]["pm25"], json!({ "kind": "pm25", "value": 12.3, "band": "fair" }));
+        assert_eq!(pages[0]["pm10"]["value"], 18.4);
+        assert_eq!(pages[0]["pollen"], json!([{ "kind": "birch", "value": 42.0, "band": "moderate" }]));
+        assert!(alerts.is_empty());
+    }
+
+    #[tokio::test]
+    async fn crossing_a_threshold_raises_an_alert() {
+        let (source, current) = test_source().await;
+        set_pm25(&current, 8.0);
+        poll(&source).await;
+
+        set_pm25(&current, 38.0);
+        let (_, alerts) = poll(&source).await;
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0]["source"], "air_quality");
+        assert_eq!(alerts[0]["level"], "warning");
+        assert_eq!(alerts[0]["title"], "PM2.5 is poor in Gdańsk");
+        assert_eq!(alerts[0]["message"], "38.0 µg/m³");
+
+        // Staying in the band doesn't repeat it, getting worse does.
+        assert!(poll(&source).await.1.is_empty());
+        set_pm25(&current, 60.0);
+        let (_, alerts) = poll(&source).await;
+        assert_eq!(alerts[0]["level"], "severe");
+
+        set_pm25(&current, 15.0);
+        let (_, alerts) = poll(&source).await;
+        assert_eq!(alerts[0]["level"], "info");
+        assert_eq!(alerts[0]["title"], "PM2.5 is fair again in Gdańsk");
+        assert!(AlertLevel::Info < AlertLevel::Severe);
+    }
+
+    #[tokio::test]
+    async fn a_failing_location_does_not_stop_the_others() {
+        let (source, current) = test_source().await;
+        set_pm25(&current, 12.34);
+        let gdansk = get_weather_locations(&source.db).await.unwrap().remove(0);
+        let null_island = WeatherLocation { name: "Null Island".to_string(), latitude: 0.0, longitude: 0.0, ..gdansk.clone() };
+        add_weather_location(&source.db, null_island).await.unwrap();
+
+        let (pages, _) = poll(&source).await;
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0]["location"], "Gdańsk");
+
+        delete_weather_location(&source.db, gdansk.id).await.unwrap();
+        assert!(source.poll().await.is_err());
+    }
+}
@@ -0,0 +1,299 @@
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use super::{forecast::condition, to_celsius, to_millimetres, Units, WeatherResponse, WeatherSettings};
+use crate::alerts::{Alert, AlertLevel};
+
+/// One step of the 15-minute forecast.
+pub const MIN_WITHIN_MINUTES: i64 = 15;
+/// Rain is forecast in 15-minute steps for the next two hours only.
+pub const MAX_RAIN_WITHIN_MINUTES: i64 = 120;
+/// Everything else is looked for in the hourly forecast for the next day.
+pub const MAX_WITHIN_MINUTES: i64 = 24 * 60;
+/// The forecast is polled every 30 seconds; without a cooldown one shower would alert on each poll.
+pub const MIN_COOLDOWN_MINUTES: i64 = 1;
+pub const MAX_COOLDOWN_MINUTES: i64 = 7 * 24 * 60;
+/// Alerts are raised by the weather source.
+const SOURCE: &str = "weather";
+const MINUTELY_STEP_MINUTES: i64 = 15;
+const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum AlertKind {
+    /// At least `threshold` mm of precipitation in a quarter-hour.
+    Rain,
+    /// A thunderstorm; `threshold` is unused.
+    Storm,
+    /// Temperature below `threshold` °C.
+    Frost,
+    /// Temperature above `threshold` °C.
+    Heat,
+}
+
+impl AlertKind {
+    pub fn max_within_minutes(self) -> i64 {
+        match self {
+            Self::Rain => MAX_RAIN_WITHIN_MINUTES,
+            Self::Storm | Self::Frost | Self::Heat => MAX_WITHIN_MINUTES,
+        }
+    }
+}
+
+impl FromStr for AlertKind {
+    type Err = anyhow::Error;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "rain" => Ok(Self::Rain),
+            "storm" => Ok(Self::Storm),
+            "frost" => Ok(Self::Frost),
+            "heat" => Ok(Self::Heat),
+            _ => Err(anyhow::anyhow!("Unknown alert rule {}", kind)),
+        }
+    }
+}
+
+/// When to warn about the forecast; thresholds are metric whatever the display units.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AlertRule {
+    pub kind: AlertKind,
+    pub enabled: bool,
+    pub threshold: f64,
+    /// How far ahead to look.
+    #[serde(rename = "withinMinutes")]
+    pub within_minutes: i64,
+    /// How long to stay quiet after alerting about a location.
+    #[serde(rename = "cooldownMinutes")]
+    pub cooldown_minutes: i64,
+}
+
+impl AlertRule {
+    /// The alert to raise about `location` given its forecast, with `now` in the location's local
+    /// time like the forecast itself.
+    pub fn evaluate(&self, location: &str, weather: &WeatherResponse, settings: &WeatherSettings, now: NaiveDateTime) -> Option<Alert> {
+        if !self.enabled {
+            return None;
+        }
+
+        let until = now + chrono::Duration::minutes(self.within_minutes);
+        match self.kind {
+            AlertKind::Rain => {
+                let minutely = &weather.minutely_15;
+                let unit = &weather.minutely_15_units.precipitation;
+                // Every value is the sum of the quarter-hour before its time.
+                let (start, amount) = minutely
+                    .time
+                    .iter()
+                    .zip(&minutely.precipitation)
+                    .filter_map(|(time, &amount)| {
+                        let end = NaiveDateTime::parse_from_str(time, TIME_FORMAT).ok()?;
+                        Some((end - chrono::Duration::minutes(MINUTELY_STEP_MINUTES), end, amount))
+                    })
+                    .find(|&(start, end, amount)| end > now && start <= until && to_millimetres(amount, unit) >= self.threshold)
+                    .map(|(start, _, amount)| (start, to_millimetres(amount, unit)))?;
+
+                let title = match lead(now, start) {
+                    Some(lead) => format!("Rain in {}", lead),
+                    None => "Rain now".to_string(),
+                };
+                let amount = settings.units.precipitation(amount);
+                let message = format!("{}: {:.1} {} in 15 min", location, amount, settings.units.precipitation_label());
+                Some(Alert::new(SOURCE, AlertLevel::Warning, title, message))
+            }
+            AlertKind::Storm => {
+                let (time, code) = hours(weather, now, until).find(|&(_, code, _)| is_thunderstorm(code)).map(|(time, code, _)| (time, code))?;
+
+                let title = match lead(now, time) {
+                    Some(lead) => format!("Thunderstorm in {}", lead),
+                    None => "Thunderstorm now".to_string(),
+                };
+                Some(Alert::new(SOURCE, AlertLevel::Severe, title, format!("{}: {}", location, condition(code))))
+            }
+            AlertKind::Frost | AlertKind::Heat => {
+                let unit = &weather.hourly_units.temperature_2m;
+                let temperatures: Vec<_> = hours(weather, now, until).map(|(time, _, temperature)| (time, to_celsius(temperature, unit))).collect();
+                let (title, extreme) = if self.kind == AlertKind::Frost {
+                    let (time, _) = temperatures.iter().find(|&&(_, celsius)| celsius < self.threshold)?;
+                    let lowest = temperatures.iter().map(|&(_, celsius)| celsius).fold(f64::INFINITY, f64::min);
+                    (format!("Frost expected at {}", time.format("%H:%M")), format!("down to {}", temperature(lowest, settings.units)))
+                } else {
+                    let (time, _) = temperatures.iter().find(|&&(_, celsius)| celsius > self.threshold)?;
+                    let highest = temperatures.iter().map(|&(_, celsius)| celsius).fold(f64::NEG_INFINITY, f64::max);
+                    (format!("Heat expected at {}", time.format("%H:%M")), format!("up to {}", temperature(highest, settings.units)))
+                };
+                Some(Alert::new(SOURCE, AlertLevel::Warning, title, format!("{}: {}", location, extreme)))
+            }
+        }
+    }
+}
+
+/// Hourly forecast time, weather code and temperature between the current hour and `until`.
+fn hours(weather: &WeatherResponse, now: NaiveDateTime, until: NaiveDateTime) -> impl Iterator<Item = (NaiveDateTime, u8, f64)> + '_ {
+    let hourly = &weather.hourly;
+    hourly
+        .time
+        .iter()
+        .zip(&hourly.weather_code)
+        .zip(&hourly.temperature_2m)
+        .filter_map(|((time, &code), &temperature)| Some((NaiveDateTime::parse_from_str(time, TIME_FORMAT).ok()?, code, temperature)))
+        .filter(move |&(time, _, _)| time + chrono::Duration::hours(1) > now && time <= until)
+}
+
+/// WMO codes 95 to 99.
+fn is_thunderstorm(code: u8) -> bool {
+    (95..=99).contains(&code)
+}
+
+/// `20 min` or `3 h` from `now` until `time`; `None` once it has started.
+fn lead(now: NaiveDateTime, time: NaiveDateTime) -> Option<String> {
+    let minutes = (time - now).num_minutes();
+    match minutes {
+        ..=0 => None,
+        1..60 => Some(format!("{} min", minutes)),
+        _ => Some(format!("{} h", (minutes + 30) / 60)),
+    }
+}
+
+fn temperature(celsius: f64, units: Units) -> String {
+    format!("{:.1}{}", units.temperature(celsius), units.temperature_label())
+}
+
+/// When every rule last alerted about every location, so that a rain shower seen on every poll
+/// only alerts once per cooldown.
+#[derive(Debug, Default)]
+pub struct Debouncer {
+    fired: Mutex<HashMap<(i64, AlertKind), Instant>>,
+}
+
+impl Debouncer {
+    /// Whether `kind` may alert about `location_id` again; if so, the cooldown starts now.
+    pub fn should_fire(&self, location_id: i64, kind: AlertKind, cooldown: Duration) -> bool {
+        let mut fired = self.fired.lock().unwrap();
+        let now = Instant::now();
+        if fired.get(&(location_id, kind)).is_some_and(|&at| now.duration_since(at) < cooldown) {
+            return false;
+        }
+        fired.insert((location_id, kind), now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::NaiveDateTime;
+
+    use super::{AlertKind, AlertRule, Debouncer};
+    use crate::{
+        alerts::AlertLevel,
+        web::weather::{Hourly, HourlyUnits, Minutely15, Minutely15Units, Units, WeatherResponse, WeatherSettings},
+    };
+
+    fn now() -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2026-10-19T12:10", "%Y-%m-%dT%H:%M").unwrap()
+    }
+
+    fn rule(kind: AlertKind, threshold: f64, within_minutes: i64) -> AlertRule {
+        AlertRule {
+            kind,
+            enabled: true,
+            threshold,
+            within_minutes,
+            cooldown_minutes: 60,
+        }
+    }
+
+    /// Dry until 12:30, then a quarter-hour of rain; a storm at 15:00 and a day from 10 to 31 °C.
+    fn response() -> WeatherResponse {
+        WeatherResponse {
+            minutely_15_units: Minutely15Units {
+                precipitation: "mm".to_string(),
+            },
+            minutely_15: Minutely15 {
+                time: ["12:15", "12:30", "12:45", "13:00"].iter().map(|time| format!("2026-10-19T{}", time)).collect(),
+                precipitation: vec![0.0, 0.0, 0.8, 0.2],
+                weather_code: vec![3, 3, 61, 61],
+            },
+            hourly_units: HourlyUnits {
+                temperature_2m: "°C".to_string(),
+            },
+            hourly: Hourly {
+                time: (12..24).map(|hour| format!("2026-10-19T{:02}:00", hour)).collect(),
+                temperature_2m: (12..24).map(|hour| 31.0 - (hour - 12) as f64 * 2.0).collect(),
+                weather_code: (12..24).map(|hour| if hour == 15 { 95 } else { 3 }).collect(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rain_is_announced_before_it_starts() {
+        let settings = WeatherSettings::default();
+
+        let alert = rule(AlertKind::Rain, 0.1, 60).evaluate("Gdańsk", &response(), &settings, now()).unwrap();
+        let later = rule(AlertKind::Rain, 0.1, 60).evaluate("Gdańsk", &response(), &settings, now() + chrono::Duration::minutes(25)).unwrap();
+        let too_far = rule(AlertKind::Rain, 0.1, 15).evaluate("Gdańsk", &response(), &settings, now());
+        let too_little = rule(AlertKind::Rain, 1.0, 60).evaluate("Gdańsk", &response(), &settings, now());
+
+        assert_eq!(alert.title, "Rain in 20 min");
+        assert_eq!(alert.message, "Gdańsk: 0.8 mm in 15 min");
+        assert_eq!(alert.level, AlertLevel::Warning);
+        assert_eq!(later.title, "Rain now");
+        assert_eq!(too_far, None);
+        assert_eq!(too_little, None);
+    }
+
+    #[test]
+    fn storms_frost_and_heat_come_from_the_hourly_forecast() {
+        let settings = WeatherSettings {
+            units: Units::Imperial,
+            ..Default::default()
+        };
+
+        let storm = rule(AlertKind::Storm, 0.0, 180).evaluate("Gdańsk", &response(), &settings, now()).unwrap();
+        let frost = rule(AlertKind::Frost, 10.0, 720).evaluate("Gdańsk", &response(), &settings, now()).unwrap();
+        let heat = rule(AlertKind::Heat, 30.0, 720).evaluate("Gdańsk", &response(), &settings, now()).unwrap();
+        let no_storm_yet = rule(AlertKind::Storm, 0.0, 60).evaluate("Gdańsk", &response(), &settings, now());
+
+        assert_eq!(storm.title, "Thunderstorm in 3 h");
+        assert_eq!(storm.level, AlertLevel::Severe);
+        assert_eq!(frost.title, "Frost expected at 23:00");
+        assert_eq!(frost.message, "Gdańsk: down to 48.2°F");
+        assert_eq!(heat.title, "Heat expected at 12:00");
+        assert_eq!(heat.message, "Gdańsk: up to 87.8°F");
+        assert_eq!(no_storm_yet, None);
+    }
+
+    #[test]
+    fn disabled_rules_never_alert() {
+        let rule = AlertRule {
+            enabled: false,
+            ..rule(AlertKind::Rain, 0.1, 60)
+        };
+
+        assert_eq!(rule.evaluate("Gdańsk", &response(), &WeatherSettings::default(), now()), None);
+    }
+
+    #[test]
+    fn repeats_are_debounced_per_location_and_kind() {
+        let debouncer = Debouncer::default();
+        let hour = Duration::from_secs(3600);
+
+        assert!(debouncer.should_fire(1, AlertKind::Rain, hour));
+        assert!(!debouncer.should_fire(1, AlertKind::Rain, hour));
+        assert!(debouncer.should_fire(1, AlertKind::Storm, hour));
+        assert!(debouncer.should_fire(2, AlertKind::Rain, hour));
+        assert!(debouncer.should_fire(1, AlertKind::Rain, Duration::ZERO));
+    }
+}
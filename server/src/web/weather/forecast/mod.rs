@@ -0,0 +1,323 @@
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use super::{history::HistorySummary, WeatherResponse, WeatherSettings};
+
+/// Hours between the entries of the hourly strip; the screen has room for six.
+const HOUR_STEP: usize = 4;
+
+/// Icons the screen knows how to draw, covering every WMO weather code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WeatherIcon {
+    Clear,
+    ClearNight,
+    PartlyCloudy,
+    PartlyCloudyNight,
+    Cloudy,
+    Fog,
+    Drizzle,
+    Rain,
+    Snow,
+    Thunderstorm,
+}
+
+impl WeatherIcon {
+    pub fn from_wmo(code: u8, is_day: bool) -> Self {
+        match code {
+            0 if is_day => Self::Clear,
+            0 => Self::ClearNight,
+            1 | 2 if is_day => Self::PartlyCloudy,
+            1 | 2 => Self::PartlyCloudyNight,
+            45 | 48 => Self::Fog,
+            51..=57 => Self::Drizzle,
+            61..=67 | 80..=82 => Self::Rain,
+            71..=77 | 85 | 86 => Self::Snow,
+            95..=99 => Self::Thunderstorm,
+            _ => Self::Cloudy,
+        }
+    }
+}
+
+/// Short description of a WMO weather code.
+pub fn condition(code: u8) -> &'static str {
+    match code {
+        0 => "Clear sky",
+        1 => "Mainly clear",
+        2 => "Partly cloudy",
+        3 => "Overcast",
+        45 => "Fog",
+        48 => "Rime fog",
+        51 => "Light drizzle",
+        53 => "Drizzle",
+        55 => "Dense drizzle",
+        56 | 57 => "Freezing drizzle",
+        61 => "Light rain",
+        63 => "Rain",
+        65 => "Heavy rain",
+        66 | 67 => "Freezing rain",
+        71 => "Light snow",
+        73 => "Snow",
+        75 => "Heavy snow",
+        77 => "Snow grains",
+        80 => "Light showers",
+        81 => "Showers",
+        82 => "Violent showers",
+        85 | 86 => "Snow showers",
+        95 => "Thunderstorm",
+        96 | 99 => "Thunderstorm, hail",
+        _ => "Unknown",
+    }
+}
+
+/// 8-point compass direction the wind blows from.
+fn compass(degrees: f64) -> &'static str {
+    const DIRECTIONS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+    let index = (degrees.rem_euclid(360.0) / 45.0).round() as usize % DIRECTIONS.len();
+    DIRECTIONS[index]
+}
+
+/// `07:12` from Open-Meteo's local `2026-10-19T07:12`.
+fn clock(time: &str) -> String {
+    time.split_once('T').map(|(_, clock)| clock).unwrap_or(time).to_string()
+}
+
+fn round(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ForecastUnits {
+    pub temp: String,
+    pub wind: String,
+    pub precip: String,
+}
+
+impl From<&WeatherSettings> for ForecastUnits {
+    fn from(settings: &WeatherSettings) -> Self {
+        Self {
+            temp: settings.units.temperature_label().to_string(),
+            wind: settings.wind_unit.label().to_string(),
+            precip: settings.units.precipitation_label().to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HourForecast {
+    /// Local hour, e.g. `14`.
+    pub hour: String,
+    pub temp: i32,
+    pub icon: WeatherIcon,
+    /// Chance of precipitation in percent.
+    pub pop: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DayForecast {
+    /// Weekday, e.g. `Mon`.
+    pub day: String,
+    pub hi: i32,
+    pub lo: i32,
+    pub icon: WeatherIcon,
+}
+
+/// One location's page on the screen. Keys are short, a whole page has to fit in one message.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ForecastView {
+    pub view: &'static str,
+    pub location: String,
+    /// Position among the configured locations, so the screen can cycle through them.
+    pub index: usize,
+    pub count: usize,
+    pub icon: WeatherIcon,
+    pub condition: &'static str,
+    pub temp: f64,
+    pub feels: f64,
+    pub humidity: f64,
+    pub wind: f64,
+    #[serde(rename = "windDir")]
+    pub wind_dir: &'static str,
+    pub precip: f64,
+    pub units: ForecastUnits,
+    pub sunrise: String,
+    pub sunset: String,
+    pub hours: Vec<HourForecast>,
+    pub days: Vec<DayForecast>,
+    /// Today's range and the trend, once readings have been recorded.
+    #[serde(flatten)]
+    pub history: Option<HistorySummary>,
+}
+
+impl ForecastView {
+    pub fn new(location: &str, index: usize, count: usize, weather: &WeatherResponse) -> Self {
+        let current = &weather.current;
+        let hourly = &weather.hourly;
+        let daily = &weather.daily;
+
+        let hours = (0..hourly.time.len().min(hourly.temperature_2m.len()).min(hourly.weather_code.len()))
+            .step_by(HOUR_STEP)
+            .map(|i| HourForecast {
+                hour: clock(&hourly.time[i]).chars().take(2).collect(),
+                temp: hourly.temperature_2m[i].round() as i32,
+                icon: WeatherIcon::from_wmo(hourly.weather_code[i], hourly.is_day.get(i).is_none_or(|&d| d == 1)),
+                pop: hourly.precipitation_probability.get(i).copied().flatten(),
+            })
+            .collect();
+
+        let days = (0..daily.time.len())
+            .filter_map(|i| {
+                Some(DayForecast {
+                    day: NaiveDate::parse_from_str(&daily.time[i], "%Y-%m-%d").ok()?.format("%a").to_string(),
+                    hi: daily.temperature_2m_max.get(i)?.round() as i32,
+                    lo: daily.temperature_2m_min.get(i)?.round() as i32,
+                    icon: WeatherIcon::from_wmo(*daily.weather_code.get(i)?, true),
+                })
+            })
+            .collect();
+
+        Self {
+            view: "forecast",
+            location: location.to_string(),
+            index,
+            count,
+            icon: WeatherIcon::from_wmo(current.weather_code, current.is_day == 1),
+            condition: condition(current.weather_code),
+            temp: round(current.temperature_2m),
+            feels: round(current.apparent_temperature),
+            humidity: current.relative_humidity_2m.round(),
+            wind: round(current.wind_speed_10m),
+            wind_dir: compass(current.wind_direction_10m),
+            precip: round(current.precipitation),
+            units: ForecastUnits {
+                temp: weather.current_units.temperature_2m.clone(),
+                wind: weather.current_units.wind_speed_10m.clone(),
+                precip: weather.current_units.precipitation.clone(),
+            },
+            sunrise: daily.sunrise.first().map(|time| clock(time)).unwrap_or_default(),
+            sunset: daily.sunset.first().map(|time| clock(time)).unwrap_or_default(),
+            hours,
+            days,
+            history: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{compass, ForecastView, WeatherIcon};
+    use crate::web::weather::{
+        history::{HistorySummary, Trend},
+        WeatherResponse,
+    };
+
+    fn sample_response() -> WeatherResponse {
+        let hours: Vec<String> = (0..24).map(|hour| format!("2026-10-19T{:02}:00", (hour + 12) % 24)).collect();
+        serde_json::from_value(json!({
+            "latitude": 54.35,
+            "longitude": 18.65,
+            "generationtime_ms": 0.1,
+            "utc_offset_seconds": 7200,
+            "timezone": "Europe/Warsaw",
+            "timezone_abbreviation": "CEST",
+            "elevation": 10.0,
+            "current_units": {
+                "time": "iso8601",
+                "interval": "seconds",
+                "temperature_2m": "°C",
+                "apparent_temperature": "°C",
+                "relative_humidity_2m": "%",
+                "wind_speed_10m": "km/h",
+                "precipitation": "mm"
+            },
+            "current": {
+                "time": "2026-10-19T12:00",
+                "interval": 900,
+                "temperature_2m": 12.46,
+                "apparent_temperature": 10.04,
+                "relative_humidity_2m": 81,
+                "wind_speed_10m": 14.2,
+                "wind_direction_10m": 230,
+                "precipitation": 0.3,
+                "weather_code": 61,
+                "is_day": 1
+            },
+            "hourly": {
+                "time": hours,
+                "temperature_2m": vec![12.5; 24],
+                "weather_code": vec![61; 24],
+                "precipitation_probability": vec![Some(60); 24],
+                "is_day": vec![1; 24]
+            },
+            "daily": {
+                "time": ["2026-10-19", "2026-10-20", "2026-10-21", "2026-10-22", "2026-10-23", "2026-10-24", "2026-10-25"],
+                "weather_code": [61, 3, 0, 95, 71, 45, 2],
+                "temperature_2m_max": [14.2, 13.0, 15.6, 11.0, 2.0, 8.4, 10.0],
+                "temperature_2m_min": [8.1, 7.0, 6.5, 5.0, -3.4, 4.0, 5.0],
+                "sunrise": ["2026-10-19T07:12", "2026-10-20T07:14", "2026-10-21T07:16", "2026-10-22T07:18",
+                            "2026-10-23T07:20", "2026-10-24T07:22", "2026-10-25T07:24"],
+                "sunset": ["2026-10-19T17:40", "2026-10-20T17:38", "2026-10-21T17:36", "2026-10-22T17:34",
+                           "2026-10-23T17:32", "2026-10-24T17:30", "2026-10-25T17:28"],
+                "precipitation_sum": [2.1, 0.0, 0.0, 5.0, 3.0, 0.0, 0.0]
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn weather_codes_map_to_icons() {
+        assert_eq!(WeatherIcon::from_wmo(0, true), WeatherIcon::Clear);
+        assert_eq!(WeatherIcon::from_wmo(0, false), WeatherIcon::ClearNight);
+        assert_eq!(WeatherIcon::from_wmo(2, false), WeatherIcon::PartlyCloudyNight);
+        assert_eq!(WeatherIcon::from_wmo(3, true), WeatherIcon::Cloudy);
+        assert_eq!(WeatherIcon::from_wmo(48, true), WeatherIcon::Fog);
+        assert_eq!(WeatherIcon::from_wmo(56, true), WeatherIcon::Drizzle);
+        assert_eq!(WeatherIcon::from_wmo(81, true), WeatherIcon::Rain);
+        assert_eq!(WeatherIcon::from_wmo(86, true), WeatherIcon::Snow);
+        assert_eq!(WeatherIcon::from_wmo(99, true), WeatherIcon::Thunderstorm);
+        assert_eq!(WeatherIcon::from_wmo(42, true), WeatherIcon::Cloudy);
+    }
+
+    #[test]
+    fn wind_direction_is_a_compass_point() {
+        assert_eq!(compass(0.0), "N");
+        assert_eq!(compass(230.0), "SW");
+        assert_eq!(compass(350.0), "N");
+        assert_eq!(compass(-90.0), "W");
+    }
+
+    #[test]
+    fn view_summarizes_the_forecast() {
+        let view = ForecastView::new("Gdańsk", 0, 2, &sample_response());
+
+        assert_eq!(view.icon, WeatherIcon::Rain);
+        assert_eq!(view.condition, "Light rain");
+        assert_eq!((view.temp, view.feels, view.humidity), (12.5, 10.0, 81.0));
+        assert_eq!(view.wind_dir, "SW");
+        assert_eq!((view.sunrise.as_str(), view.sunset.as_str()), ("07:12", "17:40"));
+        assert_eq!(view.hours.len(), 6);
+        assert_eq!(view.hours[1].hour, "16");
+        assert_eq!(view.hours[1].pop, Some(60));
+        assert_eq!(view.days.len(), 7);
+        assert_eq!(view.days[0].day, "Mon");
+        assert_eq!((view.days[4].hi, view.days[4].lo), (2, -3));
+        assert_eq!(view.days[3].icon, WeatherIcon::Thunderstorm);
+    }
+
+    #[test]
+    fn view_fits_in_one_screen_message() {
+        let mut view = ForecastView::new("Llanfairpwllgwyngyll", 4, 5, &sample_response());
+        view.history = Some(HistorySummary {
+            min: -12.3,
+            max: -10.1,
+            trend: Trend::Falling,
+            spark: vec![Some(-10.5); 12],
+        });
+
+        let payload = serde_json::to_string(&view).unwrap();
+
+        assert!(payload.len() <= 1024, "{} bytes", payload.len());
+    }
+}
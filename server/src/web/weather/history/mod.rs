@@ -0,0 +1,201 @@
+use chrono::{Duration, NaiveDateTime};
+use serde::Serialize;
+
+use super::{to_celsius, to_kmh, to_millimetres, Units, WeatherResponse};
+use crate::db::WeatherReading;
+
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+/// The trend compares the latest reading with the oldest one in this window.
+const TREND_WINDOW_HOURS: i64 = 3;
+/// Smaller changes, in °C, count as steady.
+const TREND_THRESHOLD: f64 = 0.5;
+/// Points of the sparkline, one per `SPARK_STEP_HOURS`, covering the last day.
+const SPARK_POINTS: i64 = 12;
+const SPARK_STEP_HOURS: i64 = 2;
+
+/// How long readings are kept, from `WEATHER_HISTORY_DAYS`.
+pub fn retention() -> Duration {
+    let days = std::env::var("WEATHER_HISTORY_DAYS")
+        .ok()
+        .and_then(|days| days.trim().parse::<i64>().ok())
+        .filter(|days| *days > 0)
+        .unwrap_or(DEFAULT_RETENTION_DAYS);
+
+    Duration::days(days)
+}
+
+/// The current conditions in `weather` as a metric reading taken at its observation time.
+pub fn reading(location_id: i64, weather: &WeatherResponse) -> anyhow::Result<WeatherReading> {
+    let current = &weather.current;
+    let units = &weather.current_units;
+    let local_time = NaiveDateTime::parse_from_str(&current.time, "%Y-%m-%dT%H:%M")?;
+
+    Ok(WeatherReading {
+        location_id,
+        recorded_at: local_time - Duration::seconds(weather.utc_offset_seconds),
+        temperature: to_celsius(current.temperature_2m, &units.temperature_2m),
+        apparent_temperature: to_celsius(current.apparent_temperature, &units.apparent_temperature),
+        humidity: current.relative_humidity_2m,
+        wind_speed: to_kmh(current.wind_speed_10m, &units.wind_speed_10m),
+        precipitation: to_millimetres(current.precipitation, &units.precipitation),
+        weather_code: current.weather_code.into(),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Trend {
+    Rising,
+    Falling,
+    Steady,
+}
+
+fn round(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+/// What the forecast page shows from the history, in display units.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistorySummary {
+    /// Lowest and highest temperature since local midnight.
+    pub min: f64,
+    pub max: f64,
+    pub trend: Trend,
+    /// Temperatures over the last day, oldest first; `None` where nothing was recorded.
+    pub spark: Vec<Option<f64>>,
+}
+
+impl HistorySummary {
+    /// `readings` are the last day of a location, oldest first; `utc_offset_seconds` places its
+    /// midnight. `None` without readings.
+    pub fn new(readings: &[WeatherReading], now: NaiveDateTime, utc_offset_seconds: i64, units: Units) -> Option<Self> {
+        let latest = readings.last()?;
+
+        let offset = Duration::seconds(utc_offset_seconds);
+        let midnight = (now + offset).date().and_hms_opt(0, 0, 0)? - offset;
+        let today = readings.iter().filter(|reading| reading.recorded_at >= midnight);
+        let (min, max) = today.fold((latest.temperature, latest.temperature), |(min, max), reading| {
+            (min.min(reading.temperature), max.max(reading.temperature))
+        });
+
+        let window_start = latest.recorded_at - Duration::hours(TREND_WINDOW_HOURS);
+        let earliest = readings.iter().find(|reading| reading.recorded_at >= window_start).unwrap_or(latest);
+        let change = latest.temperature - earliest.temperature;
+        let trend = if change > TREND_THRESHOLD {
+            Trend::Rising
+        } else if change < -TREND_THRESHOLD {
+            Trend::Falling
+        } else {
+            Trend::Steady
+        };
+
+        let spark = (0..SPARK_POINTS)
+            .map(|point| {
+                let end = now - Duration::hours((SPARK_POINTS - 1 - point) * SPARK_STEP_HOURS);
+                let start = end - Duration::hours(SPARK_STEP_HOURS);
+                readings
+                    .iter()
+                    .rev()
+                    .find(|reading| reading.recorded_at > start && reading.recorded_at <= end)
+                    .map(|reading| round(units.temperature(reading.temperature)))
+            })
+            .collect();
+
+        Some(Self {
+            min: round(units.temperature(min)),
+            max: round(units.temperature(max)),
+            trend,
+            spark,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate, NaiveDateTime};
+
+    use super::{reading, HistorySummary, Trend};
+    use crate::{
+        db::WeatherReading,
+        web::weather::{Current, CurrentUnits, Units, WeatherResponse},
+    };
+
+    fn at(hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, 19).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn reading_at(recorded_at: NaiveDateTime, temperature: f64) -> WeatherReading {
+        WeatherReading {
+            location_id: 1,
+            recorded_at,
+            temperature,
+            apparent_temperature: temperature,
+            humidity: 80.0,
+            wind_speed: 10.0,
+            precipitation: 0.0,
+            weather_code: 3,
+        }
+    }
+
+    #[test]
+    fn imperial_readings_are_stored_in_metric() {
+        let weather = WeatherResponse {
+            utc_offset_seconds: 7200,
+            current: Current {
+                time: "2026-10-19T14:15".to_string(),
+                temperature_2m: 50.0,
+                wind_speed_10m: 10.0,
+                precipitation: 0.1,
+                ..Default::default()
+            },
+            current_units: CurrentUnits {
+                temperature_2m: "°F".to_string(),
+                wind_speed_10m: "mph".to_string(),
+                precipitation: "inch".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let reading = reading(1, &weather).unwrap();
+
+        assert_eq!(reading.recorded_at, at(12, 15));
+        assert_eq!(reading.temperature, 10.0);
+        assert!((reading.wind_speed - 16.09344).abs() < 1e-9);
+        assert!((reading.precipitation - 2.54).abs() < 1e-9);
+    }
+
+    #[test]
+    fn summary_covers_today_and_the_recent_trend() {
+        // UTC+2: local midnight is 22:00 UTC the day before, so the 21:00 reading is yesterday's.
+        let readings = vec![
+            reading_at(at(0, 0) - Duration::hours(3), -4.0),
+            reading_at(at(2, 0), 3.0),
+            reading_at(at(9, 0), 8.0),
+            reading_at(at(11, 0), 9.0),
+            reading_at(at(12, 0), 12.0),
+        ];
+
+        let summary = HistorySummary::new(&readings, at(12, 5), 7200, Units::Metric).unwrap();
+
+        assert_eq!((summary.min, summary.max), (3.0, 12.0));
+        assert_eq!(summary.trend, Trend::Rising);
+        assert_eq!(summary.spark.len(), 12);
+        assert_eq!(summary.spark[11], Some(12.0));
+        assert_eq!(summary.spark[10], Some(8.0));
+        assert_eq!(summary.spark[9], None);
+        assert_eq!(summary.spark[6], Some(3.0));
+        assert_eq!(summary.spark[4], Some(-4.0));
+    }
+
+    #[test]
+    fn small_changes_are_steady_and_units_are_converted() {
+        let readings = vec![reading_at(at(10, 0), 10.0), reading_at(at(12, 0), 10.3)];
+
+        let summary = HistorySummary::new(&readings, at(12, 0), 0, Units::Imperial).unwrap();
+
+        assert_eq!(summary.trend, Trend::Steady);
+        assert_eq!((summary.min, summary.max), (50.0, 50.5));
+        assert!(HistorySummary::new(&[], at(12, 0), 0, Units::Metric).is_none());
+    }
+}
@@ -1,7 +1,181 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use tokio::sync::mpsc;
+use sqlx::SqlitePool;
+
+use super::error::{ApiError, ApiJson, ApiResult};
+use crate::{
+    alerts::Alert,
+    db::{
+        add_weather_location, delete_weather_location, delete_weather_readings_before, get_weather_alert_rules,
+        get_weather_history, get_weather_locations, get_weather_settings, save_weather_alert_rule, save_weather_reading,
+        save_weather_settings, WeatherLocation,
+    },
+    http,
+    sources::{DataSource, Health, SourceMode},
+    tcp::StateMessage,
+};
+use alert_rules::{AlertKind, AlertRule, Debouncer};
+use forecast::{ForecastUnits, ForecastView};
+use history::HistorySummary;
+
+pub mod air_quality;
+pub mod alert_rules;
+pub mod forecast;
+pub mod history;
+
+const POLL_INTERVAL_SECS: u64 = 30;
+const FORECAST_URL: &str = "https://api.open-meteo.com/v1";
+const GEOCODING_URL: &str = "https://geocoding-api.open-meteo.com/v1";
+const AIR_QUALITY_URL: &str = "https://air-quality-api.open-meteo.com/v1";
+/// The screen keeps every location's forecast in memory and cycles through them.
+pub const MAX_LOCATIONS: usize = 5;
+const CURRENT_VARIABLES: &str = "temperature_2m,apparent_temperature,relative_humidity_2m,wind_speed_10m,\
+wind_direction_10m,precipitation,weather_code,is_day";
+const MINUTELY_VARIABLES: &str = "precipitation,weather_code";
+const HOURLY_VARIABLES: &str = "temperature_2m,weather_code,precipitation_probability,is_day";
+const DAILY_VARIABLES: &str = "weather_code,temperature_2m_max,temperature_2m_min,sunrise,sunset,precipitation_sum";
+const FORECAST_QUARTER_HOURS: usize = 8;
+const FORECAST_HOURS: usize = 24;
+const FORECAST_DAYS: usize = 7;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum Units {
+    #[default]
+    Metric,
+    Imperial,
+}
+
+impl Units {
+    fn temperature_unit(self) -> &'static str {
+        match self {
+            Self::Metric => "celsius",
+            Self::Imperial => "fahrenheit",
+        }
+    }
 
-use crate::tcp::StateMessage;
+    fn precipitation_unit(self) -> &'static str {
+        match self {
+            Self::Metric => "mm",
+            Self::Imperial => "inch",
+        }
+    }
+
+    /// Labels as Open-Meteo reports them in `current_units`.
+    pub fn temperature_label(self) -> &'static str {
+        match self {
+            Self::Metric => "°C",
+            Self::Imperial => "°F",
+        }
+    }
+
+    pub fn precipitation_label(self) -> &'static str {
+        self.precipitation_unit()
+    }
+
+    pub fn temperature(self, celsius: f64) -> f64 {
+        match self {
+            Self::Metric => celsius,
+            Self::Imperial => celsius * 9.0 / 5.0 + 32.0,
+        }
+    }
+
+    pub fn precipitation(self, millimetres: f64) -> f64 {
+        match self {
+            Self::Metric => millimetres,
+            Self::Imperial => millimetres / MM_PER_INCH,
+        }
+    }
+}
+
+/// Named like Open-Meteo's `wind_speed_unit` values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum WindUnit {
+    #[default]
+    Kmh,
+    Ms,
+    Mph,
+    Kn,
+}
+
+impl WindUnit {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Kmh => "kmh",
+            Self::Ms => "ms",
+            Self::Mph => "mph",
+            Self::Kn => "kn",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Kmh => "km/h",
+            Self::Ms => "m/s",
+            Self::Mph => "mph",
+            Self::Kn => "kn",
+        }
+    }
+
+    fn kmh_per_unit(self) -> f64 {
+        match self {
+            Self::Kmh => 1.0,
+            Self::Ms => 3.6,
+            Self::Mph => 1.609344,
+            Self::Kn => 1.852,
+        }
+    }
+
+    pub fn speed(self, kmh: f64) -> f64 {
+        kmh / self.kmh_per_unit()
+    }
+
+    /// Unit whose `label` is `label`, e.g. `mph`.
+    fn from_label(label: &str) -> Option<Self> {
+        [Self::Kmh, Self::Ms, Self::Mph, Self::Kn].into_iter().find(|unit| unit.label() == label)
+    }
+}
+
+const MM_PER_INCH: f64 = 25.4;
+
+/// A temperature Open-Meteo reported in `label` units, in °C.
+pub fn to_celsius(value: f64, label: &str) -> f64 {
+    if label == Units::Imperial.temperature_label() {
+        (value - 32.0) * 5.0 / 9.0
+    } else {
+        value
+    }
+}
+
+pub fn to_kmh(value: f64, label: &str) -> f64 {
+    WindUnit::from_label(label).map_or(value, |unit| value * unit.kmh_per_unit())
+}
+
+pub fn to_millimetres(value: f64, label: &str) -> f64 {
+    if label == Units::Imperial.precipitation_label() {
+        value * MM_PER_INCH
+    } else {
+        value
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct WeatherSettings {
+    pub units: Units,
+    #[serde(rename = "windUnit")]
+    pub wind_unit: WindUnit,
+}
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -19,6 +193,18 @@ pub struct WeatherResponse {
     #[serde(rename = "current_units")]
     pub current_units: CurrentUnits,
     pub current: Current,
+    #[serde(default, rename = "minutely_15_units")]
+    pub minutely_15_units: Minutely15Units,
+    #[serde(default, rename = "minutely_15")]
+    pub minutely_15: Minutely15,
+    #[serde(default, rename = "hourly_units")]
+    pub hourly_units: HourlyUnits,
+    #[serde(default)]
+    pub hourly: Hourly,
+    #[serde(default, rename = "daily_units")]
+    pub daily_units: DailyUnits,
+    #[serde(default)]
+    pub daily: Daily,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -28,6 +214,14 @@ pub struct CurrentUnits {
     pub interval: String,
     #[serde(rename = "temperature_2m")]
     pub temperature_2m: String,
+    #[serde(default, rename = "apparent_temperature")]
+    pub apparent_temperature: String,
+    #[serde(default, rename = "relative_humidity_2m")]
+    pub relative_humidity_2m: String,
+    #[serde(rename = "wind_speed_10m")]
+    pub wind_speed_10m: String,
+    #[serde(default)]
+    pub precipitation: String,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -37,29 +231,881 @@ pub struct Current {
     pub interval: i64,
     #[serde(rename = "temperature_2m")]
     pub temperature_2m: f64,
+    #[serde(default, rename = "apparent_temperature")]
+    pub apparent_temperature: f64,
+    #[serde(default, rename = "relative_humidity_2m")]
+    pub relative_humidity_2m: f64,
+    #[serde(rename = "wind_speed_10m")]
+    pub wind_speed_10m: f64,
+    #[serde(default, rename = "wind_direction_10m")]
+    pub wind_direction_10m: f64,
+    #[serde(default)]
+    pub precipitation: f64,
+    /// WMO weather interpretation code.
+    #[serde(default, rename = "weather_code")]
+    pub weather_code: u8,
+    /// 1 between sunrise and sunset.
+    #[serde(default = "default_is_day", rename = "is_day")]
+    pub is_day: u8,
+}
+
+fn default_is_day() -> u8 {
+    1
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Minutely15Units {
+    #[serde(default)]
+    pub precipitation: String,
+}
+
+/// The next two hours in 15-minute steps, for alerts about rain that is about to start.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Minutely15 {
+    pub time: Vec<String>,
+    pub precipitation: Vec<f64>,
+    #[serde(rename = "weather_code")]
+    pub weather_code: Vec<u8>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HourlyUnits {
+    #[serde(default, rename = "temperature_2m")]
+    pub temperature_2m: String,
+}
+
+/// The next 24 hours, one entry per hour.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hourly {
+    pub time: Vec<String>,
+    #[serde(rename = "temperature_2m")]
+    pub temperature_2m: Vec<f64>,
+    #[serde(rename = "weather_code")]
+    pub weather_code: Vec<u8>,
+    #[serde(default, rename = "precipitation_probability")]
+    pub precipitation_probability: Vec<Option<u8>>,
+    #[serde(default, rename = "is_day")]
+    pub is_day: Vec<u8>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DailyUnits {
+    #[serde(default, rename = "precipitation_sum")]
+    pub precipitation_sum: String,
+}
+
+/// The next 7 days, one entry per day; times are local to the location.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Daily {
+    pub time: Vec<String>,
+    #[serde(rename = "weather_code")]
+    pub weather_code: Vec<u8>,
+    #[serde(rename = "temperature_2m_max")]
+    pub temperature_2m_max: Vec<f64>,
+    #[serde(rename = "temperature_2m_min")]
+    pub temperature_2m_min: Vec<f64>,
+    pub sunrise: Vec<String>,
+    pub sunset: Vec<String>,
+    #[serde(default, rename = "precipitation_sum")]
+    pub precipitation_sum: Vec<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeocodingResponse {
+    #[serde(default)]
+    results: Vec<GeocodingResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeocodingResult {
+    name: String,
+    #[serde(default)]
+    country: String,
+    latitude: f64,
+    longitude: f64,
+    #[serde(default)]
+    timezone: Option<String>,
+}
+
+/// Client for the Open-Meteo forecast, geocoding and air-quality APIs.
+#[derive(Debug, Clone)]
+pub struct OpenMeteo {
+    forecast_url: String,
+    geocoding_url: String,
+    air_quality_url: String,
+}
+
+impl Default for OpenMeteo {
+    fn default() -> Self {
+        Self::with_base_urls(FORECAST_URL, GEOCODING_URL, AIR_QUALITY_URL)
+    }
+}
+
+impl OpenMeteo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_base_urls(forecast_url: &str, geocoding_url: &str, air_quality_url: &str) -> Self {
+        Self {
+            forecast_url: forecast_url.to_string(),
+            geocoding_url: geocoding_url.to_string(),
+            air_quality_url: air_quality_url.to_string(),
+        }
+    }
+
+    /// The best match for a place name such as `Berlin`; `id` is left at 0.
+    pub async fn geocode(&self, name: &str) -> anyhow::Result<Option<WeatherLocation>> {
+        let http = http::shared();
+        let request = http
+            .get(&format!("{}/search", self.geocoding_url))
+            .query(&[("name", name), ("count", "1"), ("language", "en"), ("format", "json")]);
+        let response = http.send(request).await?.error_for_status()?;
+        let data = response.json::<GeocodingResponse>().await?;
+
+        Ok(data.results.into_iter().next().map(|result| WeatherLocation {
+            id: 0,
+            name: result.name,
+            country: result.country,
+            latitude: result.latitude,
+            longitude: result.longitude,
+            timezone: result.timezone.unwrap_or_else(|| "auto".to_string()),
+        }))
+    }
+
+    /// Current conditions plus the hourly forecast for the next day and the daily one for the week.
+    pub async fn forecast(&self, location: &WeatherLocation, settings: &WeatherSettings) -> anyhow::Result<WeatherResponse> {
+        let http = http::shared();
+        let request = http.get(&format!("{}/forecast", self.forecast_url)).query(&[
+            ("latitude", location.latitude.to_string()),
+            ("longitude", location.longitude.to_string()),
+            ("current", CURRENT_VARIABLES.to_string()),
+            ("minutely_15", MINUTELY_VARIABLES.to_string()),
+            ("forecast_minutely_15", FORECAST_QUARTER_HOURS.to_string()),
+            ("hourly", HOURLY_VARIABLES.to_string()),
+            ("daily", DAILY_VARIABLES.to_string()),
+            ("forecast_hours", FORECAST_HOURS.to_string()),
+            ("forecast_days", FORECAST_DAYS.to_string()),
+            ("timezone", location.timezone.clone()),
+            ("temperature_unit", settings.units.temperature_unit().to_string()),
+            ("precipitation_unit", settings.units.precipitation_unit().to_string()),
+            ("wind_speed_unit", settings.wind_unit.as_str().to_string()),
+        ]);
+        let response = http.send(request).await?.error_for_status()?;
+        let data = response.json::<WeatherResponse>().await?;
+        Ok(data)
+    }
+}
+
+/// Current conditions for every configured location from Open-Meteo.
+pub struct WeatherSource {
+    db: SqlitePool,
+    open_meteo: OpenMeteo,
+    retention: chrono::Duration,
+    debouncer: Debouncer,
+}
+
+impl WeatherSource {
+    pub fn new(db: SqlitePool, open_meteo: OpenMeteo) -> Self {
+        Self {
+            db,
+            open_meteo,
+            retention: history::retention(),
+            debouncer: Debouncer::default(),
+        }
+    }
+
+    /// Records the current conditions and summarizes the last day of them.
+    async fn record(&self, location: &WeatherLocation, weather: &WeatherResponse, settings: &WeatherSettings) -> anyhow::Result<Option<HistorySummary>> {
+        save_weather_reading(&self.db, &history::reading(location.id, weather)?).await?;
+
+        let now = Utc::now().naive_utc();
+        let readings = get_weather_history(&self.db, location.id, now - chrono::Duration::days(1)).await?;
+
+        Ok(HistorySummary::new(&readings, now, weather.utc_offset_seconds, settings.units))
+    }
+
+    /// Alerts `rules` raise about the forecast for `location` that weren't raised recently.
+    fn alerts(&self, location: &WeatherLocation, weather: &WeatherResponse, settings: &WeatherSettings, rules: &[AlertRule]) -> Vec<Alert> {
+        let now = Utc::now().naive_utc() + chrono::Duration::seconds(weather.utc_offset_seconds);
+        rules
+            .iter()
+            .filter_map(|rule| Some((rule, rule.evaluate(&location.name, weather, settings, now)?)))
+            .filter(|(rule, _)| {
+                let cooldown = Duration::from_secs(rule.cooldown_minutes.max(0) as u64 * 60);
+                self.debouncer.should_fire(location.id, rule.kind, cooldown)
+            })
+            .map(|(_, alert)| alert)
+            .collect()
+    }
+}
+
+#[async_trait]
+impl DataSource for WeatherSource {
+    fn name(&self) -> &'static str {
+        "weather"
+    }
+
+    fn app(&self) -> &'static str {
+        "Weather"
+    }
+
+    fn mode(&self) -> SourceMode {
+        SourceMode::Poll {
+            interval: Duration::from_secs(POLL_INTERVAL_SECS),
+        }
+    }
+
+    async fn health(&self) -> Health {
+        match get_weather_locations(&self.db).await {
+            Ok(locations) if locations.is_empty() => Health::Unconfigured {
+                reason: "No weather locations configured".to_string(),
+            },
+            Ok(_) => Health::Healthy,
+            Err(e) => Health::Degraded { reason: e.to_string() },
+        }
+    }
+
+    /// A `ForecastView` page per location, followed by alerts about the forecast. Every reading is
+    /// also added to the history. Locations that fail are skipped; the poll only fails when all do.
+    async fn poll(&self) -> anyhow::Result<Vec<StateMessage>> {
+        let locations = get_weather_locations(&self.db).await?;
+        let settings = get_weather_settings(&self.db).await?;
+        let rules = get_weather_alert_rules(&self.db).await?;
+
+        let mut messages = Vec::new();
+        let mut alerts = Vec::new();
+        let mut failure = None;
+        for (index, location) in locations.iter().enumerate() {
+            let weather = match self.open_meteo.forecast(location, &settings).await {
+                Ok(weather) => weather,
+                Err(e) => {
+                    println!("Failed to get the forecast for {}: {}", location.name, e);
+                    failure = Some(e);
+                    continue;
+                }
+            };
+            let mut view = ForecastView::new(&location.name, index, locations.len(), &weather);
+            match self.record(location, &weather, &settings).await {
+                Ok(history) => view.history = history,
+                Err(e) => println!("Failed to record weather for {}: {}", location.name, e),
+            }
+            messages.push(StateMessage::data(self.app(), serde_json::to_string(&view)?));
+            alerts.extend(self.alerts(location, &weather, &settings, &rules));
+        }
+        if let (Some(e), true) = (failure, messages.is_empty()) {
+            return Err(e);
+        }
+
+        for alert in alerts {
+            messages.push(alert.to_message()?);
+        }
+
+        delete_weather_readings_before(&self.db, Utc::now().naive_utc() - self.retention).await?;
+
+        Ok(messages)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct WeatherLocationResponse {
+    id: i64,
+    name: String,
+    country: String,
+    latitude: f64,
+    longitude: f64,
+    timezone: String,
+}
+
+impl From<WeatherLocation> for WeatherLocationResponse {
+    fn from(location: WeatherLocation) -> Self {
+        Self {
+            id: location.id,
+            name: location.name,
+            country: location.country,
+            latitude: location.latitude,
+            longitude: location.longitude,
+            timezone: location.timezone,
+        }
+    }
+}
+
+pub async fn list_locations(
+    State((db, _)): State<(SqlitePool, OpenMeteo)>,
+) -> ApiResult<Json<Vec<WeatherLocationResponse>>> {
+    let locations = get_weather_locations(&db).await.map_err(ApiError::Database)?;
+
+    Ok(Json(locations.into_iter().map(WeatherLocationResponse::from).collect()))
 }
 
-pub async fn get_weather_data() -> anyhow::Result<WeatherResponse> {
-    let url = "https://api.open-meteo.com/v1/forecast?latitude=54.3523&longitude=18.6491&current=temperature_2m&timezone=Europe%2FBerlin";
-    let response = reqwest::get(url).await?;
-    let data = response.json::<WeatherResponse>().await?;
-    Ok(data)
+#[derive(Deserialize)]
+pub struct AddLocationPayload {
+    name: String,
 }
 
-pub async fn weather_polling_task(
-    sender: mpsc::Sender<StateMessage>,
-) -> anyhow::Result<()> {
-    let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
-    loop {
-        interval.tick().await;
-        let weather_data = get_weather_data().await?;
-        
-        let payload = format!(
-            "Weather: {}°C",
-            weather_data.current.temperature_2m
-        );
+/// Resolves `name` through the geocoding API and stores the best match.
+pub async fn add_location(
+    State((db, open_meteo)): State<(SqlitePool, OpenMeteo)>,
+    ApiJson(payload): ApiJson<AddLocationPayload>,
+) -> ApiResult<(StatusCode, Json<WeatherLocationResponse>)> {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(ApiError::validation("name is required"));
+    }
 
-        sender.send(StateMessage::WeatherData(payload)).await?;
-        
+    let locations = get_weather_locations(&db).await.map_err(ApiError::Database)?;
+    if locations.len() >= MAX_LOCATIONS {
+        return Err(ApiError::validation(format!("At most {} locations can be configured", MAX_LOCATIONS)));
     }
-}
\ No newline at end of file
+
+    let location = open_meteo
+        .geocode(name)
+        .await
+        .map_err(|e| ApiError::Upstream(e.to_string()))?
+        .ok_or_else(|| ApiError::not_found(format!("No location named {}", name)))?;
+    let location = add_weather_location(&db, location).await.map_err(ApiError::Database)?;
+
+    Ok((StatusCode::CREATED, Json(location.into())))
+}
+
+pub async fn delete_location(
+    State((db, _)): State<(SqlitePool, OpenMeteo)>,
+    Path(id): Path<i64>,
+) -> ApiResult<StatusCode> {
+    if !delete_weather_location(&db, id).await.map_err(ApiError::Database)? {
+        return Err(ApiError::not_found(format!("Unknown location {}", id)));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_settings(State((db, _)): State<(SqlitePool, OpenMeteo)>) -> ApiResult<Json<WeatherSettings>> {
+    let settings = get_weather_settings(&db).await.map_err(ApiError::Database)?;
+
+    Ok(Json(settings))
+}
+
+pub async fn put_settings(
+    State((db, _)): State<(SqlitePool, OpenMeteo)>,
+    ApiJson(settings): ApiJson<WeatherSettings>,
+) -> ApiResult<Json<WeatherSettings>> {
+    save_weather_settings(&db, &settings).await.map_err(ApiError::Database)?;
+
+    Ok(Json(settings))
+}
+
+pub async fn list_alert_rules(State((db, _)): State<(SqlitePool, OpenMeteo)>) -> ApiResult<Json<Vec<AlertRule>>> {
+    let rules = get_weather_alert_rules(&db).await.map_err(ApiError::Database)?;
+
+    Ok(Json(rules))
+}
+
+#[derive(Deserialize)]
+pub struct AlertRulePayload {
+    enabled: bool,
+    threshold: f64,
+    #[serde(rename = "withinMinutes")]
+    within_minutes: i64,
+    #[serde(rename = "cooldownMinutes")]
+    cooldown_minutes: i64,
+}
+
+pub async fn put_alert_rule(
+    State((db, _)): State<(SqlitePool, OpenMeteo)>,
+    Path(kind): Path<String>,
+    ApiJson(payload): ApiJson<AlertRulePayload>,
+) -> ApiResult<Json<AlertRule>> {
+    let kind = kind.parse::<AlertKind>().map_err(|e| ApiError::not_found(e.to_string()))?;
+    if !payload.threshold.is_finite() {
+        return Err(ApiError::validation("threshold must be a number"));
+    }
+    if kind == AlertKind::Rain && payload.threshold < 0.0 {
+        return Err(ApiError::validation("threshold must not be negative"));
+    }
+    if !(alert_rules::MIN_WITHIN_MINUTES..=kind.max_within_minutes()).contains(&payload.within_minutes) {
+        return Err(ApiError::validation(format!(
+            "withinMinutes must be between {} and {}",
+            alert_rules::MIN_WITHIN_MINUTES,
+            kind.max_within_minutes()
+        )));
+    }
+    if !(alert_rules::MIN_COOLDOWN_MINUTES..=alert_rules::MAX_COOLDOWN_MINUTES).contains(&payload.cooldown_minutes) {
+        return Err(ApiError::validation(format!(
+            "cooldownMinutes must be between {} and {}",
+            alert_rules::MIN_COOLDOWN_MINUTES,
+            alert_rules::MAX_COOLDOWN_MINUTES
+        )));
+    }
+
+    let rule = AlertRule {
+        kind,
+        enabled: payload.enabled,
+        threshold: payload.threshold,
+        within_minutes: payload.within_minutes,
+        cooldown_minutes: payload.cooldown_minutes,
+    };
+    save_weather_alert_rule(&db, &rule).await.map_err(ApiError::Database)?;
+
+    Ok(Json(rule))
+}
+
+#[derive(Deserialize)]
+pub struct HistoryParams {
+    #[serde(rename = "locationId")]
+    location_id: Option<i64>,
+    hours: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WeatherReadingResponse {
+    #[serde(rename = "recordedAt")]
+    recorded_at: chrono::NaiveDateTime,
+    temperature: f64,
+    #[serde(rename = "apparentTemperature")]
+    apparent_temperature: f64,
+    humidity: f64,
+    #[serde(rename = "windSpeed")]
+    wind_speed: f64,
+    precipitation: f64,
+    #[serde(rename = "weatherCode")]
+    weather_code: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LocationHistoryResponse {
+    #[serde(rename = "locationId")]
+    location_id: i64,
+    name: String,
+    units: ForecastUnits,
+    readings: Vec<WeatherReadingResponse>,
+}
+
+/// Readings of the last `hours` (24 by default) for every location or just `locationId`, in the
+/// configured units.
+pub async fn get_history(
+    State((db, _)): State<(SqlitePool, OpenMeteo)>,
+    Query(params): Query<HistoryParams>,
+) -> ApiResult<Json<Vec<LocationHistoryResponse>>> {
+    let hours = params.hours.unwrap_or(24);
+    let max_hours = history::retention().num_hours();
+    if !(1..=max_hours).contains(&hours) {
+        return Err(ApiError::validation(format!("hours must be between 1 and {}", max_hours)));
+    }
+
+    let mut locations = get_weather_locations(&db).await.map_err(ApiError::Database)?;
+    if let Some(location_id) = params.location_id {
+        locations.retain(|location| location.id == location_id);
+        if locations.is_empty() {
+            return Err(ApiError::not_found(format!("Unknown location {}", location_id)));
+        }
+    }
+
+    let settings = get_weather_settings(&db).await.map_err(ApiError::Database)?;
+    let since = Utc::now().naive_utc() - chrono::Duration::hours(hours);
+    let mut histories = Vec::new();
+    for location in locations {
+        let readings = get_weather_history(&db, location.id, since).await.map_err(ApiError::Database)?;
+        histories.push(LocationHistoryResponse {
+            location_id: location.id,
+            name: location.name,
+            units: ForecastUnits::from(&settings),
+            readings: readings
+                .into_iter()
+                .map(|reading| WeatherReadingResponse {
+                    recorded_at: reading.recorded_at,
+                    temperature: settings.units.temperature(reading.temperature),
+                    apparent_temperature: settings.units.temperature(reading.apparent_temperature),
+                    humidity: reading.humidity,
+                    wind_speed: settings.wind_unit.speed(reading.wind_speed),
+                    precipitation: settings.units.precipitation(reading.precipitation),
+                    weather_code: reading.weather_code,
+                })
+                .collect(),
+        });
+    }
+
+    Ok(Json(histories))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use axum::{
+        body::{to_bytes, Body},
+        extract::{Query, State},
+        http::{header, Method, Request, StatusCode},
+        routing::get,
+        Json, Router,
+    };
+    use chrono::Timelike;
+    use serde_json::{json, Value};
+    use sqlx::SqlitePool;
+    use tower::ServiceExt;
+
+    use super::{OpenMeteo, Units, WeatherLocation, WeatherSettings, WeatherSource, WindUnit};
+    use crate::{
+        alerts,
+        db::{get_weather_locations, initialize_test_db, save_weather_settings},
+        sources::{DataSource, Sources},
+        supervisor::Supervisor,
+        tcp::StateMessage,
+        test_utils::spawn_mock_server,
+        web::{auth, create_router, oauth2::refresher::TokenRefresher, spotify::SpotifyPlayer},
+    };
+
+    type Queries = Arc<Mutex<Vec<HashMap<String, String>>>>;
+
+    async fn search(Query(query): Query<HashMap<String, String>>) -> Json<Value> {
+        if query["name"] != "Berlin" {
+            return Json(json!({ "generationtime_ms": 0.1 }));
+        }
+
+        Json(json!({
+            "results": [{
+                "id": 2950159,
+                "name": "Berlin",
+                "latitude": 52.52437,
+                "longitude": 13.41053,
+                "timezone": "Europe/Berlin",
+                "country": "Germany"
+            }]
+        }))
+    }
+
+    async fn forecast(
+        State(queries): State<Queries>,
+        Query(query): Query<HashMap<String, String>>,
+    ) -> Result<Json<Value>, StatusCode> {
+        // Null Island stands in for a location Open-Meteo rejects.
+        if query["latitude"] == "0" {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        let imperial = query["temperature_unit"] == "fahrenheit";
+        queries.lock().unwrap().push(query);
+        let local = chrono::Utc::now().naive_utc() + chrono::Duration::hours(2);
+        let quarter = local.date().and_hms_opt(local.hour(), local.minute() / 15 * 15, 0).unwrap();
+
+        Ok(Json(json!({
+            "latitude": 54.35,
+            "longitude": 18.65,
+            "generationtime_ms": 0.02,
+            "utc_offset_seconds": 7200,
+            "timezone": "Europe/Warsaw",
+            "timezone_abbreviation": "CEST",
+            "elevation": 10.0,
+            "current_units": {
+                "time": "iso8601",
+                "interval": "seconds",
+                "temperature_2m": if imperial { "°F" } else { "°C" },
+                "wind_speed_10m": if imperial { "mph" } else { "km/h" },
+                "precipitation": if imperial { "inch" } else { "mm" }
+            },
+            "current": {
+                // Recent, so readings fall inside the history window.
+                "time": (chrono::Utc::now() + chrono::Duration::hours(2)).format("%Y-%m-%dT%H:00").to_string(),
+                "interval": 900,
+                "temperature_2m": if imperial { 54.5 } else { 12.5 },
+                "wind_speed_10m": if imperial { 8.8 } else { 14.2 },
+                "weather_code": 3,
+                "is_day": 1
+            },
+            "minutely_15_units": { "precipitation": if imperial { "inch" } else { "mm" } },
+            // Dry for half an hour, then raining.
+            "minutely_15": {
+                "time": (1..=4).map(|step| (quarter + chrono::Duration::minutes(15 * step)).format("%Y-%m-%dT%H:%M").to_string()).collect::<Vec<_>>(),
+                "precipitation": [0.0, 0.0, 1.2, 0.4],
+                "weather_code": [3, 3, 61, 61]
+            },
+            "hourly_units": { "temperature_2m": if imperial { "°F" } else { "°C" } },
+            "hourly": {
+                "time": ["2026-10-19T12:00", "2026-10-19T13:00"],
+                "temperature_2m": [12.5, 13.1],
+                "weather_code": [3, 61],
+                "precipitation_probability": [10, null]
+            },
+            "daily": {
+                "time": ["2026-10-19"],
+                "weather_code": [61],
+                "temperature_2m_max": [14.2],
+                "temperature_2m_min": [8.1],
+                "sunrise": ["2026-10-19T07:12"],
+                "sunset": ["2026-10-19T17:40"]
+            }
+        })))
+    }
+
+    /// Open-Meteo on a local port, recording the query of every forecast request.
+    async fn spawn_open_meteo() -> (OpenMeteo, Queries) {
+        let queries = Queries::default();
+        let router = Router::new()
+            .route("/v1/search", get(search))
+            .route("/v1/forecast", get(forecast))
+            .with_state(queries.clone());
+        let base_url = format!("{}/v1", spawn_mock_server(router).await);
+
+        (OpenMeteo::with_base_urls(&base_url, &base_url, &base_url), queries)
+    }
+
+    async fn send(db: &SqlitePool, open_meteo: OpenMeteo, method: Method, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+        let token = auth::create_test_token(db, &[auth::SCOPE_WEATHER]).await;
+        let player = SpotifyPlayer::new(TokenRefresher::new(db.clone()));
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+            .unwrap();
+
+        let response = create_router(db.clone(), player, open_meteo, Sources::new(Supervisor::new(), Vec::new()))
+            .oneshot(request)
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    fn views(messages: Vec<StateMessage>) -> Vec<Value> {
+        messages
+            .into_iter()
+            .filter_map(|message| match message {
+                StateMessage::Data { app: "Weather", payload } => Some(serde_json::from_str(&payload).unwrap()),
+                StateMessage::Data { app: alerts::APP, .. } => None,
+                _ => panic!("expected a weather message"),
+            })
+            .collect()
+    }
+
+    fn alert_titles(messages: &[StateMessage]) -> Vec<String> {
+        messages
+            .iter()
+            .filter_map(|message| match message {
+                StateMessage::Data { app: alerts::APP, payload } => {
+                    let alert: Value = serde_json::from_str(payload).unwrap();
+                    assert_eq!(alert["source"], "weather");
+                    Some(alert["title"].as_str().unwrap().to_string())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn poll_reports_every_location_in_the_configured_units() {
+        let db = initialize_test_db().await;
+        let (open_meteo, queries) = spawn_open_meteo().await;
+        let source = WeatherSource::new(db.clone(), open_meteo.clone());
+        let berlin = open_meteo.geocode("Berlin").await.unwrap().unwrap();
+        crate::db::add_weather_location(&db, berlin).await.unwrap();
+
+        let pages = views(source.poll().await.unwrap());
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[1]["location"], "Berlin");
+        assert_eq!((pages[1]["index"].clone(), pages[1]["count"].clone()), (json!(1), json!(2)));
+        assert_eq!(pages[0]["temp"], 12.5);
+        assert_eq!(pages[0]["units"], json!({ "temp": "°C", "wind": "km/h", "precip": "mm" }));
+        assert_eq!(pages[0]["icon"], "cloudy");
+        assert_eq!(pages[0]["hours"][0], json!({ "hour": "12", "temp": 13, "icon": "cloudy", "pop": 10 }));
+        assert_eq!(pages[0]["days"][0], json!({ "day": "Mon", "hi": 14, "lo": 8, "icon": "rain" }));
+        let query = queries.lock().unwrap()[1].clone();
+        assert_eq!(query["latitude"], "52.52437");
+        assert_eq!(query["timezone"], "Europe/Berlin");
+        assert_eq!(query["forecast_hours"], "24");
+        assert_eq!(query["forecast_days"], "7");
+
+        let settings = WeatherSettings { units: Units::Imperial, wind_unit: WindUnit::Mph };
+        save_weather_settings(&db, &settings).await.unwrap();
+
+        let pages = views(source.poll().await.unwrap());
+        assert_eq!(pages[0]["temp"], 54.5);
+        assert_eq!(pages[0]["units"], json!({ "temp": "°F", "wind": "mph", "precip": "inch" }));
+        let query = queries.lock().unwrap()[2].clone();
+        assert_eq!(query["temperature_unit"], "fahrenheit");
+        assert_eq!(query["precipitation_unit"], "inch");
+        assert_eq!(query["wind_speed_unit"], "mph");
+    }
+
+    #[tokio::test]
+    async fn a_failing_location_does_not_stop_the_others() {
+        let db = initialize_test_db().await;
+        let (open_meteo, _) = spawn_open_meteo().await;
+        let source = WeatherSource::new(db.clone(), open_meteo);
+        let gdansk = get_weather_locations(&db).await.unwrap().remove(0);
+        let null_island = WeatherLocation { name: "Null Island".to_string(), latitude: 0.0, longitude: 0.0, ..gdansk.clone() };
+        crate::db::add_weather_location(&db, null_island).await.unwrap();
+
+        let pages = views(source.poll().await.unwrap());
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0]["location"], "Gdańsk");
+
+        crate::db::delete_weather_location(&db, gdansk.id).await.unwrap();
+        assert!(source.poll().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn readings_are_recorded_once_and_served_as_history() {
+        let db = initialize_test_db().await;
+        let (open_meteo, _) = spawn_open_meteo().await;
+        let source = WeatherSource::new(db.clone(), open_meteo.clone());
+
+        source.poll().await.unwrap();
+        let pages = views(source.poll().await.unwrap());
+        assert_eq!((pages[0]["min"].clone(), pages[0]["max"].clone()), (json!(12.5), json!(12.5)));
+        assert_eq!(pages[0]["trend"], "steady");
+        assert_eq!(pages[0]["spark"][11], 12.5);
+
+        let settings = WeatherSettings { units: Units::Imperial, wind_unit: WindUnit::Ms };
+        save_weather_settings(&db, &settings).await.unwrap();
+        let (status, history) = send(&db, open_meteo.clone(), Method::GET, "/weather/history?locationId=1", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(history[0]["name"], "Gdańsk");
+        assert_eq!(history[0]["units"], json!({ "temp": "°F", "wind": "m/s", "precip": "inch" }));
+        let readings = history[0]["readings"].as_array().unwrap();
+        assert_eq!(readings.len(), 1);
+        assert_eq!(readings[0]["temperature"], 54.5);
+        assert!((readings[0]["windSpeed"].as_f64().unwrap() - 14.2 / 3.6).abs() < 1e-9);
+
+        let (status, _) = send(&db, open_meteo.clone(), Method::GET, "/weather/history?locationId=7", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = send(&db, open_meteo, Method::GET, "/weather/history?hours=0", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn source_without_locations_is_unconfigured() {
+        let db = initialize_test_db().await;
+        let (open_meteo, queries) = spawn_open_meteo().await;
+        let source = WeatherSource::new(db.clone(), open_meteo);
+        let (status, _) = send(&db, source.open_meteo.clone(), Method::DELETE, "/weather/locations/1", None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        assert!(source.poll().await.unwrap().is_empty());
+        assert!(queries.lock().unwrap().is_empty());
+        assert!(matches!(source.health().await, crate::sources::Health::Unconfigured { .. }));
+    }
+
+    #[tokio::test]
+    async fn locations_are_added_by_name() {
+        let db = initialize_test_db().await;
+        let (open_meteo, _) = spawn_open_meteo().await;
+
+        let (status, location) =
+            send(&db, open_meteo.clone(), Method::POST, "/weather/locations", Some(json!({ "name": "Berlin" }))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(location["name"], "Berlin");
+        assert_eq!(location["country"], "Germany");
+        assert_eq!(location["timezone"], "Europe/Berlin");
+
+        let (status, locations) = send(&db, open_meteo, Method::GET, "/weather/locations", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(locations.as_array().unwrap().len(), 2);
+        assert_eq!(locations[1]["id"], location["id"]);
+    }
+
+    #[tokio::test]
+    async fn unknown_location_is_not_found() {
+        let db = initialize_test_db().await;
+        let (open_meteo, _) = spawn_open_meteo().await;
+
+        let (status, problem) =
+            send(&db, open_meteo, Method::POST, "/weather/locations", Some(json!({ "name": "Atlantis" }))).await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(problem["detail"], "No location named Atlantis");
+        assert_eq!(get_weather_locations(&db).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn location_count_is_limited() {
+        let db = initialize_test_db().await;
+        let (open_meteo, _) = spawn_open_meteo().await;
+        for _ in 1..super::MAX_LOCATIONS {
+            let (status, _) =
+                send(&db, open_meteo.clone(), Method::POST, "/weather/locations", Some(json!({ "name": "Berlin" }))).await;
+            assert_eq!(status, StatusCode::CREATED);
+        }
+
+        let (status, problem) =
+            send(&db, open_meteo, Method::POST, "/weather/locations", Some(json!({ "name": "Berlin" }))).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(problem["detail"], "At most 5 locations can be configured");
+    }
+
+    #[tokio::test]
+    async fn settings_can_be_changed() {
+        let db = initialize_test_db().await;
+        let (open_meteo, _) = spawn_open_meteo().await;
+
+        let (status, settings) = send(&db, open_meteo.clone(), Method::GET, "/weather/settings", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(settings, json!({ "units": "metric", "windUnit": "kmh" }));
+
+        let body = json!({ "units": "imperial", "windUnit": "kn" });
+        let (status, _) = send(&db, open_meteo.clone(), Method::PUT, "/weather/settings", Some(body.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (_, settings) = send(&db, open_meteo.clone(), Method::GET, "/weather/settings", None).await;
+        assert_eq!(settings, body);
+
+        let (status, _) =
+            send(&db, open_meteo, Method::PUT, "/weather/settings", Some(json!({ "units": "kelvin", "windUnit": "kn" }))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn rain_ahead_alerts_once_per_cooldown() {
+        let db = initialize_test_db().await;
+        let (open_meteo, _) = spawn_open_meteo().await;
+        let source = WeatherSource::new(db.clone(), open_meteo);
+
+        let messages = source.poll().await.unwrap();
+        let titles = alert_titles(&messages);
+        assert_eq!(titles.len(), 1);
+        assert!(titles[0].starts_with("Rain in ") && titles[0].ends_with(" min"), "{}", titles[0]);
+        // The alert follows the pages.
+        assert_eq!(views(messages).len(), 1);
+
+        assert!(alert_titles(&source.poll().await.unwrap()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn alert_rules_can_be_changed() {
+        let db = initialize_test_db().await;
+        let (open_meteo, _) = spawn_open_meteo().await;
+        let rule = json!({ "enabled": false, "threshold": 0.5, "withinMinutes": 30, "cooldownMinutes": 90 });
+
+        let (status, rules) = send(&db, open_meteo.clone(), Method::GET, "/weather/alert-rules", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(rules.as_array().unwrap().len(), 4);
+        assert_eq!(rules[0], json!({ "kind": "rain", "enabled": true, "threshold": 0.1, "withinMinutes": 60, "cooldownMinutes": 60 }));
+
+        let (status, saved) = send(&db, open_meteo.clone(), Method::PUT, "/weather/alert-rules/rain", Some(rule)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(saved["cooldownMinutes"], 90);
+        let (_, rules) = send(&db, open_meteo.clone(), Method::GET, "/weather/alert-rules", None).await;
+        assert_eq!(rules[0], saved);
+        let source = WeatherSource::new(db.clone(), open_meteo.clone());
+        assert!(alert_titles(&source.poll().await.unwrap()).is_empty());
+
+        let too_far = json!({ "enabled": true, "threshold": 0.1, "withinMinutes": 180, "cooldownMinutes": 60 });
+        let (status, body) = send(&db, open_meteo.clone(), Method::PUT, "/weather/alert-rules/rain", Some(too_far)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["detail"], "withinMinutes must be between 15 and 120");
+
+        let no_cooldown = json!({ "enabled": true, "threshold": 0.1, "withinMinutes": 60, "cooldownMinutes": 0 });
+        let (status, body) = send(&db, open_meteo.clone(), Method::PUT, "/weather/alert-rules/rain", Some(no_cooldown)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["detail"], "cooldownMinutes must be between 1 and 10080");
+
+        let hail = json!({ "enabled": true, "threshold": 1, "withinMinutes": 60, "cooldownMinutes": 60 });
+        let (status, _) = send(&db, open_meteo, Method::PUT, "/weather/alert-rules/hail", Some(hail)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,324 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    alerts::{Alert, AlertLevel},
+    money::MoneyFormat,
+};
+
+/// How far back `price_move` rules can look; ticks are kept for this long.
+pub const MAX_WINDOW_MINUTES: i64 = 240;
+/// Rules are checked on every tick while their condition holds, so they need some cooldown.
+pub const MIN_COOLDOWN_MINUTES: i64 = 1;
+pub const MAX_COOLDOWN_MINUTES: i64 = 10080;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum AlertKind {
+    /// Total floating P/L below `threshold`.
+    ProfitBelow,
+    ProfitAbove,
+    /// The bid of `symbol` below `threshold`.
+    PriceBelow,
+    PriceAbove,
+    /// The bid of `symbol` moved by at least `threshold` percent within `window_minutes`.
+    PriceMove,
+}
+
+impl AlertKind {
+    /// Price rules watch the ticks of one instrument; profit rules the whole account.
+    pub fn needs_symbol(self) -> bool {
+        matches!(self, Self::PriceBelow | Self::PriceAbove | Self::PriceMove)
+    }
+}
+
+/// A threshold the XTB streams are checked against. Profits are in the account currency.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AlertRule {
+    pub id: i64,
+    pub kind: AlertKind,
+    /// Only for price rules.
+    pub symbol: Option<String>,
+    pub threshold: f64,
+    /// Only for `price_move`.
+    #[serde(rename = "windowMinutes")]
+    pub window_minutes: i64,
+    /// How long to stay quiet after the rule alerted.
+    #[serde(rename = "cooldownMinutes")]
+    pub cooldown_minutes: i64,
+    pub enabled: bool,
+}
+
+/// An alert raised by a rule, kept for the history.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fired {
+    pub rule_id: i64,
+    pub alert: Alert,
+}
+
+fn format_price(price: f64) -> String {
+    // Drops the float noise without cutting the 5 decimals of currency pairs.
+    let price = (price * 100_000.0).round() / 100_000.0;
+    price.to_string()
+}
+
+/// Checks the enabled rules against profits and ticks as they arrive.
+#[derive(Debug)]
+pub struct Monitor {
+    rules: Vec<AlertRule>,
+    /// Recent bids of instruments with `price_move` rules, oldest first.
+    prices: HashMap<String, VecDeque<(Instant, f64)>>,
+    fired: HashMap<i64, Instant>,
+}
+
+impl Monitor {
+    pub fn new(rules: Vec<AlertRule>) -> Self {
+        Self {
+            rules: rules.into_iter().filter(|rule| rule.enabled).collect(),
+            prices: HashMap::new(),
+            fired: HashMap::new(),
+        }
+    }
+
+    /// Carries over the cooldowns and recent prices of `previous` for the rules that are still
+    /// there, so changing one rule doesn't alert again for the others.
+    pub fn keep_state(&mut self, previous: &Monitor) {
+        let symbols = self.symbols();
+        self.fired = previous
+            .fired
+            .iter()
+            .filter(|(id, _)| self.rules.iter().any(|rule| rule.id == **id))
+            .map(|(&id, &at)| (id, at))
+            .collect();
+        self.prices = previous
+            .prices
+            .iter()
+            .filter(|(symbol, _)| symbols.contains(symbol))
+            .map(|(symbol, prices)| (symbol.clone(), prices.clone()))
+            .collect();
+    }
+
+    /// Instruments whose ticks the rules need.
+    pub fn symbols(&self) -> Vec<String> {
+        let mut symbols: Vec<_> = self.rules.iter().filter_map(|rule| rule.symbol.clone()).collect();
+        symbols.sort();
+        symbols.dedup();
+        symbols
+    }
+
+    /// Whether `rule` may alert again; if so, its cooldown starts now. Rules stored before the
+    /// cooldown had a minimum still get it.
+    fn should_fire(&mut self, rule: &AlertRule, now: Instant) -> bool {
+        let cooldown = Duration::from_secs(rule.cooldown_minutes.max(MIN_COOLDOWN_MINUTES) as u64 * 60);
+        if self.fired.get(&rule.id).is_some_and(|&at| now.duration_since(at) < cooldown) {
+            return false;
+        }
+        self.fired.insert(rule.id, now);
+        true
+    }
+
+    /// Thresholds are in the account currency; the alerts show amounts as `money` formats them.
+    pub fn on_profit(&mut self, profit: f64, money: &MoneyFormat, now: Instant) -> Vec<Fired> {
+        let mut fired = Vec::new();
+        for rule in self.rules.clone() {
+            let alert = match rule.kind {
+                AlertKind::ProfitBelow if profit < rule.threshold => Alert::new(
+                    "xtb",
+                    AlertLevel::Severe,
+                    format!("P/L below {}", money.format(rule.threshold)),
+                    format!("Floating P/L is {}", money.format(profit)),
+                ),
+                AlertKind::ProfitAbove if profit > rule.threshold => Alert::new(
+                    "xtb",
+                    AlertLevel::Warning,
+                    format!("P/L above {}", money.format(rule.threshold)),
+                    format!("Floating P/L is {}", money.format(profit)),
+                ),
+                _ => continue,
+            };
+            if self.should_fire(&rule, now) {
+                fired.push(Fired { rule_id: rule.id, alert });
+            }
+        }
+        fired
+    }
+
+    pub fn on_price(&mut self, symbol: &str, bid: f64, now: Instant) -> Vec<Fired> {
+        let window = self
+            .rules
+            .iter()
+            .filter(|rule| rule.kind == AlertKind::PriceMove && rule.symbol.as_deref() == Some(symbol))
+            .map(|rule| rule.window_minutes)
+            .max();
+        if let Some(window) = window {
+            let prices = self.prices.entry(symbol.to_string()).or_default();
+            prices.push_back((now, bid));
+            let keep = Duration::from_secs(window as u64 * 60);
+            while prices.front().is_some_and(|&(at, _)| now.duration_since(at) > keep) {
+                prices.pop_front();
+            }
+        }
+
+        let mut fired = Vec::new();
+        for rule in self.rules.clone() {
+            if rule.symbol.as_deref() != Some(symbol) {
+                continue;
+            }
+            let alert = match rule.kind {
+                AlertKind::PriceBelow if bid < rule.threshold => Alert::new(
+                    "xtb",
+                    AlertLevel::Warning,
+                    format!("{} below {}", symbol, rule.threshold),
+                    format!("Bid is {}", format_price(bid)),
+                ),
+                AlertKind::PriceAbove if bid > rule.threshold => Alert::new(
+                    "xtb",
+                    AlertLevel::Warning,
+                    format!("{} above {}", symbol, rule.threshold),
+                    format!("Bid is {}", format_price(bid)),
+                ),
+                AlertKind::PriceMove => {
+                    let window = Duration::from_secs(rule.window_minutes as u64 * 60);
+                    let Some(&(_, start)) = self.prices[symbol].iter().find(|&&(at, _)| now.duration_since(at) <= window)
+                    else {
+                        continue;
+                    };
+                    if start == 0.0 {
+                        continue;
+                    }
+                    let change = (bid - start) / start * 100.0;
+                    if change.abs() < rule.threshold {
+                        continue;
+                    }
+                    Alert::new(
+                        "xtb",
+                        AlertLevel::Warning,
+                        format!(
+                            "{} {} {:.1}% in {} min",
+                            symbol,
+                            if change > 0.0 { "up" } else { "down" },
+                            change.abs(),
+                            rule.window_minutes
+                        ),
+                        format!("From {} to {}", format_price(start), format_price(bid)),
+                    )
+                }
+                _ => continue,
+            };
+            if self.should_fire(&rule, now) {
+                fired.push(Fired { rule_id: rule.id, alert });
+            }
+        }
+        fired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{AlertKind, AlertRule, Monitor};
+    use crate::{
+        alerts::AlertLevel,
+        money::{Locale, MoneyFormat, MoneySettings},
+    };
+
+    fn rule(id: i64, kind: AlertKind, symbol: Option<&str>, threshold: f64) -> AlertRule {
+        AlertRule {
+            id,
+            kind,
+            symbol: symbol.map(str::to_string),
+            threshold,
+            window_minutes: 15,
+            cooldown_minutes: 30,
+            enabled: true,
+        }
+    }
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    #[test]
+    fn profit_rules_fire_once_per_cooldown() {
+        let start = Instant::now();
+        let money = MoneyFormat::new(&MoneySettings { locale: Locale::Pl, display_currency: None }, Some("PLN"), &[]);
+        let mut monitor = Monitor::new(vec![
+            rule(1, AlertKind::ProfitBelow, None, -200.0),
+            rule(2, AlertKind::ProfitAbove, None, 500.0),
+            AlertRule { enabled: false, ..rule(3, AlertKind::ProfitBelow, None, 0.0) },
+        ]);
+
+        assert!(monitor.on_profit(-150.0, &money, start).is_empty());
+        let fired = monitor.on_profit(-215.3, &money, start + minutes(1));
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].rule_id, 1);
+        assert_eq!(fired[0].alert.level, AlertLevel::Severe);
+        assert_eq!(fired[0].alert.title, "P/L below -200,00 PLN");
+        assert_eq!(fired[0].alert.message, "Floating P/L is -215,30 PLN");
+
+        assert!(monitor.on_profit(-250.0, &money, start + minutes(20)).is_empty());
+        assert_eq!(monitor.on_profit(-250.0, &money, start + minutes(31)).len(), 1);
+        assert_eq!(monitor.on_profit(600.0, &money, start + minutes(32))[0].alert.title, "P/L above 500,00 PLN");
+    }
+
+    #[test]
+    fn rules_without_a_cooldown_still_wait_a_minute() {
+        let start = Instant::now();
+        let mut monitor = Monitor::new(vec![AlertRule { cooldown_minutes: 0, ..rule(1, AlertKind::PriceBelow, Some("US500"), 5000.0) }]);
+
+        assert_eq!(monitor.on_price("US500", 4990.0, start).len(), 1);
+        assert!(monitor.on_price("US500", 4980.0, start + Duration::from_secs(1)).is_empty());
+        assert_eq!(monitor.on_price("US500", 4970.0, start + minutes(1)).len(), 1);
+    }
+
+    #[test]
+    fn reloaded_rules_keep_their_cooldowns() {
+        let start = Instant::now();
+        let mut monitor = Monitor::new(vec![
+            rule(1, AlertKind::PriceBelow, Some("US500"), 5000.0),
+            rule(2, AlertKind::PriceAbove, Some("GOLD"), 2000.0),
+        ]);
+        assert_eq!(monitor.on_price("US500", 4990.0, start).len(), 1);
+        assert_eq!(monitor.on_price("GOLD", 2010.0, start).len(), 1);
+
+        let mut reloaded = Monitor::new(vec![rule(1, AlertKind::PriceBelow, Some("US500"), 4995.0)]);
+        reloaded.keep_state(&monitor);
+        assert!(reloaded.on_price("US500", 4980.0, start + minutes(1)).is_empty());
+
+        // Rule 2 was removed above, so adding it back starts without a cooldown.
+        let mut readded = Monitor::new(vec![rule(2, AlertKind::PriceAbove, Some("GOLD"), 2000.0)]);
+        readded.keep_state(&reloaded);
+        assert_eq!(readded.on_price("GOLD", 2010.0, start + minutes(2)).len(), 1);
+    }
+
+    #[test]
+    fn price_move_compares_against_the_window() {
+        let start = Instant::now();
+        let mut monitor = Monitor::new(vec![
+            rule(1, AlertKind::PriceMove, Some("US500"), 1.0),
+            rule(2, AlertKind::PriceAbove, Some("US500"), 5100.0),
+        ]);
+
+        assert!(monitor.on_price("US500", 5000.0, start).is_empty());
+        assert!(monitor.on_price("US500", 5040.0, start + minutes(10)).is_empty());
+        // 20 minutes in, the 5000 tick has left the 15 minute window, so this is 0.4% up.
+        assert!(monitor.on_price("US500", 5060.0, start + minutes(20)).is_empty());
+        assert!(monitor.on_price("GOLD", 1.0, start + minutes(21)).is_empty());
+
+        let fired = monitor.on_price("US500", 4980.0, start + minutes(22));
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].alert.title, "US500 down 1.2% in 15 min");
+        assert_eq!(fired[0].alert.message, "From 5040 to 4980");
+
+        let fired = monitor.on_price("US500", 5100.5, start + minutes(23));
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].rule_id, 2);
+        assert_eq!(monitor.symbols(), vec!["US500".to_string()]);
+    }
+}
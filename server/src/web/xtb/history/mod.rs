@@ -0,0 +1,168 @@
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+
+use crate::{db::XtbDay, money::MoneyFormat};
+
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+/// End-of-day records are small enough to keep for a year.
+pub const DAILY_RETENTION_DAYS: i64 = 365;
+/// Days of the screen's sparkline, ending today.
+pub const SPARK_DAYS: i64 = 30;
+
+/// How long snapshots are kept, from `XTB_HISTORY_DAYS`.
+pub fn retention() -> Duration {
+    let days = std::env::var("XTB_HISTORY_DAYS")
+        .ok()
+        .and_then(|days| days.trim().parse::<i64>().ok())
+        .filter(|days| *days > 0)
+        .unwrap_or(DEFAULT_RETENTION_DAYS);
+
+    Duration::days(days)
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// A day's closing figures and how much the equity changed over it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DailyPnl {
+    pub date: NaiveDate,
+    pub balance: f64,
+    pub equity: f64,
+    /// Floating P/L of the positions still open at the end of the day.
+    pub profit: f64,
+    /// Equity change since the previous recorded close, or since the day's first snapshot for
+    /// the first day. Deposits and withdrawals count too; XTB's streams don't tell them apart.
+    pub pnl: f64,
+}
+
+/// `days` are oldest first.
+pub fn daily_pnl(days: &[XtbDay]) -> Vec<DailyPnl> {
+    let mut previous_equity = None;
+    days.iter()
+        .map(|day| {
+            let start = previous_equity.unwrap_or(day.open_equity);
+            previous_equity = Some(day.equity);
+            DailyPnl {
+                date: day.date,
+                balance: round(day.balance),
+                equity: round(day.equity),
+                profit: round(day.profit),
+                pnl: round(day.equity - start),
+            }
+        })
+        .collect()
+}
+
+/// What the screen shows of the history, in the display currency.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistoryView {
+    pub view: &'static str,
+    /// Daily P/L of the last `SPARK_DAYS` days, oldest first; `None` for days without a record,
+    /// like weekends.
+    pub pnl: Vec<Option<f64>>,
+    /// The sum of `pnl`, formatted with its currency.
+    pub total: String,
+}
+
+impl HistoryView {
+    /// `days` are oldest first and should start the day before the sparkline, so its first day
+    /// has a previous close. `None` while nothing was recorded in the sparkline's days.
+    pub fn new(days: &[XtbDay], today: NaiveDate, money: &MoneyFormat) -> Option<Self> {
+        let first = today - Duration::days(SPARK_DAYS - 1);
+        let pnl = daily_pnl(days);
+        if !pnl.iter().any(|day| day.date >= first && day.date <= today) {
+            return None;
+        }
+
+        let spark: Vec<_> = (0..SPARK_DAYS)
+            .map(|offset| {
+                let date = first + Duration::days(offset);
+                pnl.iter().find(|day| day.date == date).map(|day| money.convert(day.pnl))
+            })
+            .collect();
+        let total: f64 = pnl
+            .iter()
+            .filter(|day| day.date >= first && day.date <= today)
+            .map(|day| day.pnl)
+            .sum();
+
+        Some(Self {
+            view: "history",
+            pnl: spark,
+            total: money.format_signed(total),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate};
+
+    use super::{daily_pnl, HistoryView, SPARK_DAYS};
+    use crate::{
+        db::XtbDay,
+        money::{FxRate, Locale, MoneyFormat, MoneySettings},
+    };
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+    }
+
+    fn day(date: NaiveDate, open_equity: f64, equity: f64) -> XtbDay {
+        XtbDay {
+            date,
+            open_equity,
+            balance: 1000.0,
+            equity,
+            profit: equity - 1000.0,
+        }
+    }
+
+    #[test]
+    fn pnl_is_measured_from_the_previous_close() {
+        let days = [day(date(15), 1000.0, 1010.0), day(date(16), 1012.0, 995.5), day(date(19), 990.0, 1020.25)];
+
+        let pnl: Vec<_> = daily_pnl(&days).iter().map(|day| day.pnl).collect();
+
+        assert_eq!(pnl, vec![10.0, -14.5, 24.75]);
+    }
+
+    #[test]
+    fn view_has_a_point_per_day() {
+        let today = date(19);
+        let days = [
+            day(today - Duration::days(SPARK_DAYS), 1000.0, 1000.0),
+            day(date(16), 1000.0, 1010.0),
+            day(today, 1005.0, 990.0),
+        ];
+
+        let view = HistoryView::new(&days, today, &MoneyFormat::default()).unwrap();
+
+        assert_eq!(view.pnl.len(), SPARK_DAYS as usize);
+        assert_eq!(view.pnl[0], None);
+        assert_eq!(&view.pnl[SPARK_DAYS as usize - 4..], &[Some(10.0), None, None, Some(-20.0)]);
+        assert_eq!(view.total, "-10.00");
+        assert_eq!(HistoryView::new(&days[..1], today, &MoneyFormat::default()), None);
+
+        let settings = MoneySettings { locale: Locale::Pl, display_currency: Some("PLN".to_string()) };
+        let rates = [FxRate { from: "EUR".to_string(), to: "PLN".to_string(), rate: 4.25 }];
+        let view = HistoryView::new(&days, today, &MoneyFormat::new(&settings, Some("EUR"), &rates)).unwrap();
+        assert_eq!(view.pnl[SPARK_DAYS as usize - 4], Some(42.5));
+        assert_eq!(view.total, "-42,50 PLN");
+    }
+
+    #[test]
+    fn view_fits_in_one_screen_message() {
+        let today = date(19);
+        let days: Vec<_> = (0..=SPARK_DAYS)
+            .map(|offset| day(today - Duration::days(offset), 0.0, -12345678.91 * offset as f64))
+            .rev()
+            .collect();
+        let money = MoneyFormat::new(&MoneySettings::default(), Some("PLN"), &[]);
+        let payload = serde_json::to_string(&HistoryView::new(&days, today, &money).unwrap()).unwrap();
+
+        assert!(payload.len() <= 1024, "{} bytes", payload.len());
+    }
+}
@@ -0,0 +1,333 @@
+use std::{collections::HashMap, fmt, path::PathBuf, time::Duration};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use tokio::{sync::watch, task::JoinSet, time::interval};
+
+use super::session::{Feed, Subscription};
+use crate::http;
+
+/// Quote files are small and local, so they can be re-read often.
+const POLL_INTERVAL_SECS: u64 = 1;
+
+/// The latest bid and ask of one instrument, from whichever provider quotes it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tick {
+    pub symbol: String,
+    pub bid: f64,
+    pub ask: f64,
+}
+
+/// Quotes of instruments, e.g. from XTB or from `OfflineQuotes`.
+#[async_trait]
+pub trait MarketData: Send {
+    /// Ticks of `symbol` whenever its quote changes.
+    async fn ticks(&mut self, symbol: &str) -> anyhow::Result<Feed<Tick>>;
+
+    /// What the daily change of `symbol` is measured against; `None` when the provider doesn't
+    /// say.
+    async fn previous_close(&mut self, symbol: &str) -> anyhow::Result<Option<f64>>;
+}
+
+/// One instrument in a quote file or endpoint.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct QuoteRecord {
+    pub symbol: String,
+    pub bid: f64,
+    /// The bid when left out.
+    #[serde(default)]
+    pub ask: Option<f64>,
+    #[serde(rename = "previousClose", default)]
+    pub previous_close: Option<f64>,
+}
+
+impl QuoteRecord {
+    fn tick(&self) -> Tick {
+        Tick {
+            symbol: self.symbol.clone(),
+            bid: self.bid,
+            ask: self.ask.unwrap_or(self.bid),
+        }
+    }
+}
+
+/// Parses a header line naming the `symbol` and `bid` columns, and optionally `ask` and
+/// `previousClose`, followed by one line per instrument.
+pub fn parse_csv(text: &str) -> anyhow::Result<Vec<QuoteRecord>> {
+    let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    let Some((_, header)) = lines.next() else {
+        return Ok(Vec::new());
+    };
+    let columns: Vec<_> = header.split(',').map(str::trim).collect();
+    let column = |name: &str| columns.iter().position(|column| *column == name);
+    let (Some(symbol), Some(bid)) = (column("symbol"), column("bid")) else {
+        return Err(anyhow::anyhow!("The header must name the symbol and bid columns"));
+    };
+    let (ask, previous_close) = (column("ask"), column("previousClose"));
+
+    lines
+        .map(|(number, line)| {
+            let fields: Vec<_> = line.split(',').map(str::trim).collect();
+            let value = |index: Option<usize>, name: &str| -> anyhow::Result<Option<f64>> {
+                match index.and_then(|index| fields.get(index)).filter(|field| !field.is_empty()) {
+                    Some(field) => field
+                        .parse()
+                        .map(Some)
+                        .map_err(|_| anyhow::anyhow!("Line {}: {} is not a number", number + 1, name)),
+                    None => Ok(None),
+                }
+            };
+            Ok(QuoteRecord {
+                symbol: fields.get(symbol).copied().unwrap_or_default().to_string(),
+                bid: value(Some(bid), "bid")?.ok_or_else(|| anyhow::anyhow!("Line {}: bid is missing", number + 1))?,
+                ask: value(ask, "ask")?,
+                previous_close: value(previous_close, "previousClose")?,
+            })
+        })
+        .collect()
+}
+
+/// Where `OfflineQuotes` reads quotes from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuoteSource {
+    Csv(PathBuf),
+    /// An array of `QuoteRecord`s.
+    Json(PathBuf),
+    /// Serves the same array as a JSON file.
+    Http(String),
+}
+
+impl QuoteSource {
+    /// From `MARKET_DATA_SOURCE`; `None` when it isn't set, in which case quotes come from XTB.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        match std::env::var("MARKET_DATA_SOURCE") {
+            Ok(value) if !value.trim().is_empty() => Self::parse(value.trim()).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// A `.csv` or `.json` path, or an `http(s)://` URL.
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        if value.starts_with("http://") || value.starts_with("https://") {
+            return Ok(Self::Http(value.to_string()));
+        }
+
+        let path = PathBuf::from(value);
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("csv") => Ok(Self::Csv(path)),
+            Some(extension) if extension.eq_ignore_ascii_case("json") => Ok(Self::Json(path)),
+            _ => Err(anyhow::anyhow!("{} is not a .csv or .json file or an http(s) URL", value)),
+        }
+    }
+
+    /// The quotes by symbol, upper-cased like the watchlist's.
+    pub async fn load(&self) -> anyhow::Result<HashMap<String, QuoteRecord>> {
+        let records = match self {
+            Self::Csv(path) => parse_csv(&tokio::fs::read_to_string(path).await?)?,
+            Self::Json(path) => serde_json::from_str(&tokio::fs::read_to_string(path).await?)?,
+            Self::Http(url) => {
+                let http = http::shared();
+                let response = http.send(http.get(url)).await?.error_for_status()?;
+                response.json().await?
+            }
+        };
+
+        Ok(records
+            .into_iter()
+            .map(|record: QuoteRecord| {
+                let symbol = record.symbol.trim().to_uppercase();
+                (symbol.clone(), QuoteRecord { symbol, ..record })
+            })
+            .collect())
+    }
+}
+
+impl fmt::Display for QuoteSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Csv(path) | Self::Json(path) => write!(f, "{}", path.display()),
+            Self::Http(url) => write!(f, "{}", url),
+        }
+    }
+}
+
+type Quotes = HashMap<String, QuoteRecord>;
+
+/// Quotes from a local file or endpoint instead of a brokerage, so the watchlist and alerts work
+/// without a login. Edits to the file show up within `poll_interval`.
+pub struct OfflineQuotes {
+    source: QuoteSource,
+    poll_interval: Duration,
+    quotes: Option<watch::Receiver<Quotes>>,
+    /// Dropping it stops polling.
+    poller: JoinSet<()>,
+}
+
+impl OfflineQuotes {
+    pub fn new(source: QuoteSource) -> Self {
+        Self {
+            source,
+            poll_interval: Duration::from_secs(POLL_INTERVAL_SECS),
+            quotes: None,
+            poller: JoinSet::new(),
+        }
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Loads the quotes on first use, so a missing file fails the subscription, and polls them
+    /// from then on. Later failures keep the last quotes.
+    async fn quotes(&mut self) -> anyhow::Result<watch::Receiver<Quotes>> {
+        if let Some(quotes) = &self.quotes {
+            return Ok(quotes.clone());
+        }
+
+        let loaded = self
+            .source
+            .load()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to load quotes from {}: {}", self.source, e))?;
+        let (sender, receiver) = watch::channel(loaded);
+        let (source, poll_interval) = (self.source.clone(), self.poll_interval);
+        self.poller.spawn(async move {
+            let mut interval = interval(poll_interval);
+            interval.tick().await;
+            while !sender.is_closed() {
+                interval.tick().await;
+                match source.load().await {
+                    Ok(quotes) => {
+                        sender.send_if_modified(|current| {
+                            let modified = *current != quotes;
+                            *current = quotes;
+                            modified
+                        });
+                    }
+                    Err(e) => println!("Failed to load quotes from {}: {}", source, e),
+                }
+            }
+        });
+
+        self.quotes = Some(receiver.clone());
+        Ok(receiver)
+    }
+}
+
+/// Ticks of one symbol out of the polled quotes.
+struct QuoteFeed {
+    symbol: String,
+    quotes: watch::Receiver<Quotes>,
+    last: Option<Tick>,
+}
+
+#[async_trait]
+impl Subscription<Tick> for QuoteFeed {
+    async fn next(&mut self) -> anyhow::Result<Option<Tick>> {
+        loop {
+            let tick = self.quotes.borrow_and_update().get(&self.symbol).map(QuoteRecord::tick);
+            if tick.is_some() && tick != self.last {
+                self.last = tick.clone();
+                return Ok(tick);
+            }
+            if self.quotes.changed().await.is_err() {
+                return Ok(None);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl MarketData for OfflineQuotes {
+    async fn ticks(&mut self, symbol: &str) -> anyhow::Result<Feed<Tick>> {
+        let quotes = self.quotes().await?;
+        Ok(Box::new(QuoteFeed {
+            symbol: symbol.to_string(),
+            quotes,
+            last: None,
+        }))
+    }
+
+    async fn previous_close(&mut self, symbol: &str) -> anyhow::Result<Option<f64>> {
+        let quotes = self.quotes().await?;
+        let previous_close = quotes.borrow().get(symbol).and_then(|record| record.previous_close);
+        Ok(previous_close)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use axum::{extract::State, routing::get, Json, Router};
+    use serde_json::{json, Value};
+    use tokio::time::timeout;
+
+    use super::{parse_csv, MarketData, OfflineQuotes, QuoteRecord, QuoteSource, Tick};
+    use crate::test_utils::spawn_mock_server;
+
+    #[test]
+    fn csv_columns_are_found_by_name() {
+        let csv = "previousClose, symbol, bid, ask\n4.25, EURPLN, 4.2551, 4.2571\n\n, US500, 5000,\n";
+
+        assert_eq!(
+            parse_csv(csv).unwrap(),
+            vec![
+                QuoteRecord { symbol: "EURPLN".to_string(), bid: 4.2551, ask: Some(4.2571), previous_close: Some(4.25) },
+                QuoteRecord { symbol: "US500".to_string(), bid: 5000.0, ask: None, previous_close: None },
+            ]
+        );
+        assert_eq!(parse_csv("symbol,bid\nUS500,high").unwrap_err().to_string(), "Line 2: bid is not a number");
+        assert!(parse_csv("symbol,price\nUS500,5000").is_err());
+    }
+
+    #[test]
+    fn sources_are_told_apart_by_extension_and_scheme() {
+        assert_eq!(QuoteSource::parse("quotes.CSV").unwrap(), QuoteSource::Csv("quotes.CSV".into()));
+        assert_eq!(QuoteSource::parse("/tmp/quotes.json").unwrap(), QuoteSource::Json("/tmp/quotes.json".into()));
+        assert_eq!(
+            QuoteSource::parse("http://localhost:8000/quotes").unwrap(),
+            QuoteSource::Http("http://localhost:8000/quotes".to_string())
+        );
+        assert!(QuoteSource::parse("quotes.txt").is_err());
+    }
+
+    #[tokio::test]
+    async fn quote_files_are_loaded_with_upper_case_symbols() {
+        let path = std::env::temp_dir().join(format!("quotes-{}.json", std::process::id()));
+        std::fs::write(&path, r#"[{ "symbol": "eurpln", "bid": 4.3, "previousClose": 4.25 }]"#).unwrap();
+
+        let quotes = QuoteSource::Json(path.clone()).load().await;
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(quotes.unwrap()["EURPLN"].previous_close, Some(4.25));
+    }
+
+    #[tokio::test]
+    async fn endpoint_changes_are_ticked() {
+        let served = Arc::new(Mutex::new(json!([
+            { "symbol": "US500", "bid": 5000.0, "ask": 5000.5, "previousClose": 4990.0 },
+            { "symbol": "GOLD", "bid": 2400.0 },
+        ])));
+        let router = Router::new()
+            .route("/quotes", get(|State(served): State<Arc<Mutex<Value>>>| async move { Json(served.lock().unwrap().clone()) }))
+            .with_state(served.clone());
+        let url = format!("{}/quotes", spawn_mock_server(router).await);
+        let mut quotes = OfflineQuotes::new(QuoteSource::Http(url)).with_poll_interval(Duration::from_millis(10));
+
+        let mut ticks = quotes.ticks("US500").await.unwrap();
+        let tick = timeout(Duration::from_secs(1), ticks.next()).await.unwrap().unwrap();
+        assert_eq!(tick, Some(Tick { symbol: "US500".to_string(), bid: 5000.0, ask: 5000.5 }));
+        assert_eq!(quotes.previous_close("US500").await.unwrap(), Some(4990.0));
+        assert_eq!(quotes.previous_close("GOLD").await.unwrap(), None);
+
+        served.lock().unwrap()[1]["bid"] = json!(2410.0);
+        served.lock().unwrap()[0]["bid"] = json!(4950.0);
+        let tick = timeout(Duration::from_secs(1), ticks.next()).await.unwrap().unwrap();
+        assert_eq!(tick.map(|tick| tick.bid), Some(4950.0));
+    }
+}
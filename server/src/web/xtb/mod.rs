@@ -1,96 +1,449 @@
-use sqlx::SqlitePool;
-use tokio::{sync::mpsc, time::interval};
-use xtb_client::{
-    schema::{StreamGetKeepAliveSubscribe, StreamGetProfitSubscribe},
-    StreamApi, XtbClient, XtbClientBuilder,
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
 };
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tokio::sync::{mpsc, Notify};
+use xtb_client::XtbClientBuilder;
 
+use super::error::{ApiError, ApiJson, ApiResult};
 use crate::{
-    db::{get_xtb_credentials, XtbCredentials},
+    db::{
+        add_xtb_alert_rule, add_xtb_watchlist_symbol, delete_fx_rate, delete_xtb_alert_rule,
+        delete_xtb_watchlist_symbol, get_fx_rates, get_money_settings,
+        get_xtb_alert_history, get_xtb_alert_rules, get_xtb_credentials, get_xtb_days, get_xtb_snapshots,
+        get_xtb_watchlist, save_fx_rate, save_money_settings, update_xtb_alert_rule, XtbAlertRecord, XtbSnapshot,
+    },
+    money::{normalize_currency, FxRate, MoneySettings},
+    sources::{ConfigField, DataSource, FieldKind, Health, SourceMode, Sources},
     tcp::StateMessage,
 };
+use alert_rules::{AlertKind, AlertRule};
+use history::DailyPnl;
+use market_data::{OfflineQuotes, QuoteSource};
+use session::{Session, SessionState, XtbConnector};
+
+pub mod alert_rules;
+pub mod history;
+pub mod market_data;
+pub mod portfolio;
+pub mod session;
+pub mod watchlist;
 
-pub async fn initialize_xtb_websocket(
+const APP: &str = "XTB";
+
+/// Demo accounts trade with virtual money on separate servers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum AccountType {
+    Demo,
+    /// What credentials saved without an account type are for.
+    #[default]
+    Real,
+}
+
+impl AccountType {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Demo => "Demo",
+            Self::Real => "Real",
+        }
+    }
+
+    fn builder(self) -> XtbClientBuilder {
+        match self {
+            Self::Demo => XtbClientBuilder::new_demo(),
+            Self::Real => XtbClientBuilder::new_real(),
+        }
+    }
+}
+
+/// Streams the account's portfolio and watchlist prices from the XTB xStation API, or only the
+/// watchlist from `MARKET_DATA_SOURCE` when that is set.
+pub struct XtbSource {
     db: SqlitePool,
-    sender: mpsc::Sender<StateMessage>,
-) -> anyhow::Result<()> {
-    let mut interval = interval(tokio::time::Duration::from_secs(30));
-    let mut is_connected = false;
-    let mut xtb_client: Option<XtbClient> = None;
-
-    loop {
-        interval.tick().await;
-
-        if !is_connected {
-            let xtb_credentials: Option<XtbCredentials> = get_xtb_credentials(&db).await?;
-
-            if xtb_credentials.is_none() {
-                println!("No XTB credentials found in the database");
-                continue;
-            }
-
-            let xtb_credentials = xtb_credentials.unwrap();
-
-            let real_builder = XtbClientBuilder::new_real();
-            match real_builder
-                .build(&xtb_credentials.user_id, &xtb_credentials.password)
-                .await
-            {
-                Ok(client) => {
-                    println!("Successfully connected to XTB");
-                    is_connected = true;
-                    xtb_client = Some(client);
-                }
-                Err(e) => {
-                    println!("Error: {:?}", e);
-                    return Ok(());
-                }
-            };
+    state: Arc<Mutex<SessionState>>,
+    offline: bool,
+    /// Tells the running session that its watchlist, alert rules or money settings changed.
+    reload: Arc<Notify>,
+}
+
+impl XtbSource {
+    pub fn new(db: SqlitePool) -> Self {
+        Self {
+            db,
+            state: Arc::default(),
+            offline: !matches!(QuoteSource::from_env(), Ok(None)),
+            reload: Arc::default(),
         }
+    }
+}
 
-        if let Some(ref mut client) = xtb_client {
-            let mut profits_listener = match client
-                .subscribe_profits(StreamGetProfitSubscribe::default())
-                .await
-            {
-                Ok(listener) => listener,
-                Err(e) => {
-                    println!("Error subscribing to profits: {:?}", e);
-                    is_connected = false;
-                    xtb_client = None;
-                    continue;
-                }
-            };
+#[async_trait]
+impl DataSource for XtbSource {
+    fn name(&self) -> &'static str {
+        "xtb"
+    }
+
+    fn app(&self) -> &'static str {
+        APP
+    }
 
-            let mut keep_alive_listener = match client
-                .subscribe_keep_alive(StreamGetKeepAliveSubscribe::default())
-                .await
-            {
-                Ok(listener) => listener,
-                Err(e) => {
-                    println!("Error subscribing to keep-alive: {:?}", e);
-                    is_connected = false;
-                    xtb_client = None;
-                    continue;
-                }
+    fn mode(&self) -> SourceMode {
+        SourceMode::Stream
+    }
+
+    fn reload(&self) -> Option<Arc<Notify>> {
+        Some(self.reload.clone())
+    }
+
+    /// Stored through `POST /xtb/credentials`.
+    fn config_schema(&self) -> Vec<ConfigField> {
+        vec![
+            ConfigField { key: "userId", label: "User ID", kind: FieldKind::Text, required: true },
+            ConfigField { key: "password", label: "Password", kind: FieldKind::Secret, required: true },
+            ConfigField { key: "accountType", label: "Account type", kind: FieldKind::Text, required: false },
+        ]
+    }
+
+    async fn health(&self) -> Health {
+        if self.offline {
+            return match self.state.lock().unwrap().clone() {
+                SessionState::Backoff { reason } => Health::Degraded { reason },
+                _ => Health::Healthy,
             };
+        }
+
+        match get_xtb_credentials(&self.db).await {
+            Ok(Some(_)) => match self.state.lock().unwrap().clone() {
+                SessionState::Backoff { reason } => Health::Degraded { reason },
+                _ => Health::Healthy,
+            },
+            Ok(None) => Health::Unconfigured { reason: "No XTB credentials saved".to_string() },
+            Err(e) => Health::Degraded { reason: e.to_string() },
+        }
+    }
+
+    async fn stream(&self, sender: mpsc::Sender<StateMessage>) -> anyhow::Result<()> {
+        let session = Session::new(XtbConnector, self.db.clone(), self.state.clone()).with_reload(self.reload.clone());
+        match QuoteSource::from_env()? {
+            Some(source) => session.run_offline(OfflineQuotes::new(source), sender).await,
+            None => session.run(sender).await,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct XtbStatus {
+    configured: bool,
+    #[serde(rename = "userId")]
+    user_id: Option<String>,
+    #[serde(rename = "accountType")]
+    account_type: Option<AccountType>,
+    health: Option<Health>,
+}
+
+/// Which account the source logs in to and how that is going.
+pub async fn get_status(State((db, sources)): State<(SqlitePool, Sources)>) -> ApiResult<Json<XtbStatus>> {
+    let credentials = get_xtb_credentials(&db).await.map_err(ApiError::Database)?;
+    let health = sources
+        .statuses()
+        .await
+        .into_iter()
+        .find(|status| status.provider.name == "xtb")
+        .map(|status| status.health);
+
+    Ok(Json(XtbStatus {
+        configured: credentials.is_some(),
+        user_id: credentials.as_ref().map(|credentials| credentials.user_id.clone()),
+        account_type: credentials.map(|credentials| credentials.account_type),
+        health,
+    }))
+}
+
+pub async fn list_watchlist(State((db, _)): State<(SqlitePool, Sources)>) -> ApiResult<Json<Vec<String>>> {
+    let symbols = get_xtb_watchlist(&db).await.map_err(ApiError::Database)?;
+
+    Ok(Json(symbols))
+}
+
+#[derive(Deserialize)]
+pub struct AddWatchlistSymbolPayload {
+    symbol: String,
+}
+
+/// Adds an instrument, which the running session subscribes to right away.
+pub async fn add_watchlist_symbol(
+    State((db, sources)): State<(SqlitePool, Sources)>,
+    ApiJson(payload): ApiJson<AddWatchlistSymbolPayload>,
+) -> ApiResult<(StatusCode, Json<Vec<String>>)> {
+    let symbol = watchlist::normalize_symbol(&payload.symbol).map_err(ApiError::validation)?;
+
+    let symbols = get_xtb_watchlist(&db).await.map_err(ApiError::Database)?;
+    if symbols.len() >= watchlist::MAX_SYMBOLS {
+        return Err(ApiError::validation(format!("At most {} symbols can be watched", watchlist::MAX_SYMBOLS)));
+    }
+    if !add_xtb_watchlist_symbol(&db, &symbol).await.map_err(ApiError::Database)? {
+        return Err(ApiError::validation(format!("{} is already on the watchlist", symbol)));
+    }
+    sources.reload("xtb");
+
+    let symbols = get_xtb_watchlist(&db).await.map_err(ApiError::Database)?;
+    Ok((StatusCode::CREATED, Json(symbols)))
+}
+
+pub async fn delete_watchlist_symbol(
+    State((db, sources)): State<(SqlitePool, Sources)>,
+    Path(symbol): Path<String>,
+) -> ApiResult<StatusCode> {
+    let symbol = symbol.to_uppercase();
+    if !delete_xtb_watchlist_symbol(&db, &symbol).await.map_err(ApiError::Database)? {
+        return Err(ApiError::not_found(format!("{} is not on the watchlist", symbol)));
+    }
+    sources.reload("xtb");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_alert_rules(State((db, _)): State<(SqlitePool, Sources)>) -> ApiResult<Json<Vec<AlertRule>>> {
+    let rules = get_xtb_alert_rules(&db).await.map_err(ApiError::Database)?;
+
+    Ok(Json(rules))
+}
+
+#[derive(Deserialize)]
+pub struct AlertRulePayload {
+    kind: AlertKind,
+    #[serde(default)]
+    symbol: Option<String>,
+    threshold: f64,
+    #[serde(default, rename = "windowMinutes")]
+    window_minutes: i64,
+    #[serde(rename = "cooldownMinutes")]
+    cooldown_minutes: i64,
+    #[serde(default = "enabled_by_default")]
+    enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// The rule `payload` describes, with `id`; price rules need a symbol, profit rules drop it.
+fn alert_rule(id: i64, payload: AlertRulePayload) -> ApiResult<AlertRule> {
+    if !payload.threshold.is_finite() {
+        return Err(ApiError::validation("threshold must be a number"));
+    }
+
+    let symbol = match (payload.kind.needs_symbol(), payload.symbol) {
+        (true, Some(symbol)) => Some(watchlist::normalize_symbol(&symbol).map_err(ApiError::validation)?),
+        (true, None) => return Err(ApiError::validation("symbol is required for price rules")),
+        (false, _) => None,
+    };
 
-            let sender_clone = sender.clone();
-            tokio::spawn(async move {
-                while let Ok(Some(item)) = profits_listener.next().await {
-                    let payload = format!("Profit: {}", item.profit);
-                    if let Err(e) = sender_clone.send(StateMessage::XtbData(payload)).await {
-                        println!("Error sending state message: {:?}", e);
-                    }
-                }
-                println!("Profit listener task terminated");
-            });
-
-            tokio::spawn(async move {
-                println!("Listening for keep-alive...");
-                while let Ok(Some(_)) = keep_alive_listener.next().await {}
-                println!("Keep-alive listener task terminated");
-            });
+    let window_minutes = if payload.kind == AlertKind::PriceMove {
+        if payload.threshold <= 0.0 {
+            return Err(ApiError::validation("threshold must be a positive percentage"));
         }
+        if !(1..=alert_rules::MAX_WINDOW_MINUTES).contains(&payload.window_minutes) {
+            return Err(ApiError::validation(format!(
+                "windowMinutes must be between 1 and {}",
+                alert_rules::MAX_WINDOW_MINUTES
+            )));
+        }
+        payload.window_minutes
+    } else {
+        0
+    };
+
+    if !(alert_rules::MIN_COOLDOWN_MINUTES..=alert_rules::MAX_COOLDOWN_MINUTES).contains(&payload.cooldown_minutes) {
+        return Err(ApiError::validation(format!(
+            "cooldownMinutes must be between {} and {}",
+            alert_rules::MIN_COOLDOWN_MINUTES,
+            alert_rules::MAX_COOLDOWN_MINUTES
+        )));
+    }
+
+    Ok(AlertRule {
+        id,
+        kind: payload.kind,
+        symbol,
+        threshold: payload.threshold,
+        window_minutes,
+        cooldown_minutes: payload.cooldown_minutes,
+        enabled: payload.enabled,
+    })
+}
+
+/// Rule changes are picked up by the running session, which checks and subscribes to them.
+pub async fn add_alert_rule(
+    State((db, sources)): State<(SqlitePool, Sources)>,
+    ApiJson(payload): ApiJson<AlertRulePayload>,
+) -> ApiResult<(StatusCode, Json<AlertRule>)> {
+    let rule = alert_rule(0, payload)?;
+    let rule = add_xtb_alert_rule(&db, rule).await.map_err(ApiError::Database)?;
+    sources.reload("xtb");
+
+    Ok((StatusCode::CREATED, Json(rule)))
+}
+
+pub async fn put_alert_rule(
+    State((db, sources)): State<(SqlitePool, Sources)>,
+    Path(id): Path<i64>,
+    ApiJson(payload): ApiJson<AlertRulePayload>,
+) -> ApiResult<Json<AlertRule>> {
+    let rule = alert_rule(id, payload)?;
+    if !update_xtb_alert_rule(&db, &rule).await.map_err(ApiError::Database)? {
+        return Err(ApiError::not_found(format!("Unknown alert rule {}", id)));
+    }
+    sources.reload("xtb");
+
+    Ok(Json(rule))
+}
+
+pub async fn delete_alert_rule(
+    State((db, sources)): State<(SqlitePool, Sources)>,
+    Path(id): Path<i64>,
+) -> ApiResult<StatusCode> {
+    if !delete_xtb_alert_rule(&db, id).await.map_err(ApiError::Database)? {
+        return Err(ApiError::not_found(format!("Unknown alert rule {}", id)));
     }
+    sources.reload("xtb");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+const DEFAULT_HISTORY_LIMIT: i64 = 50;
+const MAX_HISTORY_LIMIT: i64 = 500;
+
+#[derive(Deserialize)]
+pub struct AlertHistoryParams {
+    limit: Option<i64>,
+}
+
+/// The latest alerts the rules raised, newest first.
+pub async fn get_alert_history(
+    State((db, _)): State<(SqlitePool, Sources)>,
+    Query(params): Query<AlertHistoryParams>,
+) -> ApiResult<Json<Vec<XtbAlertRecord>>> {
+    let limit = params.limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+    if !(1..=MAX_HISTORY_LIMIT).contains(&limit) {
+        return Err(ApiError::validation(format!("limit must be between 1 and {}", MAX_HISTORY_LIMIT)));
+    }
+    let alerts = get_xtb_alert_history(&db, limit).await.map_err(ApiError::Database)?;
+
+    Ok(Json(alerts))
+}
+
+#[derive(Deserialize)]
+pub struct HistoryParams {
+    days: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct HistoryResponse {
+    /// Oldest first; days without a session have no record.
+    days: Vec<DailyPnl>,
+    /// Oldest first; only kept for `XTB_HISTORY_DAYS`.
+    snapshots: Vec<XtbSnapshot>,
+}
+
+/// The end-of-day records and snapshots of the last `days` UTC days, today included.
+pub async fn get_history(
+    State((db, _)): State<(SqlitePool, Sources)>,
+    Query(params): Query<HistoryParams>,
+) -> ApiResult<Json<HistoryResponse>> {
+    let days = params.days.unwrap_or(history::SPARK_DAYS);
+    if !(1..=history::DAILY_RETENTION_DAYS).contains(&days) {
+        return Err(ApiError::validation(format!(
+            "days must be between 1 and {}",
+            history::DAILY_RETENTION_DAYS
+        )));
+    }
+
+    let first = chrono::Utc::now().date_naive() - chrono::Duration::days(days - 1);
+    // The day before is only read for the first day's P/L.
+    let records = get_xtb_days(&db, first - chrono::Duration::days(1)).await.map_err(ApiError::Database)?;
+    let mut pnl = history::daily_pnl(&records);
+    pnl.retain(|day| day.date >= first);
+    let since = first.and_hms_opt(0, 0, 0).unwrap_or_default();
+    let snapshots = get_xtb_snapshots(&db, since).await.map_err(ApiError::Database)?;
+
+    Ok(Json(HistoryResponse { days: pnl, snapshots }))
+}
+
+pub async fn get_money(State((db, _)): State<(SqlitePool, Sources)>) -> ApiResult<Json<MoneySettings>> {
+    let settings = get_money_settings(&db).await.map_err(ApiError::Database)?;
+
+    Ok(Json(settings))
+}
+
+/// The running session formats amounts with the new settings right away.
+pub async fn put_money(
+    State((db, sources)): State<(SqlitePool, Sources)>,
+    ApiJson(payload): ApiJson<MoneySettings>,
+) -> ApiResult<Json<MoneySettings>> {
+    let display_currency = payload
+        .display_currency
+        .filter(|currency| !currency.trim().is_empty())
+        .map(|currency| normalize_currency(&currency))
+        .transpose()
+        .map_err(ApiError::validation)?;
+    let settings = MoneySettings { locale: payload.locale, display_currency };
+    save_money_settings(&db, &settings).await.map_err(ApiError::Database)?;
+    sources.reload("xtb");
+
+    Ok(Json(settings))
+}
+
+pub async fn list_fx_rates(State((db, _)): State<(SqlitePool, Sources)>) -> ApiResult<Json<Vec<FxRate>>> {
+    let rates = get_fx_rates(&db).await.map_err(ApiError::Database)?;
+
+    Ok(Json(rates))
+}
+
+#[derive(Deserialize)]
+pub struct FxRatePayload {
+    rate: f64,
+}
+
+/// Stores how many units of `to` one `from` buys; its inverse converts the other way.
+pub async fn put_fx_rate(
+    State((db, sources)): State<(SqlitePool, Sources)>,
+    Path((from, to)): Path<(String, String)>,
+    ApiJson(payload): ApiJson<FxRatePayload>,
+) -> ApiResult<Json<FxRate>> {
+    let from = normalize_currency(&from).map_err(ApiError::validation)?;
+    let to = normalize_currency(&to).map_err(ApiError::validation)?;
+    if from == to {
+        return Err(ApiError::validation("A rate needs two different currencies"));
+    }
+    if !payload.rate.is_finite() || payload.rate <= 0.0 {
+        return Err(ApiError::validation("rate must be a positive number"));
+    }
+
+    let rate = FxRate { from, to, rate: payload.rate };
+    save_fx_rate(&db, &rate).await.map_err(ApiError::Database)?;
+    sources.reload("xtb");
+
+    Ok(Json(rate))
+}
+
+pub async fn delete_fx_rate_pair(
+    State((db, sources)): State<(SqlitePool, Sources)>,
+    Path((from, to)): Path<(String, String)>,
+) -> ApiResult<StatusCode> {
+    let (from, to) = (from.to_uppercase(), to.to_uppercase());
+    if !delete_fx_rate(&db, &from, &to).await.map_err(ApiError::Database)? {
+        return Err(ApiError::not_found(format!("No rate from {} to {}", from, to)));
+    }
+    sources.reload("xtb");
+
+    Ok(StatusCode::NO_CONTENT)
 }
@@ -0,0 +1,267 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+use xtb_client::schema::{
+    StreamGetBalanceData, StreamGetProfitData, StreamGetTradesData, TradeRecord, TradeStatus, TransactionType,
+};
+
+use super::AccountType;
+use crate::{db::XtbSnapshot, money::MoneyFormat};
+
+/// The screen lists the symbols with the largest profit or loss; the rest only count towards
+/// the total.
+const MAX_SYMBOLS: usize = 8;
+
+fn to_f64(value: impl TryInto<f64>) -> f64 {
+    value.try_into().unwrap_or_default()
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Balance {
+    balance: f64,
+    equity: f64,
+    margin_level: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Position {
+    /// Empty while only the profit stream has mentioned the position.
+    symbol: String,
+    profit: f64,
+}
+
+/// The account and its open positions as the XTB streams last reported them.
+#[derive(Debug, Default)]
+pub struct Portfolio {
+    balance: Option<Balance>,
+    positions: HashMap<i32, Position>,
+}
+
+impl Portfolio {
+    /// Starts from the positions that were already open when the session began; the trade stream
+    /// only reports changes.
+    pub fn new(open_trades: &[TradeRecord]) -> Self {
+        let positions = open_trades
+            .iter()
+            .filter(|trade| !trade.closed)
+            .map(|trade| {
+                let position = Position {
+                    symbol: trade.symbol.clone().unwrap_or_default(),
+                    profit: to_f64(trade.profit),
+                };
+                (trade.position as i32, position)
+            })
+            .collect();
+
+        Self { balance: None, positions }
+    }
+
+    pub fn apply_balance(&mut self, balance: &StreamGetBalanceData) {
+        self.balance = Some(Balance {
+            balance: to_f64(balance.balance),
+            equity: to_f64(balance.equity),
+            margin_level: to_f64(balance.margin_level),
+        });
+    }
+
+    /// Tracks opened and closed positions; pending orders don't affect the P/L.
+    pub fn apply_trade(&mut self, trade: &StreamGetTradesData) {
+        let closed = trade.closed || trade.type_ == TransactionType::Close || trade.state == TradeStatus::Deleted;
+        if closed {
+            self.positions.remove(&trade.position);
+        } else if trade.type_ == TransactionType::Open {
+            let position = self.positions.entry(trade.position).or_default();
+            position.symbol = trade.symbol.clone();
+            if let Some(profit) = trade.profit {
+                position.profit = to_f64(profit);
+            }
+        }
+    }
+
+    pub fn apply_profit(&mut self, profit: &StreamGetProfitData) {
+        self.positions.entry(profit.position).or_default().profit = to_f64(profit.profit);
+    }
+
+    /// Floating P/L of all open positions.
+    pub fn profit(&self) -> f64 {
+        self.positions.values().map(|position| position.profit).sum()
+    }
+
+    /// `None` until the balance stream has reported the account.
+    pub fn snapshot(&self, recorded_at: chrono::NaiveDateTime) -> Option<XtbSnapshot> {
+        let balance = self.balance?;
+        Some(XtbSnapshot {
+            recorded_at,
+            balance: round(balance.balance),
+            equity: round(balance.equity),
+            profit: round(self.profit()),
+            positions: self.positions.len() as i64,
+        })
+    }
+
+    pub fn summary(&self, account_type: AccountType, money: &MoneyFormat) -> PortfolioSummary {
+        // Positions and profit per symbol.
+        let mut symbols = BTreeMap::<&str, (usize, f64)>::new();
+        for position in self.positions.values().filter(|position| !position.symbol.is_empty()) {
+            let (positions, profit) = symbols.entry(&position.symbol).or_default();
+            *positions += 1;
+            *profit += position.profit;
+        }
+
+        let mut symbols: Vec<_> = symbols.into_iter().collect();
+        symbols.sort_by(|(_, (_, a)), (_, (_, b))| b.abs().total_cmp(&a.abs()));
+        symbols.truncate(MAX_SYMBOLS);
+
+        let balance = self.balance.unwrap_or_default();
+        PortfolioSummary {
+            view: "portfolio",
+            account: account_type,
+            currency: money.currency().map(str::to_string),
+            balance: money.amount(balance.balance),
+            equity: money.amount(balance.equity),
+            margin_level: round(balance.margin_level),
+            profit: money.signed(self.profit()),
+            positions: self.positions.len(),
+            symbols: symbols
+                .into_iter()
+                .map(|(symbol, (positions, profit))| SymbolProfit {
+                    symbol: symbol.to_string(),
+                    positions,
+                    profit: money.signed(profit),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SymbolProfit {
+    pub symbol: String,
+    pub positions: usize,
+    pub profit: String,
+}
+
+/// What the screen shows for the account; amounts are formatted in `currency`, once for the
+/// whole page.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PortfolioSummary {
+    pub view: &'static str,
+    pub account: AccountType,
+    /// `None` when XTB didn't report the account currency.
+    pub currency: Option<String>,
+    pub balance: String,
+    pub equity: String,
+    #[serde(rename = "marginLevel")]
+    pub margin_level: f64,
+    /// Floating P/L of all open positions, with a `+` on gains.
+    pub profit: String,
+    pub positions: usize,
+    pub symbols: Vec<SymbolProfit>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use xtb_client::schema::{
+        StreamGetBalanceData, StreamGetProfitData, StreamGetTradesData, TradeRecord, TradeStatus, TransactionType,
+    };
+
+    use super::{Portfolio, MAX_SYMBOLS};
+    use crate::{
+        money::{Locale, MoneyFormat, MoneySettings},
+        web::xtb::AccountType,
+    };
+
+    fn open_trade(position: u32, symbol: &str, profit: f64) -> TradeRecord {
+        TradeRecord {
+            position,
+            symbol: Some(symbol.to_string()),
+            profit: profit.try_into().unwrap(),
+            ..Default::default()
+        }
+    }
+
+    fn trade(position: i32, symbol: &str, type_: TransactionType) -> StreamGetTradesData {
+        StreamGetTradesData {
+            position,
+            symbol: symbol.to_string(),
+            type_,
+            ..Default::default()
+        }
+    }
+
+    fn profit(position: i32, profit: f64) -> StreamGetProfitData {
+        StreamGetProfitData {
+            position,
+            profit: profit.try_into().unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn positions_are_aggregated_per_symbol() {
+        let mut portfolio = Portfolio::new(&[open_trade(1, "US500", 10.0), open_trade(2, "US500", -4.0)]);
+        portfolio.apply_balance(&StreamGetBalanceData {
+            balance: 1000.into(),
+            equity: 1012.5.try_into().unwrap(),
+            margin_level: 845.25.try_into().unwrap(),
+            ..Default::default()
+        });
+        portfolio.apply_trade(&trade(3, "GOLD", TransactionType::Open));
+        portfolio.apply_profit(&profit(3, -20.0));
+        portfolio.apply_profit(&profit(1, 12.55));
+
+        let money = MoneyFormat::new(&MoneySettings { locale: Locale::Pl, display_currency: None }, Some("PLN"), &[]);
+        let summary = portfolio.summary(AccountType::Demo, &money);
+
+        assert_eq!(
+            serde_json::to_value(&summary).unwrap(),
+            json!({
+                "view": "portfolio",
+                "account": "demo",
+                "currency": "PLN",
+                "balance": "1000,00",
+                "equity": "1012,50",
+                "marginLevel": 845.25,
+                "profit": "-11,45",
+                "positions": 3,
+                "symbols": [
+                    { "symbol": "GOLD", "positions": 1, "profit": "-20,00" },
+                    { "symbol": "US500", "positions": 2, "profit": "+8,55" },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn closed_and_deleted_positions_are_dropped() {
+        let mut portfolio = Portfolio::new(&[open_trade(1, "US500", 10.0), open_trade(2, "EURPLN", 5.0)]);
+        portfolio.apply_trade(&trade(1, "US500", TransactionType::Close));
+        portfolio.apply_trade(&StreamGetTradesData {
+            state: TradeStatus::Deleted,
+            ..trade(2, "EURPLN", TransactionType::Open)
+        });
+        portfolio.apply_trade(&trade(3, "GOLD", TransactionType::Pending));
+
+        let summary = portfolio.summary(AccountType::Real, &MoneyFormat::default());
+
+        assert_eq!(summary.positions, 0);
+        assert_eq!(summary.profit, "0.00");
+        assert!(summary.symbols.is_empty());
+    }
+
+    #[test]
+    fn summary_fits_in_one_screen_message() {
+        let trades: Vec<_> = (0..50).map(|i| open_trade(i, &format!("SYMBOL{}.PL", i), -1234567.89)).collect();
+        let money = MoneyFormat::new(&MoneySettings::default(), Some("PLN"), &[]);
+        let summary = Portfolio::new(&trades).summary(AccountType::Real, &money);
+        let payload = serde_json::to_string(&summary).unwrap();
+
+        assert_eq!(summary.symbols.len(), MAX_SYMBOLS);
+        assert!(payload.len() <= 1024, "{} bytes", payload.len());
+    }
+}
@@ -0,0 +1,1300 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use tokio::{
+    sync::{mpsc, Notify},
+    task::JoinSet,
+    time::{interval, sleep, sleep_until, Instant},
+};
+use xtb_client::{
+    schema::{
+        ChartLastInfoRecord, GetChartLastRequestRequest, GetCurrentUserDataRequest, GetTradesRequest, StreamGetBalanceData,
+        StreamGetBalanceSubscribe, StreamGetKeepAliveData, StreamGetKeepAliveSubscribe, StreamGetProfitData,
+        StreamGetProfitSubscribe, StreamGetTickPricesData, StreamGetTickPricesSubscribe, StreamGetTradesData,
+        StreamGetTradesSubscribe, TimePeriod, TradeRecord,
+    },
+    DataStream, RequestResponseApi, StreamApi, XtbClient,
+};
+
+use super::{
+    alert_rules::{Fired, Monitor},
+    history::{self, HistoryView},
+    market_data::{MarketData, Tick},
+    portfolio::Portfolio,
+    watchlist::{self, Watchlist},
+    AccountType, APP,
+};
+use crate::{
+    db::{
+        delete_xtb_alerts_before, delete_xtb_days_before, delete_xtb_snapshots_before, get_fx_rates,
+        get_money_settings, get_xtb_alert_rules, get_xtb_credentials, get_xtb_days, get_xtb_watchlist, save_xtb_alert,
+        save_xtb_snapshot, XtbAlertRecord, XtbCredentials, XtbSnapshot,
+    },
+    money::MoneyFormat,
+    tcp::StateMessage,
+};
+
+const BACKOFF_BASE_SECS: u64 = 5;
+const BACKOFF_MAX_SECS: u64 = 300;
+/// XTB sends a keep-alive every 3 seconds; missing a few means the stream is dead even if the
+/// socket is still open.
+const KEEP_ALIVE_TIMEOUT_SECS: u64 = 15;
+/// A session that lasted this long counts as a success, so the next reconnect starts the backoff
+/// over.
+const STABLE_SESSION_SECS: u64 = 60;
+/// Profits and prices change with every tick, far more often than the screens can be sent
+/// messages.
+const SUMMARY_INTERVAL_SECS: u64 = 5;
+const SNAPSHOT_INTERVAL_SECS: u64 = 300;
+const PRUNE_INTERVAL_SECS: u64 = 3600;
+/// Enough daily candles to reach back over a weekend and a holiday.
+const DAILY_CANDLES_DAYS: i64 = 7;
+const ALERT_HISTORY_RETENTION_DAYS: i64 = 90;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Timing {
+    pub backoff_base: Duration,
+    pub backoff_max: Duration,
+    pub keep_alive_timeout: Duration,
+    /// How often a changed portfolio summary or watchlist is sent.
+    pub summary_interval: Duration,
+    /// How often the account is recorded for its history.
+    pub snapshot_interval: Duration,
+    /// How often old alerts are deleted, with or without a login.
+    pub prune_interval: Duration,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self {
+            backoff_base: Duration::from_secs(BACKOFF_BASE_SECS),
+            backoff_max: Duration::from_secs(BACKOFF_MAX_SECS),
+            keep_alive_timeout: Duration::from_secs(KEEP_ALIVE_TIMEOUT_SECS),
+            summary_interval: Duration::from_secs(SUMMARY_INTERVAL_SECS),
+            snapshot_interval: Duration::from_secs(SNAPSHOT_INTERVAL_SECS),
+            prune_interval: Duration::from_secs(PRUNE_INTERVAL_SECS),
+        }
+    }
+}
+
+/// One of the XTB streams; the real ones are `DataStream`s.
+#[async_trait]
+pub trait Subscription<T>: Send {
+    /// The next item, or `None` once the stream has ended.
+    async fn next(&mut self) -> anyhow::Result<Option<T>>;
+}
+
+#[async_trait]
+impl<T> Subscription<T> for DataStream<T>
+where
+    T: for<'de> Deserialize<'de> + Send + Sync,
+{
+    async fn next(&mut self) -> anyhow::Result<Option<T>> {
+        Ok(DataStream::next(self).await?)
+    }
+}
+
+pub type Feed<T> = Box<dyn Subscription<T>>;
+
+/// Items of several feeds in the order they arrive; ends once all of them have.
+struct Merged<T> {
+    receiver: mpsc::UnboundedReceiver<anyhow::Result<T>>,
+    /// Empty without feeds, in which case nothing ever arrives. Dropping it stops the feeds.
+    tasks: JoinSet<()>,
+}
+
+#[async_trait]
+impl<T: Send> Subscription<T> for Merged<T> {
+    async fn next(&mut self) -> anyhow::Result<Option<T>> {
+        if self.tasks.is_empty() {
+            return std::future::pending().await;
+        }
+        self.receiver.recv().await.transpose()
+    }
+}
+
+fn merge<T: Send + 'static>(feeds: Vec<Feed<T>>) -> Feed<T> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let mut tasks = JoinSet::new();
+    for mut feed in feeds {
+        let sender = sender.clone();
+        tasks.spawn(async move {
+            while let Some(item) = feed.next().await.transpose() {
+                if sender.send(item).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    Box::new(Merged { receiver, tasks })
+}
+
+/// The streams a session subscribes to, for any `StreamApi` whose streams can be read.
+#[async_trait]
+pub trait Streams: Send {
+    async fn keep_alive(&mut self) -> anyhow::Result<Feed<StreamGetKeepAliveData>>;
+
+    async fn balance(&mut self) -> anyhow::Result<Feed<StreamGetBalanceData>>;
+
+    async fn trades(&mut self) -> anyhow::Result<Feed<StreamGetTradesData>>;
+
+    async fn profits(&mut self) -> anyhow::Result<Feed<StreamGetProfitData>>;
+}
+
+#[async_trait]
+impl<S> Streams for S
+where
+    S: StreamApi + Send,
+    S::Error: Debug,
+    S::Stream<StreamGetKeepAliveData>: Subscription<StreamGetKeepAliveData> + 'static,
+    S::Stream<StreamGetBalanceData>: Subscription<StreamGetBalanceData> + 'static,
+    S::Stream<StreamGetTradesData>: Subscription<StreamGetTradesData> + 'static,
+    S::Stream<StreamGetProfitData>: Subscription<StreamGetProfitData> + 'static,
+{
+    async fn keep_alive(&mut self) -> anyhow::Result<Feed<StreamGetKeepAliveData>> {
+        let stream = self
+            .subscribe_keep_alive(StreamGetKeepAliveSubscribe)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to subscribe to keep-alive: {:?}", e))?;
+        Ok(Box::new(stream))
+    }
+
+    async fn balance(&mut self) -> anyhow::Result<Feed<StreamGetBalanceData>> {
+        let stream = self
+            .subscribe_balance(StreamGetBalanceSubscribe)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to subscribe to balance: {:?}", e))?;
+        Ok(Box::new(stream))
+    }
+
+    async fn trades(&mut self) -> anyhow::Result<Feed<StreamGetTradesData>> {
+        let stream = self
+            .subscribe_trades(StreamGetTradesSubscribe)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to subscribe to trades: {:?}", e))?;
+        Ok(Box::new(stream))
+    }
+
+    async fn profits(&mut self) -> anyhow::Result<Feed<StreamGetProfitData>> {
+        let stream = self
+            .subscribe_profits(StreamGetProfitSubscribe)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to subscribe to profits: {:?}", e))?;
+        Ok(Box::new(stream))
+    }
+}
+
+/// XTB's tick prices as provider-agnostic ticks.
+struct XtbTicks(DataStream<StreamGetTickPricesData>);
+
+#[async_trait]
+impl Subscription<Tick> for XtbTicks {
+    async fn next(&mut self) -> anyhow::Result<Option<Tick>> {
+        let tick = Subscription::next(&mut self.0).await?;
+        Ok(tick.map(|tick| Tick {
+            symbol: tick.symbol,
+            bid: f64::try_from(tick.bid).unwrap_or_default(),
+            ask: f64::try_from(tick.ask).unwrap_or_default(),
+        }))
+    }
+}
+
+#[async_trait]
+impl MarketData for XtbClient {
+    async fn ticks(&mut self, symbol: &str) -> anyhow::Result<Feed<Tick>> {
+        let stream = self
+            .subscribe_tick_prices(StreamGetTickPricesSubscribe { symbol: symbol.to_string(), ..Default::default() })
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to subscribe to {} prices: {:?}", symbol, e))?;
+        Ok(Box::new(XtbTicks(stream)))
+    }
+
+    /// The tick stream doesn't say, so it comes from the last daily candles.
+    async fn previous_close(&mut self, symbol: &str) -> anyhow::Result<Option<f64>> {
+        let start = chrono::Utc::now() - chrono::Duration::days(DAILY_CANDLES_DAYS);
+        let info = ChartLastInfoRecord {
+            period: TimePeriod::PeriodD1,
+            start: start.timestamp_millis() as u64,
+            symbol: symbol.to_string(),
+        };
+        let chart = self
+            .get_chart_last_request(GetChartLastRequestRequest::default().with_info(info))
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to get {} daily candles: {:?}", symbol, e))?;
+        Ok(watchlist::previous_close(&chart))
+    }
+}
+
+/// Logs in to XTB.
+#[async_trait]
+pub trait Connector: Send + Sync {
+    type Api: Streams + MarketData;
+
+    async fn connect(&self, credentials: &XtbCredentials) -> anyhow::Result<Self::Api>;
+
+    /// Positions that are already open; the trade stream only reports changes.
+    async fn open_trades(&self, _api: &mut Self::Api) -> anyhow::Result<Vec<TradeRecord>> {
+        Ok(Vec::new())
+    }
+
+    /// ISO 4217 code of the account's currency, which its amounts are in.
+    async fn account_currency(&self, _api: &mut Self::Api) -> anyhow::Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+/// Logs in to the xStation servers of the credentials' account type.
+pub struct XtbConnector;
+
+#[async_trait]
+impl Connector for XtbConnector {
+    type Api = XtbClient;
+
+    async fn connect(&self, credentials: &XtbCredentials) -> anyhow::Result<XtbClient> {
+        credentials
+            .account_type
+            .builder()
+            .build(&credentials.user_id, &credentials.password)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to log in to XTB {} account: {}", credentials.account_type.label(), e))
+    }
+
+    async fn open_trades(&self, api: &mut XtbClient) -> anyhow::Result<Vec<TradeRecord>> {
+        let trades = api
+            .get_trades(GetTradesRequest { opened_only: true })
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to get open trades: {:?}", e))?;
+        Ok(trades.0)
+    }
+
+    async fn account_currency(&self, api: &mut XtbClient) -> anyhow::Result<Option<String>> {
+        let user = api
+            .get_current_user_data(GetCurrentUserDataRequest)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to get the account currency: {:?}", e))?;
+        Ok(Some(user.currency))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum SessionState {
+    /// Not logged in, e.g. because no credentials are saved yet.
+    #[default]
+    Disconnected,
+    LoggingIn,
+    Subscribed,
+    /// Waiting to log in again after `reason`.
+    Backoff { reason: String },
+}
+
+/// The ticks of every instrument on the watchlist or in an alert rule, and what they update.
+struct Quotes {
+    ticks: Feed<Tick>,
+    watchlist: Watchlist,
+    monitor: Monitor,
+}
+
+/// A logged-in client and the one set of subscriptions made for it. Dropping it unsubscribes
+/// and logs out.
+struct Subscriptions<A> {
+    keep_alive: Feed<StreamGetKeepAliveData>,
+    balance: Feed<StreamGetBalanceData>,
+    trades: Feed<StreamGetTradesData>,
+    profits: Feed<StreamGetProfitData>,
+    open_trades: Vec<TradeRecord>,
+    quotes: Quotes,
+    /// ISO 4217 code of the account's currency, when XTB said.
+    currency: Option<String>,
+    /// In the account currency, converted to the display one.
+    money: MoneyFormat,
+    // Last, so the streams unsubscribe before the client logs out.
+    api: A,
+}
+
+enum Step<A> {
+    Disconnected,
+    LoggingIn(XtbCredentials),
+    Subscribed(AccountType, Box<Subscriptions<A>>),
+    Backoff { failures: u32 },
+}
+
+/// Keeps one XTB session up: logs in, subscribes once, forwards stream data to the screens and
+/// logs in again with backoff when the login fails or a stream ends.
+pub struct Session<C> {
+    connector: C,
+    db: SqlitePool,
+    state: Arc<Mutex<SessionState>>,
+    timing: Timing,
+    /// Notified when the watchlist, the alert rules or the money settings changed.
+    reload: Arc<Notify>,
+}
+
+impl<C: Connector> Session<C> {
+    pub fn new(connector: C, db: SqlitePool, state: Arc<Mutex<SessionState>>) -> Self {
+        Self {
+            connector,
+            db,
+            state,
+            timing: Timing::default(),
+            reload: Arc::default(),
+        }
+    }
+
+    pub fn with_timing(mut self, timing: Timing) -> Self {
+        self.timing = timing;
+        self
+    }
+
+    pub fn with_reload(mut self, reload: Arc<Notify>) -> Self {
+        self.reload = reload;
+        self
+    }
+
+    fn set_state(&self, state: SessionState) {
+        *self.state.lock().unwrap() = state;
+    }
+
+    /// Runs until the screens go away. Without credentials it idles until restarted, which saving
+    /// them does.
+    pub async fn run(&self, sender: mpsc::Sender<StateMessage>) -> anyhow::Result<()> {
+        let mut step = Step::Disconnected;
+        let mut failures = 0;
+        loop {
+            step = match step {
+                Step::Disconnected => {
+                    self.set_state(SessionState::Disconnected);
+                    match get_xtb_credentials(&self.db).await? {
+                        Some(credentials) => Step::LoggingIn(credentials),
+                        None => std::future::pending().await,
+                    }
+                }
+                Step::LoggingIn(credentials) => {
+                    self.set_state(SessionState::LoggingIn);
+                    match self.subscribe(&credentials).await {
+                        Ok(subscriptions) => Step::Subscribed(credentials.account_type, Box::new(subscriptions)),
+                        Err(e) => {
+                            failures += 1;
+                            self.set_state(SessionState::Backoff { reason: e.to_string() });
+                            Step::Backoff { failures }
+                        }
+                    }
+                }
+                Step::Subscribed(account_type, subscriptions) => {
+                    self.set_state(SessionState::Subscribed);
+                    let started_at = Instant::now();
+                    let reason = self.forward(account_type, subscriptions, &sender).await?;
+                    println!("XTB session ended: {}", reason);
+                    if started_at.elapsed() >= Duration::from_secs(STABLE_SESSION_SECS) {
+                        failures = 0;
+                    }
+                    failures += 1;
+                    self.set_state(SessionState::Backoff { reason });
+                    Step::Backoff { failures }
+                }
+                Step::Backoff { failures } => {
+                    sleep(self.backoff(failures)).await;
+                    Step::Disconnected
+                }
+            };
+        }
+    }
+
+    async fn subscribe(&self, credentials: &XtbCredentials) -> anyhow::Result<Subscriptions<C::Api>> {
+        let mut api = self.connector.connect(credentials).await?;
+        let keep_alive = api.keep_alive().await?;
+        let balance = api.balance().await?;
+        // Subscribed to first, so no trade falls between the snapshot and the stream.
+        let trades = api.trades().await?;
+        let profits = api.profits().await?;
+        let open_trades = self.connector.open_trades(&mut api).await?;
+        let quotes = self.subscribe_quotes(&mut api).await?;
+
+        // Without it amounts are still shown, just without a currency.
+        let currency = self.connector.account_currency(&mut api).await.unwrap_or_else(|e| {
+            println!("{}", e);
+            None
+        });
+        let money = self.money_format(currency.as_deref()).await?;
+
+        Ok(Subscriptions {
+            keep_alive,
+            balance,
+            trades,
+            profits,
+            open_trades,
+            quotes,
+            currency,
+            money,
+            api,
+        })
+    }
+
+    async fn subscribe_quotes(&self, market_data: &mut impl MarketData) -> anyhow::Result<Quotes> {
+        let symbols = get_xtb_watchlist(&self.db).await?;
+        let monitor = Monitor::new(get_xtb_alert_rules(&self.db).await?);
+        let mut ticks = Vec::new();
+        let mut previous_closes = HashMap::new();
+        for symbol in &symbols {
+            ticks.push(market_data.ticks(symbol).await?);
+            // Without it the quote still shows, just without its daily change.
+            match market_data.previous_close(symbol).await {
+                Ok(Some(close)) => {
+                    previous_closes.insert(symbol.clone(), close);
+                }
+                Ok(None) => {}
+                Err(e) => println!("{}", e),
+            }
+        }
+        for symbol in monitor.symbols().iter().filter(|symbol| !symbols.contains(symbol)) {
+            ticks.push(market_data.ticks(symbol).await?);
+        }
+
+        Ok(Quotes {
+            ticks: merge(ticks),
+            watchlist: Watchlist::new(symbols, previous_closes),
+            monitor,
+        })
+    }
+
+    /// Formats amounts in `currency` with the stored money settings and FX rates.
+    async fn money_format(&self, currency: Option<&str>) -> anyhow::Result<MoneyFormat> {
+        Ok(MoneyFormat::new(&get_money_settings(&self.db).await?, currency, &get_fx_rates(&self.db).await?))
+    }
+
+    /// Subscribes to the changed watchlist and alert rules without logging in again.
+    async fn reload_quotes(&self, quotes: &mut Quotes, market_data: &mut impl MarketData) -> anyhow::Result<()> {
+        // Unsubscribed first, so instruments that stay on the list aren't unsubscribed after
+        // being subscribed to again.
+        quotes.ticks = merge(Vec::new());
+        let mut reloaded = self.subscribe_quotes(market_data).await?;
+        reloaded.monitor.keep_state(&quotes.monitor);
+        *quotes = reloaded;
+        Ok(())
+    }
+
+    /// Sends a `PortfolioSummary` and a `WatchlistView` to the screens whenever the streams changed
+    /// them, alerts as soon as a rule matches and records the account for its `HistoryView`, until
+    /// a stream ends or a reload fails; returns why it did. Fails only when the screens went away.
+    async fn forward(
+        &self,
+        account_type: AccountType,
+        mut subscriptions: Box<Subscriptions<C::Api>>,
+        sender: &mpsc::Sender<StateMessage>,
+    ) -> anyhow::Result<String> {
+        let keep_alive_timeout = self.timing.keep_alive_timeout;
+        let mut keep_alive_deadline = Instant::now() + keep_alive_timeout;
+        let mut portfolio = Portfolio::new(&subscriptions.open_trades);
+        // The first summary goes out right away, even for an empty account.
+        let mut portfolio_changed = true;
+        let mut watchlist_changed = false;
+        let mut summary_interval = interval(self.timing.summary_interval);
+        let mut snapshot_interval = interval(self.timing.snapshot_interval);
+        let mut prune_interval = interval(self.timing.prune_interval);
+        self.send_history(&subscriptions.money, sender).await?;
+        loop {
+            tokio::select! {
+                _ = sleep_until(keep_alive_deadline) => {
+                    return Ok(format!("No keep-alive for {}s", keep_alive_timeout.as_secs()));
+                }
+                _ = summary_interval.tick(), if portfolio_changed || watchlist_changed => {
+                    if portfolio_changed {
+                        let summary = portfolio.summary(account_type, &subscriptions.money);
+                        sender.send(StateMessage::data(APP, serde_json::to_string(&summary)?)).await?;
+                        portfolio_changed = false;
+                    }
+                    if watchlist_changed {
+                        let view = subscriptions.quotes.watchlist.view();
+                        sender.send(StateMessage::data(APP, serde_json::to_string(&view)?)).await?;
+                        watchlist_changed = false;
+                    }
+                }
+                _ = snapshot_interval.tick() => {
+                    if let Some(snapshot) = portfolio.snapshot(chrono::Utc::now().naive_utc()) {
+                        self.record(&snapshot, &subscriptions.money, sender).await?;
+                    }
+                }
+                _ = prune_interval.tick() => self.prune_alerts().await,
+                _ = self.reload.notified() => {
+                    if let Err(e) = self.reload_quotes(&mut subscriptions.quotes, &mut subscriptions.api).await {
+                        return Ok(format!("Failed to reload the watchlist and alert rules: {}", e));
+                    }
+                    watchlist_changed = true;
+                    // The old format is kept when the settings can't be read.
+                    match self.money_format(subscriptions.currency.as_deref()).await {
+                        Ok(money) => {
+                            subscriptions.money = money;
+                            portfolio_changed = true;
+                            self.send_history(&subscriptions.money, sender).await?;
+                        }
+                        Err(e) => println!("Failed to reload the money settings: {}", e),
+                    }
+                }
+                keep_alive = subscriptions.keep_alive.next() => match keep_alive {
+                    Ok(Some(_)) => keep_alive_deadline = Instant::now() + keep_alive_timeout,
+                    Ok(None) => return Ok("Keep-alive stream ended".to_string()),
+                    Err(e) => println!("Invalid XTB keep-alive: {}", e),
+                },
+                balance = subscriptions.balance.next() => match balance {
+                    Ok(Some(balance)) => {
+                        portfolio.apply_balance(&balance);
+                        portfolio_changed = true;
+                    }
+                    Ok(None) => return Ok("Balance stream ended".to_string()),
+                    Err(e) => println!("Invalid XTB balance: {}", e),
+                },
+                trade = subscriptions.trades.next() => match trade {
+                    Ok(Some(trade)) => {
+                        portfolio.apply_trade(&trade);
+                        portfolio_changed = true;
+                        let now = Instant::now().into_std();
+                        let fired = subscriptions.quotes.monitor.on_profit(portfolio.profit(), &subscriptions.money, now);
+                        self.alert(fired, sender).await?;
+                    }
+                    Ok(None) => return Ok("Trade stream ended".to_string()),
+                    Err(e) => println!("Invalid XTB trade: {}", e),
+                },
+                profit = subscriptions.profits.next() => match profit {
+                    Ok(Some(profit)) => {
+                        portfolio.apply_profit(&profit);
+                        portfolio_changed = true;
+                        let now = Instant::now().into_std();
+                        let fired = subscriptions.quotes.monitor.on_profit(portfolio.profit(), &subscriptions.money, now);
+                        self.alert(fired, sender).await?;
+                    }
+                    Ok(None) => return Ok("Profit stream ended".to_string()),
+                    Err(e) => println!("Invalid XTB profit: {}", e),
+                },
+                tick = subscriptions.quotes.ticks.next() => match tick {
+                    Ok(Some(tick)) => watchlist_changed |= self.apply_tick(&mut subscriptions.quotes, &tick, sender).await?,
+                    Ok(None) => return Ok("Tick price stream ended".to_string()),
+                    Err(e) => println!("Invalid XTB tick price: {}", e),
+                },
+            }
+        }
+    }
+
+    /// Without a brokerage login: only the watchlist and price alerts, from `market_data`. Fails
+    /// when the quotes can't be loaded or stop, so the supervisor retries with backoff.
+    pub async fn run_offline(&self, mut market_data: impl MarketData, sender: mpsc::Sender<StateMessage>) -> anyhow::Result<()> {
+        self.set_state(SessionState::LoggingIn);
+        let reason = match self.subscribe_quotes(&mut market_data).await {
+            Ok(quotes) => {
+                self.set_state(SessionState::Subscribed);
+                self.forward_quotes(quotes, &mut market_data, &sender).await?
+            }
+            Err(e) => e.to_string(),
+        };
+        self.set_state(SessionState::Backoff { reason: reason.clone() });
+
+        Err(anyhow::anyhow!(reason))
+    }
+
+    /// `forward` for the quotes alone.
+    async fn forward_quotes(
+        &self,
+        mut quotes: Quotes,
+        market_data: &mut impl MarketData,
+        sender: &mpsc::Sender<StateMessage>,
+    ) -> anyhow::Result<String> {
+        let mut watchlist_changed = false;
+        let mut summary_interval = interval(self.timing.summary_interval);
+        let mut prune_interval = interval(self.timing.prune_interval);
+        loop {
+            tokio::select! {
+                _ = summary_interval.tick(), if watchlist_changed => {
+                    let view = quotes.watchlist.view();
+                    sender.send(StateMessage::data(APP, serde_json::to_string(&view)?)).await?;
+                    watchlist_changed = false;
+                }
+                _ = prune_interval.tick() => self.prune_alerts().await,
+                _ = self.reload.notified() => {
+                    if let Err(e) = self.reload_quotes(&mut quotes, market_data).await {
+                        return Ok(format!("Failed to reload the watchlist and alert rules: {}", e));
+                    }
+                    watchlist_changed = true;
+                }
+                tick = quotes.ticks.next() => match tick {
+                    Ok(Some(tick)) => watchlist_changed |= self.apply_tick(&mut quotes, &tick, sender).await?,
+                    Ok(None) => return Ok("Quote feed ended".to_string()),
+                    Err(e) => println!("Invalid quote: {}", e),
+                },
+            }
+        }
+    }
+
+    /// Returns whether the watchlist changed; alerts right away.
+    async fn apply_tick(&self, quotes: &mut Quotes, tick: &Tick, sender: &mpsc::Sender<StateMessage>) -> anyhow::Result<bool> {
+        let changed = quotes.watchlist.apply_tick(tick);
+        let fired = quotes.monitor.on_price(&tick.symbol, tick.bid, Instant::now().into_std());
+        self.alert(fired, sender).await?;
+
+        Ok(changed)
+    }
+
+    /// Sends the alerts ahead of other messages and records them; a failed record only loses
+    /// history.
+    async fn alert(&self, fired: Vec<Fired>, sender: &mpsc::Sender<StateMessage>) -> anyhow::Result<()> {
+        for Fired { rule_id, alert } in fired {
+            sender.send(alert.to_message()?).await?;
+
+            let now = chrono::Utc::now().naive_utc();
+            let record = XtbAlertRecord {
+                id: 0,
+                rule_id: Some(rule_id),
+                fired_at: now,
+                level: alert.level,
+                title: alert.title,
+                message: alert.message,
+            };
+            if let Err(e) = save_xtb_alert(&self.db, &record).await {
+                println!("Failed to record XTB alert: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Saves the snapshot and sends the history it completes; a failed save only loses history.
+    /// Old snapshots are pruned here too.
+    async fn record(&self, snapshot: &XtbSnapshot, money: &MoneyFormat, sender: &mpsc::Sender<StateMessage>) -> anyhow::Result<()> {
+        if let Err(e) = save_xtb_snapshot(&self.db, snapshot).await {
+            println!("Failed to record XTB snapshot: {}", e);
+            return Ok(());
+        }
+        let before = snapshot.recorded_at - history::retention();
+        if let Err(e) = delete_xtb_snapshots_before(&self.db, before).await {
+            println!("Failed to prune XTB snapshots: {}", e);
+        }
+        let before = snapshot.recorded_at.date() - chrono::Duration::days(history::DAILY_RETENTION_DAYS);
+        if let Err(e) = delete_xtb_days_before(&self.db, before).await {
+            println!("Failed to prune XTB daily records: {}", e);
+        }
+
+        self.send_history(money, sender).await
+    }
+
+    /// Deletes alerts past their retention, on a timer rather than on every alert.
+    async fn prune_alerts(&self) {
+        let before = chrono::Utc::now().naive_utc() - chrono::Duration::days(ALERT_HISTORY_RETENTION_DAYS);
+        if let Err(e) = delete_xtb_alerts_before(&self.db, before).await {
+            println!("Failed to prune XTB alerts: {}", e);
+        }
+    }
+
+    /// Sends the `HistoryView` once anything was recorded in its days.
+    async fn send_history(&self, money: &MoneyFormat, sender: &mpsc::Sender<StateMessage>) -> anyhow::Result<()> {
+        let today = chrono::Utc::now().date_naive();
+        // From the day before the sparkline, which its first day's P/L is measured against.
+        let days = match get_xtb_days(&self.db, today - chrono::Duration::days(history::SPARK_DAYS)).await {
+            Ok(days) => days,
+            Err(e) => {
+                println!("Failed to read XTB history: {}", e);
+                return Ok(());
+            }
+        };
+        if let Some(view) = HistoryView::new(&days, today, money) {
+            sender.send(StateMessage::data(APP, serde_json::to_string(&view)?)).await?;
+        }
+        Ok(())
+    }
+
+    /// Doubles with every consecutive failure, up to `backoff_max`.
+    fn backoff(&self, failures: u32) -> Duration {
+        let exponent = failures.saturating_sub(1).min(16);
+        self.timing.backoff_base.saturating_mul(1 << exponent).min(self.timing.backoff_max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, VecDeque},
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+    use sqlx::SqlitePool;
+    use tokio::{
+        sync::{mpsc, Notify},
+        time::timeout,
+    };
+    use xtb_client::{
+        schema::{
+            StreamGetBalanceData, StreamGetBalanceSubscribe, StreamGetCandlesData, StreamGetCandlesSubscribe,
+            StreamGetKeepAliveData, StreamGetKeepAliveSubscribe, StreamGetNewsData, StreamGetNewsSubscribe,
+            StreamGetProfitData, StreamGetProfitSubscribe, StreamGetTickPricesData, StreamGetTickPricesSubscribe,
+            StreamGetTradeStatusData, StreamGetTradeStatusSubscribe, StreamGetTradesData, StreamGetTradesSubscribe,
+            TransactionType,
+        },
+        StreamApi,
+    };
+
+    use super::{Connector, Feed, Session, SessionState, Subscription, Timing};
+    use crate::{
+        db::{
+            add_xtb_alert_rule, add_xtb_watchlist_symbol, delete_xtb_watchlist_symbol, get_xtb_alert_history,
+            get_xtb_snapshots, initialize_test_db, save_fx_rate, save_money_settings, save_xtb_alert, save_xtb_credentials,
+            XtbAlertRecord, XtbCredentials,
+        },
+        money::{FxRate, Locale, MoneySettings},
+        alerts::AlertLevel,
+        tcp::StateMessage,
+        web::xtb::{
+            alert_rules::{AlertKind, AlertRule},
+            market_data::{MarketData, OfflineQuotes, QuoteSource, Tick},
+            AccountType,
+        },
+    };
+
+    const WATCHLIST: [&str; 2] = ["EURPLN", "US500"];
+
+    /// Items are sent by the test; dropping the sender ends the stream.
+    struct FakeStream<T> {
+        receiver: mpsc::UnboundedReceiver<T>,
+    }
+
+    #[async_trait]
+    impl<T: Send> Subscription<T> for FakeStream<T> {
+        async fn next(&mut self) -> anyhow::Result<Option<T>> {
+            Ok(self.receiver.recv().await)
+        }
+    }
+
+    /// The test's ends of one session's streams.
+    struct Feeds {
+        keep_alive: mpsc::UnboundedSender<StreamGetKeepAliveData>,
+        balance: mpsc::UnboundedSender<StreamGetBalanceData>,
+        trades: mpsc::UnboundedSender<StreamGetTradesData>,
+        profits: mpsc::UnboundedSender<StreamGetProfitData>,
+        ticks: HashMap<String, mpsc::UnboundedSender<Tick>>,
+    }
+
+    #[derive(Default)]
+    struct FakeApi {
+        keep_alive: Option<FakeStream<StreamGetKeepAliveData>>,
+        balance: Option<FakeStream<StreamGetBalanceData>>,
+        trades: Option<FakeStream<StreamGetTradesData>>,
+        profits: Option<FakeStream<StreamGetProfitData>>,
+        ticks: HashMap<String, FakeStream<Tick>>,
+        subscriptions: Arc<AtomicU32>,
+    }
+
+    impl FakeApi {
+        fn subscribe<T>(&self, stream: Option<FakeStream<T>>) -> Result<FakeStream<T>, String> {
+            self.subscriptions.fetch_add(1, Ordering::SeqCst);
+            stream.ok_or_else(|| "already subscribed".to_string())
+        }
+    }
+
+    #[async_trait]
+    impl StreamApi for FakeApi {
+        type Error = String;
+
+        type Stream<T: Send + Sync + for<'de> Deserialize<'de>> = FakeStream<T>;
+
+        async fn subscribe_balance(&mut self, _: StreamGetBalanceSubscribe) -> Result<FakeStream<StreamGetBalanceData>, String> {
+            let stream = self.balance.take();
+            self.subscribe(stream)
+        }
+
+        async fn subscribe_candles(&mut self, _: StreamGetCandlesSubscribe) -> Result<FakeStream<StreamGetCandlesData>, String> {
+            Err("not faked".to_string())
+        }
+
+        async fn subscribe_keep_alive(&mut self, _: StreamGetKeepAliveSubscribe) -> Result<FakeStream<StreamGetKeepAliveData>, String> {
+            let stream = self.keep_alive.take();
+            self.subscribe(stream)
+        }
+
+        async fn subscribe_news(&mut self, _: StreamGetNewsSubscribe) -> Result<FakeStream<StreamGetNewsData>, String> {
+            Err("not faked".to_string())
+        }
+
+        async fn subscribe_profits(&mut self, _: StreamGetProfitSubscribe) -> Result<FakeStream<StreamGetProfitData>, String> {
+            let stream = self.profits.take();
+            self.subscribe(stream)
+        }
+
+        async fn subscribe_tick_prices(&mut self, _: StreamGetTickPricesSubscribe) -> Result<FakeStream<StreamGetTickPricesData>, String> {
+            Err("not faked".to_string())
+        }
+
+        async fn subscribe_trades(&mut self, _: StreamGetTradesSubscribe) -> Result<FakeStream<StreamGetTradesData>, String> {
+            let stream = self.trades.take();
+            self.subscribe(stream)
+        }
+
+        async fn subscribe_trade_status(&mut self, _: StreamGetTradeStatusSubscribe) -> Result<FakeStream<StreamGetTradeStatusData>, String> {
+            Err("not faked".to_string())
+        }
+    }
+
+    #[async_trait]
+    impl MarketData for FakeApi {
+        async fn ticks(&mut self, symbol: &str) -> anyhow::Result<Feed<Tick>> {
+            let stream = self.ticks.remove(symbol);
+            Ok(Box::new(self.subscribe(stream).map_err(|e| anyhow::anyhow!(e))?))
+        }
+
+        async fn previous_close(&mut self, symbol: &str) -> anyhow::Result<Option<f64>> {
+            Ok((symbol == "EURPLN").then_some(4.25))
+        }
+    }
+
+    /// Hands out the queued logins in order; once they run out, every login fails.
+    #[derive(Default, Clone)]
+    struct FakeConnector {
+        logins: Arc<Mutex<VecDeque<Result<FakeApi, String>>>>,
+        attempts: Arc<AtomicU32>,
+        subscriptions: Arc<AtomicU32>,
+    }
+
+    impl FakeConnector {
+        /// Queues a successful login and returns the ends of its streams.
+        fn accept(&self) -> Feeds {
+            let (keep_alive, keep_alive_receiver) = mpsc::unbounded_channel();
+            let (balance, balance_receiver) = mpsc::unbounded_channel();
+            let (trades, trades_receiver) = mpsc::unbounded_channel();
+            let (profits, profits_receiver) = mpsc::unbounded_channel();
+            let (ticks, tick_receivers) = WATCHLIST
+                .iter()
+                .map(|symbol| {
+                    let (sender, receiver) = mpsc::unbounded_channel();
+                    ((symbol.to_string(), sender), (symbol.to_string(), FakeStream { receiver }))
+                })
+                .unzip();
+            let api = FakeApi {
+                keep_alive: Some(FakeStream { receiver: keep_alive_receiver }),
+                balance: Some(FakeStream { receiver: balance_receiver }),
+                trades: Some(FakeStream { receiver: trades_receiver }),
+                profits: Some(FakeStream { receiver: profits_receiver }),
+                ticks: tick_receivers,
+                subscriptions: self.subscriptions.clone(),
+            };
+            self.logins.lock().unwrap().push_back(Ok(api));
+            Feeds {
+                keep_alive,
+                balance,
+                trades,
+                profits,
+                ticks,
+            }
+        }
+
+        fn reject(&self) {
+            self.logins.lock().unwrap().push_back(Err("Invalid credentials".to_string()));
+        }
+    }
+
+    #[async_trait]
+    impl Connector for FakeConnector {
+        type Api = FakeApi;
+
+        async fn connect(&self, credentials: &XtbCredentials) -> anyhow::Result<FakeApi> {
+            assert_eq!((credentials.user_id.as_str(), credentials.account_type), ("12345", AccountType::Demo));
+            self.attempts.fetch_add(1, Ordering::SeqCst);
+            let login = self.logins.lock().unwrap().pop_front();
+            login.unwrap_or_else(|| Err("No more logins".to_string())).map_err(|e| anyhow::anyhow!(e))
+        }
+
+        async fn account_currency(&self, _: &mut FakeApi) -> anyhow::Result<Option<String>> {
+            Ok(Some("PLN".to_string()))
+        }
+    }
+
+    fn profit(profit: i64) -> StreamGetProfitData {
+        serde_json::from_value(serde_json::json!({ "order": 1, "order2": 1, "position": 1, "profit": profit })).unwrap()
+    }
+
+    fn opened(position: i32, symbol: &str) -> StreamGetTradesData {
+        StreamGetTradesData {
+            position,
+            symbol: symbol.to_string(),
+            type_: TransactionType::Open,
+            ..Default::default()
+        }
+    }
+
+    fn tick(symbol: &str, bid: f64, ask: f64) -> Tick {
+        Tick { symbol: symbol.to_string(), bid, ask }
+    }
+
+    fn keep_alive() -> StreamGetKeepAliveData {
+        serde_json::from_value(serde_json::json!({ "timestamp": 1 })).unwrap()
+    }
+
+    async fn test_db() -> SqlitePool {
+        let db = initialize_test_db().await;
+        save_xtb_credentials(&db, "12345".to_string(), "secret".to_string(), AccountType::Demo).await.unwrap();
+        for symbol in WATCHLIST {
+            add_xtb_watchlist_symbol(&db, symbol).await.unwrap();
+        }
+        db
+    }
+
+    /// Runs a session with millisecond timings, returning its state and what it sends.
+    async fn start(
+        connector: &FakeConnector,
+        db: SqlitePool,
+        keep_alive_timeout: Duration,
+    ) -> (Arc<Mutex<SessionState>>, mpsc::Receiver<StateMessage>) {
+        let state = Arc::new(Mutex::new(SessionState::default()));
+        let session = Session::new(connector.clone(), db, state.clone()).with_timing(Timing {
+            backoff_base: Duration::from_millis(10),
+            backoff_max: Duration::from_millis(20),
+            keep_alive_timeout,
+            summary_interval: Duration::from_millis(10),
+            snapshot_interval: Duration::from_millis(20),
+            prune_interval: Duration::from_millis(20),
+        });
+        let (sender, receiver) = mpsc::channel(10);
+        tokio::spawn(async move { session.run(sender).await });
+
+        (state, receiver)
+    }
+
+    async fn next_summary(receiver: &mut mpsc::Receiver<StateMessage>) -> Value {
+        match timeout(Duration::from_secs(1), receiver.recv()).await.unwrap().unwrap() {
+            StateMessage::Data { payload, .. } => serde_json::from_str(&payload).unwrap(),
+            _ => panic!("expected a data message"),
+        }
+    }
+
+    /// Skips summaries sent before the session saw the given total profit.
+    async fn summary_with_profit(receiver: &mut mpsc::Receiver<StateMessage>, profit: &str) -> Value {
+        loop {
+            let summary = next_summary(receiver).await;
+            if summary["view"] == "portfolio" && summary["profit"] == profit {
+                return summary;
+            }
+        }
+    }
+
+    async fn eventually(condition: impl Fn() -> bool) {
+        timeout(Duration::from_secs(1), async {
+            while !condition() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_login_is_retried_with_backoff() {
+        let connector = FakeConnector::default();
+        connector.reject();
+        let feeds = connector.accept();
+
+        let (state, mut receiver) = start(&connector, test_db().await, Duration::from_secs(1)).await;
+        feeds.profits.send(profit(12)).unwrap();
+
+        assert_eq!(summary_with_profit(&mut receiver, "+12.00").await["account"], "demo");
+        assert_eq!(connector.attempts.load(Ordering::SeqCst), 2);
+        assert_eq!(*state.lock().unwrap(), SessionState::Subscribed);
+    }
+
+    #[tokio::test]
+    async fn session_subscribes_once() {
+        let connector = FakeConnector::default();
+        let feeds = connector.accept();
+
+        let (_, mut receiver) = start(&connector, test_db().await, Duration::from_secs(1)).await;
+        for value in 1..=3 {
+            feeds.keep_alive.send(keep_alive()).unwrap();
+            feeds.profits.send(profit(value)).unwrap();
+            summary_with_profit(&mut receiver, &format!("+{}.00", value)).await;
+        }
+
+        assert_eq!(connector.subscriptions.load(Ordering::SeqCst), 6);
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn ended_stream_starts_a_new_session() {
+        let connector = FakeConnector::default();
+        let first = connector.accept();
+        let second = connector.accept();
+
+        let (state, mut receiver) = start(&connector, test_db().await, Duration::from_secs(1)).await;
+        first.profits.send(profit(1)).unwrap();
+        summary_with_profit(&mut receiver, "+1.00").await;
+        drop(first);
+
+        second.profits.send(profit(2)).unwrap();
+        summary_with_profit(&mut receiver, "+2.00").await;
+        assert_eq!(connector.attempts.load(Ordering::SeqCst), 2);
+        assert_eq!(connector.subscriptions.load(Ordering::SeqCst), 12);
+        assert_eq!(*state.lock().unwrap(), SessionState::Subscribed);
+    }
+
+    #[tokio::test]
+    async fn summary_combines_balance_trades_and_profits() {
+        let connector = FakeConnector::default();
+        let feeds = connector.accept();
+
+        let (_, mut receiver) = start(&connector, test_db().await, Duration::from_secs(1)).await;
+        feeds
+            .balance
+            .send(StreamGetBalanceData {
+                balance: 1000.into(),
+                equity: 1030.into(),
+                margin_level: 2060.into(),
+                ..Default::default()
+            })
+            .unwrap();
+        feeds.trades.send(opened(1, "US500")).unwrap();
+        feeds.profits.send(profit(30)).unwrap();
+
+        let summary = summary_with_profit(&mut receiver, "+30.00").await;
+        assert_eq!(summary["currency"], "PLN");
+        assert_eq!(summary["equity"], "1,030.00");
+        assert_eq!(summary["marginLevel"], 2060.0);
+        assert_eq!(summary["symbols"], json!([{ "symbol": "US500", "positions": 1, "profit": "+30.00" }]));
+    }
+
+    #[tokio::test]
+    async fn watchlist_prices_are_sent() {
+        let connector = FakeConnector::default();
+        let feeds = connector.accept();
+
+        let (_, mut receiver) = start(&connector, test_db().await, Duration::from_secs(1)).await;
+        feeds.ticks["EURPLN"].send(tick("EURPLN", 4.3, 4.31)).unwrap();
+        feeds.ticks["US500"].send(tick("US500", 5000.0, 5000.5)).unwrap();
+
+        let view = loop {
+            let view = next_summary(&mut receiver).await;
+            if view["view"] == "watchlist" && view["quotes"].as_array().unwrap().len() == 2 {
+                break view;
+            }
+        };
+        assert_eq!(
+            view["quotes"],
+            json!([
+                { "symbol": "EURPLN", "bid": 4.3, "ask": 4.31, "change": 0.05, "changePercent": 1.18, "color": "green" },
+                { "symbol": "US500", "bid": 5000.0, "ask": 5000.5, "change": null, "changePercent": null, "color": "neutral" },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn matching_rules_alert_and_are_recorded() {
+        let db = test_db().await;
+        let rule = AlertRule {
+            id: 0,
+            kind: AlertKind::ProfitBelow,
+            symbol: None,
+            threshold: -100.0,
+            window_minutes: 0,
+            cooldown_minutes: 60,
+            enabled: true,
+        };
+        let rule = add_xtb_alert_rule(&db, rule).await.unwrap();
+        let connector = FakeConnector::default();
+        let feeds = connector.accept();
+
+        let (_, mut receiver) = start(&connector, db.clone(), Duration::from_secs(1)).await;
+        feeds.profits.send(profit(-150)).unwrap();
+        feeds.profits.send(profit(-160)).unwrap();
+
+        let alert = loop {
+            match timeout(Duration::from_secs(1), receiver.recv()).await.unwrap().unwrap() {
+                StateMessage::Data { app, payload } if app == crate::alerts::APP => {
+                    break serde_json::from_str::<Value>(&payload).unwrap();
+                }
+                _ => {}
+            }
+        };
+        assert_eq!(alert["title"], "P/L below -100.00 PLN");
+        assert_eq!(alert["level"], "severe");
+
+        summary_with_profit(&mut receiver, "-160.00").await;
+        let history = get_xtb_alert_history(&db, 10).await.unwrap();
+        assert_eq!(history.len(), 1, "the second profit is within the cooldown");
+        assert_eq!(history[0].rule_id, Some(rule.id));
+        assert_eq!(history[0].message, "Floating P/L is -150.00 PLN");
+    }
+
+    #[tokio::test]
+    async fn changed_money_settings_are_reloaded_without_a_login() {
+        // Without a watchlist, so reloading doesn't subscribe to ticks again.
+        let db = initialize_test_db().await;
+        save_xtb_credentials(&db, "12345".to_string(), "secret".to_string(), AccountType::Demo).await.unwrap();
+        let connector = FakeConnector::default();
+        let feeds = connector.accept();
+        let reload = Arc::new(Notify::new());
+        let session = Session::new(connector.clone(), db.clone(), Arc::default())
+            .with_timing(Timing { summary_interval: Duration::from_millis(10), ..Timing::default() })
+            .with_reload(reload.clone());
+        let (sender, mut receiver) = mpsc::channel(10);
+        tokio::spawn(async move { session.run(sender).await });
+        feeds.profits.send(profit(425)).unwrap();
+        assert_eq!(summary_with_profit(&mut receiver, "+425.00").await["currency"], "PLN");
+
+        let settings = MoneySettings { locale: Locale::En, display_currency: Some("EUR".to_string()) };
+        save_money_settings(&db, &settings).await.unwrap();
+        save_fx_rate(&db, &FxRate { from: "EUR".to_string(), to: "PLN".to_string(), rate: 4.25 }).await.unwrap();
+        reload.notify_one();
+
+        assert_eq!(summary_with_profit(&mut receiver, "+100.00").await["currency"], "EUR");
+        assert_eq!(connector.attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn missing_keep_alive_ends_the_session() {
+        let connector = FakeConnector::default();
+        let _feeds = connector.accept();
+
+        let (state, _receiver) = start(&connector, test_db().await, Duration::from_millis(20)).await;
+
+        eventually(|| connector.attempts.load(Ordering::SeqCst) >= 2).await;
+        eventually(|| matches!(&*state.lock().unwrap(), SessionState::Backoff { reason } if reason == "No more logins")).await;
+    }
+
+    #[tokio::test]
+    async fn account_is_recorded_for_the_history() {
+        let db = test_db().await;
+        let connector = FakeConnector::default();
+        let feeds = connector.accept();
+
+        let (_, mut receiver) = start(&connector, db.clone(), Duration::from_secs(1)).await;
+        feeds
+            .balance
+            .send(StreamGetBalanceData {
+                balance: 1000.into(),
+                equity: 1030.into(),
+                ..Default::default()
+            })
+            .unwrap();
+
+        let view = loop {
+            let view = next_summary(&mut receiver).await;
+            if view["view"] == "history" {
+                break view;
+            }
+        };
+        assert_eq!(view["pnl"].as_array().unwrap().last().unwrap(), &json!(0.0));
+        let snapshots = get_xtb_snapshots(&db, chrono::NaiveDateTime::default()).await.unwrap();
+        assert_eq!((snapshots[0].balance, snapshots[0].equity), (1000.0, 1030.0));
+    }
+
+    #[tokio::test]
+    async fn offline_quotes_feed_the_watchlist_without_a_login() {
+        let path = std::env::temp_dir().join(format!("session-quotes-{}.json", std::process::id()));
+        let quotes = json!([
+            { "symbol": "EURPLN", "bid": 4.3, "ask": 4.31, "previousClose": 4.25 },
+            { "symbol": "US500", "bid": 5000.0, "ask": 5000.5 },
+        ]);
+        std::fs::write(&path, quotes.to_string()).unwrap();
+        let connector = FakeConnector::default();
+        let state = Arc::new(Mutex::new(SessionState::default()));
+        let session = Session::new(connector.clone(), test_db().await, state.clone())
+            .with_timing(Timing { summary_interval: Duration::from_millis(10), ..Timing::default() });
+        let (sender, mut receiver) = mpsc::channel(10);
+        let market_data = OfflineQuotes::new(QuoteSource::Json(path.clone()));
+        tokio::spawn(async move { session.run_offline(market_data, sender).await });
+
+        let view = next_summary(&mut receiver).await;
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(view["view"], "watchlist");
+        assert_eq!(view["quotes"][0]["changePercent"], 1.18);
+        assert_eq!(view["quotes"][1]["bid"], 5000.0);
+        assert_eq!(*state.lock().unwrap(), SessionState::Subscribed);
+        assert_eq!(connector.attempts.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn changed_watchlist_is_reloaded_in_place() {
+        let path = std::env::temp_dir().join(format!("session-reload-{}.json", std::process::id()));
+        let quotes = json!([
+            { "symbol": "EURPLN", "bid": 4.3, "ask": 4.31 },
+            { "symbol": "US500", "bid": 5000.0, "ask": 5000.5 },
+            { "symbol": "GOLD", "bid": 2400.0, "ask": 2400.4 },
+        ]);
+        std::fs::write(&path, quotes.to_string()).unwrap();
+        let db = test_db().await;
+        let reload = Arc::new(Notify::new());
+        let state = Arc::new(Mutex::new(SessionState::default()));
+        let session = Session::new(FakeConnector::default(), db.clone(), state.clone())
+            .with_timing(Timing { summary_interval: Duration::from_millis(10), ..Timing::default() })
+            .with_reload(reload.clone());
+        let (sender, mut receiver) = mpsc::channel(10);
+        let market_data = OfflineQuotes::new(QuoteSource::Json(path.clone()));
+        let task = tokio::spawn(async move { session.run_offline(market_data, sender).await });
+        assert_eq!(next_summary(&mut receiver).await["quotes"].as_array().unwrap().len(), 2);
+
+        add_xtb_watchlist_symbol(&db, "GOLD").await.unwrap();
+        delete_xtb_watchlist_symbol(&db, "US500").await.unwrap();
+        reload.notify_one();
+
+        let view = next_summary(&mut receiver).await;
+        std::fs::remove_file(&path).unwrap();
+        let symbols: Vec<_> = view["quotes"].as_array().unwrap().iter().map(|quote| quote["symbol"].clone()).collect();
+        assert_eq!(symbols, [json!("EURPLN"), json!("GOLD")]);
+        assert_eq!(*state.lock().unwrap(), SessionState::Subscribed);
+        assert!(!task.is_finished());
+    }
+
+    #[tokio::test]
+    async fn offline_session_prunes_old_alerts() {
+        let path = std::env::temp_dir().join(format!("session-prune-{}.json", std::process::id()));
+        std::fs::write(&path, json!([{ "symbol": "EURPLN", "bid": 4.3 }]).to_string()).unwrap();
+        let db = test_db().await;
+        let now = chrono::Utc::now().naive_utc();
+        for days in [100, 1] {
+            let record = XtbAlertRecord {
+                id: 0,
+                rule_id: None,
+                fired_at: now - chrono::Duration::days(days),
+                level: AlertLevel::Warning,
+                title: format!("{} days ago", days),
+                message: String::new(),
+            };
+            save_xtb_alert(&db, &record).await.unwrap();
+        }
+        let state = Arc::new(Mutex::new(SessionState::default()));
+        let session = Session::new(FakeConnector::default(), db.clone(), state)
+            .with_timing(Timing { prune_interval: Duration::from_millis(20), ..Timing::default() });
+        let (sender, _receiver) = mpsc::channel(10);
+        let market_data = OfflineQuotes::new(QuoteSource::Json(path.clone()));
+        tokio::spawn(async move { session.run_offline(market_data, sender).await });
+
+        let history = timeout(Duration::from_secs(1), async {
+            loop {
+                let history = get_xtb_alert_history(&db, 10).await.unwrap();
+                if history.len() == 1 {
+                    break history;
+                }
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(history[0].title, "1 days ago");
+    }
+
+    #[tokio::test]
+    async fn offline_session_fails_without_quotes() {
+        let state = Arc::new(Mutex::new(SessionState::default()));
+        let session = Session::new(FakeConnector::default(), test_db().await, state.clone());
+        let (sender, _receiver) = mpsc::channel(10);
+        let market_data = OfflineQuotes::new(QuoteSource::Csv("/nonexistent/quotes.csv".into()));
+
+        assert!(session.run_offline(market_data, sender).await.is_err());
+        assert!(matches!(*state.lock().unwrap(), SessionState::Backoff { .. }));
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use xtb_client::schema::GetChartLastRequestResponse;
+
+use super::market_data::Tick;
+
+/// As many rows as the screen has room for.
+pub const MAX_SYMBOLS: usize = 8;
+pub const MAX_SYMBOL_LENGTH: usize = 16;
+
+fn to_f64(value: impl TryInto<f64>) -> f64 {
+    value.try_into().unwrap_or_default()
+}
+
+fn round(value: f64, decimals: i32) -> f64 {
+    let factor = 10f64.powi(decimals);
+    (value * factor).round() / factor
+}
+
+/// Upper-cases `symbol` as XTB names instruments, or says why it can't be one.
+pub fn normalize_symbol(symbol: &str) -> Result<String, String> {
+    let symbol = symbol.trim().to_uppercase();
+    if symbol.is_empty() {
+        return Err("symbol is required".to_string());
+    }
+    if symbol.len() > MAX_SYMBOL_LENGTH {
+        return Err(format!("symbol must be at most {} characters", MAX_SYMBOL_LENGTH));
+    }
+    if !symbol.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '#')) {
+        return Err(format!("{} is not a valid symbol", symbol));
+    }
+
+    Ok(symbol)
+}
+
+/// The close of the day before the last daily candle, which the daily change is measured
+/// against.
+pub fn previous_close(chart: &GetChartLastRequestResponse) -> Option<f64> {
+    let mut candles: Vec<_> = chart.rate_infos.iter().collect();
+    candles.sort_by_key(|candle| candle.ctm);
+    let candle = candles.iter().rev().nth(1)?;
+
+    // Opens are scaled by 10^digits and closes are shifts from the open.
+    let scale = 10f64.powi(chart.digits as i32);
+    Some((to_f64(candle.open) + to_f64(candle.close)) / scale)
+}
+
+/// The latest prices of the watched instruments.
+#[derive(Debug, Default)]
+pub struct Watchlist {
+    symbols: Vec<String>,
+    previous_closes: HashMap<String, f64>,
+    ticks: HashMap<String, Tick>,
+}
+
+impl Watchlist {
+    pub fn new(symbols: Vec<String>, previous_closes: HashMap<String, f64>) -> Self {
+        Self {
+            symbols,
+            previous_closes,
+            ticks: HashMap::new(),
+        }
+    }
+
+    /// Returns whether the tick was for a watched instrument.
+    pub fn apply_tick(&mut self, tick: &Tick) -> bool {
+        if !self.symbols.contains(&tick.symbol) {
+            return false;
+        }
+
+        self.ticks.insert(tick.symbol.clone(), tick.clone());
+        true
+    }
+
+    /// Instruments without a tick yet are left out.
+    pub fn view(&self) -> WatchlistView {
+        let quotes = self
+            .symbols
+            .iter()
+            .filter_map(|symbol| {
+                let tick = self.ticks.get(symbol)?;
+                let change = self.previous_closes.get(symbol).map(|close| (tick.bid - close, close));
+                Some(Quote {
+                    symbol: symbol.clone(),
+                    bid: tick.bid,
+                    ask: tick.ask,
+                    change: change.map(|(change, _)| round(change, 5)),
+                    change_percent: change.map(|(change, close)| round(change / close * 100.0, 2)),
+                    color: match change {
+                        Some((change, _)) if change > 0.0 => "green",
+                        Some((change, _)) if change < 0.0 => "red",
+                        _ => "neutral",
+                    },
+                })
+            })
+            .collect();
+
+        WatchlistView { view: "watchlist", quotes }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Quote {
+    pub symbol: String,
+    pub bid: f64,
+    pub ask: f64,
+    /// Since the previous day's close; `None` when it isn't known.
+    pub change: Option<f64>,
+    #[serde(rename = "changePercent")]
+    pub change_percent: Option<f64>,
+    /// `green`, `red` or `neutral`, for the screen to color the row with.
+    pub color: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WatchlistView {
+    pub view: &'static str,
+    pub quotes: Vec<Quote>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+    use xtb_client::schema::{GetChartLastRequestResponse, RateInfoRecord};
+
+    use super::{normalize_symbol, previous_close, Watchlist, MAX_SYMBOLS, MAX_SYMBOL_LENGTH};
+    use crate::web::xtb::market_data::Tick;
+
+    fn tick(symbol: &str, bid: f64, ask: f64) -> Tick {
+        Tick { symbol: symbol.to_string(), bid, ask }
+    }
+
+    #[test]
+    fn quotes_show_the_daily_change() {
+        let symbols = vec!["EURPLN".to_string(), "US500".to_string(), "GOLD".to_string()];
+        let closes = HashMap::from([("EURPLN".to_string(), 4.25), ("US500".to_string(), 5000.0)]);
+        let mut watchlist = Watchlist::new(symbols, closes);
+
+        assert!(watchlist.apply_tick(&tick("US500", 4950.0, 4950.5)));
+        assert!(watchlist.apply_tick(&tick("EURPLN", 4.2551, 4.2571)));
+        assert!(!watchlist.apply_tick(&tick("OIL", 80.0, 80.1)));
+
+        assert_eq!(
+            serde_json::to_value(watchlist.view()).unwrap(),
+            json!({
+                "view": "watchlist",
+                "quotes": [
+                    { "symbol": "EURPLN", "bid": 4.2551, "ask": 4.2571, "change": 0.0051, "changePercent": 0.12, "color": "green" },
+                    { "symbol": "US500", "bid": 4950.0, "ask": 4950.5, "change": -50.0, "changePercent": -1.0, "color": "red" },
+                ],
+            })
+        );
+
+        watchlist.apply_tick(&tick("GOLD", 2400.0, 2400.4));
+        let gold = watchlist.view().quotes.pop().unwrap();
+        assert_eq!((gold.change, gold.color), (None, "neutral"));
+    }
+
+    #[test]
+    fn previous_close_is_the_day_before_the_last_candle() {
+        let candle = |ctm: u64, open: i64, close: i64| RateInfoRecord {
+            ctm,
+            open: open.into(),
+            close: close.into(),
+            ..Default::default()
+        };
+        let chart = GetChartLastRequestResponse {
+            digits: 4,
+            rate_infos: vec![candle(3, 42600, 10), candle(2, 42500, -20), candle(1, 42000, 500)],
+        };
+
+        assert_eq!(previous_close(&chart), Some(4.248));
+        assert_eq!(previous_close(&GetChartLastRequestResponse { digits: 4, rate_infos: vec![candle(1, 1, 1)] }), None);
+    }
+
+    #[test]
+    fn symbols_are_normalized() {
+        assert_eq!(normalize_symbol(" eurpln "), Ok("EURPLN".to_string()));
+        assert_eq!(normalize_symbol("US500.cash"), Ok("US500.CASH".to_string()));
+        assert!(normalize_symbol("").is_err());
+        assert!(normalize_symbol("US 500").is_err());
+        assert!(normalize_symbol(&"X".repeat(MAX_SYMBOL_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn view_fits_in_one_screen_message() {
+        let symbols: Vec<_> = (0..MAX_SYMBOLS).map(|i| format!("{:X<16}", i)).collect();
+        let closes = symbols.iter().map(|symbol| (symbol.clone(), 123456.789)).collect();
+        let mut watchlist = Watchlist::new(symbols.clone(), closes);
+        for symbol in &symbols {
+            watchlist.apply_tick(&tick(symbol, 98765.43211, 98765.54321));
+        }
+        let payload = serde_json::to_string(&watchlist.view()).unwrap();
+
+        assert!(payload.len() <= 1024, "{} bytes", payload.len());
+    }
+}